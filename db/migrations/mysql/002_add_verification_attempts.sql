@@ -0,0 +1,3 @@
+-- Track failed verification code attempts
+ALTER TABLE users
+    ADD COLUMN verification_attempts INT NOT NULL DEFAULT 0 AFTER verification_code_expires_at;
@@ -0,0 +1,9 @@
+-- Preferred language for transactional emails and user role
+ALTER TABLE users
+    ADD COLUMN locale VARCHAR(16) NOT NULL DEFAULT 'en' AFTER verification_attempts,
+    ADD COLUMN role VARCHAR(20) NOT NULL DEFAULT 'user' AFTER locale;
+
+-- Per-app email branding
+ALTER TABLE apps
+    ADD COLUMN logo_url VARCHAR(500) NULL AFTER identifier,
+    ADD COLUMN email_from VARCHAR(255) NULL AFTER logo_url;
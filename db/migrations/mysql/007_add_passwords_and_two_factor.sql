@@ -0,0 +1,35 @@
+-- Optional password and TOTP second factor
+ALTER TABLE users
+    ADD COLUMN password_hash VARCHAR(255) NULL AFTER role,
+    ADD COLUMN totp_secret VARCHAR(64) NULL AFTER password_hash,
+    ADD COLUMN totp_enabled SMALLINT NOT NULL DEFAULT 0 AFTER totp_secret,
+    ADD COLUMN totp_last_step BIGINT NULL AFTER totp_enabled;
+
+-- Single-use recovery codes for the second factor
+CREATE TABLE IF NOT EXISTS recovery_codes (
+    id BIGINT PRIMARY KEY AUTO_INCREMENT,
+    user_id BIGINT NOT NULL,
+    code_hash CHAR(64) NOT NULL,
+    used_at TIMESTAMP NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    INDEX idx_recovery_codes_user (user_id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+-- Short-lived tokens for password resets and pending two-factor logins
+CREATE TABLE IF NOT EXISTS user_tokens (
+    id BIGINT PRIMARY KEY AUTO_INCREMENT,
+    user_id BIGINT NOT NULL,
+    purpose VARCHAR(20) NOT NULL,
+    token_hash CHAR(64) NOT NULL UNIQUE,
+    attempts INT NOT NULL DEFAULT 0,
+    expires_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    used_at TIMESTAMP NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    INDEX idx_user_tokens_user (user_id, purpose),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+-- Per-app policy requiring two-factor sessions for changes
+ALTER TABLE apps
+    ADD COLUMN require_two_factor SMALLINT NOT NULL DEFAULT 0 AFTER email_from;
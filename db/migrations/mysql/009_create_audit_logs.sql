@@ -0,0 +1,21 @@
+-- Append-only record of who changed what; rows are only deleted by the retention job
+CREATE TABLE IF NOT EXISTS audit_logs (
+    id BIGINT PRIMARY KEY AUTO_INCREMENT,
+    actor_type VARCHAR(20) NOT NULL,
+    actor_id BIGINT NULL,
+    api_key VARCHAR(16) NULL,
+    action VARCHAR(50) NOT NULL,
+    target_type VARCHAR(20) NOT NULL,
+    target_id BIGINT NULL,
+    app_id BIGINT NULL,
+    before_data MEDIUMTEXT NULL,
+    after_data MEDIUMTEXT NULL,
+    ip VARCHAR(45) NULL,
+    user_agent VARCHAR(500) NULL,
+    request_id VARCHAR(64) NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    INDEX idx_audit_logs_created (created_at),
+    INDEX idx_audit_logs_actor (actor_id, created_at),
+    INDEX idx_audit_logs_target (target_type, target_id),
+    INDEX idx_audit_logs_app (app_id, created_at)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
@@ -0,0 +1,16 @@
+-- Articles can belong to an app
+ALTER TABLE articles
+    ADD COLUMN app_id BIGINT NULL AFTER author_id,
+    ADD FOREIGN KEY (app_id) REFERENCES apps(id),
+    ADD INDEX idx_app (app_id);
+
+-- Soft delete: trashed rows are kept until purged
+ALTER TABLE apps
+    ADD COLUMN deleted_at TIMESTAMP NULL,
+    ADD COLUMN deleted_by BIGINT NULL,
+    ADD INDEX idx_deleted (deleted_at);
+
+ALTER TABLE articles
+    ADD COLUMN deleted_at TIMESTAMP NULL,
+    ADD COLUMN deleted_by BIGINT NULL,
+    ADD INDEX idx_deleted (deleted_at);
@@ -0,0 +1,12 @@
+-- Shareable draft previews; only the HMAC of a token is stored
+CREATE TABLE IF NOT EXISTS preview_links (
+    id BIGINT PRIMARY KEY AUTO_INCREMENT,
+    article_id BIGINT NOT NULL,
+    token_hash CHAR(64) NOT NULL UNIQUE,
+    version BIGINT NULL,
+    created_by BIGINT NOT NULL,
+    expires_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    revoked_at TIMESTAMP NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    INDEX idx_preview_links_article (article_id)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
@@ -0,0 +1,9 @@
+-- Per-app feed metadata
+ALTER TABLE apps
+    ADD COLUMN feed_title VARCHAR(255) NULL AFTER email_from,
+    ADD COLUMN feed_description TEXT NULL AFTER feed_title,
+    ADD COLUMN feed_link VARCHAR(500) NULL AFTER feed_description;
+
+-- Article category, used to filter feeds
+ALTER TABLE articles ADD COLUMN category VARCHAR(50) NULL AFTER app_id;
+CREATE INDEX idx_articles_app_category ON articles (app_id, category);
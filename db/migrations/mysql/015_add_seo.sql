@@ -0,0 +1,13 @@
+-- Site the app's articles are published on
+ALTER TABLE apps ADD COLUMN base_url VARCHAR(500) NULL AFTER feed_link;
+
+-- Per-article SEO metadata and translations
+ALTER TABLE articles
+    ADD COLUMN locale VARCHAR(16) NULL AFTER category,
+    ADD COLUMN translation_of BIGINT NULL AFTER locale,
+    ADD COLUMN meta_title VARCHAR(255) NULL AFTER translation_of,
+    ADD COLUMN meta_description VARCHAR(500) NULL AFTER meta_title,
+    ADD COLUMN canonical_url VARCHAR(500) NULL AFTER meta_description,
+    ADD COLUMN og_image_url VARCHAR(500) NULL AFTER canonical_url,
+    ADD COLUMN noindex SMALLINT NOT NULL DEFAULT 0 AFTER og_image_url;
+CREATE INDEX idx_articles_translation_of ON articles (translation_of);
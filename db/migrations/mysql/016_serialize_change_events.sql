@@ -0,0 +1,8 @@
+-- One row locked by every change event append, so events of concurrent writers
+-- (also on other instances) commit in id order and subscribers never skip a smaller id
+CREATE TABLE IF NOT EXISTS change_event_sequence (
+    id INT PRIMARY KEY,
+    appended BIGINT NOT NULL
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+INSERT INTO change_event_sequence (id, appended) VALUES (1, 0);
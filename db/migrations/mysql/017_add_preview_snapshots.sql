@@ -0,0 +1,9 @@
+-- Pinned preview links keep the content of the pinned version
+ALTER TABLE preview_links
+    ADD COLUMN title VARCHAR(255) NULL AFTER version,
+    ADD COLUMN content TEXT NULL AFTER title,
+    ADD COLUMN meta_title VARCHAR(255) NULL AFTER content,
+    ADD COLUMN meta_description VARCHAR(500) NULL AFTER meta_title,
+    ADD COLUMN canonical_url VARCHAR(500) NULL AFTER meta_description,
+    ADD COLUMN og_image_url VARCHAR(500) NULL AFTER canonical_url,
+    ADD COLUMN noindex SMALLINT NULL AFTER og_image_url;
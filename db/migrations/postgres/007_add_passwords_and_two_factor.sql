@@ -0,0 +1,35 @@
+-- Optional password and TOTP second factor
+ALTER TABLE users
+    ADD COLUMN password_hash VARCHAR(255),
+    ADD COLUMN totp_secret VARCHAR(64),
+    ADD COLUMN totp_enabled SMALLINT NOT NULL DEFAULT 0,
+    ADD COLUMN totp_last_step BIGINT;
+
+-- Single-use recovery codes for the second factor
+CREATE TABLE IF NOT EXISTS recovery_codes (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash CHAR(64) NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_recovery_codes_user ON recovery_codes (user_id);
+
+-- Short-lived tokens for password resets and pending two-factor logins
+CREATE TABLE IF NOT EXISTS user_tokens (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    purpose VARCHAR(20) NOT NULL,
+    token_hash CHAR(64) NOT NULL UNIQUE,
+    attempts INT NOT NULL DEFAULT 0,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_tokens_user ON user_tokens (user_id, purpose);
+
+-- Per-app policy requiring two-factor sessions for changes
+ALTER TABLE apps
+    ADD COLUMN require_two_factor SMALLINT NOT NULL DEFAULT 0;
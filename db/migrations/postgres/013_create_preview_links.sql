@@ -0,0 +1,13 @@
+-- Shareable draft previews; only the HMAC of a token is stored
+CREATE TABLE IF NOT EXISTS preview_links (
+    id BIGSERIAL PRIMARY KEY,
+    article_id BIGINT NOT NULL,
+    token_hash CHAR(64) NOT NULL UNIQUE,
+    version BIGINT,
+    created_by BIGINT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_preview_links_article ON preview_links (article_id);
@@ -0,0 +1,13 @@
+-- Site the app's articles are published on
+ALTER TABLE apps ADD COLUMN base_url VARCHAR(500);
+
+-- Per-article SEO metadata and translations
+ALTER TABLE articles
+    ADD COLUMN locale VARCHAR(16),
+    ADD COLUMN translation_of BIGINT,
+    ADD COLUMN meta_title VARCHAR(255),
+    ADD COLUMN meta_description VARCHAR(500),
+    ADD COLUMN canonical_url VARCHAR(500),
+    ADD COLUMN og_image_url VARCHAR(500),
+    ADD COLUMN noindex SMALLINT NOT NULL DEFAULT 0;
+CREATE INDEX idx_articles_translation_of ON articles (translation_of);
@@ -0,0 +1,9 @@
+-- Pinned preview links keep the content of the pinned version
+ALTER TABLE preview_links
+    ADD COLUMN title VARCHAR(255),
+    ADD COLUMN content TEXT,
+    ADD COLUMN meta_title VARCHAR(255),
+    ADD COLUMN meta_description VARCHAR(500),
+    ADD COLUMN canonical_url VARCHAR(500),
+    ADD COLUMN og_image_url VARCHAR(500),
+    ADD COLUMN noindex SMALLINT;
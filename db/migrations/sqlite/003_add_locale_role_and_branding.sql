@@ -0,0 +1,7 @@
+-- Preferred language for transactional emails and user role
+ALTER TABLE users ADD COLUMN locale VARCHAR(16) NOT NULL DEFAULT 'en';
+ALTER TABLE users ADD COLUMN role VARCHAR(20) NOT NULL DEFAULT 'user';
+
+-- Per-app email branding
+ALTER TABLE apps ADD COLUMN logo_url VARCHAR(500);
+ALTER TABLE apps ADD COLUMN email_from VARCHAR(255);
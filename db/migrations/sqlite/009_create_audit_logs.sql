@@ -0,0 +1,22 @@
+-- Append-only record of who changed what; rows are only deleted by the retention job
+CREATE TABLE IF NOT EXISTS audit_logs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    actor_type VARCHAR(20) NOT NULL,
+    actor_id INTEGER,
+    api_key VARCHAR(16),
+    action VARCHAR(50) NOT NULL,
+    target_type VARCHAR(20) NOT NULL,
+    target_id INTEGER,
+    app_id INTEGER,
+    before_data TEXT,
+    after_data TEXT,
+    ip VARCHAR(45),
+    user_agent VARCHAR(500),
+    request_id VARCHAR(64),
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_logs_created ON audit_logs (created_at);
+CREATE INDEX IF NOT EXISTS idx_audit_logs_actor ON audit_logs (actor_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_audit_logs_target ON audit_logs (target_type, target_id);
+CREATE INDEX IF NOT EXISTS idx_audit_logs_app ON audit_logs (app_id, created_at);
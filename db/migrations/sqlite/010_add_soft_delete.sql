@@ -0,0 +1,12 @@
+-- Articles can belong to an app
+ALTER TABLE articles ADD COLUMN app_id INTEGER REFERENCES apps(id);
+
+-- Soft delete: trashed rows are kept until purged
+ALTER TABLE apps ADD COLUMN deleted_at DATETIME;
+ALTER TABLE apps ADD COLUMN deleted_by INTEGER;
+ALTER TABLE articles ADD COLUMN deleted_at DATETIME;
+ALTER TABLE articles ADD COLUMN deleted_by INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_articles_app ON articles (app_id);
+CREATE INDEX IF NOT EXISTS idx_apps_deleted ON apps (deleted_at);
+CREATE INDEX IF NOT EXISTS idx_articles_deleted ON articles (deleted_at);
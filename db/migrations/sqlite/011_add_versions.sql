@@ -0,0 +1,3 @@
+-- Optimistic concurrency: incremented on every update
+ALTER TABLE apps ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE articles ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,13 @@
+-- Shareable draft previews; only the HMAC of a token is stored
+CREATE TABLE IF NOT EXISTS preview_links (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    article_id INTEGER NOT NULL,
+    token_hash CHAR(64) NOT NULL UNIQUE,
+    version INTEGER,
+    created_by INTEGER NOT NULL,
+    expires_at DATETIME NOT NULL,
+    revoked_at DATETIME,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_preview_links_article ON preview_links (article_id);
@@ -0,0 +1,8 @@
+-- Per-app feed metadata
+ALTER TABLE apps ADD COLUMN feed_title VARCHAR(255);
+ALTER TABLE apps ADD COLUMN feed_description TEXT;
+ALTER TABLE apps ADD COLUMN feed_link VARCHAR(500);
+
+-- Article category, used to filter feeds
+ALTER TABLE articles ADD COLUMN category VARCHAR(50);
+CREATE INDEX idx_articles_app_category ON articles (app_id, category);
@@ -0,0 +1,8 @@
+-- One row locked by every change event append, so events of concurrent writers
+-- (also on other instances) commit in id order and subscribers never skip a smaller id
+CREATE TABLE IF NOT EXISTS change_event_sequence (
+    id INTEGER PRIMARY KEY,
+    appended BIGINT NOT NULL
+);
+
+INSERT INTO change_event_sequence (id, appended) VALUES (1, 0);
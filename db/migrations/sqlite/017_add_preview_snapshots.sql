@@ -0,0 +1,8 @@
+-- Pinned preview links keep the content of the pinned version
+ALTER TABLE preview_links ADD COLUMN title VARCHAR(255);
+ALTER TABLE preview_links ADD COLUMN content TEXT;
+ALTER TABLE preview_links ADD COLUMN meta_title VARCHAR(255);
+ALTER TABLE preview_links ADD COLUMN meta_description VARCHAR(500);
+ALTER TABLE preview_links ADD COLUMN canonical_url VARCHAR(500);
+ALTER TABLE preview_links ADD COLUMN og_image_url VARCHAR(500);
+ALTER TABLE preview_links ADD COLUMN noindex SMALLINT;
@@ -0,0 +1,29 @@
+use std::env;
+
+// 内容导入导出的配置
+#[derive(Debug, Clone)]
+pub struct ArchiveConfig {
+    // 导入请求体的最大字节数，导入文件通常远大于普通 JSON 请求
+    pub max_import_bytes: usize,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self {
+            max_import_bytes: 50 * 1024 * 1024,
+        }
+    }
+}
+
+impl ArchiveConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_import_bytes: env::var("IMPORT_MAX_BYTES")
+                .ok()
+                .and_then(|value| value.parse::<usize>().ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(defaults.max_import_bytes),
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use std::env;
+
+// 审计日志的配置
+#[derive(Debug, Clone)]
+pub struct AuditConfig {
+    // 超过保留天数的记录由后台任务删除，为 0 时永久保留
+    pub retention_days: i64,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self { retention_days: 365 }
+    }
+}
+
+impl AuditConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            retention_days: env::var("AUDIT_RETENTION_DAYS")
+                .ok()
+                .and_then(|value| value.parse::<i64>().ok())
+                .filter(|value| *value >= 0)
+                .unwrap_or(defaults.retention_days),
+        }
+    }
+}
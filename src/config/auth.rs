@@ -1,27 +1,467 @@
-use jsonwebtoken::{DecodingKey, EncodingKey};
+use crate::utils::AppError;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::env;
 
+// JWT 签名密钥，用 kid 区分
+#[derive(Clone)]
+pub struct JwtKey {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    // 从该时间起用于签发新 token，之前的密钥在宽限期内继续用于校验
+    pub active_from: DateTime<Utc>,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    // 公钥，HS256 密钥没有公钥，不会发布到 JWKS
+    jwk: Option<Jwk>,
+}
+
+impl JwtKey {
+    // HS256 共享密钥，只在没有配置非对称密钥时使用
+    pub fn from_secret(kid: &str, secret: &str) -> Self {
+        Self {
+            kid: kid.to_string(),
+            algorithm: Algorithm::HS256,
+            active_from: DateTime::<Utc>::MIN_UTC,
+            encoding_key: EncodingKey::from_secret(secret.as_bytes()),
+            decoding_key: DecodingKey::from_secret(secret.as_bytes()),
+            jwk: None,
+        }
+    }
+
+    // PEM 格式的私钥：RSA（PKCS#1 或 PKCS#8）使用 RS256，Ed25519 使用 EdDSA
+    pub fn from_pem(kid: &str, pem: &str) -> Result<Self, anyhow::Error> {
+        use ed25519_dalek::pkcs8::DecodePrivateKey as _;
+        use rsa::pkcs1::DecodeRsaPrivateKey as _;
+        use rsa::traits::PublicKeyParts as _;
+
+        let rsa_key = rsa::RsaPrivateKey::from_pkcs1_pem(pem).or_else(|_| rsa::RsaPrivateKey::from_pkcs8_pem(pem));
+        let (algorithm, encoding_key, jwk) = if let Ok(private_key) = rsa_key {
+            let jwk = json!({
+                "kty": "RSA",
+                "use": "sig",
+                "alg": "RS256",
+                "kid": kid,
+                "n": URL_SAFE_NO_PAD.encode(private_key.n().to_bytes_be()),
+                "e": URL_SAFE_NO_PAD.encode(private_key.e().to_bytes_be()),
+            });
+            (Algorithm::RS256, EncodingKey::from_rsa_pem(pem.as_bytes())?, jwk)
+        } else if let Ok(signing_key) = ed25519_dalek::SigningKey::from_pkcs8_pem(pem) {
+            let jwk = json!({
+                "kty": "OKP",
+                "use": "sig",
+                "alg": "EdDSA",
+                "kid": kid,
+                "crv": "Ed25519",
+                "x": URL_SAFE_NO_PAD.encode(signing_key.verifying_key().as_bytes()),
+            });
+            (Algorithm::EdDSA, EncodingKey::from_ed_pem(pem.as_bytes())?, jwk)
+        } else {
+            anyhow::bail!("key {} is not an RSA or Ed25519 private key", kid);
+        };
+
+        let jwk: Jwk = serde_json::from_value(jwk)?;
+        Ok(Self {
+            kid: kid.to_string(),
+            algorithm,
+            active_from: DateTime::<Utc>::MIN_UTC,
+            encoding_key,
+            decoding_key: DecodingKey::from_jwk(&jwk)?,
+            jwk: Some(jwk),
+        })
+    }
+
+    pub fn active_from(mut self, active_from: DateTime<Utc>) -> Self {
+        self.active_from = active_from;
+        self
+    }
+}
+
 #[derive(Clone)]
 pub struct JwtConfig {
+    // HS256 密钥，未配置 JWT_KEYS 时用于签名，也是登录链接的默认密钥
     pub secret: String,
-    pub encoding_key: EncodingKey,
-    pub decoding_key: DecodingKey,
+    pub issuer: String,
+    pub audience: String,
+    pub ttl_hours: i64,
+    // 新密钥生效后，旧密钥签发的 token 继续有效的时长，不应短于 ttl_hours
+    pub grace_hours: i64,
+    // 按 active_from 排序
+    keys: Vec<JwtKey>,
 }
 
 impl JwtConfig {
+    pub fn new(secret: &str, issuer: &str, audience: &str) -> Self {
+        Self {
+            secret: secret.to_string(),
+            issuer: issuer.to_string(),
+            audience: audience.to_string(),
+            ttl_hours: 24,
+            grace_hours: 24,
+            keys: vec![JwtKey::from_secret("default", secret)],
+        }
+    }
+
+    // 使用非对称密钥替换 HS256 密钥
+    pub fn with_keys(mut self, mut keys: Vec<JwtKey>) -> Self {
+        if !keys.is_empty() {
+            keys.sort_by_key(|key| key.active_from);
+            self.keys = keys;
+        }
+        self
+    }
+
     pub fn from_env() -> Self {
         let secret = env::var("JWT_SECRET").unwrap_or_else(|_| "your-super-secret-and-ultra-long-secret-key".to_string());
-        Self {
-            encoding_key: EncodingKey::from_secret(secret.as_bytes()),
-            decoding_key: DecodingKey::from_secret(secret.as_bytes()),
-            secret,
+        let issuer = env::var("JWT_ISSUER").unwrap_or_else(|_| {
+            let host = env::var("SERVER_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
+            let port = env::var("SERVER_PORT").unwrap_or_else(|_| "8080".to_string());
+            format!("http://{}:{}", host, port)
+        });
+        let audience = env::var("JWT_AUDIENCE").unwrap_or_else(|_| "rscms".to_string());
+        let hours = |name: &str| env::var(name).ok().and_then(|value| value.parse::<i64>().ok()).filter(|value| *value > 0);
+
+        let mut config = Self::new(&secret, &issuer, &audience);
+        config.ttl_hours = hours("JWT_TTL_HOURS").unwrap_or(config.ttl_hours);
+        config.grace_hours = hours("JWT_KEY_GRACE_HOURS").unwrap_or(config.ttl_hours);
+        match env::var("JWT_KEYS").ok().filter(|value| !value.is_empty()) {
+            Some(keys) => {
+                let keys = parse_keys(&keys).unwrap_or_else(|e| panic!("Invalid JWT_KEYS: {}", e));
+                config.with_keys(keys)
+            }
+            None => {
+                log::warn!("JWT_KEYS is not set, signing tokens with the HS256 JWT_SECRET");
+                config
+            }
         }
     }
+
+    // 当前用于签名的密钥：已经生效的密钥中最新的一个
+    pub fn signing_key(&self, now: DateTime<Utc>) -> Result<&JwtKey, AppError> {
+        self.keys
+            .iter()
+            .rev()
+            .find(|key| key.active_from <= now)
+            .ok_or_else(|| AppError::Internal("No JWT signing key is active".to_string()))
+    }
+
+    // 已经被替换超过宽限期的密钥不再接受
+    fn is_retired(&self, index: usize, now: DateTime<Utc>) -> bool {
+        self.keys[index + 1..]
+            .iter()
+            .any(|newer| newer.active_from <= now - Duration::hours(self.grace_hours))
+    }
+
+    // 可以用于校验的密钥，未生效的密钥也会提前发布，方便其他服务缓存
+    fn verification_keys(&self, now: DateTime<Utc>) -> impl Iterator<Item = &JwtKey> {
+        self.keys
+            .iter()
+            .enumerate()
+            .filter(move |(index, _)| !self.is_retired(*index, now))
+            .map(|(_, key)| key)
+    }
+
+    // /.well-known/jwks.json 的内容
+    pub fn jwks(&self, now: DateTime<Utc>) -> JwkSet {
+        JwkSet {
+            keys: self.verification_keys(now).filter_map(|key| key.jwk.clone()).collect(),
+        }
+    }
+
+    pub fn encode(&self, claims: &Claims, now: DateTime<Utc>) -> Result<String, AppError> {
+        let key = self.signing_key(now)?;
+        let mut header = Header::new(key.algorithm);
+        header.kid = Some(key.kid.clone());
+        jsonwebtoken::encode(&header, claims, &key.encoding_key)
+            .map_err(|e| AppError::Internal(format!("Failed to create JWT token: {}", e)))
+    }
+
+    // 按 kid 选择密钥，并校验算法、签名、过期时间、issuer 和 audience
+    pub fn decode(&self, token: &str, now: DateTime<Utc>) -> Result<Claims, AppError> {
+        let invalid_token = || AppError::Unauthorized("Invalid token".to_string());
+
+        let header = jsonwebtoken::decode_header(token).map_err(|_| invalid_token())?;
+        let key = match &header.kid {
+            Some(kid) => self.verification_keys(now).find(|key| &key.kid == kid),
+            None => self.signing_key(now).ok(),
+        }
+        .ok_or_else(invalid_token)?;
+        // 算法由密钥决定，不信任 token 头部
+        if header.alg != key.algorithm {
+            return Err(invalid_token());
+        }
+
+        let mut validation = Validation::new(key.algorithm);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        jsonwebtoken::decode::<Claims>(token, &key.decoding_key, &validation)
+            .map(|token_data| token_data.claims)
+            .map_err(|_| invalid_token())
+    }
 }
 
+// JWT_KEYS 的格式为 kid=私钥文件[@生效时间]，多个密钥用逗号分隔，例如
+// 2026-01=/etc/rscms/jwt-2026-01.pem,2026-04=/etc/rscms/jwt-2026-04.pem@2026-04-01T00:00:00Z
+fn parse_keys(value: &str) -> Result<Vec<JwtKey>, anyhow::Error> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (kid, rest) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("expected kid=path, got {}", entry))?;
+            let (path, active_from) = match rest.split_once('@') {
+                Some((path, active_from)) => (path, Some(DateTime::parse_from_rfc3339(active_from)?.with_timezone(&Utc))),
+                None => (rest, None),
+            };
+            let pem = std::fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("failed to read {}: {}", path, e))?;
+            let key = JwtKey::from_pem(kid.trim(), &pem)?;
+            Ok(match active_from {
+                Some(active_from) => key.active_from(active_from),
+                None => key,
+            })
+        })
+        .collect()
+}
+
+// 认证方式（amr），参考 RFC 8176
+pub const AMR_EMAIL: &str = "email";  // 邮件验证码或登录链接
+pub const AMR_PASSWORD: &str = "pwd";
+pub const AMR_TOTP: &str = "otp";
+pub const AMR_RECOVERY_CODE: &str = "rec";
+pub const AMR_OIDC: &str = "oidc";  // 通过身份提供方单点登录
+pub const AMR_MFA: &str = "mfa";  // 身份提供方报告使用了多因素认证
+
+// 认证强度：单因素为 1，两个因素为 2
+pub const AAL_SINGLE_FACTOR: u8 = 1;
+pub const AAL_TWO_FACTOR: u8 = 2;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,  // user_id as string
     pub exp: usize,   // expiration time as usize
+    pub iat: usize,
+    pub iss: String,
+    pub aud: String,
+    #[serde(default)]
+    pub amr: Vec<String>,  // 本次登录使用的认证方式
+    #[serde(default)]
+    pub aal: u8,  // 认证强度
+}
+
+// 密码登录和重置的配置
+#[derive(Debug, Clone)]
+pub struct PasswordConfig {
+    pub min_length: usize,
+    // 前端的重置密码页面，token 作为查询参数附加在后面
+    pub reset_url: String,
+    pub reset_ttl_minutes: i64,
+}
+
+impl Default for PasswordConfig {
+    fn default() -> Self {
+        Self {
+            min_length: 10,
+            reset_url: "http://127.0.0.1:8080/reset-password".to_string(),
+            reset_ttl_minutes: 60,
+        }
+    }
+}
+
+impl PasswordConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let number = |name: &str| env::var(name).ok().and_then(|value| value.parse::<i64>().ok()).filter(|value| *value > 0);
+        Self {
+            min_length: number("PASSWORD_MIN_LENGTH").map(|value| value as usize).unwrap_or(defaults.min_length),
+            reset_url: env::var("PASSWORD_RESET_URL").unwrap_or_else(|_| {
+                let host = env::var("SERVER_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
+                let port = env::var("SERVER_PORT").unwrap_or_else(|_| "8080".to_string());
+                format!("http://{}:{}/reset-password", host, port)
+            }),
+            reset_ttl_minutes: number("PASSWORD_RESET_TTL_MINUTES").unwrap_or(defaults.reset_ttl_minutes),
+        }
+    }
+}
+
+// TOTP 两步验证的配置
+#[derive(Debug, Clone)]
+pub struct TwoFactorConfig {
+    // 验证器应用中显示的名称
+    pub issuer: String,
+    // 密码正确后输入第二个因素的时限
+    pub challenge_ttl_minutes: i64,
+    // 同一次登录中允许输错的次数
+    pub max_attempts: i32,
+}
+
+impl Default for TwoFactorConfig {
+    fn default() -> Self {
+        Self {
+            issuer: "RSCMS".to_string(),
+            challenge_ttl_minutes: 5,
+            max_attempts: 5,
+        }
+    }
+}
+
+impl TwoFactorConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            issuer: env::var("TOTP_ISSUER").unwrap_or(defaults.issuer),
+            challenge_ttl_minutes: env::var("TWO_FACTOR_CHALLENGE_TTL_MINUTES")
+                .ok()
+                .and_then(|value| value.parse::<i64>().ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(defaults.challenge_ttl_minutes),
+            max_attempts: defaults.max_attempts,
+        }
+    }
+}
+
+// 免密码登录链接的配置
+#[derive(Debug, Clone)]
+pub struct MagicLinkConfig {
+    // 邮件中的登录地址，token 作为查询参数附加在后面
+    pub url: String,
+    pub ttl_minutes: i64,
+    // 签名 token 和设备标识的密钥，默认使用 JWT 密钥
+    pub secret: String,
+    // 设备 cookie 是否只在 HTTPS 下发送
+    pub cookie_secure: bool,
+}
+
+impl Default for MagicLinkConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://127.0.0.1:8080/api/auth/magic".to_string(),
+            ttl_minutes: 15,
+            secret: "your-super-secret-and-ultra-long-secret-key".to_string(),
+            cookie_secure: false,
+        }
+    }
+}
+
+impl MagicLinkConfig {
+    pub fn from_env(jwt: &JwtConfig) -> Self {
+        let defaults = Self::default();
+        let url = env::var("MAGIC_LINK_URL").unwrap_or_else(|_| {
+            let host = env::var("SERVER_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
+            let port = env::var("SERVER_PORT").unwrap_or_else(|_| "8080".to_string());
+            format!("http://{}:{}/api/auth/magic", host, port)
+        });
+        Self {
+            cookie_secure: env::var("MAGIC_LINK_COOKIE_SECURE")
+                .map(|value| value == "true")
+                .unwrap_or_else(|_| url.starts_with("https://")),
+            url,
+            ttl_minutes: env::var("MAGIC_LINK_TTL_MINUTES")
+                .ok()
+                .and_then(|value| value.parse::<i64>().ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(defaults.ttl_minutes),
+            secret: env::var("MAGIC_LINK_SECRET").unwrap_or_else(|_| jwt.secret.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TEST_JWT_ED25519_KEY, TEST_JWT_ED25519_NEXT_KEY, TEST_JWT_RSA_KEY};
+
+    fn claims(config: &JwtConfig, now: DateTime<Utc>) -> Claims {
+        Claims {
+            sub: "1".to_string(),
+            exp: (now + Duration::hours(config.ttl_hours)).timestamp() as usize,
+            iat: now.timestamp() as usize,
+            iss: config.issuer.clone(),
+            aud: config.audience.clone(),
+            amr: vec![AMR_EMAIL.to_string()],
+            aal: AAL_SINGLE_FACTOR,
+        }
+    }
+
+    #[test]
+    fn rsa_and_ed25519_keys_sign_tokens_verifiable_from_the_jwks() {
+        let now = Utc::now();
+        for (pem, alg, kty) in [(TEST_JWT_RSA_KEY, "RS256", "RSA"), (TEST_JWT_ED25519_KEY, "EdDSA", "OKP")] {
+            let config = JwtConfig::new("secret", "https://cms.example.com", "rscms")
+                .with_keys(vec![JwtKey::from_pem("k1", pem).unwrap()]);
+            let token = config.encode(&claims(&config, now), now).unwrap();
+            assert_eq!(config.decode(&token, now).unwrap().sub, "1");
+
+            // 只用 JWKS 中的公钥也能校验
+            let jwks = serde_json::to_value(config.jwks(now)).unwrap();
+            assert_eq!(jwks["keys"][0]["kid"], "k1");
+            assert_eq!(jwks["keys"][0]["alg"], alg);
+            assert_eq!(jwks["keys"][0]["kty"], kty);
+            let jwk: Jwk = serde_json::from_value(jwks["keys"][0].clone()).unwrap();
+            let mut validation = Validation::new(config.signing_key(now).unwrap().algorithm);
+            validation.set_audience(&["rscms"]);
+            jsonwebtoken::decode::<Claims>(&token, &DecodingKey::from_jwk(&jwk).unwrap(), &validation).unwrap();
+        }
+
+        // HS256 密钥不会发布
+        let config = JwtConfig::new("secret", "https://cms.example.com", "rscms");
+        assert!(config.jwks(now).keys.is_empty());
+    }
+
+    #[test]
+    fn keys_rotate_on_schedule_and_old_keys_expire_after_the_grace_period() {
+        let now = Utc::now();
+        let rotation = now + Duration::hours(1);
+        let config = JwtConfig::new("secret", "https://cms.example.com", "rscms").with_keys(vec![
+            JwtKey::from_pem("next", TEST_JWT_ED25519_NEXT_KEY).unwrap().active_from(rotation),
+            JwtKey::from_pem("current", TEST_JWT_ED25519_KEY).unwrap(),
+        ]);
+        let kids = |at| config.jwks(at).keys.into_iter().filter_map(|jwk| jwk.common.key_id).collect::<Vec<_>>();
+
+        // 新密钥生效前已经发布，但还不用于签名
+        assert_eq!(config.signing_key(now).unwrap().kid, "current");
+        assert_eq!(kids(now), ["current", "next"]);
+        let old_token = config.encode(&claims(&config, now), now).unwrap();
+
+        let after_rotation = rotation + Duration::minutes(1);
+        assert_eq!(config.signing_key(after_rotation).unwrap().kid, "next");
+        assert!(config.decode(&old_token, after_rotation).is_ok());
+        let new_token = config.encode(&claims(&config, now), after_rotation).unwrap();
+        assert_eq!(jsonwebtoken::decode_header(&new_token).unwrap().kid.as_deref(), Some("next"));
+
+        let after_grace = rotation + Duration::hours(config.grace_hours) + Duration::minutes(1);
+        assert_eq!(kids(after_grace), ["next"]);
+        assert!(config.decode(&old_token, after_grace).is_err());
+        assert!(config.decode(&new_token, after_grace).is_ok());
+    }
+
+    #[test]
+    fn tokens_for_another_issuer_audience_or_algorithm_are_rejected() {
+        let now = Utc::now();
+        let config = JwtConfig::new("secret", "https://cms.example.com", "rscms")
+            .with_keys(vec![JwtKey::from_pem("k1", TEST_JWT_ED25519_KEY).unwrap()]);
+
+        let mut other_audience = claims(&config, now);
+        other_audience.aud = "billing".to_string();
+        assert!(config.decode(&config.encode(&other_audience, now).unwrap(), now).is_err());
+
+        let mut other_issuer = claims(&config, now);
+        other_issuer.iss = "https://evil.example.com".to_string();
+        assert!(config.decode(&config.encode(&other_issuer, now).unwrap(), now).is_err());
+
+        // 用 HS256 冒充该 kid
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some("k1".to_string());
+        let forged =
+            jsonwebtoken::encode(&header, &claims(&config, now), &EncodingKey::from_secret(b"secret")).unwrap();
+        assert!(config.decode(&forged, now).is_err());
+    }
 }
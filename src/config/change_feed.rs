@@ -0,0 +1,39 @@
+use std::env;
+use std::time::Duration;
+
+// 应用变更推送的配置
+#[derive(Debug, Clone)]
+pub struct ChangeFeedConfig {
+    // 事件保留天数，断线的客户端可以在此期间内续传，为 0 时不自动删除
+    pub retention_days: i64,
+    // 订阅者定期查询事件表，多实例部署时也能收到其他实例写入的事件
+    pub poll_interval: Duration,
+}
+
+impl Default for ChangeFeedConfig {
+    fn default() -> Self {
+        Self {
+            retention_days: 7,
+            poll_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+impl ChangeFeedConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            retention_days: env::var("CHANGE_FEED_RETENTION_DAYS")
+                .ok()
+                .and_then(|value| value.parse::<i64>().ok())
+                .filter(|value| *value >= 0)
+                .unwrap_or(defaults.retention_days),
+            poll_interval: env::var("CHANGE_FEED_POLL_INTERVAL_SECS")
+                .ok()
+                .and_then(|value| value.parse::<u64>().ok())
+                .filter(|value| *value > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.poll_interval),
+        }
+    }
+}
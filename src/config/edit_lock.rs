@@ -0,0 +1,39 @@
+use std::env;
+use std::time::Duration;
+
+// 文章编辑锁的配置
+#[derive(Debug, Clone)]
+pub struct EditLockConfig {
+    // 超过有效期没有心跳续期的锁自动释放
+    pub ttl_secs: i64,
+    // 后台检查过期锁并通知编辑者的间隔
+    pub sweep_interval: Duration,
+}
+
+impl Default for EditLockConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 90,
+            sweep_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+impl EditLockConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            ttl_secs: env::var("EDIT_LOCK_TTL_SECS")
+                .ok()
+                .and_then(|value| value.parse::<i64>().ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(defaults.ttl_secs),
+            sweep_interval: env::var("EDIT_LOCK_SWEEP_INTERVAL_SECS")
+                .ok()
+                .and_then(|value| value.parse::<u64>().ok())
+                .filter(|value| *value > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.sweep_interval),
+        }
+    }
+}
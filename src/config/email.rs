@@ -0,0 +1,172 @@
+use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+// SMTP 连接的加密方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpTls {
+    // 直接建立 TLS 连接（通常是 465 端口）
+    Implicit,
+    // 先明文连接再升级（通常是 587 端口）
+    StartTls,
+    // 不加密，仅用于 MailHog 等本地中继
+    None,
+}
+
+impl SmtpTls {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "implicit" | "tls" | "wrapper" => Some(SmtpTls::Implicit),
+            "starttls" => Some(SmtpTls::StartTls),
+            "none" | "plain" => Some(SmtpTls::None),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub tls: SmtpTls,
+}
+
+// 邮件发送方式
+#[derive(Debug, Clone)]
+pub enum EmailTransportConfig {
+    Smtp(SmtpConfig),
+    // 把邮件写成 .eml 文件
+    File(PathBuf),
+    // 只打印到日志
+    Log,
+    // 保存在内存中，用于测试
+    Memory,
+}
+
+#[derive(Debug, Clone)]
+pub struct EmailConfig {
+    pub from_email: String,
+    // 未指定应用时邮件中使用的品牌
+    pub brand_name: String,
+    pub brand_logo_url: Option<String>,
+    pub transport: EmailTransportConfig,
+}
+
+impl EmailConfig {
+    pub fn from_env() -> Result<Self, anyhow::Error> {
+        let from_email = env::var("SMTP_FROM_EMAIL").unwrap_or_else(|_| "noreply@localhost".to_string());
+
+        // 未指定 EMAIL_TRANSPORT 时，配置了 SMTP_HOST 就使用 SMTP，否则只打印日志
+        let kind = env::var("EMAIL_TRANSPORT").unwrap_or_else(|_| {
+            if env::var("SMTP_HOST").is_ok() {
+                "smtp".to_string()
+            } else {
+                "log".to_string()
+            }
+        });
+
+        let transport = match kind.as_str() {
+            "smtp" => EmailTransportConfig::Smtp(Self::smtp_from_env()?),
+            "file" => EmailTransportConfig::File(PathBuf::from(
+                env::var("EMAIL_FILE_DIR").unwrap_or_else(|_| "./tmp/emails".to_string()),
+            )),
+            "log" => EmailTransportConfig::Log,
+            "memory" => EmailTransportConfig::Memory,
+            other => return Err(anyhow::anyhow!("Unsupported EMAIL_TRANSPORT: {}", other)),
+        };
+
+        Ok(Self {
+            from_email,
+            brand_name: env::var("EMAIL_BRAND_NAME").unwrap_or_else(|_| "RSCMS".to_string()),
+            brand_logo_url: env::var("EMAIL_BRAND_LOGO_URL").ok().filter(|value| !value.is_empty()),
+            transport,
+        })
+    }
+
+    fn smtp_from_env() -> Result<SmtpConfig, anyhow::Error> {
+        let host = env::var("SMTP_HOST").map_err(|_| anyhow::anyhow!("SMTP_HOST must be set"))?;
+        let port = match env::var("SMTP_PORT") {
+            Ok(value) => value
+                .parse::<u16>()
+                .map_err(|_| anyhow::anyhow!("SMTP_PORT must be a valid port number"))?,
+            Err(_) => 587,
+        };
+
+        // 未指定时按端口推断：465 使用隐式 TLS，其余使用 STARTTLS
+        let tls = match env::var("SMTP_TLS") {
+            Ok(value) => SmtpTls::parse(&value)
+                .ok_or_else(|| anyhow::anyhow!("Unsupported SMTP_TLS: {}", value))?,
+            Err(_) if port == 465 => SmtpTls::Implicit,
+            Err(_) => SmtpTls::StartTls,
+        };
+
+        Ok(SmtpConfig {
+            host,
+            port,
+            username: env::var("SMTP_USERNAME").ok().filter(|value| !value.is_empty()),
+            password: env::var("SMTP_PASSWORD").ok().filter(|value| !value.is_empty()),
+            tls,
+        })
+    }
+}
+
+// 发件箱 worker 的投递策略
+#[derive(Debug, Clone)]
+pub struct OutboxConfig {
+    // 没有待发邮件时的轮询间隔
+    pub poll_interval: Duration,
+    pub batch_size: i64,
+    // 达到次数后进入死信，需要管理员手动重试
+    pub max_attempts: i32,
+    // 第 n 次失败后等待 retry_base * 2^(n-1)，最多 retry_max
+    pub retry_base: Duration,
+    pub retry_max: Duration,
+    // 领取后的租约，worker 崩溃时过期的邮件会被重新领取
+    pub lease: Duration,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(5),
+            batch_size: 20,
+            max_attempts: 8,
+            retry_base: Duration::from_secs(30),
+            retry_max: Duration::from_secs(3600),
+            lease: Duration::from_secs(300),
+        }
+    }
+}
+
+impl OutboxConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let secs = |name: &str, default: Duration| {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.parse::<u64>().ok())
+                .filter(|value| *value > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(default)
+        };
+
+        Self {
+            poll_interval: secs("OUTBOX_POLL_INTERVAL_SECS", defaults.poll_interval),
+            batch_size: env::var("OUTBOX_BATCH_SIZE")
+                .ok()
+                .and_then(|value| value.parse::<i64>().ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(defaults.batch_size),
+            max_attempts: env::var("OUTBOX_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|value| value.parse::<i32>().ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(defaults.max_attempts),
+            retry_base: secs("OUTBOX_RETRY_BASE_SECS", defaults.retry_base),
+            retry_max: secs("OUTBOX_RETRY_MAX_SECS", defaults.retry_max),
+            lease: secs("OUTBOX_LEASE_SECS", defaults.lease),
+        }
+    }
+}
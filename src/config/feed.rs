@@ -0,0 +1,48 @@
+use std::env;
+
+// RSS、Atom 和 JSON Feed 订阅源的配置
+#[derive(Debug, Clone)]
+pub struct FeedConfig {
+    // 订阅源自身的地址前缀，如 http://127.0.0.1:8080/feeds
+    pub url: String,
+    // 文章链接，{app} 替换为应用标识，{id} 替换为文章 id
+    pub article_url: String,
+    // 每个订阅源最多包含的文章数
+    pub max_items: i64,
+    // 摘要模式下截取的字符数
+    pub summary_length: usize,
+}
+
+impl Default for FeedConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://127.0.0.1:8080/feeds".to_string(),
+            article_url: "http://127.0.0.1:8080/articles/{id}".to_string(),
+            max_items: 20,
+            summary_length: 280,
+        }
+    }
+}
+
+impl FeedConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let host = env::var("SERVER_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
+        let port = env::var("SERVER_PORT").unwrap_or_else(|_| "8080".to_string());
+        Self {
+            url: env::var("FEED_URL").unwrap_or_else(|_| format!("http://{}:{}/feeds", host, port)),
+            article_url: env::var("FEED_ARTICLE_URL")
+                .unwrap_or_else(|_| format!("http://{}:{}/articles/{{id}}", host, port)),
+            max_items: env::var("FEED_MAX_ITEMS")
+                .ok()
+                .and_then(|value| value.parse::<i64>().ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(defaults.max_items),
+            summary_length: env::var("FEED_SUMMARY_LENGTH")
+                .ok()
+                .and_then(|value| value.parse::<usize>().ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(defaults.summary_length),
+        }
+    }
+}
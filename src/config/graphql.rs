@@ -0,0 +1,26 @@
+use std::env;
+
+#[derive(Debug, Clone)]
+pub struct GraphqlConfig {
+    pub max_depth: usize,
+    pub max_complexity: usize,
+    // 持久化查询缓存的条目数
+    pub persisted_query_cache_size: usize,
+}
+
+impl GraphqlConfig {
+    pub fn from_env() -> Self {
+        let read = |name: &str, default: usize| {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.parse::<usize>().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            max_depth: read("GRAPHQL_MAX_DEPTH", 10),
+            max_complexity: read("GRAPHQL_MAX_COMPLEXITY", 1000),
+            persisted_query_cache_size: read("GRAPHQL_PERSISTED_QUERY_CACHE_SIZE", 1024),
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use std::env;
+use std::time::Duration;
+
+// 后台任务 worker 的配置
+#[derive(Debug, Clone)]
+pub struct JobConfig {
+    // 本进程处理的队列
+    pub queues: Vec<String>,
+    // 并发执行的任务数
+    pub concurrency: usize,
+    pub poll_interval: Duration,
+    // 第 n 次失败后等待 retry_base * 2^(n-1)，最多 retry_max
+    pub retry_base: Duration,
+    pub retry_max: Duration,
+    // 任务执行超过租约时会被其他 worker 重新领取
+    pub lease: Duration,
+    // 为 false 时 HTTP 服务不运行后台任务，需要单独启动 worker 模式
+    pub run_in_server: bool,
+    // 已完成任务的保留天数
+    pub retention_days: i64,
+}
+
+impl Default for JobConfig {
+    fn default() -> Self {
+        Self {
+            queues: vec!["default".to_string()],
+            concurrency: 4,
+            poll_interval: Duration::from_secs(1),
+            retry_base: Duration::from_secs(10),
+            retry_max: Duration::from_secs(3600),
+            lease: Duration::from_secs(600),
+            run_in_server: true,
+            retention_days: 7,
+        }
+    }
+}
+
+impl JobConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let number = |name: &str| env::var(name).ok().and_then(|value| value.parse::<u64>().ok()).filter(|value| *value > 0);
+        let secs = |name: &str, default: Duration| number(name).map(Duration::from_secs).unwrap_or(default);
+
+        let queues: Vec<String> = env::var("JOB_QUEUES")
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(|queue| queue.trim().to_string())
+                    .filter(|queue| !queue.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            queues: if queues.is_empty() { defaults.queues } else { queues },
+            concurrency: number("JOB_CONCURRENCY").map(|value| value as usize).unwrap_or(defaults.concurrency),
+            poll_interval: secs("JOB_POLL_INTERVAL_SECS", defaults.poll_interval),
+            retry_base: secs("JOB_RETRY_BASE_SECS", defaults.retry_base),
+            retry_max: secs("JOB_RETRY_MAX_SECS", defaults.retry_max),
+            lease: secs("JOB_LEASE_SECS", defaults.lease),
+            run_in_server: env::var("JOB_RUN_IN_SERVER")
+                .map(|value| value != "false" && value != "0")
+                .unwrap_or(defaults.run_in_server),
+            retention_days: number("JOB_RETENTION_DAYS").map(|value| value as i64).unwrap_or(defaults.retention_days),
+        }
+    }
+}
@@ -1,4 +1,17 @@
+pub mod archive;
+pub mod audit;
 pub mod auth;
+pub mod change_feed;
+pub mod edit_lock;
+pub mod email;
+pub mod feed;
+pub mod graphql;
+pub mod job;
+pub mod oidc;
+pub mod preview;
+pub mod rate_limit;
+pub mod sitemap;
+pub mod trash;
 
 use serde::Deserialize;
 use std::env;
@@ -0,0 +1,200 @@
+use std::env;
+
+use crate::models::{ROLE_ADMIN, ROLE_USER};
+
+// 一个 OpenID Connect 身份提供方
+#[derive(Debug, Clone)]
+pub struct OidcProviderConfig {
+    // 路由中使用的标识，例如 /api/auth/oidc/corp/authorize
+    pub name: String,
+    pub display_name: String,
+    // 发现文档位于 {issuer}/.well-known/openid-configuration
+    pub issuer: String,
+    pub client_id: String,
+    // 公共客户端只使用 PKCE，不需要密钥
+    pub client_secret: Option<String>,
+    pub scopes: Vec<String>,
+    // ID token 中保存用户组的声明
+    pub groups_claim: String,
+    // (组, 角色)，为空时不修改用户角色
+    pub role_mapping: Vec<(String, String)>,
+    // 没有对应用户时自动创建
+    pub auto_provision: bool,
+}
+
+impl OidcProviderConfig {
+    pub fn new(name: &str, issuer: &str, client_id: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            display_name: name.to_string(),
+            issuer: issuer.trim_end_matches('/').to_string(),
+            client_id: client_id.to_string(),
+            client_secret: None,
+            scopes: vec!["openid".to_string(), "email".to_string(), "profile".to_string()],
+            groups_claim: "groups".to_string(),
+            role_mapping: Vec::new(),
+            auto_provision: true,
+        }
+    }
+
+    // 变量名前缀为 OIDC_{NAME}_，缺少 ISSUER 或 CLIENT_ID 时返回 None
+    fn from_env(name: &str) -> Option<Self> {
+        let var = |key: &str| {
+            env::var(format!("OIDC_{}_{}", name.to_uppercase().replace('-', "_"), key))
+                .ok()
+                .filter(|value| !value.is_empty())
+        };
+        let mut provider = Self::new(name, &var("ISSUER")?, &var("CLIENT_ID")?);
+        provider.client_secret = var("CLIENT_SECRET");
+        if let Some(display_name) = var("DISPLAY_NAME") {
+            provider.display_name = display_name;
+        }
+        if let Some(scopes) = var("SCOPES") {
+            provider.scopes = scopes.split_whitespace().map(str::to_string).collect();
+            // openid 是必需的
+            if !provider.scopes.iter().any(|scope| scope == "openid") {
+                provider.scopes.insert(0, "openid".to_string());
+            }
+        }
+        if let Some(groups_claim) = var("GROUPS_CLAIM") {
+            provider.groups_claim = groups_claim;
+        }
+        if let Some(mapping) = var("ROLE_MAPPING") {
+            provider.role_mapping = parse_role_mapping(&mapping);
+        }
+        if let Some(auto_provision) = var("AUTO_PROVISION") {
+            provider.auto_provision = auto_provision == "true" || auto_provision == "1";
+        }
+        Some(provider)
+    }
+
+    // 任何一个组映射到管理员时为管理员，其余为普通用户；未配置映射时为 None
+    pub fn map_role(&self, groups: &[String]) -> Option<String> {
+        if self.role_mapping.is_empty() {
+            return None;
+        }
+        let admin = self
+            .role_mapping
+            .iter()
+            .any(|(group, role)| role == ROLE_ADMIN && groups.contains(group));
+        Some(if admin { ROLE_ADMIN } else { ROLE_USER }.to_string())
+    }
+}
+
+// 形如 "cms-admins=admin,editors=user"，忽略未知的角色
+pub fn parse_role_mapping(value: &str) -> Vec<(String, String)> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let parsed = entry
+                .split_once('=')
+                .map(|(group, role)| (group.trim().to_string(), role.trim().to_string()))
+                .filter(|(group, role)| !group.is_empty() && (role == ROLE_ADMIN || role == ROLE_USER));
+            if parsed.is_none() {
+                log::warn!("Ignoring invalid OIDC role mapping: {}", entry);
+            }
+            parsed
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    pub providers: Vec<OidcProviderConfig>,
+    // OIDC 路由的外部地址；回调地址为 {base_url}/{provider}/callback，需要在身份提供方登记
+    pub base_url: String,
+    // 从跳转到身份提供方到回调的时限
+    pub state_ttl_minutes: i64,
+    // state cookie 是否只在 HTTPS 下发送
+    pub cookie_secure: bool,
+}
+
+impl Default for OidcConfig {
+    fn default() -> Self {
+        Self {
+            providers: Vec::new(),
+            base_url: "http://127.0.0.1:8080/api/auth/oidc".to_string(),
+            state_ttl_minutes: 10,
+            cookie_secure: false,
+        }
+    }
+}
+
+impl OidcConfig {
+    // OIDC_PROVIDERS 使用 ',' 分隔提供方名称
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let providers = env::var("OIDC_PROVIDERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .filter_map(|name| {
+                let provider = OidcProviderConfig::from_env(name);
+                if provider.is_none() {
+                    log::warn!("Ignoring OIDC provider {}: ISSUER and CLIENT_ID are required", name);
+                }
+                provider
+            })
+            .collect();
+        let base_url = env::var("OIDC_BASE_URL").unwrap_or_else(|_| {
+            let host = env::var("SERVER_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
+            let port = env::var("SERVER_PORT").unwrap_or_else(|_| "8080".to_string());
+            format!("http://{}:{}/api/auth/oidc", host, port)
+        });
+        Self {
+            providers,
+            cookie_secure: base_url.starts_with("https://"),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            state_ttl_minutes: env::var("OIDC_STATE_TTL_MINUTES")
+                .ok()
+                .and_then(|value| value.parse::<i64>().ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(defaults.state_ttl_minutes),
+        }
+    }
+
+    pub fn provider(&self, name: &str) -> Option<&OidcProviderConfig> {
+        self.providers.iter().find(|provider| provider.name == name)
+    }
+
+    pub fn authorize_url(&self, provider: &str) -> String {
+        format!("{}/{}/authorize", self.base_url, provider)
+    }
+
+    pub fn callback_url(&self, provider: &str) -> String {
+        format!("{}/{}/callback", self.base_url, provider)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn role_mapping_ignores_unknown_roles() {
+        let mapping = parse_role_mapping("cms-admins=admin, editors = user,owners=root,broken");
+        assert_eq!(
+            mapping,
+            vec![
+                ("cms-admins".to_string(), "admin".to_string()),
+                ("editors".to_string(), "user".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn groups_map_to_the_highest_role() {
+        let mut provider = OidcProviderConfig::new("corp", "https://idp.example.com/", "cms");
+        assert_eq!(provider.issuer, "https://idp.example.com");
+        assert_eq!(provider.map_role(&["cms-admins".to_string()]), None);
+
+        provider.role_mapping = parse_role_mapping("cms-admins=admin,editors=user");
+        let groups = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+        assert_eq!(provider.map_role(&groups(&["editors", "cms-admins"])).as_deref(), Some(ROLE_ADMIN));
+        assert_eq!(provider.map_role(&groups(&["editors"])).as_deref(), Some(ROLE_USER));
+        assert_eq!(provider.map_role(&[]).as_deref(), Some(ROLE_USER));
+    }
+}
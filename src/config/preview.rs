@@ -0,0 +1,49 @@
+use crate::config::auth::JwtConfig;
+use std::env;
+
+// 草稿预览链接的配置
+#[derive(Debug, Clone)]
+pub struct PreviewConfig {
+    // 分享给他人的预览地址，token 附加在后面
+    pub url: String,
+    // 创建时未指定有效期时使用
+    pub default_ttl_secs: i64,
+    pub max_ttl_secs: i64,
+    // 签名 token 的密钥，默认使用 JWT 密钥
+    pub secret: String,
+}
+
+impl Default for PreviewConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://127.0.0.1:8080/previews".to_string(),
+            default_ttl_secs: 7 * 24 * 3600,
+            max_ttl_secs: 30 * 24 * 3600,
+            secret: "your-super-secret-and-ultra-long-secret-key".to_string(),
+        }
+    }
+}
+
+impl PreviewConfig {
+    pub fn from_env(jwt: &JwtConfig) -> Self {
+        let defaults = Self::default();
+        let ttl = |name: &str, default: i64| {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.parse::<i64>().ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(default)
+        };
+        let max_ttl_secs = ttl("PREVIEW_LINK_MAX_TTL_SECS", defaults.max_ttl_secs);
+        Self {
+            url: env::var("PREVIEW_URL").unwrap_or_else(|_| {
+                let host = env::var("SERVER_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
+                let port = env::var("SERVER_PORT").unwrap_or_else(|_| "8080".to_string());
+                format!("http://{}:{}/previews", host, port)
+            }),
+            default_ttl_secs: ttl("PREVIEW_LINK_TTL_SECS", defaults.default_ttl_secs).min(max_ttl_secs),
+            max_ttl_secs,
+            secret: env::var("PREVIEW_SECRET").unwrap_or_else(|_| jwt.secret.clone()),
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use std::env;
+use std::time::Duration;
+
+// 限流键的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitKey {
+    Ip,
+    Email,
+    User,
+    ApiKey,
+}
+
+impl RateLimitKey {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitKey::Ip => "ip",
+            RateLimitKey::Email => "email",
+            RateLimitKey::User => "user",
+            RateLimitKey::ApiKey => "api_key",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "ip" => Some(RateLimitKey::Ip),
+            "email" => Some(RateLimitKey::Email),
+            "user" => Some(RateLimitKey::User),
+            "api_key" => Some(RateLimitKey::ApiKey),
+            _ => None,
+        }
+    }
+}
+
+// 令牌桶配额：period 时间内最多 limit 次请求
+#[derive(Debug, Clone, Copy)]
+pub struct Quota {
+    pub limit: u32,
+    pub period: Duration,
+}
+
+impl Quota {
+    pub fn new(limit: u32, period_secs: u64) -> Self {
+        Self {
+            limit,
+            period: Duration::from_secs(period_secs),
+        }
+    }
+
+    // 每秒补充的令牌数
+    pub fn refill_rate(&self) -> f64 {
+        self.limit as f64 / self.period.as_secs_f64()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitRule {
+    pub method: String,
+    pub path: String,
+    pub key: RateLimitKey,
+    pub quota: Quota,
+}
+
+impl RateLimitRule {
+    pub fn new(method: &str, path: &str, key: RateLimitKey, quota: Quota) -> Self {
+        Self {
+            method: method.to_uppercase(),
+            path: path.to_string(),
+            key,
+            quota,
+        }
+    }
+
+    // 规则格式: "POST /api/auth/login ip 20/60"
+    fn parse(value: &str) -> Option<Self> {
+        let parts: Vec<&str> = value.split_whitespace().collect();
+        if parts.len() != 4 {
+            return None;
+        }
+        let key = RateLimitKey::parse(parts[2])?;
+        let (limit, period) = parts[3].split_once('/')?;
+        let limit = limit.parse::<u32>().ok()?;
+        let period = period.parse::<u64>().ok()?;
+        if limit == 0 || period == 0 {
+            return None;
+        }
+        Some(Self::new(parts[0], parts[1], key, Quota::new(limit, period)))
+    }
+
+    // 以 /* 结尾的规则匹配该前缀下的所有路径
+    pub fn matches(&self, method: &str, path: &str) -> bool {
+        let path = path.trim_end_matches('/');
+        self.method == method
+            && match self.path.strip_suffix("/*") {
+                Some(prefix) => path.starts_with(prefix) && path[prefix.len()..].starts_with('/'),
+                None => self.path == path,
+            }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RateLimitBackend {
+    Memory,
+    Redis(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub backend: RateLimitBackend,
+    pub rules: Vec<RateLimitRule>,
+    // 验证码允许的最大错误次数，超过后验证码失效
+    pub max_verification_attempts: i32,
+    // 部署在反向代理之后时，从 X-Forwarded-For 读取客户端 IP
+    pub trust_proxy: bool,
+}
+
+impl RateLimitConfig {
+    pub fn from_env() -> Self {
+        let backend = match env::var("RATE_LIMIT_BACKEND").as_deref() {
+            Ok("redis") => RateLimitBackend::Redis(
+                env::var("RATE_LIMIT_REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string()),
+            ),
+            _ => RateLimitBackend::Memory,
+        };
+
+        // RATE_LIMIT_RULES 使用 ';' 分隔多条规则，设置后替换默认规则
+        let rules = match env::var("RATE_LIMIT_RULES") {
+            Ok(value) => value
+                .split(';')
+                .map(str::trim)
+                .filter(|rule| !rule.is_empty())
+                .filter_map(|rule| {
+                    let parsed = RateLimitRule::parse(rule);
+                    if parsed.is_none() {
+                        log::warn!("Ignoring invalid rate limit rule: {}", rule);
+                    }
+                    parsed
+                })
+                .collect(),
+            Err(_) => Self::default_rules(),
+        };
+
+        let max_verification_attempts = env::var("MAX_VERIFICATION_ATTEMPTS")
+            .ok()
+            .and_then(|value| value.parse::<i32>().ok())
+            .filter(|value| *value > 0)
+            .unwrap_or(5);
+
+        let trust_proxy = env::var("RATE_LIMIT_TRUST_PROXY")
+            .map(|value| value == "true" || value == "1")
+            .unwrap_or(false);
+
+        Self {
+            backend,
+            rules,
+            max_verification_attempts,
+            trust_proxy,
+        }
+    }
+
+    pub fn default_rules() -> Vec<RateLimitRule> {
+        vec![
+            RateLimitRule::new("POST", "/api/auth/verification-code", RateLimitKey::Email, Quota::new(3, 600)),
+            RateLimitRule::new("POST", "/api/auth/verification-code", RateLimitKey::Ip, Quota::new(20, 3600)),
+            RateLimitRule::new("POST", "/api/auth/register", RateLimitKey::Email, Quota::new(3, 600)),
+            RateLimitRule::new("POST", "/api/auth/register", RateLimitKey::Ip, Quota::new(10, 3600)),
+            RateLimitRule::new("POST", "/api/auth/login", RateLimitKey::Email, Quota::new(10, 600)),
+            RateLimitRule::new("POST", "/api/auth/login", RateLimitKey::Ip, Quota::new(30, 600)),
+            RateLimitRule::new("POST", "/api/auth/magic-link", RateLimitKey::Email, Quota::new(3, 600)),
+            RateLimitRule::new("POST", "/api/auth/magic-link", RateLimitKey::Ip, Quota::new(20, 3600)),
+            RateLimitRule::new("GET", "/api/auth/magic", RateLimitKey::Ip, Quota::new(30, 600)),
+            RateLimitRule::new("POST", "/api/auth/password/login", RateLimitKey::Email, Quota::new(10, 600)),
+            RateLimitRule::new("POST", "/api/auth/password/login", RateLimitKey::Ip, Quota::new(30, 600)),
+            RateLimitRule::new("POST", "/api/auth/2fa/login", RateLimitKey::Ip, Quota::new(30, 600)),
+            RateLimitRule::new("POST", "/api/auth/password/forgot", RateLimitKey::Email, Quota::new(3, 600)),
+            RateLimitRule::new("POST", "/api/auth/password/forgot", RateLimitKey::Ip, Quota::new(20, 3600)),
+            RateLimitRule::new("POST", "/api/auth/password/reset", RateLimitKey::Ip, Quota::new(30, 600)),
+            RateLimitRule::new("GET", "/api/auth/oidc/*", RateLimitKey::Ip, Quota::new(60, 600)),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcard_rules_match_paths_under_the_prefix() {
+        let rule = RateLimitRule::parse("GET /api/auth/oidc/* ip 60/600").unwrap();
+        assert!(rule.matches("GET", "/api/auth/oidc/corp/callback"));
+        assert!(!rule.matches("GET", "/api/auth/oidc"));
+        assert!(!rule.matches("GET", "/api/auth/oidcx/corp"));
+        assert!(!rule.matches("POST", "/api/auth/oidc/corp/callback"));
+
+        let exact = RateLimitRule::parse("POST /api/auth/login email 10/600").unwrap();
+        assert!(exact.matches("POST", "/api/auth/login/"));
+        assert!(!exact.matches("POST", "/api/auth/login/extra"));
+    }
+}
@@ -0,0 +1,42 @@
+use std::env;
+
+// 站点地图的配置
+#[derive(Debug, Clone)]
+pub struct SitemapConfig {
+    // 站点地图自身的地址前缀，如 http://127.0.0.1:8080/sitemaps
+    pub url: String,
+    // 文章在应用 base_url 下的路径，{id} 替换为文章 id
+    pub article_path: String,
+    // 每个站点地图最多包含的地址数，超过时拆分并返回站点地图索引
+    pub max_urls: i64,
+}
+
+// 协议规定单个站点地图最多 50000 个地址
+pub const MAX_URLS_PER_SITEMAP: i64 = 50_000;
+
+impl Default for SitemapConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://127.0.0.1:8080/sitemaps".to_string(),
+            article_path: "/articles/{id}".to_string(),
+            max_urls: MAX_URLS_PER_SITEMAP,
+        }
+    }
+}
+
+impl SitemapConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let host = env::var("SERVER_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
+        let port = env::var("SERVER_PORT").unwrap_or_else(|_| "8080".to_string());
+        Self {
+            url: env::var("SITEMAP_URL").unwrap_or_else(|_| format!("http://{}:{}/sitemaps", host, port)),
+            article_path: env::var("SITEMAP_ARTICLE_PATH").unwrap_or(defaults.article_path),
+            max_urls: env::var("SITEMAP_MAX_URLS")
+                .ok()
+                .and_then(|value| value.parse::<i64>().ok())
+                .filter(|value| *value > 0 && *value <= MAX_URLS_PER_SITEMAP)
+                .unwrap_or(defaults.max_urls),
+        }
+    }
+}
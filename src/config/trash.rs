@@ -0,0 +1,27 @@
+use std::env;
+
+// 回收站的配置
+#[derive(Debug, Clone)]
+pub struct TrashConfig {
+    // 移入回收站超过保留天数的应用和文章由后台任务永久删除，为 0 时不自动删除
+    pub retention_days: i64,
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        Self { retention_days: 30 }
+    }
+}
+
+impl TrashConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            retention_days: env::var("TRASH_RETENTION_DAYS")
+                .ok()
+                .and_then(|value| value.parse::<i64>().ok())
+                .filter(|value| *value >= 0)
+                .unwrap_or(defaults.retention_days),
+        }
+    }
+}
@@ -0,0 +1,1395 @@
+use super::{
+    ApiKeyRepository, AppFilter, AppRepository, ArticleFilter, ArticleRepository, AuditFilter, AuditRepository,
+    ChangeEventRepository, JobFilter, JobRepository, NewUser, OutboxFilter, OutboxRepository, Page,
+    PreviewLinkRepository, UserRepository,
+};
+use crate::models::api_key::{ApiKey, NewApiKey};
+use crate::models::audit::{AuditLog, NewAuditLog};
+use crate::models::change::{
+    ChangeEvent, NewChangeEvent, APP_CREATED, APP_DELETED, APP_RESTORED, APP_UPDATED, ARTICLE_CREATED,
+    ARTICLE_DELETED, ARTICLE_PUBLISHED, ARTICLE_RESTORED, ARTICLE_UPDATED,
+};
+use crate::models::credential::{NewUserToken, UserToken};
+use crate::models::magic_link::{MagicLink, NewMagicLink};
+use crate::models::oidc::{NewFederatedUser, NewOidcState, NewUserIdentity, OidcState, UserIdentity};
+use crate::models::job::{JobCount, JobRecord, NewJob, JOB_COMPLETED, JOB_DEAD, JOB_PENDING, JOB_RUNNING};
+use crate::models::article::{CreateArticleRequest, UpdateArticleRequest};
+use crate::models::outbox::{EmailMessage, OutboxEmail, OUTBOX_DEAD, OUTBOX_PENDING, OUTBOX_SENDING, OUTBOX_SENT};
+use crate::models::preview::{NewPreviewLink, PreviewLink};
+use crate::models::{App, Article, CreateAppRequest, UpdateAppRequest, User};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// 内存实现，用于单元测试和不需要持久化的场景
+#[derive(Clone, Default)]
+pub struct MemoryRepository {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    users: Vec<User>,
+    apps: Vec<App>,
+    articles: Vec<Article>,
+    outbox: Vec<OutboxEmail>,
+    jobs: Vec<JobRecord>,
+    audit_logs: Vec<AuditLog>,
+    api_keys: Vec<ApiKey>,
+    change_events: Vec<ChangeEvent>,
+    preview_links: Vec<PreviewLink>,
+    magic_links: Vec<MagicLink>,
+    // (user_id, code_hash, used_at)
+    recovery_codes: Vec<(i64, String, Option<DateTime<Utc>>)>,
+    tokens: Vec<UserToken>,
+    oidc_states: Vec<OidcState>,
+    identities: Vec<UserIdentity>,
+    schedules: HashMap<String, DateTime<Utc>>,
+    next_id: i64,
+}
+
+impl MemoryState {
+    fn next_id(&mut self) -> i64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn enqueue(&mut self, message: &EmailMessage, now: DateTime<Utc>) -> i64 {
+        let id = self.next_id();
+        self.outbox.push(OutboxEmail {
+            id,
+            message: message.clone(),
+            status: OUTBOX_PENDING.to_string(),
+            attempts: 0,
+            last_error: None,
+            next_attempt_at: now,
+            locked_until: None,
+            sent_at: None,
+            created_at: now,
+            updated_at: now,
+        });
+        id
+    }
+
+    fn record_change(&mut self, event: NewChangeEvent, now: DateTime<Utc>) {
+        let id = self.next_id();
+        self.change_events.push(ChangeEvent {
+            id,
+            app_id: event.app_id,
+            event_type: event.event_type,
+            target_type: event.target_type,
+            target_id: event.target_id,
+            created_at: now,
+        });
+    }
+
+    // 属于应用的文章才有变更事件
+    fn record_article_change(&mut self, article_id: i64, event_type: &str, now: DateTime<Utc>) {
+        let app_id = self.articles.iter().find(|article| article.id == article_id).and_then(|a| a.app_id);
+        if let Some(app_id) = app_id {
+            self.record_change(NewChangeEvent::article(app_id, article_id, event_type), now);
+        }
+    }
+
+    fn link_identity(&mut self, user_id: i64, identity: &NewUserIdentity) -> i64 {
+        let id = self.next_id();
+        let now = Utc::now();
+        self.identities.push(UserIdentity {
+            id,
+            user_id,
+            provider: identity.provider.clone(),
+            subject: identity.subject.clone(),
+            email: identity.email.clone(),
+            created_at: now,
+            last_login_at: now,
+        });
+        id
+    }
+}
+
+impl MemoryRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn update_user(&self, id: i64, update: impl FnOnce(&mut User)) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(user) = state.users.iter_mut().find(|user| user.id == id) {
+            update(user);
+            user.updated_at = Utc::now();
+        }
+    }
+}
+
+// 与 SQL 的 LIKE 一样不区分大小写
+fn contains(haystack: &str, keyword: &str) -> bool {
+    haystack.to_lowercase().contains(&keyword.to_lowercase())
+}
+
+fn paginate<T>(items: Vec<T>, page: Page) -> Vec<T> {
+    items
+        .into_iter()
+        .skip(page.offset.max(0) as usize)
+        .take(page.limit.max(0) as usize)
+        .collect()
+}
+
+fn app_matches(app: &App, filter: &AppFilter) -> bool {
+    app.deleted_at.is_some() == filter.trashed
+        && filter.keyword.as_deref().is_none_or(|keyword| contains(&app.name, keyword))
+        && filter.identifier.as_deref().is_none_or(|identifier| app.identifier == identifier)
+        && filter.creator_id.is_none_or(|creator_id| app.creator_id == creator_id)
+}
+
+fn article_matches(article: &Article, viewer_id: i64, filter: &ArticleFilter) -> bool {
+    article.deleted_at.is_none()
+        && (article.status == 2 || article.author_id == viewer_id)
+        && filter.keyword.as_deref().is_none_or(|keyword| contains(&article.title, keyword))
+        && filter.status.is_none_or(|status| article.status == status)
+        && filter.author_id.is_none_or(|author_id| article.author_id == author_id)
+        && filter.app_id.is_none_or(|app_id| article.app_id == Some(app_id))
+        && filter.category.as_deref().is_none_or(|category| article.category.as_deref() == Some(category))
+        && (!filter.indexable || article.noindex == 0)
+}
+
+fn audit_matches(log: &AuditLog, filter: &AuditFilter) -> bool {
+    filter.actor_id.is_none_or(|actor_id| log.actor_id == Some(actor_id))
+        && filter.action.as_deref().is_none_or(|action| log.action == action)
+        && filter.target_type.as_deref().is_none_or(|target_type| log.target_type == target_type)
+        && filter.target_id.is_none_or(|target_id| log.target_id == Some(target_id))
+        && filter.app_id.is_none_or(|app_id| log.app_id == Some(app_id))
+        && filter.since.is_none_or(|since| log.created_at >= since)
+        && filter.until.is_none_or(|until| log.created_at < until)
+}
+
+// 最新的排在前面
+fn newest_first<T>(items: &mut [T], key: impl Fn(&T) -> (DateTime<Utc>, i64)) {
+    items.sort_by_key(|item| std::cmp::Reverse(key(item)));
+}
+
+#[async_trait]
+impl UserRepository for MemoryRepository {
+    async fn find_by_id(&self, id: i64) -> Result<Option<User>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.users.iter().find(|user| user.id == id).cloned())
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.users.iter().find(|user| user.email == email).cloned())
+    }
+
+    async fn find_by_ids(&self, ids: &[i64]) -> Result<Vec<User>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.users.iter().filter(|user| ids.contains(&user.id)).cloned().collect())
+    }
+
+    async fn create(&self, user: NewUser, message: &EmailMessage) -> Result<i64, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id();
+        let now = Utc::now();
+        state.users.push(User {
+            id,
+            username: user.username,
+            email: user.email,
+            email_verified: 0,
+            verification_code: Some(user.verification_code),
+            verification_code_expires_at: Some(user.verification_code_expires_at),
+            verification_attempts: 0,
+            locale: user.locale,
+            role: user.role,
+            password_hash: None,
+            totp_secret: None,
+            totp_enabled: 0,
+            totp_last_step: None,
+            created_at: now,
+            updated_at: now,
+        });
+        state.enqueue(message, now);
+        Ok(id)
+    }
+
+    async fn set_verification_code(
+        &self,
+        email: &str,
+        code: &str,
+        expires_at: DateTime<Utc>,
+        message: &EmailMessage,
+    ) -> Result<(), sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let now = Utc::now();
+        if let Some(user) = state.users.iter_mut().find(|user| user.email == email) {
+            user.verification_code = Some(code.to_string());
+            user.verification_code_expires_at = Some(expires_at);
+            user.verification_attempts = 0;
+            user.updated_at = now;
+        }
+        state.enqueue(message, now);
+        Ok(())
+    }
+
+    async fn record_failed_attempt(&self, id: i64, max_attempts: i32) -> Result<(), sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(user) = state.users.iter_mut().find(|user| user.id == id) {
+            user.verification_attempts += 1;
+            if user.verification_attempts >= max_attempts {
+                user.verification_code = None;
+                user.verification_code_expires_at = None;
+            }
+        }
+        Ok(())
+    }
+
+    async fn mark_verified(&self, id: i64, welcome: Option<&EmailMessage>) -> Result<(), sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let now = Utc::now();
+        if let Some(user) = state.users.iter_mut().find(|user| user.id == id) {
+            user.email_verified = 1;
+            user.verification_code = None;
+            user.verification_code_expires_at = None;
+            user.verification_attempts = 0;
+            user.updated_at = now;
+        }
+        state.magic_links.retain(|link| link.user_id != id || link.used_at.is_some());
+        if let Some(welcome) = welcome {
+            state.enqueue(welcome, now);
+        }
+        Ok(())
+    }
+
+    async fn create_magic_link(&self, link: &NewMagicLink, code: &str, message: &EmailMessage) -> Result<i64, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let now = Utc::now();
+        state.magic_links.retain(|existing| existing.user_id != link.user_id || existing.used_at.is_some());
+        let id = state.next_id();
+        state.magic_links.push(MagicLink {
+            id,
+            user_id: link.user_id,
+            token_hash: link.token_hash.clone(),
+            device_hash: link.device_hash.clone(),
+            expires_at: link.expires_at,
+            used_at: None,
+            created_at: now,
+        });
+        if let Some(user) = state.users.iter_mut().find(|user| user.id == link.user_id) {
+            user.verification_code = Some(code.to_string());
+            user.verification_code_expires_at = Some(link.expires_at);
+            user.verification_attempts = 0;
+            user.updated_at = now;
+        }
+        state.enqueue(message, now);
+        Ok(id)
+    }
+
+    async fn find_magic_link(&self, token_hash: &str) -> Result<Option<MagicLink>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.magic_links.iter().find(|link| link.token_hash == token_hash).cloned())
+    }
+
+    async fn consume_magic_link(&self, id: i64, now: DateTime<Utc>) -> Result<bool, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        match state.magic_links.iter_mut().find(|link| link.id == id && link.used_at.is_none()) {
+            Some(link) => {
+                link.used_at = Some(now);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn set_password(&self, id: i64, password_hash: &str) -> Result<(), sqlx::Error> {
+        self.update_user(id, |user| user.password_hash = Some(password_hash.to_string()));
+        Ok(())
+    }
+
+    async fn set_totp_secret(&self, id: i64, secret: &str) -> Result<(), sqlx::Error> {
+        self.update_user(id, |user| {
+            user.totp_secret = Some(secret.to_string());
+            user.totp_enabled = 0;
+            user.totp_last_step = None;
+        });
+        Ok(())
+    }
+
+    async fn enable_totp(&self, id: i64, recovery_code_hashes: &[String]) -> Result<(), sqlx::Error> {
+        self.update_user(id, |user| user.totp_enabled = 1);
+        self.replace_recovery_codes(id, recovery_code_hashes).await
+    }
+
+    async fn disable_totp(&self, id: i64) -> Result<(), sqlx::Error> {
+        self.update_user(id, |user| {
+            user.totp_secret = None;
+            user.totp_enabled = 0;
+            user.totp_last_step = None;
+        });
+        self.replace_recovery_codes(id, &[]).await
+    }
+
+    async fn replace_recovery_codes(&self, id: i64, recovery_code_hashes: &[String]) -> Result<(), sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        state.recovery_codes.retain(|(user_id, _, _)| *user_id != id);
+        state
+            .recovery_codes
+            .extend(recovery_code_hashes.iter().map(|code_hash| (id, code_hash.clone(), None)));
+        Ok(())
+    }
+
+    async fn use_recovery_code(&self, id: i64, code_hash: &str, now: DateTime<Utc>) -> Result<bool, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        match state
+            .recovery_codes
+            .iter_mut()
+            .find(|(user_id, hash, used_at)| *user_id == id && hash == code_hash && used_at.is_none())
+        {
+            Some((_, _, used_at)) => {
+                *used_at = Some(now);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn count_recovery_codes(&self, id: i64) -> Result<i64, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .recovery_codes
+            .iter()
+            .filter(|(user_id, _, used_at)| *user_id == id && used_at.is_none())
+            .count() as i64)
+    }
+
+    async fn record_totp_step(&self, id: i64, step: i64) -> Result<bool, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        match state.users.iter_mut().find(|user| user.id == id) {
+            Some(user) if user.totp_last_step.is_none_or(|last| last < step) => {
+                user.totp_last_step = Some(step);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn create_token(&self, token: &NewUserToken, message: Option<&EmailMessage>) -> Result<i64, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let now = Utc::now();
+        state
+            .tokens
+            .retain(|existing| existing.user_id != token.user_id || existing.purpose != token.purpose);
+        let id = state.next_id();
+        state.tokens.push(UserToken {
+            id,
+            user_id: token.user_id,
+            purpose: token.purpose.clone(),
+            token_hash: token.token_hash.clone(),
+            attempts: 0,
+            expires_at: token.expires_at,
+            used_at: None,
+            created_at: now,
+        });
+        if let Some(message) = message {
+            state.enqueue(message, now);
+        }
+        Ok(id)
+    }
+
+    async fn find_token(&self, purpose: &str, token_hash: &str) -> Result<Option<UserToken>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .tokens
+            .iter()
+            .find(|token| token.purpose == purpose && token.token_hash == token_hash)
+            .cloned())
+    }
+
+    async fn consume_token(&self, id: i64, now: DateTime<Utc>) -> Result<bool, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        match state.tokens.iter_mut().find(|token| token.id == id && token.used_at.is_none()) {
+            Some(token) => {
+                token.used_at = Some(now);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn record_token_failure(&self, id: i64, max_attempts: i32, now: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(token) = state.tokens.iter_mut().find(|token| token.id == id) {
+            token.attempts += 1;
+            if token.attempts >= max_attempts {
+                token.used_at = token.used_at.or(Some(now));
+            }
+        }
+        Ok(())
+    }
+
+    async fn set_role(&self, id: i64, role: &str) -> Result<(), sqlx::Error> {
+        self.update_user(id, |user| user.role = role.to_string());
+        Ok(())
+    }
+
+    async fn create_oidc_state(&self, state: &NewOidcState, now: DateTime<Utc>) -> Result<i64, sqlx::Error> {
+        let mut memory = self.state.lock().unwrap();
+        memory.oidc_states.retain(|existing| existing.expires_at > now);
+        let id = memory.next_id();
+        memory.oidc_states.push(OidcState {
+            id,
+            provider: state.provider.clone(),
+            state_hash: state.state_hash.clone(),
+            nonce: state.nonce.clone(),
+            code_verifier: state.code_verifier.clone(),
+            expires_at: state.expires_at,
+            created_at: now,
+        });
+        Ok(id)
+    }
+
+    async fn take_oidc_state(&self, state_hash: &str) -> Result<Option<OidcState>, sqlx::Error> {
+        let mut memory = self.state.lock().unwrap();
+        let position = memory.oidc_states.iter().position(|state| state.state_hash == state_hash);
+        Ok(position.map(|position| memory.oidc_states.remove(position)))
+    }
+
+    async fn find_identity(&self, provider: &str, subject: &str) -> Result<Option<UserIdentity>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .identities
+            .iter()
+            .find(|identity| identity.provider == provider && identity.subject == subject)
+            .cloned())
+    }
+
+    async fn record_identity_login(&self, id: i64, now: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(identity) = state.identities.iter_mut().find(|identity| identity.id == id) {
+            identity.last_login_at = now;
+        }
+        Ok(())
+    }
+
+    async fn link_identity(&self, user_id: i64, identity: &NewUserIdentity) -> Result<i64, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        Ok(state.link_identity(user_id, identity))
+    }
+
+    async fn create_federated_user(
+        &self,
+        user: &NewFederatedUser,
+        identity: &NewUserIdentity,
+    ) -> Result<i64, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id();
+        let now = Utc::now();
+        state.users.push(User {
+            id,
+            username: user.username.clone(),
+            email: user.email.clone(),
+            email_verified: 0,
+            verification_code: None,
+            verification_code_expires_at: None,
+            verification_attempts: 0,
+            locale: user.locale.clone(),
+            role: user.role.clone(),
+            password_hash: None,
+            totp_secret: None,
+            totp_enabled: 0,
+            totp_last_step: None,
+            created_at: now,
+            updated_at: now,
+        });
+        state.link_identity(id, identity);
+        Ok(id)
+    }
+}
+
+#[async_trait]
+impl AppRepository for MemoryRepository {
+    async fn find_by_id(&self, id: i64) -> Result<Option<App>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.apps.iter().find(|app| app.id == id && app.deleted_at.is_none()).cloned())
+    }
+
+    async fn find_by_identifier(&self, identifier: &str) -> Result<Option<App>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .apps
+            .iter()
+            .find(|app| app.identifier == identifier && app.deleted_at.is_none())
+            .cloned())
+    }
+
+    async fn list(&self, filter: &AppFilter, page: Page) -> Result<Vec<App>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        let mut apps: Vec<App> = state.apps.iter().filter(|app| app_matches(app, filter)).cloned().collect();
+        if filter.trashed {
+            newest_first(&mut apps, |app| (app.deleted_at.unwrap_or(app.created_at), app.id));
+        } else {
+            newest_first(&mut apps, |app| (app.created_at, app.id));
+        }
+        Ok(paginate(apps, page))
+    }
+
+    async fn count(&self, filter: &AppFilter) -> Result<i64, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.apps.iter().filter(|app| app_matches(app, filter)).count() as i64)
+    }
+
+    async fn list_by_creators(&self, creator_ids: &[i64]) -> Result<Vec<App>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        let mut apps: Vec<App> = state
+            .apps
+            .iter()
+            .filter(|app| app.deleted_at.is_none() && creator_ids.contains(&app.creator_id))
+            .cloned()
+            .collect();
+        newest_first(&mut apps, |app| (app.created_at, app.id));
+        Ok(apps)
+    }
+
+    async fn create(&self, app: &CreateAppRequest, creator_id: i64) -> Result<App, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let now = Utc::now();
+        let app = App {
+            id: state.next_id(),
+            name: app.name.clone(),
+            description: app.description.clone(),
+            identifier: app.identifier.clone(),
+            logo_url: app.logo_url.clone(),
+            email_from: app.email_from.clone(),
+            feed_title: app.feed_title.clone(),
+            feed_description: app.feed_description.clone(),
+            feed_link: app.feed_link.clone(),
+            base_url: app.base_url.clone(),
+            require_two_factor: app.require_two_factor as i16,
+            creator_id,
+            created_at: now,
+            updater_id: creator_id,
+            updated_at: now,
+            deleted_at: None,
+            deleted_by: None,
+            version: 1,
+        };
+        state.apps.push(app.clone());
+        state.record_change(NewChangeEvent::app(app.id, APP_CREATED), now);
+        Ok(app)
+    }
+
+    async fn update(
+        &self,
+        id: i64,
+        changes: &UpdateAppRequest,
+        updater_id: i64,
+        version: i64,
+    ) -> Result<Option<App>, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let Some(app) = state
+            .apps
+            .iter_mut()
+            .find(|app| app.id == id && app.version == version && app.deleted_at.is_none())
+        else {
+            return Ok(None);
+        };
+        if let Some(name) = &changes.name {
+            app.name = name.clone();
+        }
+        if let Some(description) = &changes.description {
+            app.description = description.clone();
+        }
+        if let Some(logo_url) = &changes.logo_url {
+            app.logo_url = Some(logo_url.clone());
+        }
+        if let Some(email_from) = &changes.email_from {
+            app.email_from = Some(email_from.clone());
+        }
+        if let Some(feed_title) = &changes.feed_title {
+            app.feed_title = Some(feed_title.clone());
+        }
+        if let Some(feed_description) = &changes.feed_description {
+            app.feed_description = Some(feed_description.clone());
+        }
+        if let Some(feed_link) = &changes.feed_link {
+            app.feed_link = Some(feed_link.clone());
+        }
+        if let Some(base_url) = &changes.base_url {
+            app.base_url = Some(base_url.clone());
+        }
+        if let Some(require_two_factor) = changes.require_two_factor {
+            app.require_two_factor = require_two_factor as i16;
+        }
+        app.updater_id = updater_id;
+        app.updated_at = Utc::now();
+        app.version += 1;
+        let app = app.clone();
+        state.record_change(NewChangeEvent::app(id, APP_UPDATED), app.updated_at);
+        Ok(Some(app))
+    }
+
+    async fn trash(&self, id: i64, deleted_by: i64) -> Result<bool, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let now = Utc::now();
+        let Some(app) = state.apps.iter_mut().find(|app| app.id == id && app.deleted_at.is_none()) else {
+            return Ok(false);
+        };
+        app.deleted_at = Some(now);
+        app.deleted_by = Some(deleted_by);
+        for article in state
+            .articles
+            .iter_mut()
+            .filter(|article| article.app_id == Some(id) && article.deleted_at.is_none())
+        {
+            article.deleted_at = Some(now);
+            article.deleted_by = Some(deleted_by);
+        }
+        state.record_change(NewChangeEvent::app(id, APP_DELETED), now);
+        Ok(true)
+    }
+
+    async fn find_trashed(&self, id: i64) -> Result<Option<App>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.apps.iter().find(|app| app.id == id && app.deleted_at.is_some()).cloned())
+    }
+
+    async fn restore(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let Some(app) = state.apps.iter_mut().find(|app| app.id == id && app.deleted_at.is_some()) else {
+            return Ok(false);
+        };
+        let deleted_at = app.deleted_at.take();
+        app.deleted_by = None;
+        for article in state
+            .articles
+            .iter_mut()
+            .filter(|article| article.app_id == Some(id) && article.deleted_at == deleted_at)
+        {
+            article.deleted_at = None;
+            article.deleted_by = None;
+        }
+        state.record_change(NewChangeEvent::app(id, APP_RESTORED), Utc::now());
+        Ok(true)
+    }
+
+    async fn purge_trashed(&self, before: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let purged: Vec<i64> = state
+            .apps
+            .iter()
+            .filter(|app| app.deleted_at.is_some_and(|deleted_at| deleted_at < before))
+            .map(|app| app.id)
+            .collect();
+        let articles: Vec<i64> = state
+            .articles
+            .iter()
+            .filter(|article| article.app_id.is_some_and(|app_id| purged.contains(&app_id)))
+            .map(|article| article.id)
+            .collect();
+        state.preview_links.retain(|link| !articles.contains(&link.article_id));
+        state.articles.retain(|article| !articles.contains(&article.id));
+        state.api_keys.retain(|key| !purged.contains(&key.app_id));
+        state.change_events.retain(|event| !purged.contains(&event.app_id));
+        state.apps.retain(|app| !purged.contains(&app.id));
+        Ok(purged.len() as u64)
+    }
+}
+
+#[async_trait]
+impl ArticleRepository for MemoryRepository {
+    async fn find_visible(&self, id: i64, viewer_id: i64) -> Result<Option<Article>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .articles
+            .iter()
+            .find(|article| {
+                article.id == id && article.deleted_at.is_none() && (article.status == 2 || article.author_id == viewer_id)
+            })
+            .cloned())
+    }
+
+    async fn list_visible(
+        &self,
+        viewer_id: i64,
+        filter: &ArticleFilter,
+        page: Option<Page>,
+    ) -> Result<Vec<Article>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        let mut articles: Vec<Article> = state
+            .articles
+            .iter()
+            .filter(|article| article_matches(article, viewer_id, filter))
+            .cloned()
+            .collect();
+        newest_first(&mut articles, |article| (article.created_at, article.id));
+        Ok(match page {
+            Some(page) => paginate(articles, page),
+            None => articles,
+        })
+    }
+
+    async fn count_visible(&self, viewer_id: i64, filter: &ArticleFilter) -> Result<i64, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .articles
+            .iter()
+            .filter(|article| article_matches(article, viewer_id, filter))
+            .count() as i64)
+    }
+
+    async fn list_translations(&self, group_ids: &[i64]) -> Result<Vec<Article>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        let mut articles: Vec<Article> = state
+            .articles
+            .iter()
+            .filter(|article| article.deleted_at.is_none() && article.status == 2)
+            .filter(|article| {
+                group_ids.contains(&article.id)
+                    || article.translation_of.is_some_and(|original| group_ids.contains(&original))
+            })
+            .cloned()
+            .collect();
+        articles.sort_by_key(|article| article.id);
+        Ok(articles)
+    }
+
+    async fn list_by_authors(&self, author_ids: &[i64]) -> Result<Vec<Article>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        let mut articles: Vec<Article> = state
+            .articles
+            .iter()
+            .filter(|article| article.deleted_at.is_none() && author_ids.contains(&article.author_id))
+            .cloned()
+            .collect();
+        newest_first(&mut articles, |article| (article.created_at, article.id));
+        Ok(articles)
+    }
+
+    async fn list_in_app(&self, app_id: i64) -> Result<Vec<Article>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        let mut articles: Vec<Article> = state
+            .articles
+            .iter()
+            .filter(|article| article.deleted_at.is_none() && article.app_id == Some(app_id))
+            .cloned()
+            .collect();
+        articles.sort_by_key(|article| article.id);
+        Ok(articles)
+    }
+
+    async fn create(&self, article: &CreateArticleRequest, author_id: i64) -> Result<Article, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let now = Utc::now();
+        let article = Article {
+            id: state.next_id(),
+            title: article.title.clone(),
+            content: article.content.clone(),
+            author_id,
+            app_id: article.app_id,
+            category: article.category.clone(),
+            locale: article.locale.clone(),
+            translation_of: article.translation_of,
+            meta_title: article.meta_title.clone(),
+            meta_description: article.meta_description.clone(),
+            canonical_url: article.canonical_url.clone(),
+            og_image_url: article.og_image_url.clone(),
+            noindex: article.noindex.unwrap_or(false) as i16,
+            status: article.status.unwrap_or(1), // 默认为草稿状态
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+            deleted_by: None,
+            version: 1,
+        };
+        state.articles.push(article.clone());
+        state.record_article_change(article.id, ARTICLE_CREATED, now);
+        Ok(article)
+    }
+
+    async fn update(
+        &self,
+        id: i64,
+        author_id: i64,
+        changes: &UpdateArticleRequest,
+        version: i64,
+    ) -> Result<Option<Article>, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let Some(article) = state.articles.iter_mut().find(|article| {
+            article.id == id
+                && article.author_id == author_id
+                && article.version == version
+                && article.deleted_at.is_none()
+        }) else {
+            return Ok(None);
+        };
+        let before_status = article.status;
+        if let Some(title) = &changes.title {
+            article.title = title.clone();
+        }
+        if let Some(content) = &changes.content {
+            article.content = content.clone();
+        }
+        if let Some(status) = changes.status {
+            article.status = status;
+        }
+        if let Some(category) = &changes.category {
+            article.category = Some(category.clone());
+        }
+        for (field, value) in [
+            (&mut article.locale, &changes.locale),
+            (&mut article.meta_title, &changes.meta_title),
+            (&mut article.meta_description, &changes.meta_description),
+            (&mut article.canonical_url, &changes.canonical_url),
+            (&mut article.og_image_url, &changes.og_image_url),
+        ] {
+            if let Some(value) = value {
+                *field = Some(value.clone());
+            }
+        }
+        if let Some(noindex) = changes.noindex {
+            article.noindex = noindex as i16;
+        }
+        // 区分发布和普通修改
+        let published = before_status != 2 && article.status == 2;
+        article.updated_at = Utc::now();
+        article.version += 1;
+        let article = article.clone();
+        let event_type = if published { ARTICLE_PUBLISHED } else { ARTICLE_UPDATED };
+        state.record_article_change(id, event_type, article.updated_at);
+        Ok(Some(article))
+    }
+
+    async fn set_timestamps(
+        &self,
+        id: i64,
+        created_at: DateTime<Utc>,
+        updated_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(article) = state.articles.iter_mut().find(|article| article.id == id) {
+            article.created_at = created_at;
+            article.updated_at = updated_at;
+        }
+        Ok(())
+    }
+
+    async fn trash(&self, id: i64, author_id: i64) -> Result<bool, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let Some(article) = state
+            .articles
+            .iter_mut()
+            .find(|article| article.id == id && article.author_id == author_id && article.deleted_at.is_none())
+        else {
+            return Ok(false);
+        };
+        let now = Utc::now();
+        article.deleted_at = Some(now);
+        article.deleted_by = Some(author_id);
+        state.record_article_change(id, ARTICLE_DELETED, now);
+        Ok(true)
+    }
+
+    async fn find_trashed(&self, id: i64, author_id: i64) -> Result<Option<Article>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .articles
+            .iter()
+            .find(|article| article.id == id && article.author_id == author_id && article.deleted_at.is_some())
+            .cloned())
+    }
+
+    async fn list_trashed(&self, author_id: i64) -> Result<Vec<Article>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        let mut articles: Vec<Article> = state
+            .articles
+            .iter()
+            .filter(|article| article.author_id == author_id && article.deleted_at.is_some())
+            .cloned()
+            .collect();
+        newest_first(&mut articles, |article| (article.deleted_at.unwrap_or(article.created_at), article.id));
+        Ok(articles)
+    }
+
+    async fn restore(&self, id: i64, author_id: i64) -> Result<bool, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let Some(article) = state
+            .articles
+            .iter_mut()
+            .find(|article| article.id == id && article.author_id == author_id && article.deleted_at.is_some())
+        else {
+            return Ok(false);
+        };
+        article.deleted_at = None;
+        article.deleted_by = None;
+        state.record_article_change(id, ARTICLE_RESTORED, Utc::now());
+        Ok(true)
+    }
+
+    async fn purge_trashed(&self, before: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let purged: Vec<i64> = state
+            .articles
+            .iter()
+            .filter(|article| article.deleted_at.is_some_and(|deleted_at| deleted_at < before))
+            .map(|article| article.id)
+            .collect();
+        state.preview_links.retain(|link| !purged.contains(&link.article_id));
+        state.articles.retain(|article| !purged.contains(&article.id));
+        Ok(purged.len() as u64)
+    }
+}
+
+fn outbox_matches(message: &OutboxEmail, filter: &OutboxFilter) -> bool {
+    filter.status.as_deref().is_none_or(|status| message.status == status)
+}
+
+// 与 SQL 实现的领取条件一致
+fn outbox_due(message: &OutboxEmail, now: DateTime<Utc>) -> bool {
+    (message.status == OUTBOX_PENDING && (message.attempts == 0 || message.next_attempt_at <= now))
+        || (message.status == OUTBOX_SENDING && message.locked_until.is_some_and(|until| until < now))
+}
+
+#[async_trait]
+impl OutboxRepository for MemoryRepository {
+    async fn enqueue(&self, message: &EmailMessage) -> Result<i64, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        Ok(state.enqueue(message, Utc::now()))
+    }
+
+    async fn find_by_id(&self, id: i64) -> Result<Option<OutboxEmail>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.outbox.iter().find(|message| message.id == id).cloned())
+    }
+
+    async fn list(&self, filter: &OutboxFilter, page: Page) -> Result<Vec<OutboxEmail>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        let messages: Vec<OutboxEmail> = state
+            .outbox
+            .iter()
+            .rev()
+            .filter(|message| outbox_matches(message, filter))
+            .cloned()
+            .collect();
+        Ok(paginate(messages, page))
+    }
+
+    async fn count(&self, filter: &OutboxFilter) -> Result<i64, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.outbox.iter().filter(|message| outbox_matches(message, filter)).count() as i64)
+    }
+
+    async fn claim_due(
+        &self,
+        now: DateTime<Utc>,
+        locked_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<OutboxEmail>, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let claimed = state
+            .outbox
+            .iter_mut()
+            .filter(|message| outbox_due(message, now))
+            .take(limit.max(0) as usize)
+            .map(|message| {
+                message.status = OUTBOX_SENDING.to_string();
+                message.locked_until = Some(locked_until);
+                message.attempts += 1;
+                message.updated_at = now;
+                message.clone()
+            })
+            .collect();
+        Ok(claimed)
+    }
+
+    async fn mark_sent(&self, id: i64, now: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(message) = state.outbox.iter_mut().find(|message| message.id == id) {
+            message.status = OUTBOX_SENT.to_string();
+            message.sent_at = Some(now);
+            message.locked_until = None;
+            message.updated_at = now;
+        }
+        Ok(())
+    }
+
+    async fn mark_failed(
+        &self,
+        id: i64,
+        error: &str,
+        next_attempt_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(message) = state.outbox.iter_mut().find(|message| message.id == id) {
+            message.status = match next_attempt_at {
+                Some(_) => OUTBOX_PENDING,
+                None => OUTBOX_DEAD,
+            }
+            .to_string();
+            message.last_error = Some(error.to_string());
+            message.next_attempt_at = next_attempt_at.unwrap_or(now);
+            message.locked_until = None;
+            message.updated_at = now;
+        }
+        Ok(())
+    }
+
+    async fn retry(&self, id: i64, now: DateTime<Utc>) -> Result<bool, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        match state
+            .outbox
+            .iter_mut()
+            .find(|message| message.id == id && message.status == OUTBOX_DEAD)
+        {
+            Some(message) => {
+                message.status = OUTBOX_PENDING.to_string();
+                message.attempts = 0;
+                message.next_attempt_at = now;
+                message.locked_until = None;
+                message.updated_at = now;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+fn job_matches(job: &JobRecord, filter: &JobFilter) -> bool {
+    filter.status.as_deref().is_none_or(|status| job.status == status)
+        && filter.queue.as_deref().is_none_or(|queue| job.queue == queue)
+        && filter.kind.as_deref().is_none_or(|kind| job.kind == kind)
+}
+
+fn job_due(job: &JobRecord, queues: &[String], now: DateTime<Utc>) -> bool {
+    queues.contains(&job.queue)
+        && ((job.status == JOB_PENDING && job.run_at <= now)
+            || (job.status == JOB_RUNNING && job.locked_until.is_some_and(|until| until < now)))
+}
+
+#[async_trait]
+impl JobRepository for MemoryRepository {
+    async fn enqueue(&self, job: &NewJob) -> Result<(i64, bool), sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(key) = &job.unique_key {
+            if let Some(existing) = state.jobs.iter().find(|existing| existing.unique_key.as_ref() == Some(key)) {
+                return Ok((existing.id, false));
+            }
+        }
+
+        let id = state.next_id();
+        let now = Utc::now();
+        state.jobs.push(JobRecord {
+            id,
+            queue: job.queue.clone(),
+            kind: job.kind.clone(),
+            payload: job.payload.clone(),
+            status: JOB_PENDING.to_string(),
+            attempts: 0,
+            max_attempts: job.max_attempts,
+            unique_key: job.unique_key.clone(),
+            run_at: job.run_at,
+            locked_until: None,
+            last_error: None,
+            finished_at: None,
+            created_at: now,
+            updated_at: now,
+        });
+        Ok((id, true))
+    }
+
+    async fn find_by_id(&self, id: i64) -> Result<Option<JobRecord>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.jobs.iter().find(|job| job.id == id).cloned())
+    }
+
+    async fn list(&self, filter: &JobFilter, page: Page) -> Result<Vec<JobRecord>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        let jobs: Vec<JobRecord> = state.jobs.iter().rev().filter(|job| job_matches(job, filter)).cloned().collect();
+        Ok(paginate(jobs, page))
+    }
+
+    async fn count(&self, filter: &JobFilter) -> Result<i64, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.jobs.iter().filter(|job| job_matches(job, filter)).count() as i64)
+    }
+
+    async fn counts(&self) -> Result<Vec<JobCount>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        let mut counts: Vec<JobCount> = Vec::new();
+        for job in &state.jobs {
+            match counts
+                .iter_mut()
+                .find(|count| count.queue == job.queue && count.status == job.status)
+            {
+                Some(count) => count.count += 1,
+                None => counts.push(JobCount {
+                    queue: job.queue.clone(),
+                    status: job.status.clone(),
+                    count: 1,
+                }),
+            }
+        }
+        counts.sort_by(|a, b| (&a.queue, &a.status).cmp(&(&b.queue, &b.status)));
+        Ok(counts)
+    }
+
+    async fn claim(
+        &self,
+        queues: &[String],
+        now: DateTime<Utc>,
+        locked_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<JobRecord>, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let mut due: Vec<&mut JobRecord> = state.jobs.iter_mut().filter(|job| job_due(job, queues, now)).collect();
+        due.sort_by_key(|job| (job.run_at, job.id));
+        let claimed = due
+            .into_iter()
+            .take(limit.max(0) as usize)
+            .map(|job| {
+                job.status = JOB_RUNNING.to_string();
+                job.locked_until = Some(locked_until);
+                job.attempts += 1;
+                job.updated_at = now;
+                job.clone()
+            })
+            .collect();
+        Ok(claimed)
+    }
+
+    async fn complete(&self, id: i64, now: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(job) = state.jobs.iter_mut().find(|job| job.id == id) {
+            job.status = JOB_COMPLETED.to_string();
+            job.unique_key = None;
+            job.locked_until = None;
+            job.finished_at = Some(now);
+            job.updated_at = now;
+        }
+        Ok(())
+    }
+
+    async fn fail(
+        &self,
+        id: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(job) = state.jobs.iter_mut().find(|job| job.id == id) {
+            job.last_error = Some(error.to_string());
+            job.locked_until = None;
+            job.updated_at = now;
+            match retry_at {
+                Some(retry_at) => {
+                    job.status = JOB_PENDING.to_string();
+                    job.run_at = retry_at;
+                }
+                None => {
+                    job.status = JOB_DEAD.to_string();
+                    job.unique_key = None;
+                    job.finished_at = Some(now);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn retry(&self, id: i64, unique_key: Option<&str>, now: DateTime<Utc>) -> Result<bool, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        if unique_key.is_some() && state.jobs.iter().any(|job| job.unique_key.as_deref() == unique_key) {
+            return Ok(false);
+        }
+        match state.jobs.iter_mut().find(|job| job.id == id && job.status == JOB_DEAD) {
+            Some(job) => {
+                job.status = JOB_PENDING.to_string();
+                job.attempts = 0;
+                job.unique_key = unique_key.map(str::to_string);
+                job.run_at = now;
+                job.finished_at = None;
+                job.updated_at = now;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn purge_completed(&self, before: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let count = state.jobs.len();
+        state
+            .jobs
+            .retain(|job| !(job.status == JOB_COMPLETED && job.finished_at.is_some_and(|at| at < before)));
+        Ok((count - state.jobs.len()) as u64)
+    }
+
+    async fn claim_schedule(&self, name: &str, fire_at: DateTime<Utc>) -> Result<bool, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        match state.schedules.get(name) {
+            Some(last_run_at) if *last_run_at >= fire_at => Ok(false),
+            _ => {
+                state.schedules.insert(name.to_string(), fire_at);
+                Ok(true)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AuditRepository for MemoryRepository {
+    async fn append(&self, entry: &NewAuditLog, now: DateTime<Utc>) -> Result<i64, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id();
+        state.audit_logs.push(AuditLog {
+            id,
+            actor_type: entry.actor_type.clone(),
+            actor_id: entry.actor_id,
+            api_key: entry.api_key.clone(),
+            action: entry.action.clone(),
+            target_type: entry.target_type.clone(),
+            target_id: entry.target_id,
+            app_id: entry.app_id,
+            before_data: entry.before_data.clone(),
+            after_data: entry.after_data.clone(),
+            ip: entry.ip.clone(),
+            user_agent: entry.user_agent.clone(),
+            request_id: entry.request_id.clone(),
+            created_at: now,
+        });
+        Ok(id)
+    }
+
+    async fn list(&self, filter: &AuditFilter, page: Page) -> Result<Vec<AuditLog>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        let logs: Vec<AuditLog> = state.audit_logs.iter().rev().filter(|log| audit_matches(log, filter)).cloned().collect();
+        Ok(paginate(logs, page))
+    }
+
+    async fn count(&self, filter: &AuditFilter) -> Result<i64, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.audit_logs.iter().filter(|log| audit_matches(log, filter)).count() as i64)
+    }
+
+    async fn purge_before(&self, before: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let count = state.audit_logs.len();
+        state.audit_logs.retain(|log| log.created_at >= before);
+        Ok((count - state.audit_logs.len()) as u64)
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for MemoryRepository {
+    async fn create(&self, key: &NewApiKey, now: DateTime<Utc>) -> Result<ApiKey, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let api_key = ApiKey {
+            id: state.next_id(),
+            app_id: key.app_id,
+            name: key.name.clone(),
+            prefix: key.prefix.clone(),
+            key_hash: key.key_hash.clone(),
+            created_by: key.created_by,
+            created_at: now,
+        };
+        state.api_keys.push(api_key.clone());
+        Ok(api_key)
+    }
+
+    async fn list_by_app(&self, app_id: i64) -> Result<Vec<ApiKey>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.api_keys.iter().filter(|key| key.app_id == app_id).cloned().collect())
+    }
+
+    async fn find_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.api_keys.iter().find(|key| key.key_hash == key_hash).cloned())
+    }
+
+    async fn delete(&self, app_id: i64, id: i64) -> Result<bool, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let count = state.api_keys.len();
+        state.api_keys.retain(|key| key.id != id || key.app_id != app_id);
+        Ok(state.api_keys.len() < count)
+    }
+}
+
+#[async_trait]
+impl ChangeEventRepository for MemoryRepository {
+    async fn list_since(&self, app_id: i64, after_id: i64, limit: i64) -> Result<Vec<ChangeEvent>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .change_events
+            .iter()
+            .filter(|event| event.app_id == app_id && event.id > after_id)
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn last_id(&self, app_id: i64) -> Result<i64, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .change_events
+            .iter()
+            .filter(|event| event.app_id == app_id)
+            .map(|event| event.id)
+            .max()
+            .unwrap_or(0))
+    }
+
+    async fn purge_before(&self, before: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let count = state.change_events.len();
+        state.change_events.retain(|event| event.created_at >= before);
+        Ok((count - state.change_events.len()) as u64)
+    }
+}
+
+#[async_trait]
+impl PreviewLinkRepository for MemoryRepository {
+    async fn create(&self, link: &NewPreviewLink, now: DateTime<Utc>) -> Result<PreviewLink, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        let preview = PreviewLink {
+            id: state.next_id(),
+            article_id: link.article_id,
+            token_hash: link.token_hash.clone(),
+            version: link.version,
+            snapshot: link.snapshot.clone(),
+            created_by: link.created_by,
+            expires_at: link.expires_at,
+            revoked_at: None,
+            created_at: now,
+        };
+        state.preview_links.push(preview.clone());
+        Ok(preview)
+    }
+
+    async fn find_by_hash(&self, token_hash: &str) -> Result<Option<PreviewLink>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.preview_links.iter().find(|link| link.token_hash == token_hash).cloned())
+    }
+
+    async fn list_by_article(&self, article_id: i64) -> Result<Vec<PreviewLink>, sqlx::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .preview_links
+            .iter()
+            .rev()
+            .filter(|link| link.article_id == article_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn revoke(&self, article_id: i64, id: i64, now: DateTime<Utc>) -> Result<bool, sqlx::Error> {
+        let mut state = self.state.lock().unwrap();
+        match state
+            .preview_links
+            .iter_mut()
+            .find(|link| link.id == id && link.article_id == article_id && link.revoked_at.is_none())
+        {
+            Some(link) => {
+                link.revoked_at = Some(now);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
@@ -1,71 +1,64 @@
-use sqlx::mysql::MySqlPool;
-use std::env;
+pub mod memory;
+pub mod mysql;
+pub mod postgres;
+pub mod repository;
+mod sql;
+pub mod sqlite;
 
-pub async fn init_pool() -> Result<MySqlPool, sqlx::Error> {
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    MySqlPool::connect(&database_url).await
-}
+pub use repository::*;
 
-// 初始化数据库表
-pub async fn init_db(pool: &MySqlPool) -> Result<(), sqlx::Error> {
-    // 创建用户表
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS users (
-            id BIGINT PRIMARY KEY AUTO_INCREMENT,
-            username VARCHAR(50) NOT NULL UNIQUE,
-            email VARCHAR(255) NOT NULL UNIQUE,
-            email_verified TINYINT NOT NULL DEFAULT 0,
-            verification_code VARCHAR(6),
-            verification_code_expires_at TIMESTAMP,
-            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
-            updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
+use std::sync::Arc;
 
-    // 创建文章表
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS articles (
-            id BIGINT PRIMARY KEY AUTO_INCREMENT,
-            title VARCHAR(255) NOT NULL,
-            content TEXT NOT NULL,
-            author_id BIGINT NOT NULL,
-            status TINYINT NOT NULL DEFAULT 1 COMMENT '1: draft, 2: published',
-            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
-            updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
-            FOREIGN KEY (author_id) REFERENCES users(id)
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
+// 处理器只依赖这些仓储接口，不关心具体数据库
+#[derive(Clone)]
+pub struct Repositories {
+    pub users: Arc<dyn UserRepository>,
+    pub apps: Arc<dyn AppRepository>,
+    pub articles: Arc<dyn ArticleRepository>,
+    pub outbox: Arc<dyn OutboxRepository>,
+    pub jobs: Arc<dyn JobRepository>,
+    pub audit: Arc<dyn AuditRepository>,
+    pub api_keys: Arc<dyn ApiKeyRepository>,
+    pub changes: Arc<dyn ChangeEventRepository>,
+    pub previews: Arc<dyn PreviewLinkRepository>,
+}
 
-    // 创建应用表
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS apps (
-            id BIGINT PRIMARY KEY AUTO_INCREMENT,
-            name VARCHAR(100) NOT NULL,
-            description TEXT,
-            identifier VARCHAR(50) NOT NULL UNIQUE,
-            creator_id BIGINT NOT NULL,
-            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
-            updater_id BIGINT NOT NULL,
-            updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
-            FOREIGN KEY (creator_id) REFERENCES users(id),
-            FOREIGN KEY (updater_id) REFERENCES users(id),
-            INDEX idx_name (name),
-            INDEX idx_identifier (identifier),
-            INDEX idx_creator (creator_id)
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
+impl Repositories {
+    pub fn new<R>(repository: R) -> Self
+    where
+        R: UserRepository
+            + AppRepository
+            + ArticleRepository
+            + OutboxRepository
+            + JobRepository
+            + AuditRepository
+            + ApiKeyRepository
+            + ChangeEventRepository
+            + PreviewLinkRepository
+            + Clone
+            + 'static,
+    {
+        Repositories {
+            users: Arc::new(repository.clone()),
+            apps: Arc::new(repository.clone()),
+            articles: Arc::new(repository.clone()),
+            outbox: Arc::new(repository.clone()),
+            jobs: Arc::new(repository.clone()),
+            audit: Arc::new(repository.clone()),
+            api_keys: Arc::new(repository.clone()),
+            changes: Arc::new(repository.clone()),
+            previews: Arc::new(repository),
+        }
+    }
 
-    Ok(())
+    // 根据 DATABASE_URL 的协议选择数据库，并执行对应的迁移
+    pub async fn connect(database_url: &str) -> Result<Self, anyhow::Error> {
+        let scheme = database_url.split(':').next().unwrap_or_default();
+        match scheme {
+            "mysql" | "mariadb" => Ok(Self::new(mysql::MySqlRepository::connect(database_url).await?)),
+            "postgres" | "postgresql" => Ok(Self::new(postgres::PgRepository::connect(database_url).await?)),
+            "sqlite" => Ok(Self::new(sqlite::SqliteRepository::connect(database_url).await?)),
+            _ => Err(anyhow::anyhow!("Unsupported database scheme: {}", scheme)),
+        }
+    }
 }
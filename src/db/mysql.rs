@@ -0,0 +1,34 @@
+use sqlx::mysql::{MySqlArguments, MySqlPool};
+use sqlx::query::Query;
+use std::borrow::Cow;
+
+type Db = sqlx::MySql;
+
+// MySQL 不支持 RETURNING，使用 last_insert_id
+const RETURNING_ID: &str = "";
+
+fn sql(query: &str) -> Cow<'_, str> {
+    Cow::Borrowed(query)
+}
+
+async fn insert_id<'e, E>(executor: E, query: Query<'_, Db, MySqlArguments>) -> Result<i64, sqlx::Error>
+where
+    E: sqlx::Executor<'e, Database = Db>,
+{
+    Ok(query.execute(executor).await?.last_insert_id() as i64)
+}
+
+#[derive(Clone)]
+pub struct MySqlRepository {
+    pool: MySqlPool,
+}
+
+impl MySqlRepository {
+    pub async fn connect(database_url: &str) -> Result<Self, anyhow::Error> {
+        let pool = MySqlPool::connect(database_url).await?;
+        sqlx::migrate!("./db/migrations/mysql").run(&pool).await?;
+        Ok(Self { pool })
+    }
+}
+
+super::sql::impl_repositories!(MySqlRepository);
@@ -0,0 +1,59 @@
+use sqlx::postgres::{PgArguments, PgPool};
+use sqlx::query::Query;
+use sqlx::Row;
+use std::borrow::Cow;
+
+type Db = sqlx::Postgres;
+
+const RETURNING_ID: &str = " RETURNING id";
+
+// 把 ? 占位符改写为 $1, $2, ...
+fn sql(query: &str) -> Cow<'_, str> {
+    let mut rewritten = String::with_capacity(query.len() + 8);
+    let mut index = 0;
+    for c in query.chars() {
+        if c == '?' {
+            index += 1;
+            rewritten.push('$');
+            rewritten.push_str(&index.to_string());
+        } else {
+            rewritten.push(c);
+        }
+    }
+    Cow::Owned(rewritten)
+}
+
+async fn insert_id<'e, E>(executor: E, query: Query<'_, Db, PgArguments>) -> Result<i64, sqlx::Error>
+where
+    E: sqlx::Executor<'e, Database = Db>,
+{
+    query.fetch_one(executor).await?.try_get(0)
+}
+
+#[derive(Clone)]
+pub struct PgRepository {
+    pool: PgPool,
+}
+
+impl PgRepository {
+    pub async fn connect(database_url: &str) -> Result<Self, anyhow::Error> {
+        let pool = PgPool::connect(database_url).await?;
+        sqlx::migrate!("./db/migrations/postgres").run(&pool).await?;
+        Ok(Self { pool })
+    }
+}
+
+super::sql::impl_repositories!(PgRepository);
+
+#[cfg(test)]
+mod tests {
+    use super::sql;
+
+    #[test]
+    fn rewrites_placeholders() {
+        assert_eq!(
+            sql("SELECT * FROM articles WHERE id = ? AND (status = 2 OR author_id = ?)"),
+            "SELECT * FROM articles WHERE id = $1 AND (status = 2 OR author_id = $2)"
+        );
+    }
+}
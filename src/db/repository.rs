@@ -0,0 +1,395 @@
+use crate::models::api_key::{ApiKey, NewApiKey};
+use crate::models::audit::{AuditLog, NewAuditLog};
+use crate::models::article::{CreateArticleRequest, UpdateArticleRequest};
+use crate::models::change::ChangeEvent;
+use crate::models::credential::{NewUserToken, UserToken};
+use crate::models::job::{JobCount, JobRecord, NewJob};
+use crate::models::magic_link::{MagicLink, NewMagicLink};
+use crate::models::oidc::{NewFederatedUser, NewOidcState, NewUserIdentity, OidcState, UserIdentity};
+use crate::models::outbox::{EmailMessage, OutboxEmail};
+use crate::models::preview::{NewPreviewLink, PreviewLink};
+use crate::models::{App, Article, CreateAppRequest, UpdateAppRequest, User};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+// 分页参数
+#[derive(Debug, Clone, Copy)]
+pub struct Page {
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Page {
+    pub fn new(page: i64, page_size: i64) -> Self {
+        Page {
+            limit: page_size,
+            offset: (page - 1) * page_size,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct AppFilter {
+    pub keyword: Option<String>,
+    pub identifier: Option<String>,
+    pub creator_id: Option<i64>,
+    // 为 true 时只查询回收站中的应用，否则只查询未删除的应用
+    pub trashed: bool,
+}
+
+// viewer 只能看到已发布的文章和自己的草稿
+#[derive(Debug, Default, Clone)]
+pub struct ArticleFilter {
+    pub keyword: Option<String>,
+    pub status: Option<i16>,
+    pub author_id: Option<i64>,
+    pub app_id: Option<i64>,
+    pub category: Option<String>,
+    // 为 true 时排除 noindex 的文章
+    pub indexable: bool,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct OutboxFilter {
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct AuditFilter {
+    pub actor_id: Option<i64>,
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<i64>,
+    pub app_id: Option<i64>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct JobFilter {
+    pub status: Option<String>,
+    pub queue: Option<String>,
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewUser {
+    pub username: String,
+    pub email: String,
+    pub verification_code: String,
+    pub verification_code_expires_at: DateTime<Utc>,
+    pub locale: String,
+    pub role: String,
+}
+
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn find_by_id(&self, id: i64) -> Result<Option<User>, sqlx::Error>;
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error>;
+
+    async fn find_by_ids(&self, ids: &[i64]) -> Result<Vec<User>, sqlx::Error>;
+
+    // 验证码邮件与用户在同一事务中写入发件箱
+    async fn create(&self, user: NewUser, message: &EmailMessage) -> Result<i64, sqlx::Error>;
+
+    // 设置新的验证码并清零错误次数，同时写入验证码邮件
+    async fn set_verification_code(
+        &self,
+        email: &str,
+        code: &str,
+        expires_at: DateTime<Utc>,
+        message: &EmailMessage,
+    ) -> Result<(), sqlx::Error>;
+
+    // 记录一次验证码错误，达到上限后作废验证码
+    async fn record_failed_attempt(&self, id: i64, max_attempts: i32) -> Result<(), sqlx::Error>;
+
+    // 登录成功：标记邮箱已验证、清除验证码并作废该用户的登录链接，首次验证时写入欢迎邮件
+    async fn mark_verified(&self, id: i64, welcome: Option<&EmailMessage>) -> Result<(), sqlx::Error>;
+
+    // 作废该用户之前的登录链接，写入新链接和备用验证码，同时写入登录邮件
+    async fn create_magic_link(
+        &self,
+        link: &NewMagicLink,
+        code: &str,
+        message: &EmailMessage,
+    ) -> Result<i64, sqlx::Error>;
+
+    async fn find_magic_link(&self, token_hash: &str) -> Result<Option<MagicLink>, sqlx::Error>;
+
+    // 只有未使用的链接可以被使用一次，并发请求中只有一个返回 true
+    async fn consume_magic_link(&self, id: i64, now: DateTime<Utc>) -> Result<bool, sqlx::Error>;
+
+    async fn set_password(&self, id: i64, password_hash: &str) -> Result<(), sqlx::Error>;
+
+    // 保存待确认的 TOTP 密钥，启用前不影响登录
+    async fn set_totp_secret(&self, id: i64, secret: &str) -> Result<(), sqlx::Error>;
+
+    // 启用 TOTP，同时替换全部恢复码
+    async fn enable_totp(&self, id: i64, recovery_code_hashes: &[String]) -> Result<(), sqlx::Error>;
+
+    // 关闭 TOTP 并删除恢复码
+    async fn disable_totp(&self, id: i64) -> Result<(), sqlx::Error>;
+
+    async fn replace_recovery_codes(&self, id: i64, recovery_code_hashes: &[String]) -> Result<(), sqlx::Error>;
+
+    // 每个恢复码只能使用一次
+    async fn use_recovery_code(&self, id: i64, code_hash: &str, now: DateTime<Utc>) -> Result<bool, sqlx::Error>;
+
+    async fn count_recovery_codes(&self, id: i64) -> Result<i64, sqlx::Error>;
+
+    // 只有比上次更新的时间步才会被记录，返回 false 表示验证码被重放
+    async fn record_totp_step(&self, id: i64, step: i64) -> Result<bool, sqlx::Error>;
+
+    // 作废该用户同一用途的旧 token，需要时同时写入邮件
+    async fn create_token(&self, token: &NewUserToken, message: Option<&EmailMessage>) -> Result<i64, sqlx::Error>;
+
+    async fn find_token(&self, purpose: &str, token_hash: &str) -> Result<Option<UserToken>, sqlx::Error>;
+
+    // 只有未使用的 token 可以被使用一次
+    async fn consume_token(&self, id: i64, now: DateTime<Utc>) -> Result<bool, sqlx::Error>;
+
+    // 记录一次验证失败，达到上限后作废 token
+    async fn record_token_failure(&self, id: i64, max_attempts: i32, now: DateTime<Utc>) -> Result<(), sqlx::Error>;
+
+    async fn set_role(&self, id: i64, role: &str) -> Result<(), sqlx::Error>;
+
+    // 同时清理已过期的登录状态
+    async fn create_oidc_state(&self, state: &NewOidcState, now: DateTime<Utc>) -> Result<i64, sqlx::Error>;
+
+    // 取出并删除，每个 state 只能使用一次
+    async fn take_oidc_state(&self, state_hash: &str) -> Result<Option<OidcState>, sqlx::Error>;
+
+    async fn find_identity(&self, provider: &str, subject: &str) -> Result<Option<UserIdentity>, sqlx::Error>;
+
+    async fn record_identity_login(&self, id: i64, now: DateTime<Utc>) -> Result<(), sqlx::Error>;
+
+    async fn link_identity(&self, user_id: i64, identity: &NewUserIdentity) -> Result<i64, sqlx::Error>;
+
+    // 创建未验证邮箱的用户并关联身份，返回用户 id
+    async fn create_federated_user(
+        &self,
+        user: &NewFederatedUser,
+        identity: &NewUserIdentity,
+    ) -> Result<i64, sqlx::Error>;
+}
+
+// 创建、修改、删除和恢复在同一事务中写入对应的变更事件
+#[async_trait]
+pub trait AppRepository: Send + Sync {
+    async fn find_by_id(&self, id: i64) -> Result<Option<App>, sqlx::Error>;
+
+    async fn find_by_identifier(&self, identifier: &str) -> Result<Option<App>, sqlx::Error>;
+
+    async fn list(&self, filter: &AppFilter, page: Page) -> Result<Vec<App>, sqlx::Error>;
+
+    async fn count(&self, filter: &AppFilter) -> Result<i64, sqlx::Error>;
+
+    async fn list_by_creators(&self, creator_ids: &[i64]) -> Result<Vec<App>, sqlx::Error>;
+
+    async fn create(&self, app: &CreateAppRequest, creator_id: i64) -> Result<App, sqlx::Error>;
+
+    // 只在当前版本等于 version 时更新并将版本加一；应用不存在或版本已变化时返回 None
+    async fn update(
+        &self,
+        id: i64,
+        changes: &UpdateAppRequest,
+        updater_id: i64,
+        version: i64,
+    ) -> Result<Option<App>, sqlx::Error>;
+
+    // 移入回收站，同时移入该应用下未删除的文章；应用不存在或已删除时返回 false
+    async fn trash(&self, id: i64, deleted_by: i64) -> Result<bool, sqlx::Error>;
+
+    async fn find_trashed(&self, id: i64) -> Result<Option<App>, sqlx::Error>;
+
+    // 恢复应用和随它一起移入回收站的文章，之前单独删除的文章仍留在回收站中
+    async fn restore(&self, id: i64) -> Result<bool, sqlx::Error>;
+
+    // 永久删除在 before 之前移入回收站的应用及其全部文章
+    async fn purge_trashed(&self, before: DateTime<Utc>) -> Result<u64, sqlx::Error>;
+}
+
+// 属于应用的文章在创建、修改、删除和恢复时，同一事务中写入对应的变更事件
+#[async_trait]
+pub trait ArticleRepository: Send + Sync {
+    async fn find_visible(&self, id: i64, viewer_id: i64) -> Result<Option<Article>, sqlx::Error>;
+
+    // page 为 None 时返回全部
+    async fn list_visible(
+        &self,
+        viewer_id: i64,
+        filter: &ArticleFilter,
+        page: Option<Page>,
+    ) -> Result<Vec<Article>, sqlx::Error>;
+
+    async fn count_visible(&self, viewer_id: i64, filter: &ArticleFilter) -> Result<i64, sqlx::Error>;
+
+    // 已发布的原文及其译文，group_ids 为原文 id
+    async fn list_translations(&self, group_ids: &[i64]) -> Result<Vec<Article>, sqlx::Error>;
+
+    // 不做可见性过滤，由调用方处理
+    async fn list_by_authors(&self, author_ids: &[i64]) -> Result<Vec<Article>, sqlx::Error>;
+
+    // 应用下未删除的全部文章，包括其他作者的草稿，按 id 排序；不做可见性过滤，由调用方处理
+    async fn list_in_app(&self, app_id: i64) -> Result<Vec<Article>, sqlx::Error>;
+
+    async fn create(&self, article: &CreateArticleRequest, author_id: i64) -> Result<Article, sqlx::Error>;
+
+    // 导入时保留原来的创建和修改时间
+    async fn set_timestamps(
+        &self,
+        id: i64,
+        created_at: DateTime<Utc>,
+        updated_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+
+    // 只在当前版本等于 version 时更新并将版本加一；文章不存在、不属于该作者或版本已变化时返回 None
+    async fn update(
+        &self,
+        id: i64,
+        author_id: i64,
+        changes: &UpdateArticleRequest,
+        version: i64,
+    ) -> Result<Option<Article>, sqlx::Error>;
+
+    // 移入回收站；文章不存在、已删除或不属于该作者时返回 false
+    async fn trash(&self, id: i64, author_id: i64) -> Result<bool, sqlx::Error>;
+
+    async fn find_trashed(&self, id: i64, author_id: i64) -> Result<Option<Article>, sqlx::Error>;
+
+    // 该作者回收站中的文章，最近删除的在前
+    async fn list_trashed(&self, author_id: i64) -> Result<Vec<Article>, sqlx::Error>;
+
+    async fn restore(&self, id: i64, author_id: i64) -> Result<bool, sqlx::Error>;
+
+    // 永久删除在 before 之前移入回收站的文章
+    async fn purge_trashed(&self, before: DateTime<Utc>) -> Result<u64, sqlx::Error>;
+}
+
+#[async_trait]
+pub trait OutboxRepository: Send + Sync {
+    async fn enqueue(&self, message: &EmailMessage) -> Result<i64, sqlx::Error>;
+
+    async fn find_by_id(&self, id: i64) -> Result<Option<OutboxEmail>, sqlx::Error>;
+
+    async fn list(&self, filter: &OutboxFilter, page: Page) -> Result<Vec<OutboxEmail>, sqlx::Error>;
+
+    async fn count(&self, filter: &OutboxFilter) -> Result<i64, sqlx::Error>;
+
+    // 领取新邮件、退避已到期的待发邮件和租约已过期的发送中邮件，领取时 attempts 加一
+    async fn claim_due(
+        &self,
+        now: DateTime<Utc>,
+        locked_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<OutboxEmail>, sqlx::Error>;
+
+    async fn mark_sent(&self, id: i64, now: DateTime<Utc>) -> Result<(), sqlx::Error>;
+
+    // next_attempt_at 为 None 时进入死信
+    async fn mark_failed(
+        &self,
+        id: i64,
+        error: &str,
+        next_attempt_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+
+    // 死信重新投递，其他状态返回 false
+    async fn retry(&self, id: i64, now: DateTime<Utc>) -> Result<bool, sqlx::Error>;
+}
+
+#[async_trait]
+pub trait JobRepository: Send + Sync {
+    // 已有相同 unique_key 的未结束任务时不再创建，返回 (id, 是否新建)
+    async fn enqueue(&self, job: &NewJob) -> Result<(i64, bool), sqlx::Error>;
+
+    async fn find_by_id(&self, id: i64) -> Result<Option<JobRecord>, sqlx::Error>;
+
+    async fn list(&self, filter: &JobFilter, page: Page) -> Result<Vec<JobRecord>, sqlx::Error>;
+
+    async fn count(&self, filter: &JobFilter) -> Result<i64, sqlx::Error>;
+
+    async fn counts(&self) -> Result<Vec<JobCount>, sqlx::Error>;
+
+    // 领取这些队列中到期的任务和租约已过期的运行中任务，领取时 attempts 加一
+    async fn claim(
+        &self,
+        queues: &[String],
+        now: DateTime<Utc>,
+        locked_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<JobRecord>, sqlx::Error>;
+
+    async fn complete(&self, id: i64, now: DateTime<Utc>) -> Result<(), sqlx::Error>;
+
+    // retry_at 为 None 时进入死信
+    async fn fail(
+        &self,
+        id: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+
+    // 死信重新排队并恢复结束时清空的 unique_key；不是死信或 key 已被其他未结束的任务占用时返回 false
+    async fn retry(&self, id: i64, unique_key: Option<&str>, now: DateTime<Utc>) -> Result<bool, sqlx::Error>;
+
+    // 删除在 before 之前完成的任务
+    async fn purge_completed(&self, before: DateTime<Utc>) -> Result<u64, sqlx::Error>;
+
+    // 多个 worker 中只有一个能把定时任务推进到 fire_at
+    async fn claim_schedule(&self, name: &str, fire_at: DateTime<Utc>) -> Result<bool, sqlx::Error>;
+}
+
+// 只提供追加和查询，旧记录由保留期任务删除
+#[async_trait]
+pub trait AuditRepository: Send + Sync {
+    async fn append(&self, entry: &NewAuditLog, now: DateTime<Utc>) -> Result<i64, sqlx::Error>;
+
+    async fn list(&self, filter: &AuditFilter, page: Page) -> Result<Vec<AuditLog>, sqlx::Error>;
+
+    async fn count(&self, filter: &AuditFilter) -> Result<i64, sqlx::Error>;
+
+    // 删除 before 之前的记录
+    async fn purge_before(&self, before: DateTime<Utc>) -> Result<u64, sqlx::Error>;
+}
+
+#[async_trait]
+pub trait ApiKeyRepository: Send + Sync {
+    async fn create(&self, key: &NewApiKey, now: DateTime<Utc>) -> Result<ApiKey, sqlx::Error>;
+
+    async fn list_by_app(&self, app_id: i64) -> Result<Vec<ApiKey>, sqlx::Error>;
+
+    async fn find_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>, sqlx::Error>;
+
+    // 不属于该应用时返回 false
+    async fn delete(&self, app_id: i64, id: i64) -> Result<bool, sqlx::Error>;
+}
+
+// 变更事件由应用和文章的修改写入，写入串行执行，事件按 id 顺序提交；旧事件由保留期任务删除
+#[async_trait]
+pub trait ChangeEventRepository: Send + Sync {
+    // 应用中 id 大于 after_id 的事件，按 id 升序
+    async fn list_since(&self, app_id: i64, after_id: i64, limit: i64) -> Result<Vec<ChangeEvent>, sqlx::Error>;
+
+    // 应用最新事件的 id，没有事件时为 0
+    async fn last_id(&self, app_id: i64) -> Result<i64, sqlx::Error>;
+
+    // 删除 before 之前的事件
+    async fn purge_before(&self, before: DateTime<Utc>) -> Result<u64, sqlx::Error>;
+}
+
+#[async_trait]
+pub trait PreviewLinkRepository: Send + Sync {
+    async fn create(&self, link: &NewPreviewLink, now: DateTime<Utc>) -> Result<PreviewLink, sqlx::Error>;
+
+    async fn find_by_hash(&self, token_hash: &str) -> Result<Option<PreviewLink>, sqlx::Error>;
+
+    async fn list_by_article(&self, article_id: i64) -> Result<Vec<PreviewLink>, sqlx::Error>;
+
+    // 已吊销或不属于该文章时返回 false
+    async fn revoke(&self, article_id: i64, id: i64, now: DateTime<Utc>) -> Result<bool, sqlx::Error>;
+}
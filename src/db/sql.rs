@@ -0,0 +1,1899 @@
+// 三种数据库共用的仓储实现。
+// 调用方模块需要提供：
+//   type Db                        —— sqlx 数据库类型
+//   fn sql(&str) -> Cow<str>       —— 把 ? 占位符转换成该数据库的写法
+//   const RETURNING_ID: &str       —— INSERT 语句返回自增 id 的后缀
+//   async fn insert_id(executor, query) —— 执行 INSERT 并返回新记录的 id
+// 需要与发件箱或变更事件一起写入的操作在同一事务中执行。
+// 动态拼接的查询使用 QueryBuilder，占位符由 sqlx 生成。
+macro_rules! impl_repositories {
+    ($repo:ty) => {
+        fn push_app_filter(qb: &mut sqlx::QueryBuilder<'_, Db>, filter: &$crate::db::AppFilter) {
+            if filter.trashed {
+                qb.push(" AND deleted_at IS NOT NULL");
+            } else {
+                qb.push(" AND deleted_at IS NULL");
+            }
+            if let Some(keyword) = &filter.keyword {
+                qb.push(" AND name LIKE ").push_bind(format!("%{}%", keyword));
+            }
+            if let Some(identifier) = &filter.identifier {
+                qb.push(" AND identifier = ").push_bind(identifier.clone());
+            }
+            if let Some(creator_id) = filter.creator_id {
+                qb.push(" AND creator_id = ").push_bind(creator_id);
+            }
+        }
+
+        // 只能看到已发布的文章和自己的草稿
+        fn push_article_filter(
+            qb: &mut sqlx::QueryBuilder<'_, Db>,
+            viewer_id: i64,
+            filter: &$crate::db::ArticleFilter,
+        ) {
+            qb.push(" WHERE deleted_at IS NULL AND (status = 2 OR author_id = ")
+                .push_bind(viewer_id)
+                .push(")");
+            if let Some(keyword) = &filter.keyword {
+                qb.push(" AND title LIKE ").push_bind(format!("%{}%", keyword));
+            }
+            if let Some(status) = filter.status {
+                qb.push(" AND status = ").push_bind(status);
+            }
+            if let Some(author_id) = filter.author_id {
+                qb.push(" AND author_id = ").push_bind(author_id);
+            }
+            if let Some(app_id) = filter.app_id {
+                qb.push(" AND app_id = ").push_bind(app_id);
+            }
+            if let Some(category) = &filter.category {
+                qb.push(" AND category = ").push_bind(category.clone());
+            }
+            if filter.indexable {
+                qb.push(" AND noindex = 0");
+            }
+        }
+
+        fn push_outbox_filter(qb: &mut sqlx::QueryBuilder<'_, Db>, filter: &$crate::db::OutboxFilter) {
+            if let Some(status) = &filter.status {
+                qb.push(" AND status = ").push_bind(status.clone());
+            }
+        }
+
+        fn push_job_filter(qb: &mut sqlx::QueryBuilder<'_, Db>, filter: &$crate::db::JobFilter) {
+            if let Some(status) = &filter.status {
+                qb.push(" AND status = ").push_bind(status.clone());
+            }
+            if let Some(queue) = &filter.queue {
+                qb.push(" AND queue = ").push_bind(queue.clone());
+            }
+            if let Some(kind) = &filter.kind {
+                qb.push(" AND kind = ").push_bind(kind.clone());
+            }
+        }
+
+        fn is_unique_violation(error: &sqlx::Error) -> bool {
+            matches!(error, sqlx::Error::Database(e) if e.is_unique_violation())
+        }
+
+        fn push_audit_filter(qb: &mut sqlx::QueryBuilder<'_, Db>, filter: &$crate::db::AuditFilter) {
+            if let Some(actor_id) = filter.actor_id {
+                qb.push(" AND actor_id = ").push_bind(actor_id);
+            }
+            if let Some(action) = &filter.action {
+                qb.push(" AND action = ").push_bind(action.clone());
+            }
+            if let Some(target_type) = &filter.target_type {
+                qb.push(" AND target_type = ").push_bind(target_type.clone());
+            }
+            if let Some(target_id) = filter.target_id {
+                qb.push(" AND target_id = ").push_bind(target_id);
+            }
+            if let Some(app_id) = filter.app_id {
+                qb.push(" AND app_id = ").push_bind(app_id);
+            }
+            if let Some(since) = filter.since {
+                qb.push(" AND created_at >= ").push_bind(since);
+            }
+            if let Some(until) = filter.until {
+                qb.push(" AND created_at < ").push_bind(until);
+            }
+        }
+
+        async fn insert_outbox<'e, E>(
+            executor: E,
+            message: &$crate::models::outbox::EmailMessage,
+            now: chrono::DateTime<chrono::Utc>,
+        ) -> Result<i64, sqlx::Error>
+        where
+            E: sqlx::Executor<'e, Database = Db>,
+        {
+            let statement = format!(
+                "INSERT INTO email_outbox (template, recipient, from_name, from_email, subject, html_body, \
+                 text_body, status, attempts, next_attempt_at, created_at, updated_at) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?){}",
+                RETURNING_ID
+            );
+            let statement = sql(&statement);
+            let query = sqlx::query(&statement)
+                .bind(&message.template)
+                .bind(&message.recipient)
+                .bind(&message.from_name)
+                .bind(&message.from_email)
+                .bind(&message.subject)
+                .bind(&message.html_body)
+                .bind(&message.text_body)
+                .bind($crate::models::outbox::OUTBOX_PENDING)
+                .bind(0i32)
+                .bind(now)
+                .bind(now)
+                .bind(now);
+            insert_id(executor, query).await
+        }
+
+        // 变更事件与应用、文章的修改在同一事务中写入，放在事务最后以缩短持锁时间。
+        // 先锁住 change_event_sequence 的唯一一行，其他写入者（包括其他实例）等到本事务提交后
+        // 才分配 id，事件按 id 顺序提交，订阅者按 id 读取时不会跳过稍后提交的较小 id
+        async fn insert_change_event(
+            tx: &mut sqlx::Transaction<'_, Db>,
+            event: &$crate::models::change::NewChangeEvent,
+            now: chrono::DateTime<chrono::Utc>,
+        ) -> Result<i64, sqlx::Error> {
+            sqlx::query("UPDATE change_event_sequence SET appended = appended + 1 WHERE id = 1")
+                .execute(&mut **tx)
+                .await?;
+            let statement = format!(
+                "INSERT INTO change_events (app_id, event_type, target_type, target_id, created_at) \
+                 VALUES (?, ?, ?, ?, ?){}",
+                RETURNING_ID
+            );
+            let statement = sql(&statement);
+            let query = sqlx::query(&statement)
+                .bind(event.app_id)
+                .bind(&event.event_type)
+                .bind(&event.target_type)
+                .bind(event.target_id)
+                .bind(now);
+            insert_id(&mut **tx, query).await
+        }
+
+        // 属于应用的文章才有变更事件
+        async fn record_article_change(
+            tx: &mut sqlx::Transaction<'_, Db>,
+            article_id: i64,
+            event_type: &str,
+            now: chrono::DateTime<chrono::Utc>,
+        ) -> Result<(), sqlx::Error> {
+            let app_id: Option<i64> = sqlx::query_scalar(&sql("SELECT app_id FROM articles WHERE id = ?"))
+                .bind(article_id)
+                .fetch_one(&mut **tx)
+                .await?;
+            if let Some(app_id) = app_id {
+                let event = $crate::models::change::NewChangeEvent::article(app_id, article_id, event_type);
+                insert_change_event(tx, &event, now).await?;
+            }
+            Ok(())
+        }
+
+        // 删除该用户的全部恢复码，再写入新的恢复码
+        async fn insert_recovery_codes(
+            tx: &mut sqlx::Transaction<'_, Db>,
+            user_id: i64,
+            code_hashes: &[String],
+            now: chrono::DateTime<chrono::Utc>,
+        ) -> Result<(), sqlx::Error> {
+            sqlx::query(&sql("DELETE FROM recovery_codes WHERE user_id = ?"))
+                .bind(user_id)
+                .execute(&mut **tx)
+                .await?;
+            for code_hash in code_hashes {
+                sqlx::query(&sql("INSERT INTO recovery_codes (user_id, code_hash, created_at) VALUES (?, ?, ?)"))
+                    .bind(user_id)
+                    .bind(code_hash)
+                    .bind(now)
+                    .execute(&mut **tx)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn insert_identity<'e, E>(
+            executor: E,
+            user_id: i64,
+            identity: &$crate::models::oidc::NewUserIdentity,
+            now: chrono::DateTime<chrono::Utc>,
+        ) -> Result<i64, sqlx::Error>
+        where
+            E: sqlx::Executor<'e, Database = Db>,
+        {
+            let statement = format!(
+                "INSERT INTO user_identities (user_id, provider, subject, email, created_at, last_login_at) \
+                 VALUES (?, ?, ?, ?, ?, ?){}",
+                RETURNING_ID
+            );
+            let statement = sql(&statement);
+            let query = sqlx::query(&statement)
+                .bind(user_id)
+                .bind(&identity.provider)
+                .bind(&identity.subject)
+                .bind(&identity.email)
+                .bind(now)
+                .bind(now);
+            insert_id(executor, query).await
+        }
+
+        fn push_id_list(qb: &mut sqlx::QueryBuilder<'_, Db>, ids: &[i64]) {
+            let mut separated = qb.separated(", ");
+            for id in ids {
+                separated.push_bind(*id);
+            }
+            separated.push_unseparated(")");
+        }
+
+        #[async_trait::async_trait]
+        impl $crate::db::UserRepository for $repo {
+            async fn find_by_id(&self, id: i64) -> Result<Option<$crate::models::User>, sqlx::Error> {
+                sqlx::query_as::<Db, $crate::models::User>(&sql("SELECT * FROM users WHERE id = ?"))
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn find_by_email(&self, email: &str) -> Result<Option<$crate::models::User>, sqlx::Error> {
+                sqlx::query_as::<Db, $crate::models::User>(&sql("SELECT * FROM users WHERE email = ?"))
+                    .bind(email)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn find_by_ids(&self, ids: &[i64]) -> Result<Vec<$crate::models::User>, sqlx::Error> {
+                if ids.is_empty() {
+                    return Ok(Vec::new());
+                }
+                let mut qb = sqlx::QueryBuilder::<Db>::new("SELECT * FROM users WHERE id IN (");
+                push_id_list(&mut qb, ids);
+                qb.build_query_as().fetch_all(&self.pool).await
+            }
+
+            async fn create(
+                &self,
+                user: $crate::db::NewUser,
+                message: &$crate::models::outbox::EmailMessage,
+            ) -> Result<i64, sqlx::Error> {
+                let now = chrono::Utc::now();
+                let mut tx = self.pool.begin().await?;
+                let statement = format!(
+                    "INSERT INTO users (username, email, email_verified, verification_code, \
+                     verification_code_expires_at, locale, role, created_at, updated_at) \
+                     VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?){}",
+                    RETURNING_ID
+                );
+                let statement = sql(&statement);
+                let query = sqlx::query(&statement)
+                    .bind(user.username)
+                    .bind(user.email)
+                    .bind(0i16) // false
+                    .bind(user.verification_code)
+                    .bind(user.verification_code_expires_at)
+                    .bind(user.locale)
+                    .bind(user.role)
+                    .bind(now)
+                    .bind(now);
+                let id = insert_id(&mut *tx, query).await?;
+                insert_outbox(&mut *tx, message, now).await?;
+                tx.commit().await?;
+                Ok(id)
+            }
+
+            async fn set_verification_code(
+                &self,
+                email: &str,
+                code: &str,
+                expires_at: chrono::DateTime<chrono::Utc>,
+                message: &$crate::models::outbox::EmailMessage,
+            ) -> Result<(), sqlx::Error> {
+                let now = chrono::Utc::now();
+                let mut tx = self.pool.begin().await?;
+                sqlx::query(&sql(
+                    "UPDATE users SET verification_code = ?, verification_code_expires_at = ?, \
+                     verification_attempts = 0, updated_at = ? WHERE email = ?",
+                ))
+                .bind(code)
+                .bind(expires_at)
+                .bind(now)
+                .bind(email)
+                .execute(&mut *tx)
+                .await?;
+                insert_outbox(&mut *tx, message, now).await?;
+                tx.commit().await
+            }
+
+            async fn record_failed_attempt(&self, id: i64, max_attempts: i32) -> Result<(), sqlx::Error> {
+                sqlx::query(&sql(
+                    "UPDATE users \
+                     SET verification_code = CASE WHEN verification_attempts + 1 >= ? THEN NULL ELSE verification_code END, \
+                         verification_code_expires_at = CASE WHEN verification_attempts + 1 >= ? THEN NULL ELSE verification_code_expires_at END, \
+                         verification_attempts = verification_attempts + 1 \
+                     WHERE id = ?",
+                ))
+                .bind(max_attempts)
+                .bind(max_attempts)
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+                Ok(())
+            }
+
+            async fn mark_verified(
+                &self,
+                id: i64,
+                welcome: Option<&$crate::models::outbox::EmailMessage>,
+            ) -> Result<(), sqlx::Error> {
+                let now = chrono::Utc::now();
+                let mut tx = self.pool.begin().await?;
+                sqlx::query(&sql(
+                    "UPDATE users SET email_verified = ?, verification_code = NULL, \
+                     verification_code_expires_at = NULL, verification_attempts = 0, updated_at = ? \
+                     WHERE id = ?",
+                ))
+                .bind(1i16) // true
+                .bind(now)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+                sqlx::query(&sql("DELETE FROM magic_links WHERE user_id = ? AND used_at IS NULL"))
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                if let Some(welcome) = welcome {
+                    insert_outbox(&mut *tx, welcome, now).await?;
+                }
+                tx.commit().await
+            }
+
+            async fn create_magic_link(
+                &self,
+                link: &$crate::models::magic_link::NewMagicLink,
+                code: &str,
+                message: &$crate::models::outbox::EmailMessage,
+            ) -> Result<i64, sqlx::Error> {
+                let now = chrono::Utc::now();
+                let mut tx = self.pool.begin().await?;
+                sqlx::query(&sql("DELETE FROM magic_links WHERE user_id = ? AND used_at IS NULL"))
+                    .bind(link.user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                let statement = format!(
+                    "INSERT INTO magic_links (user_id, token_hash, device_hash, expires_at, created_at) \
+                     VALUES (?, ?, ?, ?, ?){}",
+                    RETURNING_ID
+                );
+                let statement = sql(&statement);
+                let query = sqlx::query(&statement)
+                    .bind(link.user_id)
+                    .bind(&link.token_hash)
+                    .bind(&link.device_hash)
+                    .bind(link.expires_at)
+                    .bind(now);
+                let id = insert_id(&mut *tx, query).await?;
+                // 备用验证码与链接同时过期
+                sqlx::query(&sql(
+                    "UPDATE users SET verification_code = ?, verification_code_expires_at = ?, \
+                     verification_attempts = 0, updated_at = ? WHERE id = ?",
+                ))
+                .bind(code)
+                .bind(link.expires_at)
+                .bind(now)
+                .bind(link.user_id)
+                .execute(&mut *tx)
+                .await?;
+                insert_outbox(&mut *tx, message, now).await?;
+                tx.commit().await?;
+                Ok(id)
+            }
+
+            async fn find_magic_link(
+                &self,
+                token_hash: &str,
+            ) -> Result<Option<$crate::models::magic_link::MagicLink>, sqlx::Error> {
+                sqlx::query_as::<Db, $crate::models::magic_link::MagicLink>(&sql(
+                    "SELECT * FROM magic_links WHERE token_hash = ?",
+                ))
+                .bind(token_hash)
+                .fetch_optional(&self.pool)
+                .await
+            }
+
+            async fn consume_magic_link(&self, id: i64, now: chrono::DateTime<chrono::Utc>) -> Result<bool, sqlx::Error> {
+                let result = sqlx::query(&sql("UPDATE magic_links SET used_at = ? WHERE id = ? AND used_at IS NULL"))
+                    .bind(now)
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn set_password(&self, id: i64, password_hash: &str) -> Result<(), sqlx::Error> {
+                sqlx::query(&sql("UPDATE users SET password_hash = ?, updated_at = ? WHERE id = ?"))
+                    .bind(password_hash)
+                    .bind(chrono::Utc::now())
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn set_totp_secret(&self, id: i64, secret: &str) -> Result<(), sqlx::Error> {
+                sqlx::query(&sql(
+                    "UPDATE users SET totp_secret = ?, totp_enabled = ?, totp_last_step = NULL, updated_at = ? \
+                     WHERE id = ?",
+                ))
+                .bind(secret)
+                .bind(0i16) // false
+                .bind(chrono::Utc::now())
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+                Ok(())
+            }
+
+            async fn enable_totp(&self, id: i64, recovery_code_hashes: &[String]) -> Result<(), sqlx::Error> {
+                let now = chrono::Utc::now();
+                let mut tx = self.pool.begin().await?;
+                sqlx::query(&sql("UPDATE users SET totp_enabled = ?, updated_at = ? WHERE id = ?"))
+                    .bind(1i16) // true
+                    .bind(now)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                insert_recovery_codes(&mut tx, id, recovery_code_hashes, now).await?;
+                tx.commit().await
+            }
+
+            async fn disable_totp(&self, id: i64) -> Result<(), sqlx::Error> {
+                let now = chrono::Utc::now();
+                let mut tx = self.pool.begin().await?;
+                sqlx::query(&sql(
+                    "UPDATE users SET totp_secret = NULL, totp_enabled = ?, totp_last_step = NULL, updated_at = ? \
+                     WHERE id = ?",
+                ))
+                .bind(0i16) // false
+                .bind(now)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+                insert_recovery_codes(&mut tx, id, &[], now).await?;
+                tx.commit().await
+            }
+
+            async fn replace_recovery_codes(&self, id: i64, recovery_code_hashes: &[String]) -> Result<(), sqlx::Error> {
+                let mut tx = self.pool.begin().await?;
+                insert_recovery_codes(&mut tx, id, recovery_code_hashes, chrono::Utc::now()).await?;
+                tx.commit().await
+            }
+
+            async fn use_recovery_code(
+                &self,
+                id: i64,
+                code_hash: &str,
+                now: chrono::DateTime<chrono::Utc>,
+            ) -> Result<bool, sqlx::Error> {
+                let result = sqlx::query(&sql(
+                    "UPDATE recovery_codes SET used_at = ? WHERE user_id = ? AND code_hash = ? AND used_at IS NULL",
+                ))
+                .bind(now)
+                .bind(id)
+                .bind(code_hash)
+                .execute(&self.pool)
+                .await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn count_recovery_codes(&self, id: i64) -> Result<i64, sqlx::Error> {
+                sqlx::query_scalar(&sql("SELECT COUNT(*) FROM recovery_codes WHERE user_id = ? AND used_at IS NULL"))
+                    .bind(id)
+                    .fetch_one(&self.pool)
+                    .await
+            }
+
+            async fn record_totp_step(&self, id: i64, step: i64) -> Result<bool, sqlx::Error> {
+                let result = sqlx::query(&sql(
+                    "UPDATE users SET totp_last_step = ? \
+                     WHERE id = ? AND (totp_last_step IS NULL OR totp_last_step < ?)",
+                ))
+                .bind(step)
+                .bind(id)
+                .bind(step)
+                .execute(&self.pool)
+                .await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn create_token(
+                &self,
+                token: &$crate::models::credential::NewUserToken,
+                message: Option<&$crate::models::outbox::EmailMessage>,
+            ) -> Result<i64, sqlx::Error> {
+                let now = chrono::Utc::now();
+                let mut tx = self.pool.begin().await?;
+                sqlx::query(&sql("DELETE FROM user_tokens WHERE user_id = ? AND purpose = ?"))
+                    .bind(token.user_id)
+                    .bind(&token.purpose)
+                    .execute(&mut *tx)
+                    .await?;
+                let statement = format!(
+                    "INSERT INTO user_tokens (user_id, purpose, token_hash, attempts, expires_at, created_at) \
+                     VALUES (?, ?, ?, ?, ?, ?){}",
+                    RETURNING_ID
+                );
+                let statement = sql(&statement);
+                let query = sqlx::query(&statement)
+                    .bind(token.user_id)
+                    .bind(&token.purpose)
+                    .bind(&token.token_hash)
+                    .bind(0i32)
+                    .bind(token.expires_at)
+                    .bind(now);
+                let id = insert_id(&mut *tx, query).await?;
+                if let Some(message) = message {
+                    insert_outbox(&mut *tx, message, now).await?;
+                }
+                tx.commit().await?;
+                Ok(id)
+            }
+
+            async fn find_token(
+                &self,
+                purpose: &str,
+                token_hash: &str,
+            ) -> Result<Option<$crate::models::credential::UserToken>, sqlx::Error> {
+                sqlx::query_as::<Db, $crate::models::credential::UserToken>(&sql(
+                    "SELECT * FROM user_tokens WHERE purpose = ? AND token_hash = ?",
+                ))
+                .bind(purpose)
+                .bind(token_hash)
+                .fetch_optional(&self.pool)
+                .await
+            }
+
+            async fn consume_token(&self, id: i64, now: chrono::DateTime<chrono::Utc>) -> Result<bool, sqlx::Error> {
+                let result = sqlx::query(&sql("UPDATE user_tokens SET used_at = ? WHERE id = ? AND used_at IS NULL"))
+                    .bind(now)
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn record_token_failure(
+                &self,
+                id: i64,
+                max_attempts: i32,
+                now: chrono::DateTime<chrono::Utc>,
+            ) -> Result<(), sqlx::Error> {
+                sqlx::query(&sql(
+                    "UPDATE user_tokens \
+                     SET used_at = CASE WHEN attempts + 1 >= ? THEN ? ELSE used_at END, \
+                         attempts = attempts + 1 \
+                     WHERE id = ?",
+                ))
+                .bind(max_attempts)
+                .bind(now)
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+                Ok(())
+            }
+
+            async fn set_role(&self, id: i64, role: &str) -> Result<(), sqlx::Error> {
+                sqlx::query(&sql("UPDATE users SET role = ?, updated_at = ? WHERE id = ?"))
+                    .bind(role)
+                    .bind(chrono::Utc::now())
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn create_oidc_state(
+                &self,
+                state: &$crate::models::oidc::NewOidcState,
+                now: chrono::DateTime<chrono::Utc>,
+            ) -> Result<i64, sqlx::Error> {
+                let mut tx = self.pool.begin().await?;
+                sqlx::query(&sql("DELETE FROM oidc_states WHERE expires_at <= ?"))
+                    .bind(now)
+                    .execute(&mut *tx)
+                    .await?;
+                let statement = format!(
+                    "INSERT INTO oidc_states (provider, state_hash, nonce, code_verifier, expires_at, created_at) \
+                     VALUES (?, ?, ?, ?, ?, ?){}",
+                    RETURNING_ID
+                );
+                let statement = sql(&statement);
+                let query = sqlx::query(&statement)
+                    .bind(&state.provider)
+                    .bind(&state.state_hash)
+                    .bind(&state.nonce)
+                    .bind(&state.code_verifier)
+                    .bind(state.expires_at)
+                    .bind(now);
+                let id = insert_id(&mut *tx, query).await?;
+                tx.commit().await?;
+                Ok(id)
+            }
+
+            async fn take_oidc_state(
+                &self,
+                state_hash: &str,
+            ) -> Result<Option<$crate::models::oidc::OidcState>, sqlx::Error> {
+                let Some(state) = sqlx::query_as::<Db, $crate::models::oidc::OidcState>(&sql(
+                    "SELECT * FROM oidc_states WHERE state_hash = ?",
+                ))
+                .bind(state_hash)
+                .fetch_optional(&self.pool)
+                .await?
+                else {
+                    return Ok(None);
+                };
+                // 并发回调中只有删除成功的一方可以继续
+                let result = sqlx::query(&sql("DELETE FROM oidc_states WHERE id = ?"))
+                    .bind(state.id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok((result.rows_affected() > 0).then_some(state))
+            }
+
+            async fn find_identity(
+                &self,
+                provider: &str,
+                subject: &str,
+            ) -> Result<Option<$crate::models::oidc::UserIdentity>, sqlx::Error> {
+                sqlx::query_as::<Db, $crate::models::oidc::UserIdentity>(&sql(
+                    "SELECT * FROM user_identities WHERE provider = ? AND subject = ?",
+                ))
+                .bind(provider)
+                .bind(subject)
+                .fetch_optional(&self.pool)
+                .await
+            }
+
+            async fn record_identity_login(&self, id: i64, now: chrono::DateTime<chrono::Utc>) -> Result<(), sqlx::Error> {
+                sqlx::query(&sql("UPDATE user_identities SET last_login_at = ? WHERE id = ?"))
+                    .bind(now)
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn link_identity(
+                &self,
+                user_id: i64,
+                identity: &$crate::models::oidc::NewUserIdentity,
+            ) -> Result<i64, sqlx::Error> {
+                insert_identity(&self.pool, user_id, identity, chrono::Utc::now()).await
+            }
+
+            async fn create_federated_user(
+                &self,
+                user: &$crate::models::oidc::NewFederatedUser,
+                identity: &$crate::models::oidc::NewUserIdentity,
+            ) -> Result<i64, sqlx::Error> {
+                let now = chrono::Utc::now();
+                let mut tx = self.pool.begin().await?;
+                let statement = format!(
+                    "INSERT INTO users (username, email, email_verified, locale, role, created_at, updated_at) \
+                     VALUES (?, ?, ?, ?, ?, ?, ?){}",
+                    RETURNING_ID
+                );
+                let statement = sql(&statement);
+                let query = sqlx::query(&statement)
+                    .bind(&user.username)
+                    .bind(&user.email)
+                    .bind(0i16) // false，登录完成时标记
+                    .bind(&user.locale)
+                    .bind(&user.role)
+                    .bind(now)
+                    .bind(now);
+                let id = insert_id(&mut *tx, query).await?;
+                insert_identity(&mut *tx, id, identity, now).await?;
+                tx.commit().await?;
+                Ok(id)
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl $crate::db::AppRepository for $repo {
+            async fn find_by_id(&self, id: i64) -> Result<Option<$crate::models::App>, sqlx::Error> {
+                sqlx::query_as::<Db, $crate::models::App>(&sql(
+                    "SELECT * FROM apps WHERE id = ? AND deleted_at IS NULL",
+                ))
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await
+            }
+
+            async fn find_by_identifier(&self, identifier: &str) -> Result<Option<$crate::models::App>, sqlx::Error> {
+                sqlx::query_as::<Db, $crate::models::App>(&sql(
+                    "SELECT * FROM apps WHERE identifier = ? AND deleted_at IS NULL",
+                ))
+                .bind(identifier)
+                .fetch_optional(&self.pool)
+                .await
+            }
+
+            async fn list(
+                &self,
+                filter: &$crate::db::AppFilter,
+                page: $crate::db::Page,
+            ) -> Result<Vec<$crate::models::App>, sqlx::Error> {
+                let mut qb = sqlx::QueryBuilder::<Db>::new("SELECT * FROM apps WHERE 1=1");
+                push_app_filter(&mut qb, filter);
+                // 回收站按删除时间排序
+                qb.push(if filter.trashed {
+                    " ORDER BY deleted_at DESC, id DESC LIMIT "
+                } else {
+                    " ORDER BY created_at DESC LIMIT "
+                })
+                    .push_bind(page.limit)
+                    .push(" OFFSET ")
+                    .push_bind(page.offset);
+                qb.build_query_as().fetch_all(&self.pool).await
+            }
+
+            async fn count(&self, filter: &$crate::db::AppFilter) -> Result<i64, sqlx::Error> {
+                let mut qb = sqlx::QueryBuilder::<Db>::new("SELECT COUNT(*) FROM apps WHERE 1=1");
+                push_app_filter(&mut qb, filter);
+                qb.build_query_scalar().fetch_one(&self.pool).await
+            }
+
+            async fn list_by_creators(&self, creator_ids: &[i64]) -> Result<Vec<$crate::models::App>, sqlx::Error> {
+                if creator_ids.is_empty() {
+                    return Ok(Vec::new());
+                }
+                let mut qb =
+                    sqlx::QueryBuilder::<Db>::new("SELECT * FROM apps WHERE deleted_at IS NULL AND creator_id IN (");
+                push_id_list(&mut qb, creator_ids);
+                qb.push(" ORDER BY created_at DESC");
+                qb.build_query_as().fetch_all(&self.pool).await
+            }
+
+            async fn create(
+                &self,
+                app: &$crate::models::CreateAppRequest,
+                creator_id: i64,
+            ) -> Result<$crate::models::App, sqlx::Error> {
+                let now = chrono::Utc::now();
+                let statement = format!(
+                    "INSERT INTO apps (name, description, identifier, logo_url, email_from, feed_title, \
+                     feed_description, feed_link, base_url, require_two_factor, creator_id, created_at, updater_id, \
+                     updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?){}",
+                    RETURNING_ID
+                );
+                let statement = sql(&statement);
+                let query = sqlx::query(&statement)
+                    .bind(&app.name)
+                    .bind(&app.description)
+                    .bind(&app.identifier)
+                    .bind(&app.logo_url)
+                    .bind(&app.email_from)
+                    .bind(&app.feed_title)
+                    .bind(&app.feed_description)
+                    .bind(&app.feed_link)
+                    .bind(&app.base_url)
+                    .bind(app.require_two_factor as i16)
+                    .bind(creator_id)
+                    .bind(now)
+                    .bind(creator_id)
+                    .bind(now);
+                let mut tx = self.pool.begin().await?;
+                let id = insert_id(&mut *tx, query).await?;
+                let event = $crate::models::change::NewChangeEvent::app(id, $crate::models::change::APP_CREATED);
+                insert_change_event(&mut tx, &event, now).await?;
+                tx.commit().await?;
+
+                <Self as $crate::db::AppRepository>::find_by_id(self, id)
+                    .await?
+                    .ok_or(sqlx::Error::RowNotFound)
+            }
+
+            async fn update(
+                &self,
+                id: i64,
+                changes: &$crate::models::UpdateAppRequest,
+                updater_id: i64,
+                version: i64,
+            ) -> Result<Option<$crate::models::App>, sqlx::Error> {
+                let now = chrono::Utc::now();
+                let mut qb = sqlx::QueryBuilder::<Db>::new("UPDATE apps SET version = version + 1, updater_id = ");
+                qb.push_bind(updater_id).push(", updated_at = ").push_bind(now);
+                if let Some(name) = &changes.name {
+                    qb.push(", name = ").push_bind(name.clone());
+                }
+                if let Some(description) = &changes.description {
+                    qb.push(", description = ").push_bind(description.clone());
+                }
+                if let Some(logo_url) = &changes.logo_url {
+                    qb.push(", logo_url = ").push_bind(logo_url.clone());
+                }
+                if let Some(email_from) = &changes.email_from {
+                    qb.push(", email_from = ").push_bind(email_from.clone());
+                }
+                if let Some(feed_title) = &changes.feed_title {
+                    qb.push(", feed_title = ").push_bind(feed_title.clone());
+                }
+                if let Some(feed_description) = &changes.feed_description {
+                    qb.push(", feed_description = ").push_bind(feed_description.clone());
+                }
+                if let Some(feed_link) = &changes.feed_link {
+                    qb.push(", feed_link = ").push_bind(feed_link.clone());
+                }
+                if let Some(base_url) = &changes.base_url {
+                    qb.push(", base_url = ").push_bind(base_url.clone());
+                }
+                if let Some(require_two_factor) = changes.require_two_factor {
+                    qb.push(", require_two_factor = ").push_bind(require_two_factor as i16);
+                }
+                qb.push(" WHERE id = ")
+                    .push_bind(id)
+                    .push(" AND version = ")
+                    .push_bind(version)
+                    .push(" AND deleted_at IS NULL");
+                let mut tx = self.pool.begin().await?;
+                if qb.build().execute(&mut *tx).await?.rows_affected() == 0 {
+                    return Ok(None);
+                }
+                let event = $crate::models::change::NewChangeEvent::app(id, $crate::models::change::APP_UPDATED);
+                insert_change_event(&mut tx, &event, now).await?;
+                tx.commit().await?;
+
+                <Self as $crate::db::AppRepository>::find_by_id(self, id).await
+            }
+
+            async fn trash(&self, id: i64, deleted_by: i64) -> Result<bool, sqlx::Error> {
+                let now = chrono::Utc::now();
+                let mut tx = self.pool.begin().await?;
+                let result = sqlx::query(&sql(
+                    "UPDATE apps SET deleted_at = ?, deleted_by = ? WHERE id = ? AND deleted_at IS NULL",
+                ))
+                .bind(now)
+                .bind(deleted_by)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+                if result.rows_affected() == 0 {
+                    return Ok(false);
+                }
+                // 文章使用相同的删除时间，恢复时据此找出随应用一起删除的文章
+                sqlx::query(&sql(
+                    "UPDATE articles SET deleted_at = ?, deleted_by = ? WHERE app_id = ? AND deleted_at IS NULL",
+                ))
+                .bind(now)
+                .bind(deleted_by)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+                let event = $crate::models::change::NewChangeEvent::app(id, $crate::models::change::APP_DELETED);
+                insert_change_event(&mut tx, &event, now).await?;
+                tx.commit().await?;
+                Ok(true)
+            }
+
+            async fn find_trashed(&self, id: i64) -> Result<Option<$crate::models::App>, sqlx::Error> {
+                sqlx::query_as::<Db, $crate::models::App>(&sql(
+                    "SELECT * FROM apps WHERE id = ? AND deleted_at IS NOT NULL",
+                ))
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await
+            }
+
+            async fn restore(&self, id: i64) -> Result<bool, sqlx::Error> {
+                let mut tx = self.pool.begin().await?;
+                let deleted_at: Option<Option<chrono::DateTime<chrono::Utc>>> =
+                    sqlx::query_scalar(&sql("SELECT deleted_at FROM apps WHERE id = ? AND deleted_at IS NOT NULL"))
+                        .bind(id)
+                        .fetch_optional(&mut *tx)
+                        .await?;
+                let Some(Some(deleted_at)) = deleted_at else {
+                    return Ok(false);
+                };
+                sqlx::query(&sql(
+                    "UPDATE articles SET deleted_at = NULL, deleted_by = NULL WHERE app_id = ? AND deleted_at = ?",
+                ))
+                .bind(id)
+                .bind(deleted_at)
+                .execute(&mut *tx)
+                .await?;
+                sqlx::query(&sql("UPDATE apps SET deleted_at = NULL, deleted_by = NULL WHERE id = ?"))
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                let event = $crate::models::change::NewChangeEvent::app(id, $crate::models::change::APP_RESTORED);
+                insert_change_event(&mut tx, &event, chrono::Utc::now()).await?;
+                tx.commit().await?;
+                Ok(true)
+            }
+
+            async fn purge_trashed(&self, before: chrono::DateTime<chrono::Utc>) -> Result<u64, sqlx::Error> {
+                let mut tx = self.pool.begin().await?;
+                sqlx::query(&sql(
+                    "DELETE FROM preview_links WHERE article_id IN (SELECT id FROM articles WHERE app_id IN \
+                     (SELECT id FROM apps WHERE deleted_at IS NOT NULL AND deleted_at < ?))",
+                ))
+                .bind(before)
+                .execute(&mut *tx)
+                .await?;
+                sqlx::query(&sql(
+                    "DELETE FROM articles WHERE app_id IN \
+                     (SELECT id FROM apps WHERE deleted_at IS NOT NULL AND deleted_at < ?)",
+                ))
+                .bind(before)
+                .execute(&mut *tx)
+                .await?;
+                for table in ["api_keys", "change_events"] {
+                    let statement = format!(
+                        "DELETE FROM {} WHERE app_id IN \
+                         (SELECT id FROM apps WHERE deleted_at IS NOT NULL AND deleted_at < ?)",
+                        table
+                    );
+                    sqlx::query(&sql(&statement)).bind(before).execute(&mut *tx).await?;
+                }
+                let result = sqlx::query(&sql("DELETE FROM apps WHERE deleted_at IS NOT NULL AND deleted_at < ?"))
+                    .bind(before)
+                    .execute(&mut *tx)
+                    .await?;
+                tx.commit().await?;
+                Ok(result.rows_affected())
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl $crate::db::ArticleRepository for $repo {
+            async fn find_visible(
+                &self,
+                id: i64,
+                viewer_id: i64,
+            ) -> Result<Option<$crate::models::Article>, sqlx::Error> {
+                sqlx::query_as::<Db, $crate::models::Article>(&sql(
+                    "SELECT * FROM articles WHERE id = ? AND deleted_at IS NULL AND (status = 2 OR author_id = ?)",
+                ))
+                .bind(id)
+                .bind(viewer_id)
+                .fetch_optional(&self.pool)
+                .await
+            }
+
+            async fn list_visible(
+                &self,
+                viewer_id: i64,
+                filter: &$crate::db::ArticleFilter,
+                page: Option<$crate::db::Page>,
+            ) -> Result<Vec<$crate::models::Article>, sqlx::Error> {
+                let mut qb = sqlx::QueryBuilder::<Db>::new("SELECT * FROM articles");
+                push_article_filter(&mut qb, viewer_id, filter);
+                qb.push(" ORDER BY created_at DESC");
+                if let Some(page) = page {
+                    qb.push(" LIMIT ")
+                        .push_bind(page.limit)
+                        .push(" OFFSET ")
+                        .push_bind(page.offset);
+                }
+                qb.build_query_as().fetch_all(&self.pool).await
+            }
+
+            async fn count_visible(
+                &self,
+                viewer_id: i64,
+                filter: &$crate::db::ArticleFilter,
+            ) -> Result<i64, sqlx::Error> {
+                let mut qb = sqlx::QueryBuilder::<Db>::new("SELECT COUNT(*) FROM articles");
+                push_article_filter(&mut qb, viewer_id, filter);
+                qb.build_query_scalar().fetch_one(&self.pool).await
+            }
+
+            async fn list_translations(&self, group_ids: &[i64]) -> Result<Vec<$crate::models::Article>, sqlx::Error> {
+                if group_ids.is_empty() {
+                    return Ok(Vec::new());
+                }
+                let mut qb = sqlx::QueryBuilder::<Db>::new(
+                    "SELECT * FROM articles WHERE deleted_at IS NULL AND status = 2 AND (id IN (",
+                );
+                push_id_list(&mut qb, group_ids);
+                qb.push(" OR translation_of IN (");
+                push_id_list(&mut qb, group_ids);
+                qb.push(") ORDER BY id");
+                qb.build_query_as().fetch_all(&self.pool).await
+            }
+
+            async fn list_by_authors(&self, author_ids: &[i64]) -> Result<Vec<$crate::models::Article>, sqlx::Error> {
+                if author_ids.is_empty() {
+                    return Ok(Vec::new());
+                }
+                let mut qb = sqlx::QueryBuilder::<Db>::new(
+                    "SELECT * FROM articles WHERE deleted_at IS NULL AND author_id IN (",
+                );
+                push_id_list(&mut qb, author_ids);
+                qb.push(" ORDER BY created_at DESC");
+                qb.build_query_as().fetch_all(&self.pool).await
+            }
+
+            async fn list_in_app(&self, app_id: i64) -> Result<Vec<$crate::models::Article>, sqlx::Error> {
+                sqlx::query_as::<Db, $crate::models::Article>(&sql(
+                    "SELECT * FROM articles WHERE app_id = ? AND deleted_at IS NULL ORDER BY id",
+                ))
+                .bind(app_id)
+                .fetch_all(&self.pool)
+                .await
+            }
+
+            async fn set_timestamps(
+                &self,
+                id: i64,
+                created_at: chrono::DateTime<chrono::Utc>,
+                updated_at: chrono::DateTime<chrono::Utc>,
+            ) -> Result<(), sqlx::Error> {
+                sqlx::query(&sql("UPDATE articles SET created_at = ?, updated_at = ? WHERE id = ?"))
+                    .bind(created_at)
+                    .bind(updated_at)
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn create(
+                &self,
+                article: &$crate::models::article::CreateArticleRequest,
+                author_id: i64,
+            ) -> Result<$crate::models::Article, sqlx::Error> {
+                let now = chrono::Utc::now();
+                let statement = format!(
+                    "INSERT INTO articles (title, content, author_id, app_id, category, locale, translation_of, \
+                     meta_title, meta_description, canonical_url, og_image_url, noindex, status, created_at, updated_at) \
+                     VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?){}",
+                    RETURNING_ID
+                );
+                let statement = sql(&statement);
+                let query = sqlx::query(&statement)
+                    .bind(&article.title)
+                    .bind(&article.content)
+                    .bind(author_id)
+                    .bind(article.app_id)
+                    .bind(&article.category)
+                    .bind(&article.locale)
+                    .bind(article.translation_of)
+                    .bind(&article.meta_title)
+                    .bind(&article.meta_description)
+                    .bind(&article.canonical_url)
+                    .bind(&article.og_image_url)
+                    .bind(article.noindex.unwrap_or(false) as i16)
+                    .bind(article.status.unwrap_or(1)) // 默认为草稿状态
+                    .bind(now)
+                    .bind(now);
+                let mut tx = self.pool.begin().await?;
+                let id = insert_id(&mut *tx, query).await?;
+                if let Some(app_id) = article.app_id {
+                    let event = $crate::models::change::NewChangeEvent::article(
+                        app_id,
+                        id,
+                        $crate::models::change::ARTICLE_CREATED,
+                    );
+                    insert_change_event(&mut tx, &event, now).await?;
+                }
+                tx.commit().await?;
+
+                sqlx::query_as::<Db, $crate::models::Article>(&sql("SELECT * FROM articles WHERE id = ?"))
+                    .bind(id)
+                    .fetch_one(&self.pool)
+                    .await
+            }
+
+            async fn update(
+                &self,
+                id: i64,
+                author_id: i64,
+                changes: &$crate::models::article::UpdateArticleRequest,
+                version: i64,
+            ) -> Result<Option<$crate::models::Article>, sqlx::Error> {
+                let now = chrono::Utc::now();
+                let mut tx = self.pool.begin().await?;
+                // 修改前的状态，用来区分发布和普通修改；版本不符时下面的 UPDATE 不会生效
+                let before: Option<(Option<i64>, i16)> = sqlx::query_as(&sql(
+                    "SELECT app_id, status FROM articles \
+                     WHERE id = ? AND author_id = ? AND version = ? AND deleted_at IS NULL",
+                ))
+                .bind(id)
+                .bind(author_id)
+                .bind(version)
+                .fetch_optional(&mut *tx)
+                .await?;
+                let Some((app_id, status)) = before else {
+                    return Ok(None);
+                };
+                let mut qb = sqlx::QueryBuilder::<Db>::new("UPDATE articles SET version = version + 1, updated_at = ");
+                qb.push_bind(now);
+                if let Some(title) = &changes.title {
+                    qb.push(", title = ").push_bind(title.clone());
+                }
+                if let Some(content) = &changes.content {
+                    qb.push(", content = ").push_bind(content.clone());
+                }
+                if let Some(status) = changes.status {
+                    qb.push(", status = ").push_bind(status);
+                }
+                if let Some(category) = &changes.category {
+                    qb.push(", category = ").push_bind(category.clone());
+                }
+                for (column, value) in [
+                    ("locale", &changes.locale),
+                    ("meta_title", &changes.meta_title),
+                    ("meta_description", &changes.meta_description),
+                    ("canonical_url", &changes.canonical_url),
+                    ("og_image_url", &changes.og_image_url),
+                ] {
+                    if let Some(value) = value {
+                        qb.push(format!(", {} = ", column)).push_bind(value.clone());
+                    }
+                }
+                if let Some(noindex) = changes.noindex {
+                    qb.push(", noindex = ").push_bind(noindex as i16);
+                }
+                qb.push(" WHERE id = ")
+                    .push_bind(id)
+                    .push(" AND author_id = ")
+                    .push_bind(author_id)
+                    .push(" AND version = ")
+                    .push_bind(version)
+                    .push(" AND deleted_at IS NULL");
+                if qb.build().execute(&mut *tx).await?.rows_affected() == 0 {
+                    return Ok(None);
+                }
+                if let Some(app_id) = app_id {
+                    let event_type = if status != 2 && changes.status == Some(2) {
+                        $crate::models::change::ARTICLE_PUBLISHED
+                    } else {
+                        $crate::models::change::ARTICLE_UPDATED
+                    };
+                    let event = $crate::models::change::NewChangeEvent::article(app_id, id, event_type);
+                    insert_change_event(&mut tx, &event, now).await?;
+                }
+                tx.commit().await?;
+
+                sqlx::query_as::<Db, $crate::models::Article>(&sql("SELECT * FROM articles WHERE id = ?"))
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn trash(&self, id: i64, author_id: i64) -> Result<bool, sqlx::Error> {
+                let now = chrono::Utc::now();
+                let mut tx = self.pool.begin().await?;
+                let result = sqlx::query(&sql(
+                    "UPDATE articles SET deleted_at = ?, deleted_by = ? \
+                     WHERE id = ? AND author_id = ? AND deleted_at IS NULL",
+                ))
+                .bind(now)
+                .bind(author_id)
+                .bind(id)
+                .bind(author_id)
+                .execute(&mut *tx)
+                .await?;
+                if result.rows_affected() == 0 {
+                    return Ok(false);
+                }
+                record_article_change(&mut tx, id, $crate::models::change::ARTICLE_DELETED, now).await?;
+                tx.commit().await?;
+                Ok(true)
+            }
+
+            async fn find_trashed(
+                &self,
+                id: i64,
+                author_id: i64,
+            ) -> Result<Option<$crate::models::Article>, sqlx::Error> {
+                sqlx::query_as::<Db, $crate::models::Article>(&sql(
+                    "SELECT * FROM articles WHERE id = ? AND author_id = ? AND deleted_at IS NOT NULL",
+                ))
+                .bind(id)
+                .bind(author_id)
+                .fetch_optional(&self.pool)
+                .await
+            }
+
+            async fn list_trashed(&self, author_id: i64) -> Result<Vec<$crate::models::Article>, sqlx::Error> {
+                sqlx::query_as::<Db, $crate::models::Article>(&sql(
+                    "SELECT * FROM articles WHERE author_id = ? AND deleted_at IS NOT NULL \
+                     ORDER BY deleted_at DESC, id DESC",
+                ))
+                .bind(author_id)
+                .fetch_all(&self.pool)
+                .await
+            }
+
+            async fn restore(&self, id: i64, author_id: i64) -> Result<bool, sqlx::Error> {
+                let mut tx = self.pool.begin().await?;
+                let result = sqlx::query(&sql(
+                    "UPDATE articles SET deleted_at = NULL, deleted_by = NULL \
+                     WHERE id = ? AND author_id = ? AND deleted_at IS NOT NULL",
+                ))
+                .bind(id)
+                .bind(author_id)
+                .execute(&mut *tx)
+                .await?;
+                if result.rows_affected() == 0 {
+                    return Ok(false);
+                }
+                record_article_change(&mut tx, id, $crate::models::change::ARTICLE_RESTORED, chrono::Utc::now()).await?;
+                tx.commit().await?;
+                Ok(true)
+            }
+
+            async fn purge_trashed(&self, before: chrono::DateTime<chrono::Utc>) -> Result<u64, sqlx::Error> {
+                let mut tx = self.pool.begin().await?;
+                sqlx::query(&sql(
+                    "DELETE FROM preview_links WHERE article_id IN \
+                     (SELECT id FROM articles WHERE deleted_at IS NOT NULL AND deleted_at < ?)",
+                ))
+                .bind(before)
+                .execute(&mut *tx)
+                .await?;
+                let result = sqlx::query(&sql("DELETE FROM articles WHERE deleted_at IS NOT NULL AND deleted_at < ?"))
+                    .bind(before)
+                    .execute(&mut *tx)
+                    .await?;
+                tx.commit().await?;
+                Ok(result.rows_affected())
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl $crate::db::OutboxRepository for $repo {
+            async fn enqueue(&self, message: &$crate::models::outbox::EmailMessage) -> Result<i64, sqlx::Error> {
+                insert_outbox(&self.pool, message, chrono::Utc::now()).await
+            }
+
+            async fn find_by_id(&self, id: i64) -> Result<Option<$crate::models::outbox::OutboxEmail>, sqlx::Error> {
+                sqlx::query_as::<Db, $crate::models::outbox::OutboxEmail>(&sql(
+                    "SELECT * FROM email_outbox WHERE id = ?",
+                ))
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await
+            }
+
+            async fn list(
+                &self,
+                filter: &$crate::db::OutboxFilter,
+                page: $crate::db::Page,
+            ) -> Result<Vec<$crate::models::outbox::OutboxEmail>, sqlx::Error> {
+                let mut qb = sqlx::QueryBuilder::<Db>::new("SELECT * FROM email_outbox WHERE 1=1");
+                push_outbox_filter(&mut qb, filter);
+                qb.push(" ORDER BY id DESC LIMIT ")
+                    .push_bind(page.limit)
+                    .push(" OFFSET ")
+                    .push_bind(page.offset);
+                qb.build_query_as().fetch_all(&self.pool).await
+            }
+
+            async fn count(&self, filter: &$crate::db::OutboxFilter) -> Result<i64, sqlx::Error> {
+                let mut qb = sqlx::QueryBuilder::<Db>::new("SELECT COUNT(*) FROM email_outbox WHERE 1=1");
+                push_outbox_filter(&mut qb, filter);
+                qb.build_query_scalar().fetch_one(&self.pool).await
+            }
+
+            // 先查出候选，再逐条用条件更新抢占，多个 worker 并发时只有一个能更新成功
+            async fn claim_due(
+                &self,
+                now: chrono::DateTime<chrono::Utc>,
+                locked_until: chrono::DateTime<chrono::Utc>,
+                limit: i64,
+            ) -> Result<Vec<$crate::models::outbox::OutboxEmail>, sqlx::Error> {
+                use $crate::models::outbox::{OUTBOX_PENDING, OUTBOX_SENDING};
+
+                const DUE: &str =
+                    "((status = ? AND (attempts = 0 OR next_attempt_at <= ?)) OR (status = ? AND locked_until < ?))";
+
+                let candidates = sqlx::query_as::<Db, $crate::models::outbox::OutboxEmail>(&sql(&format!(
+                    "SELECT * FROM email_outbox WHERE {} ORDER BY id LIMIT ?",
+                    DUE
+                )))
+                .bind(OUTBOX_PENDING)
+                .bind(now)
+                .bind(OUTBOX_SENDING)
+                .bind(now)
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await?;
+
+                let mut claimed = Vec::with_capacity(candidates.len());
+                for mut message in candidates {
+                    let result = sqlx::query(&sql(&format!(
+                        "UPDATE email_outbox SET status = ?, locked_until = ?, attempts = attempts + 1, \
+                         updated_at = ? WHERE id = ? AND {}",
+                        DUE
+                    )))
+                    .bind(OUTBOX_SENDING)
+                    .bind(locked_until)
+                    .bind(now)
+                    .bind(message.id)
+                    .bind(OUTBOX_PENDING)
+                    .bind(now)
+                    .bind(OUTBOX_SENDING)
+                    .bind(now)
+                    .execute(&self.pool)
+                    .await?;
+
+                    if result.rows_affected() == 1 {
+                        message.status = OUTBOX_SENDING.to_string();
+                        message.locked_until = Some(locked_until);
+                        message.attempts += 1;
+                        message.updated_at = now;
+                        claimed.push(message);
+                    }
+                }
+                Ok(claimed)
+            }
+
+            async fn mark_sent(&self, id: i64, now: chrono::DateTime<chrono::Utc>) -> Result<(), sqlx::Error> {
+                sqlx::query(&sql(
+                    "UPDATE email_outbox SET status = ?, sent_at = ?, locked_until = NULL, updated_at = ? WHERE id = ?",
+                ))
+                .bind($crate::models::outbox::OUTBOX_SENT)
+                .bind(now)
+                .bind(now)
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+                Ok(())
+            }
+
+            async fn mark_failed(
+                &self,
+                id: i64,
+                error: &str,
+                next_attempt_at: Option<chrono::DateTime<chrono::Utc>>,
+                now: chrono::DateTime<chrono::Utc>,
+            ) -> Result<(), sqlx::Error> {
+                let status = match next_attempt_at {
+                    Some(_) => $crate::models::outbox::OUTBOX_PENDING,
+                    None => $crate::models::outbox::OUTBOX_DEAD,
+                };
+                sqlx::query(&sql(
+                    "UPDATE email_outbox SET status = ?, last_error = ?, next_attempt_at = ?, \
+                     locked_until = NULL, updated_at = ? WHERE id = ?",
+                ))
+                .bind(status)
+                .bind(error)
+                .bind(next_attempt_at.unwrap_or(now))
+                .bind(now)
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+                Ok(())
+            }
+
+            async fn retry(&self, id: i64, now: chrono::DateTime<chrono::Utc>) -> Result<bool, sqlx::Error> {
+                let result = sqlx::query(&sql(
+                    "UPDATE email_outbox SET status = ?, attempts = 0, next_attempt_at = ?, \
+                     locked_until = NULL, updated_at = ? WHERE id = ? AND status = ?",
+                ))
+                .bind($crate::models::outbox::OUTBOX_PENDING)
+                .bind(now)
+                .bind(now)
+                .bind(id)
+                .bind($crate::models::outbox::OUTBOX_DEAD)
+                .execute(&self.pool)
+                .await?;
+                Ok(result.rows_affected() > 0)
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl $crate::db::JobRepository for $repo {
+            async fn enqueue(&self, job: &$crate::models::job::NewJob) -> Result<(i64, bool), sqlx::Error> {
+                let find_active = |key: String| async move {
+                    sqlx::query_scalar::<Db, i64>(&sql("SELECT id FROM jobs WHERE unique_key = ?"))
+                        .bind(key)
+                        .fetch_optional(&self.pool)
+                        .await
+                };
+
+                if let Some(key) = &job.unique_key {
+                    if let Some(id) = find_active(key.clone()).await? {
+                        return Ok((id, false));
+                    }
+                }
+
+                let now = chrono::Utc::now();
+                let statement = format!(
+                    "INSERT INTO jobs (queue, kind, payload, status, attempts, max_attempts, unique_key, run_at, \
+                     created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?){}",
+                    RETURNING_ID
+                );
+                let statement = sql(&statement);
+                let query = sqlx::query(&statement)
+                    .bind(&job.queue)
+                    .bind(&job.kind)
+                    .bind(&job.payload)
+                    .bind($crate::models::job::JOB_PENDING)
+                    .bind(0i32)
+                    .bind(job.max_attempts)
+                    .bind(&job.unique_key)
+                    .bind(job.run_at)
+                    .bind(now)
+                    .bind(now);
+
+                match insert_id(&self.pool, query).await {
+                    Ok(id) => Ok((id, true)),
+                    // 并发插入相同 unique_key 时，以先插入的为准
+                    Err(e) if is_unique_violation(&e) => match &job.unique_key {
+                        Some(key) => match find_active(key.clone()).await? {
+                            Some(id) => Ok((id, false)),
+                            None => Err(e),
+                        },
+                        None => Err(e),
+                    },
+                    Err(e) => Err(e),
+                }
+            }
+
+            async fn find_by_id(&self, id: i64) -> Result<Option<$crate::models::job::JobRecord>, sqlx::Error> {
+                sqlx::query_as::<Db, $crate::models::job::JobRecord>(&sql("SELECT * FROM jobs WHERE id = ?"))
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn list(
+                &self,
+                filter: &$crate::db::JobFilter,
+                page: $crate::db::Page,
+            ) -> Result<Vec<$crate::models::job::JobRecord>, sqlx::Error> {
+                let mut qb = sqlx::QueryBuilder::<Db>::new("SELECT * FROM jobs WHERE 1=1");
+                push_job_filter(&mut qb, filter);
+                qb.push(" ORDER BY id DESC LIMIT ")
+                    .push_bind(page.limit)
+                    .push(" OFFSET ")
+                    .push_bind(page.offset);
+                qb.build_query_as().fetch_all(&self.pool).await
+            }
+
+            async fn count(&self, filter: &$crate::db::JobFilter) -> Result<i64, sqlx::Error> {
+                let mut qb = sqlx::QueryBuilder::<Db>::new("SELECT COUNT(*) FROM jobs WHERE 1=1");
+                push_job_filter(&mut qb, filter);
+                qb.build_query_scalar().fetch_one(&self.pool).await
+            }
+
+            async fn counts(&self) -> Result<Vec<$crate::models::job::JobCount>, sqlx::Error> {
+                sqlx::query_as::<Db, $crate::models::job::JobCount>(
+                    "SELECT queue, status, COUNT(*) AS count FROM jobs GROUP BY queue, status ORDER BY queue, status",
+                )
+                .fetch_all(&self.pool)
+                .await
+            }
+
+            // 与发件箱一样先查候选再逐条条件更新，保证一个任务只被一个 worker 领取
+            async fn claim(
+                &self,
+                queues: &[String],
+                now: chrono::DateTime<chrono::Utc>,
+                locked_until: chrono::DateTime<chrono::Utc>,
+                limit: i64,
+            ) -> Result<Vec<$crate::models::job::JobRecord>, sqlx::Error> {
+                use $crate::models::job::{JOB_PENDING, JOB_RUNNING};
+
+                if queues.is_empty() {
+                    return Ok(Vec::new());
+                }
+
+                let mut qb = sqlx::QueryBuilder::<Db>::new("SELECT * FROM jobs WHERE queue IN (");
+                let mut separated = qb.separated(", ");
+                for queue in queues {
+                    separated.push_bind(queue.clone());
+                }
+                separated.push_unseparated(")");
+                qb.push(" AND ((status = ")
+                    .push_bind(JOB_PENDING)
+                    .push(" AND run_at <= ")
+                    .push_bind(now)
+                    .push(") OR (status = ")
+                    .push_bind(JOB_RUNNING)
+                    .push(" AND locked_until < ")
+                    .push_bind(now)
+                    .push(")) ORDER BY run_at, id LIMIT ")
+                    .push_bind(limit);
+                let candidates: Vec<$crate::models::job::JobRecord> =
+                    qb.build_query_as().fetch_all(&self.pool).await?;
+
+                let mut claimed = Vec::with_capacity(candidates.len());
+                for mut job in candidates {
+                    let result = sqlx::query(&sql(
+                        "UPDATE jobs SET status = ?, locked_until = ?, attempts = attempts + 1, updated_at = ? \
+                         WHERE id = ? AND ((status = ? AND run_at <= ?) OR (status = ? AND locked_until < ?))",
+                    ))
+                    .bind(JOB_RUNNING)
+                    .bind(locked_until)
+                    .bind(now)
+                    .bind(job.id)
+                    .bind(JOB_PENDING)
+                    .bind(now)
+                    .bind(JOB_RUNNING)
+                    .bind(now)
+                    .execute(&self.pool)
+                    .await?;
+
+                    if result.rows_affected() == 1 {
+                        job.status = JOB_RUNNING.to_string();
+                        job.locked_until = Some(locked_until);
+                        job.attempts += 1;
+                        job.updated_at = now;
+                        claimed.push(job);
+                    }
+                }
+                Ok(claimed)
+            }
+
+            async fn complete(&self, id: i64, now: chrono::DateTime<chrono::Utc>) -> Result<(), sqlx::Error> {
+                sqlx::query(&sql(
+                    "UPDATE jobs SET status = ?, unique_key = NULL, locked_until = NULL, finished_at = ?, \
+                     updated_at = ? WHERE id = ?",
+                ))
+                .bind($crate::models::job::JOB_COMPLETED)
+                .bind(now)
+                .bind(now)
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+                Ok(())
+            }
+
+            async fn fail(
+                &self,
+                id: i64,
+                error: &str,
+                retry_at: Option<chrono::DateTime<chrono::Utc>>,
+                now: chrono::DateTime<chrono::Utc>,
+            ) -> Result<(), sqlx::Error> {
+                match retry_at {
+                    Some(retry_at) => {
+                        sqlx::query(&sql(
+                            "UPDATE jobs SET status = ?, last_error = ?, run_at = ?, locked_until = NULL, \
+                             updated_at = ? WHERE id = ?",
+                        ))
+                        .bind($crate::models::job::JOB_PENDING)
+                        .bind(error)
+                        .bind(retry_at)
+                        .bind(now)
+                        .bind(id)
+                        .execute(&self.pool)
+                        .await?;
+                    }
+                    None => {
+                        sqlx::query(&sql(
+                            "UPDATE jobs SET status = ?, last_error = ?, unique_key = NULL, locked_until = NULL, \
+                             finished_at = ?, updated_at = ? WHERE id = ?",
+                        ))
+                        .bind($crate::models::job::JOB_DEAD)
+                        .bind(error)
+                        .bind(now)
+                        .bind(now)
+                        .bind(id)
+                        .execute(&self.pool)
+                        .await?;
+                    }
+                }
+                Ok(())
+            }
+
+            async fn retry(
+                &self,
+                id: i64,
+                unique_key: Option<&str>,
+                now: chrono::DateTime<chrono::Utc>,
+            ) -> Result<bool, sqlx::Error> {
+                if let Some(key) = unique_key {
+                    let active = sqlx::query_scalar::<Db, i64>(&sql("SELECT id FROM jobs WHERE unique_key = ?"))
+                        .bind(key)
+                        .fetch_optional(&self.pool)
+                        .await?;
+                    if active.is_some() {
+                        return Ok(false);
+                    }
+                }
+                let result = sqlx::query(&sql(
+                    "UPDATE jobs SET status = ?, attempts = 0, unique_key = ?, run_at = ?, finished_at = NULL, \
+                     updated_at = ? WHERE id = ? AND status = ?",
+                ))
+                .bind($crate::models::job::JOB_PENDING)
+                .bind(unique_key)
+                .bind(now)
+                .bind(now)
+                .bind(id)
+                .bind($crate::models::job::JOB_DEAD)
+                .execute(&self.pool)
+                .await;
+                match result {
+                    Ok(result) => Ok(result.rows_affected() > 0),
+                    // 检查之后相同 key 的任务被创建
+                    Err(e) if is_unique_violation(&e) => Ok(false),
+                    Err(e) => Err(e),
+                }
+            }
+
+            async fn purge_completed(&self, before: chrono::DateTime<chrono::Utc>) -> Result<u64, sqlx::Error> {
+                let result = sqlx::query(&sql("DELETE FROM jobs WHERE status = ? AND finished_at < ?"))
+                    .bind($crate::models::job::JOB_COMPLETED)
+                    .bind(before)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected())
+            }
+
+            async fn claim_schedule(
+                &self,
+                name: &str,
+                fire_at: chrono::DateTime<chrono::Utc>,
+            ) -> Result<bool, sqlx::Error> {
+                let result = sqlx::query(&sql(
+                    "UPDATE job_schedules SET last_run_at = ? WHERE name = ? AND last_run_at < ?",
+                ))
+                .bind(fire_at)
+                .bind(name)
+                .bind(fire_at)
+                .execute(&self.pool)
+                .await?;
+                if result.rows_affected() == 1 {
+                    return Ok(true);
+                }
+
+                // 第一次运行时还没有记录；已有记录说明其他 worker 已经触发
+                match sqlx::query(&sql("INSERT INTO job_schedules (name, last_run_at) VALUES (?, ?)"))
+                    .bind(name)
+                    .bind(fire_at)
+                    .execute(&self.pool)
+                    .await
+                {
+                    Ok(_) => Ok(true),
+                    Err(e) if is_unique_violation(&e) => Ok(false),
+                    Err(e) => Err(e),
+                }
+            }
+        }
+        #[async_trait::async_trait]
+        impl $crate::db::AuditRepository for $repo {
+            async fn append(
+                &self,
+                entry: &$crate::models::audit::NewAuditLog,
+                now: chrono::DateTime<chrono::Utc>,
+            ) -> Result<i64, sqlx::Error> {
+                let statement = format!(
+                    "INSERT INTO audit_logs (actor_type, actor_id, api_key, action, target_type, target_id, app_id, \
+                     before_data, after_data, ip, user_agent, request_id, created_at) \
+                     VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?){}",
+                    RETURNING_ID
+                );
+                let statement = sql(&statement);
+                let query = sqlx::query(&statement)
+                    .bind(&entry.actor_type)
+                    .bind(entry.actor_id)
+                    .bind(&entry.api_key)
+                    .bind(&entry.action)
+                    .bind(&entry.target_type)
+                    .bind(entry.target_id)
+                    .bind(entry.app_id)
+                    .bind(&entry.before_data)
+                    .bind(&entry.after_data)
+                    .bind(&entry.ip)
+                    .bind(&entry.user_agent)
+                    .bind(&entry.request_id)
+                    .bind(now);
+                insert_id(&self.pool, query).await
+            }
+
+            async fn list(
+                &self,
+                filter: &$crate::db::AuditFilter,
+                page: $crate::db::Page,
+            ) -> Result<Vec<$crate::models::audit::AuditLog>, sqlx::Error> {
+                let mut qb = sqlx::QueryBuilder::<Db>::new("SELECT * FROM audit_logs WHERE 1=1");
+                push_audit_filter(&mut qb, filter);
+                qb.push(" ORDER BY id DESC LIMIT ")
+                    .push_bind(page.limit)
+                    .push(" OFFSET ")
+                    .push_bind(page.offset);
+                qb.build_query_as().fetch_all(&self.pool).await
+            }
+
+            async fn count(&self, filter: &$crate::db::AuditFilter) -> Result<i64, sqlx::Error> {
+                let mut qb = sqlx::QueryBuilder::<Db>::new("SELECT COUNT(*) FROM audit_logs WHERE 1=1");
+                push_audit_filter(&mut qb, filter);
+                qb.build_query_scalar().fetch_one(&self.pool).await
+            }
+
+            async fn purge_before(&self, before: chrono::DateTime<chrono::Utc>) -> Result<u64, sqlx::Error> {
+                let result = sqlx::query(&sql("DELETE FROM audit_logs WHERE created_at < ?"))
+                    .bind(before)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected())
+            }
+        }
+        #[async_trait::async_trait]
+        impl $crate::db::ApiKeyRepository for $repo {
+            async fn create(
+                &self,
+                key: &$crate::models::api_key::NewApiKey,
+                now: chrono::DateTime<chrono::Utc>,
+            ) -> Result<$crate::models::api_key::ApiKey, sqlx::Error> {
+                let statement = format!(
+                    "INSERT INTO api_keys (app_id, name, prefix, key_hash, created_by, created_at) \
+                     VALUES (?, ?, ?, ?, ?, ?){}",
+                    RETURNING_ID
+                );
+                let statement = sql(&statement);
+                let query = sqlx::query(&statement)
+                    .bind(key.app_id)
+                    .bind(&key.name)
+                    .bind(&key.prefix)
+                    .bind(&key.key_hash)
+                    .bind(key.created_by)
+                    .bind(now);
+                let id = insert_id(&self.pool, query).await?;
+
+                sqlx::query_as::<Db, $crate::models::api_key::ApiKey>(&sql("SELECT * FROM api_keys WHERE id = ?"))
+                    .bind(id)
+                    .fetch_one(&self.pool)
+                    .await
+            }
+
+            async fn list_by_app(&self, app_id: i64) -> Result<Vec<$crate::models::api_key::ApiKey>, sqlx::Error> {
+                sqlx::query_as::<Db, $crate::models::api_key::ApiKey>(&sql(
+                    "SELECT * FROM api_keys WHERE app_id = ? ORDER BY id",
+                ))
+                .bind(app_id)
+                .fetch_all(&self.pool)
+                .await
+            }
+
+            async fn find_by_hash(
+                &self,
+                key_hash: &str,
+            ) -> Result<Option<$crate::models::api_key::ApiKey>, sqlx::Error> {
+                sqlx::query_as::<Db, $crate::models::api_key::ApiKey>(&sql(
+                    "SELECT * FROM api_keys WHERE key_hash = ?",
+                ))
+                .bind(key_hash)
+                .fetch_optional(&self.pool)
+                .await
+            }
+
+            async fn delete(&self, app_id: i64, id: i64) -> Result<bool, sqlx::Error> {
+                let result = sqlx::query(&sql("DELETE FROM api_keys WHERE id = ? AND app_id = ?"))
+                    .bind(id)
+                    .bind(app_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected() > 0)
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl $crate::db::ChangeEventRepository for $repo {
+            async fn list_since(
+                &self,
+                app_id: i64,
+                after_id: i64,
+                limit: i64,
+            ) -> Result<Vec<$crate::models::change::ChangeEvent>, sqlx::Error> {
+                sqlx::query_as::<Db, $crate::models::change::ChangeEvent>(&sql(
+                    "SELECT * FROM change_events WHERE app_id = ? AND id > ? ORDER BY id LIMIT ?",
+                ))
+                .bind(app_id)
+                .bind(after_id)
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await
+            }
+
+            async fn last_id(&self, app_id: i64) -> Result<i64, sqlx::Error> {
+                let id: Option<i64> = sqlx::query_scalar(&sql("SELECT MAX(id) FROM change_events WHERE app_id = ?"))
+                    .bind(app_id)
+                    .fetch_one(&self.pool)
+                    .await?;
+                Ok(id.unwrap_or(0))
+            }
+
+            async fn purge_before(&self, before: chrono::DateTime<chrono::Utc>) -> Result<u64, sqlx::Error> {
+                let result = sqlx::query(&sql("DELETE FROM change_events WHERE created_at < ?"))
+                    .bind(before)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected())
+            }
+        }
+        #[async_trait::async_trait]
+        impl $crate::db::PreviewLinkRepository for $repo {
+            async fn create(
+                &self,
+                link: &$crate::models::preview::NewPreviewLink,
+                now: chrono::DateTime<chrono::Utc>,
+            ) -> Result<$crate::models::preview::PreviewLink, sqlx::Error> {
+                let statement = format!(
+                    "INSERT INTO preview_links (article_id, token_hash, version, title, content, meta_title, \
+                     meta_description, canonical_url, og_image_url, noindex, created_by, expires_at, created_at) \
+                     VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?){}",
+                    RETURNING_ID
+                );
+                let statement = sql(&statement);
+                let snapshot = &link.snapshot;
+                let query = sqlx::query(&statement)
+                    .bind(link.article_id)
+                    .bind(&link.token_hash)
+                    .bind(link.version)
+                    .bind(&snapshot.title)
+                    .bind(&snapshot.content)
+                    .bind(&snapshot.meta_title)
+                    .bind(&snapshot.meta_description)
+                    .bind(&snapshot.canonical_url)
+                    .bind(&snapshot.og_image_url)
+                    .bind(snapshot.noindex)
+                    .bind(link.created_by)
+                    .bind(link.expires_at)
+                    .bind(now);
+                let id = insert_id(&self.pool, query).await?;
+
+                sqlx::query_as::<Db, $crate::models::preview::PreviewLink>(&sql(
+                    "SELECT * FROM preview_links WHERE id = ?",
+                ))
+                .bind(id)
+                .fetch_one(&self.pool)
+                .await
+            }
+
+            async fn find_by_hash(
+                &self,
+                token_hash: &str,
+            ) -> Result<Option<$crate::models::preview::PreviewLink>, sqlx::Error> {
+                sqlx::query_as::<Db, $crate::models::preview::PreviewLink>(&sql(
+                    "SELECT * FROM preview_links WHERE token_hash = ?",
+                ))
+                .bind(token_hash)
+                .fetch_optional(&self.pool)
+                .await
+            }
+
+            async fn list_by_article(
+                &self,
+                article_id: i64,
+            ) -> Result<Vec<$crate::models::preview::PreviewLink>, sqlx::Error> {
+                sqlx::query_as::<Db, $crate::models::preview::PreviewLink>(&sql(
+                    "SELECT * FROM preview_links WHERE article_id = ? ORDER BY id DESC",
+                ))
+                .bind(article_id)
+                .fetch_all(&self.pool)
+                .await
+            }
+
+            async fn revoke(
+                &self,
+                article_id: i64,
+                id: i64,
+                now: chrono::DateTime<chrono::Utc>,
+            ) -> Result<bool, sqlx::Error> {
+                let result = sqlx::query(&sql(
+                    "UPDATE preview_links SET revoked_at = ? WHERE id = ? AND article_id = ? AND revoked_at IS NULL",
+                ))
+                .bind(now)
+                .bind(id)
+                .bind(article_id)
+                .execute(&self.pool)
+                .await?;
+                Ok(result.rows_affected() > 0)
+            }
+        }
+    };
+}
+
+pub(crate) use impl_repositories;
@@ -0,0 +1,624 @@
+use sqlx::query::Query;
+use sqlx::sqlite::{SqliteArguments, SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+use std::borrow::Cow;
+use std::str::FromStr;
+
+type Db = sqlx::Sqlite;
+
+const RETURNING_ID: &str = " RETURNING id";
+
+fn sql(query: &str) -> Cow<'_, str> {
+    Cow::Borrowed(query)
+}
+
+async fn insert_id<'e, 'q, E>(executor: E, query: Query<'q, Db, SqliteArguments<'q>>) -> Result<i64, sqlx::Error>
+where
+    E: sqlx::Executor<'e, Database = Db>,
+{
+    query.fetch_one(executor).await?.try_get(0)
+}
+
+#[derive(Clone)]
+pub struct SqliteRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteRepository {
+    pub async fn connect(database_url: &str) -> Result<Self, anyhow::Error> {
+        let options = SqliteConnectOptions::from_str(database_url)?
+            .create_if_missing(true)
+            .foreign_keys(true);
+
+        // 内存数据库只存在于单个连接中，连接池只能保留一个且不能回收
+        let pool_options = if database_url.contains(":memory:") || database_url.contains("mode=memory") {
+            SqlitePoolOptions::new()
+                .max_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+        } else {
+            SqlitePoolOptions::new()
+        };
+
+        let pool = pool_options.connect_with(options).await?;
+        sqlx::migrate!("./db/migrations/sqlite").run(&pool).await?;
+        Ok(Self { pool })
+    }
+}
+
+super::sql::impl_repositories!(SqliteRepository);
+
+#[cfg(test)]
+mod tests {
+    use super::SqliteRepository;
+    use crate::db::{
+        ApiKeyRepository, AppFilter, AppRepository, ArticleFilter, ArticleRepository, AuditFilter, AuditRepository,
+        ChangeEventRepository, JobFilter, JobRepository, NewUser, OutboxFilter, OutboxRepository, Page, UserRepository,
+    };
+    use crate::models::api_key::NewApiKey;
+    use crate::models::audit::NewAuditLog;
+    use crate::models::change::{
+        APP_CREATED, ARTICLE_CREATED, ARTICLE_DELETED, ARTICLE_PUBLISHED, ARTICLE_RESTORED, ARTICLE_UPDATED,
+    };
+    use crate::models::job::{NewJob, JOB_COMPLETED, JOB_DEAD, JOB_RUNNING};
+    use crate::models::magic_link::NewMagicLink;
+    use crate::models::article::{CreateArticleRequest, UpdateArticleRequest};
+    use crate::models::outbox::{EmailMessage, OUTBOX_DEAD, OUTBOX_PENDING, OUTBOX_SENDING, OUTBOX_SENT};
+    use crate::models::{CreateAppRequest, UpdateAppRequest};
+    use crate::test_support::app_request;
+    use chrono::{Duration, Utc};
+
+    async fn repository() -> SqliteRepository {
+        SqliteRepository::connect("sqlite::memory:").await.unwrap()
+    }
+
+    fn message(template: &str, recipient: &str) -> EmailMessage {
+        EmailMessage {
+            template: template.to_string(),
+            recipient: recipient.to_string(),
+            from_name: "RSCMS".to_string(),
+            from_email: "noreply@example.com".to_string(),
+            subject: "Subject".to_string(),
+            html_body: "<p>Body</p>".to_string(),
+            text_body: "Body".to_string(),
+        }
+    }
+
+    async fn create_user(repo: &SqliteRepository, email: &str) -> i64 {
+        UserRepository::create(
+            repo,
+            NewUser {
+                username: email.split('@').next().unwrap().to_string(),
+                email: email.to_string(),
+                verification_code: "123456".to_string(),
+                verification_code_expires_at: Utc::now() + Duration::minutes(30),
+                locale: "en".to_string(),
+                role: "user".to_string(),
+            },
+            &message("verification", email),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn verification_code_lifecycle() {
+        let repo = repository().await;
+        let id = create_user(&repo, "alice@example.com").await;
+
+        repo.record_failed_attempt(id, 2).await.unwrap();
+        let user = repo.find_by_email("alice@example.com").await.unwrap().unwrap();
+        assert_eq!(user.verification_attempts, 1);
+        assert!(user.verification_code.is_some());
+
+        // 达到上限后验证码作废
+        repo.record_failed_attempt(id, 2).await.unwrap();
+        let user = UserRepository::find_by_id(&repo, id).await.unwrap().unwrap();
+        assert!(user.verification_code.is_none());
+
+        repo.set_verification_code(
+            "alice@example.com",
+            "654321",
+            Utc::now() + Duration::minutes(30),
+            &message("verification", "alice@example.com"),
+        )
+        .await
+        .unwrap();
+        repo.mark_verified(id, Some(&message("welcome", "alice@example.com")))
+            .await
+            .unwrap();
+        let user = UserRepository::find_by_id(&repo, id).await.unwrap().unwrap();
+        assert_eq!(user.email_verified, 1);
+        assert_eq!(user.verification_attempts, 0);
+        assert!(user.verification_code.is_none());
+
+        // 每次变更都在同一事务中写入了邮件
+        let queued = OutboxRepository::list(&repo, &OutboxFilter::default(), Page::new(1, 10))
+            .await
+            .unwrap();
+        let templates: Vec<&str> = queued.iter().map(|queued| queued.message.template.as_str()).collect();
+        assert_eq!(templates, ["welcome", "verification", "verification"]);
+    }
+
+    #[actix_web::test]
+    async fn outbox_claims_backs_off_and_dead_letters() {
+        let repo = repository().await;
+        let id = OutboxRepository::enqueue(&repo, &message("welcome", "alice@example.com")).await.unwrap();
+        let now = Utc::now();
+
+        let claimed = repo.claim_due(now, now + Duration::minutes(5), 10).await.unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].status, OUTBOX_SENDING);
+        assert_eq!(claimed[0].attempts, 1);
+        // 租约未过期时不会被重复领取
+        assert!(repo.claim_due(now, now + Duration::minutes(5), 10).await.unwrap().is_empty());
+
+        // 租约过期后重新领取
+        let later = now + Duration::minutes(6);
+        assert_eq!(repo.claim_due(later, later + Duration::minutes(5), 10).await.unwrap().len(), 1);
+
+        repo.mark_failed(id, "timeout", Some(later + Duration::minutes(1)), later)
+            .await
+            .unwrap();
+        let failed = OutboxRepository::find_by_id(&repo, id).await.unwrap().unwrap();
+        assert_eq!(failed.status, OUTBOX_PENDING);
+        assert_eq!(failed.attempts, 2);
+        assert_eq!(failed.last_error.as_deref(), Some("timeout"));
+        assert!(repo.claim_due(later, later, 10).await.unwrap().is_empty());
+
+        assert!(!OutboxRepository::retry(&repo, id, later).await.unwrap());
+        repo.mark_failed(id, "timeout", None, later).await.unwrap();
+        let filter = OutboxFilter {
+            status: Some(OUTBOX_DEAD.to_string()),
+        };
+        assert_eq!(OutboxRepository::count(&repo, &filter).await.unwrap(), 1);
+
+        assert!(OutboxRepository::retry(&repo, id, later).await.unwrap());
+        let claimed = repo.claim_due(later, later + Duration::minutes(5), 10).await.unwrap();
+        assert_eq!(claimed[0].attempts, 1);
+        repo.mark_sent(id, later).await.unwrap();
+        let sent = OutboxRepository::find_by_id(&repo, id).await.unwrap().unwrap();
+        assert_eq!(sent.status, OUTBOX_SENT);
+        assert!(sent.sent_at.is_some());
+    }
+
+    #[actix_web::test]
+    async fn apps_crud_and_filters() {
+        let repo = repository().await;
+        let alice = create_user(&repo, "alice@example.com").await;
+        let bob = create_user(&repo, "bob@example.com").await;
+
+        let blog = AppRepository::create(
+            &repo,
+            &CreateAppRequest {
+                description: "Personal blog".to_string(),
+                logo_url: Some("https://example.com/logo.png".to_string()),
+                email_from: Some("blog@example.com".to_string()),
+                ..app_request("Blog", "blog")
+            },
+            alice,
+        )
+        .await
+        .unwrap();
+        AppRepository::create(
+            &repo,
+            &app_request("Docs", "docs"),
+            bob,
+        )
+        .await
+        .unwrap();
+
+        let filter = AppFilter {
+            creator_id: Some(alice),
+            ..Default::default()
+        };
+        assert_eq!(AppRepository::count(&repo, &filter).await.unwrap(), 1);
+        assert_eq!(AppRepository::count(&repo, &AppFilter::default()).await.unwrap(), 2);
+        assert_eq!(AppRepository::list(&repo, &AppFilter::default(), Page::new(2, 1)).await.unwrap().len(), 1);
+        assert_eq!(repo.list_by_creators(&[alice, bob]).await.unwrap().len(), 2);
+
+        let updated = AppRepository::update(
+            &repo,
+            blog.id,
+            &UpdateAppRequest {
+                name: Some("Weblog".to_string()),
+                description: None,
+                logo_url: None,
+                email_from: None,
+                feed_title: None,
+                feed_description: None,
+                feed_link: None,
+                base_url: None,
+                require_two_factor: Some(true),
+                version: None,
+            },
+            bob,
+            blog.version,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(updated.name, "Weblog");
+        assert_eq!(updated.version, blog.version + 1);
+        assert_eq!(updated.updater_id, bob);
+        assert_eq!(updated.email_from.as_deref(), Some("blog@example.com"));
+        assert_eq!(updated.require_two_factor, 1);
+        assert_eq!(repo.find_by_identifier("blog").await.unwrap().unwrap().id, blog.id);
+
+        assert!(AppRepository::trash(&repo, blog.id, bob).await.unwrap());
+        assert!(!AppRepository::trash(&repo, blog.id, bob).await.unwrap());
+        assert!(AppRepository::find_by_id(&repo, blog.id).await.unwrap().is_none());
+        assert!(repo.find_by_identifier("blog").await.unwrap().is_none());
+        assert_eq!(AppRepository::find_trashed(&repo, blog.id).await.unwrap().unwrap().deleted_by, Some(bob));
+    }
+
+    #[actix_web::test]
+    async fn articles_respect_visibility_and_ownership() {
+        let repo = repository().await;
+        let alice = create_user(&repo, "alice@example.com").await;
+        let bob = create_user(&repo, "bob@example.com").await;
+
+        let draft = ArticleRepository::create(
+            &repo,
+            &CreateArticleRequest {
+                title: "Draft".to_string(),
+                content: "Work in progress".to_string(),
+                status: None,
+                app_id: None,
+                category: None,
+                ..Default::default()
+            },
+            alice,
+        )
+        .await
+        .unwrap();
+        assert_eq!(draft.status, 1);
+
+        assert!(repo.find_visible(draft.id, bob).await.unwrap().is_none());
+        assert!(repo.find_visible(draft.id, alice).await.unwrap().is_some());
+
+        let changes = UpdateArticleRequest {
+            title: None,
+            content: None,
+            status: Some(2),
+            category: None,
+            version: None,
+            ..Default::default()
+        };
+        assert!(ArticleRepository::update(&repo, draft.id, bob, &changes, draft.version).await.unwrap().is_none());
+        let published = ArticleRepository::update(&repo, draft.id, alice, &changes, draft.version)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(published.status, 2);
+        assert_eq!(published.version, draft.version + 1);
+        // 版本已变化时不更新
+        assert!(ArticleRepository::update(&repo, draft.id, alice, &changes, draft.version).await.unwrap().is_none());
+
+        let visible = repo.list_visible(bob, &ArticleFilter::default(), None).await.unwrap();
+        assert_eq!(visible.len(), 1);
+        assert_eq!(repo.count_visible(bob, &ArticleFilter::default()).await.unwrap(), 1);
+
+        assert!(!ArticleRepository::trash(&repo, draft.id, bob).await.unwrap());
+        assert!(ArticleRepository::trash(&repo, draft.id, alice).await.unwrap());
+        assert!(repo.find_visible(draft.id, alice).await.unwrap().is_none());
+        assert_eq!(repo.list_trashed(alice).await.unwrap().len(), 1);
+        assert!(ArticleRepository::restore(&repo, draft.id, alice).await.unwrap());
+        assert!(repo.find_visible(draft.id, bob).await.unwrap().is_some());
+    }
+
+    #[actix_web::test]
+    async fn trashing_an_app_cascades_to_its_articles() {
+        let repo = repository().await;
+        let alice = create_user(&repo, "alice@example.com").await;
+        let app = AppRepository::create(
+            &repo,
+            &app_request("Blog", "blog"),
+            alice,
+        )
+        .await
+        .unwrap();
+        let article = |title: &str| CreateArticleRequest {
+            title: title.to_string(),
+            content: "Content".to_string(),
+            status: Some(2),
+            app_id: Some(app.id),
+            category: None,
+            ..Default::default()
+        };
+        let kept = ArticleRepository::create(&repo, &article("Kept"), alice).await.unwrap();
+        let removed = ArticleRepository::create(&repo, &article("Removed"), alice).await.unwrap();
+        assert!(ArticleRepository::trash(&repo, removed.id, alice).await.unwrap());
+
+        assert!(AppRepository::trash(&repo, app.id, alice).await.unwrap());
+        assert!(repo.find_visible(kept.id, alice).await.unwrap().is_none());
+        assert_eq!(repo.list_trashed(alice).await.unwrap().len(), 2);
+
+        // 只恢复随应用一起删除的文章
+        assert!(AppRepository::restore(&repo, app.id).await.unwrap());
+        assert!(repo.find_visible(kept.id, alice).await.unwrap().is_some());
+        assert!(repo.find_visible(removed.id, alice).await.unwrap().is_none());
+
+        // 永久删除应用时一起删除它的全部文章
+        assert!(AppRepository::trash(&repo, app.id, alice).await.unwrap());
+        let later = Utc::now() + Duration::seconds(1);
+        assert_eq!(AppRepository::purge_trashed(&repo, later).await.unwrap(), 1);
+        assert!(ArticleRepository::find_trashed(&repo, kept.id, alice).await.unwrap().is_none());
+        assert!(ArticleRepository::find_trashed(&repo, removed.id, alice).await.unwrap().is_none());
+        assert_eq!(ArticleRepository::purge_trashed(&repo, later).await.unwrap(), 0);
+    }
+
+    #[actix_web::test]
+    async fn translations_and_indexable_articles() {
+        let repo = repository().await;
+        let alice = create_user(&repo, "alice@example.com").await;
+        let article = |locale: &str, translation_of: Option<i64>, noindex: bool| CreateArticleRequest {
+            title: locale.to_string(),
+            content: "Content".to_string(),
+            status: Some(2),
+            locale: Some(locale.to_string()),
+            translation_of,
+            noindex: Some(noindex),
+            ..Default::default()
+        };
+        let original = ArticleRepository::create(&repo, &article("en", None, false), alice).await.unwrap();
+        let translation = ArticleRepository::create(&repo, &article("de", Some(original.id), true), alice)
+            .await
+            .unwrap();
+        ArticleRepository::create(&repo, &article("fr", None, false), alice).await.unwrap();
+        assert_eq!(translation.translation_of, Some(original.id));
+        assert_eq!(translation.noindex, 1);
+
+        let versions = repo.list_translations(&[original.id]).await.unwrap();
+        let ids: Vec<i64> = versions.iter().map(|article| article.id).collect();
+        assert_eq!(ids, vec![original.id, translation.id]);
+        assert!(repo.list_translations(&[]).await.unwrap().is_empty());
+
+        let filter = ArticleFilter {
+            indexable: true,
+            ..Default::default()
+        };
+        assert_eq!(repo.count_visible(alice, &filter).await.unwrap(), 2);
+        let changes = UpdateArticleRequest {
+            meta_title: Some("English".to_string()),
+            noindex: Some(true),
+            ..Default::default()
+        };
+        let updated = ArticleRepository::update(&repo, original.id, alice, &changes, original.version)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.meta_title.as_deref(), Some("English"));
+        assert_eq!(repo.count_visible(alice, &filter).await.unwrap(), 1);
+    }
+
+    #[actix_web::test]
+    async fn jobs_claim_by_queue_and_deduplicate() {
+        let repo = repository().await;
+        let now = Utc::now();
+        let job = |queue: &str, unique_key: Option<&str>| NewJob {
+            queue: queue.to_string(),
+            kind: "echo".to_string(),
+            payload: "{}".to_string(),
+            max_attempts: 3,
+            unique_key: unique_key.map(str::to_string),
+            run_at: now,
+        };
+
+        let (id, inserted) = JobRepository::enqueue(&repo, &job("default", Some("echo:1"))).await.unwrap();
+        assert!(inserted);
+        assert_eq!(
+            JobRepository::enqueue(&repo, &job("default", Some("echo:1"))).await.unwrap(),
+            (id, false)
+        );
+        JobRepository::enqueue(&repo, &job("media", None)).await.unwrap();
+
+        let queues = vec!["default".to_string()];
+        let claimed = repo.claim(&queues, now, now + Duration::minutes(5), 10).await.unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].status, JOB_RUNNING);
+        assert!(repo.claim(&queues, now, now + Duration::minutes(5), 10).await.unwrap().is_empty());
+
+        // 完成后释放 unique_key
+        repo.complete(id, now).await.unwrap();
+        let completed = JobRepository::find_by_id(&repo, id).await.unwrap().unwrap();
+        assert_eq!(completed.status, JOB_COMPLETED);
+        assert!(completed.unique_key.is_none());
+        assert!(JobRepository::enqueue(&repo, &job("default", Some("echo:1"))).await.unwrap().1);
+
+        let (failing, _) = JobRepository::enqueue(&repo, &job("media", None)).await.unwrap();
+        repo.fail(failing, "boom", None, now).await.unwrap();
+        let filter = JobFilter {
+            status: Some(JOB_DEAD.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(JobRepository::count(&repo, &filter).await.unwrap(), 1);
+        let counts = repo.counts().await.unwrap();
+        let media: Vec<(&str, i64)> = counts
+            .iter()
+            .filter(|count| count.queue == "media")
+            .map(|count| (count.status.as_str(), count.count))
+            .collect();
+        assert_eq!(media, [("dead", 1), ("pending", 1)]);
+
+        assert_eq!(repo.purge_completed(now + Duration::seconds(1)).await.unwrap(), 1);
+        assert!(JobRepository::find_by_id(&repo, id).await.unwrap().is_none());
+
+        // 重试死信时恢复 unique_key，key 被占用时不重试
+        let (dead, _) = JobRepository::enqueue(&repo, &job("default", Some("echo:2"))).await.unwrap();
+        repo.fail(dead, "boom", None, now).await.unwrap();
+        assert!(JobRepository::retry(&repo, dead, Some("echo:2"), now).await.unwrap());
+        assert_eq!(
+            JobRepository::enqueue(&repo, &job("default", Some("echo:2"))).await.unwrap(),
+            (dead, false)
+        );
+        repo.fail(dead, "boom", None, now).await.unwrap();
+        assert!(JobRepository::enqueue(&repo, &job("default", Some("echo:2"))).await.unwrap().1);
+        assert!(!JobRepository::retry(&repo, dead, Some("echo:2"), now).await.unwrap());
+    }
+
+    #[actix_web::test]
+    async fn schedules_fire_once_per_time() {
+        let repo = repository().await;
+        let fire_at = Utc::now();
+
+        assert!(repo.claim_schedule("nightly", fire_at).await.unwrap());
+        assert!(!repo.claim_schedule("nightly", fire_at).await.unwrap());
+        assert!(repo.claim_schedule("nightly", fire_at + Duration::days(1)).await.unwrap());
+        assert!(!repo.claim_schedule("nightly", fire_at).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn magic_links_are_single_use_and_replaced_by_newer_links() {
+        let repo = repository().await;
+        let user_id = create_user(&repo, "alice@example.com").await;
+        let link = |token_hash: &str| NewMagicLink {
+            user_id,
+            token_hash: token_hash.to_string(),
+            device_hash: "device".to_string(),
+            expires_at: Utc::now() + Duration::minutes(15),
+        };
+
+        repo.create_magic_link(&link("first"), "111111", &message("magic_link", "alice@example.com"))
+            .await
+            .unwrap();
+        let id = repo
+            .create_magic_link(&link("second"), "222222", &message("magic_link", "alice@example.com"))
+            .await
+            .unwrap();
+        assert!(repo.find_magic_link("first").await.unwrap().is_none());
+        let user = UserRepository::find_by_id(&repo, user_id).await.unwrap().unwrap();
+        assert_eq!(user.verification_code.as_deref(), Some("222222"));
+
+        assert!(repo.consume_magic_link(id, Utc::now()).await.unwrap());
+        assert!(!repo.consume_magic_link(id, Utc::now()).await.unwrap());
+        assert!(repo.find_magic_link("second").await.unwrap().unwrap().used_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn audit_logs_filter_and_purge() {
+        let repo = repository().await;
+        let entry = |action: &str, target_id: i64| NewAuditLog {
+            actor_type: "user".to_string(),
+            actor_id: Some(1),
+            api_key: None,
+            action: action.to_string(),
+            target_type: "app".to_string(),
+            target_id: Some(target_id),
+            app_id: Some(target_id),
+            before_data: None,
+            after_data: Some(r#"{"name":"Blog"}"#.to_string()),
+            ip: Some("127.0.0.1".to_string()),
+            user_agent: None,
+            request_id: None,
+        };
+        let now = Utc::now();
+
+        AuditRepository::append(&repo, &entry("app.create", 1), now - Duration::days(10)).await.unwrap();
+        AuditRepository::append(&repo, &entry("app.create", 2), now).await.unwrap();
+        AuditRepository::append(&repo, &entry("app.delete", 1), now).await.unwrap();
+
+        let filter = AuditFilter {
+            target_id: Some(1),
+            ..Default::default()
+        };
+        let logs = AuditRepository::list(&repo, &filter, Page::new(1, 10)).await.unwrap();
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].action, "app.delete");
+        let filter = AuditFilter {
+            since: Some(now - Duration::days(1)),
+            ..Default::default()
+        };
+        assert_eq!(AuditRepository::count(&repo, &filter).await.unwrap(), 2);
+
+        assert_eq!(AuditRepository::purge_before(&repo, now - Duration::days(1)).await.unwrap(), 1);
+        assert_eq!(AuditRepository::count(&repo, &AuditFilter::default()).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn change_events_and_api_keys_belong_to_their_app() {
+        let repo = repository().await;
+        let alice = create_user(&repo, "alice@example.com").await;
+        let app = AppRepository::create(
+            &repo,
+            &app_request("Blog", "blog"),
+            alice,
+        )
+        .await
+        .unwrap();
+        let docs = AppRepository::create(&repo, &app_request("Docs", "docs"), alice).await.unwrap();
+        let now = Utc::now();
+
+        // 应用和文章的修改在同一事务中写入事件
+        let created = ChangeEventRepository::last_id(&repo, app.id).await.unwrap();
+        assert!(created > 0);
+        let draft = CreateArticleRequest {
+            title: "Draft".to_string(),
+            content: "Content".to_string(),
+            app_id: Some(app.id),
+            ..Default::default()
+        };
+        let article = ArticleRepository::create(&repo, &draft, alice).await.unwrap();
+        let status = |status: i16| UpdateArticleRequest {
+            status: Some(status),
+            ..Default::default()
+        };
+        ArticleRepository::update(&repo, article.id, alice, &status(2), 1).await.unwrap().unwrap();
+        ArticleRepository::update(&repo, article.id, alice, &status(2), 2).await.unwrap().unwrap();
+        // 版本不符的修改不写入事件
+        assert!(ArticleRepository::update(&repo, article.id, alice, &status(1), 1).await.unwrap().is_none());
+        assert!(ArticleRepository::trash(&repo, article.id, alice).await.unwrap());
+        assert!(ArticleRepository::restore(&repo, article.id, alice).await.unwrap());
+        // 不属于应用的文章没有事件
+        let loose = CreateArticleRequest { app_id: None, ..draft };
+        ArticleRepository::create(&repo, &loose, alice).await.unwrap();
+
+        let events = repo.list_since(app.id, 0, 10).await.unwrap();
+        let types: Vec<&str> = events.iter().map(|event| event.event_type.as_str()).collect();
+        assert_eq!(
+            types,
+            [APP_CREATED, ARTICLE_CREATED, ARTICLE_PUBLISHED, ARTICLE_UPDATED, ARTICLE_DELETED, ARTICLE_RESTORED]
+        );
+        assert!(events[1..].iter().all(|event| event.target_id == article.id));
+        // 每次写入都锁住同一行，保证事件按 id 顺序提交
+        let appended: i64 = sqlx::query_scalar("SELECT appended FROM change_event_sequence WHERE id = 1")
+            .fetch_one(&repo.pool)
+            .await
+            .unwrap();
+        assert_eq!(appended, 7);
+        let last = events.last().unwrap().id;
+        assert_eq!(repo.list_since(app.id, events[4].id, 10).await.unwrap()[0].id, last);
+        assert_eq!(ChangeEventRepository::last_id(&repo, app.id).await.unwrap(), last);
+
+        sqlx::query("UPDATE change_events SET created_at = ? WHERE id = ?")
+            .bind(now - Duration::days(10))
+            .bind(created)
+            .execute(&repo.pool)
+            .await
+            .unwrap();
+        assert_eq!(ChangeEventRepository::purge_before(&repo, now - Duration::days(1)).await.unwrap(), 1);
+
+        let key = ApiKeyRepository::create(
+            &repo,
+            &NewApiKey {
+                app_id: app.id,
+                name: "Preview".to_string(),
+                prefix: "rsk_0123abcd".to_string(),
+                key_hash: "hash".to_string(),
+                created_by: alice,
+            },
+            now,
+        )
+        .await
+        .unwrap();
+        assert_eq!(repo.find_by_hash("hash").await.unwrap().unwrap().id, key.id);
+        assert!(!ApiKeyRepository::delete(&repo, docs.id, key.id).await.unwrap());
+
+        // 永久删除应用时一起删除它的 key 和事件
+        assert!(AppRepository::trash(&repo, app.id, alice).await.unwrap());
+        assert_eq!(AppRepository::purge_trashed(&repo, Utc::now() + Duration::seconds(1)).await.unwrap(), 1);
+        assert!(repo.list_by_app(app.id).await.unwrap().is_empty());
+        assert!(repo.list_since(app.id, 0, 10).await.unwrap().is_empty());
+        assert_eq!(repo.list_since(docs.id, 0, 10).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,77 @@
+use crate::db::{AppRepository, ArticleRepository, UserRepository};
+use crate::models::{App, Article, User};
+use crate::utils::AppError;
+use async_graphql::dataloader::Loader;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub struct UserLoader {
+    users: Arc<dyn UserRepository>,
+}
+
+impl UserLoader {
+    pub fn new(users: Arc<dyn UserRepository>) -> Self {
+        Self { users }
+    }
+}
+
+impl Loader<i64> for UserLoader {
+    type Value = User;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, keys: &[i64]) -> Result<HashMap<i64, User>, Self::Error> {
+        let users = self.users.find_by_ids(keys).await.map_err(AppError::from)?;
+        Ok(users.into_iter().map(|user| (user.id, user)).collect())
+    }
+}
+
+// 按作者批量加载文章，可见性由调用方过滤
+pub struct ArticlesByAuthorLoader {
+    articles: Arc<dyn ArticleRepository>,
+}
+
+impl ArticlesByAuthorLoader {
+    pub fn new(articles: Arc<dyn ArticleRepository>) -> Self {
+        Self { articles }
+    }
+}
+
+impl Loader<i64> for ArticlesByAuthorLoader {
+    type Value = Vec<Article>;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, keys: &[i64]) -> Result<HashMap<i64, Vec<Article>>, Self::Error> {
+        let articles = self.articles.list_by_authors(keys).await.map_err(AppError::from)?;
+
+        let mut result: HashMap<i64, Vec<Article>> = HashMap::new();
+        for article in articles {
+            result.entry(article.author_id).or_default().push(article);
+        }
+        Ok(result)
+    }
+}
+
+pub struct AppsByCreatorLoader {
+    apps: Arc<dyn AppRepository>,
+}
+
+impl AppsByCreatorLoader {
+    pub fn new(apps: Arc<dyn AppRepository>) -> Self {
+        Self { apps }
+    }
+}
+
+impl Loader<i64> for AppsByCreatorLoader {
+    type Value = Vec<App>;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, keys: &[i64]) -> Result<HashMap<i64, Vec<App>>, Self::Error> {
+        let apps = self.apps.list_by_creators(keys).await.map_err(AppError::from)?;
+
+        let mut result: HashMap<i64, Vec<App>> = HashMap::new();
+        for app in apps {
+            result.entry(app.creator_id).or_default().push(app);
+        }
+        Ok(result)
+    }
+}
@@ -0,0 +1,122 @@
+mod loaders;
+mod mutation;
+mod query;
+mod types;
+
+use crate::config::graphql::GraphqlConfig;
+use crate::db::Repositories;
+use crate::middleware::audit::Audit;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::middleware::request_id::current_request_id;
+use crate::services::{AppService, ArticleService};
+use crate::utils::AppError;
+use actix_web::http::header::AUTHORIZATION;
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse, ResponseError};
+use async_graphql::dataloader::DataLoader;
+use async_graphql::extensions::apollo_persisted_queries::{ApolloPersistedQueries, LruCacheStorage};
+use async_graphql::http::GraphiQLSource;
+use async_graphql::{Context, EmptySubscription, ErrorExtensions, Schema};
+
+pub use self::mutation::MutationRoot;
+pub use self::query::QueryRoot;
+
+pub type AppSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+// 列表字段单次最多返回的条数
+const MAX_PAGE_SIZE: i64 = 100;
+
+// 当前请求的用户，未登录时为 None
+#[derive(Debug, Clone, Copy)]
+pub struct Viewer(pub Option<i64>);
+
+// 当前登录的认证强度，未登录时为 0
+#[derive(Debug, Clone, Copy)]
+pub struct ViewerAal(pub u8);
+
+pub fn build_schema(apps: AppService, articles: ArticleService, config: &GraphqlConfig) -> AppSchema {
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+        .data(apps)
+        .data(articles)
+        .limit_depth(config.max_depth)
+        .limit_complexity(config.max_complexity)
+        .extension(ApolloPersistedQueries::new(LruCacheStorage::new(
+            config.persisted_query_cache_size,
+        )))
+        .finish()
+}
+
+// 与 REST 接口相同的登录要求
+fn require_user(ctx: &Context<'_>) -> Result<i64, AppError> {
+    ctx.data_unchecked::<Viewer>()
+        .0
+        .ok_or_else(|| AppError::Unauthorized("Authentication required".to_string()))
+}
+
+impl From<AppError> for async_graphql::Error {
+    fn from(e: AppError) -> Self {
+        if e.status_code().is_server_error() {
+            log::error!("[{}] {:?}", current_request_id().as_deref().unwrap_or("-"), e);
+        }
+
+        let details = match &e {
+            AppError::ValidationError(details) => serde_json::to_value(details).ok(),
+            _ => None,
+        };
+
+        async_graphql::Error::new(e.public_message()).extend_with(|_, extensions| {
+            extensions.set("code", e.code());
+            if let Some(details) = details {
+                if let Ok(value) = async_graphql::Value::from_json(details) {
+                    extensions.set("details", value);
+                }
+            }
+            if let Some(request_id) = current_request_id() {
+                extensions.set("request_id", request_id);
+            }
+        })
+    }
+}
+
+impl ErrorExtensions for AppError {
+    fn extend(&self) -> async_graphql::Error {
+        async_graphql::Error::new(self.public_message()).extend_with(|_, extensions| {
+            extensions.set("code", self.code());
+        })
+    }
+}
+
+pub async fn graphql(
+    schema: web::Data<AppSchema>,
+    repositories: web::Data<Repositories>,
+    audit: Audit,
+    req: HttpRequest,
+    body: web::Json<async_graphql::Request>,
+) -> Result<HttpResponse, AppError> {
+    // 带了 Authorization 头就必须是合法的 token
+    let (viewer, aal) = if req.headers().contains_key(AUTHORIZATION) {
+        let user = AuthenticatedUser::extract(&req).await?;
+        (Some(user.user_id), user.aal)
+    } else {
+        (None, 0)
+    };
+
+    let request = body
+        .into_inner()
+        .data(Viewer(viewer))
+        .data(ViewerAal(aal))
+        .data(audit)
+        .data(DataLoader::new(loaders::UserLoader::new(repositories.users.clone()), tokio::spawn))
+        .data(DataLoader::new(
+            loaders::ArticlesByAuthorLoader::new(repositories.articles.clone()),
+            tokio::spawn,
+        ))
+        .data(DataLoader::new(loaders::AppsByCreatorLoader::new(repositories.apps.clone()), tokio::spawn));
+
+    Ok(HttpResponse::Ok().json(schema.execute(request).await))
+}
+
+pub async fn graphiql() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(GraphiQLSource::build().endpoint("/api/graphql").finish())
+}
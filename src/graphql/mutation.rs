@@ -0,0 +1,120 @@
+use super::{require_user, ViewerAal};
+use super::types::{GqlApp, GqlArticle};
+use crate::middleware::audit::Audit;
+use crate::models::article::{CreateArticleRequest, UpdateArticleRequest};
+use crate::models::audit::{
+    APP_CREATE, APP_DELETE, APP_RESTORE, APP_UPDATE, ARTICLE_CREATE, ARTICLE_DELETE, ARTICLE_RESTORE, ARTICLE_UPDATE,
+    TARGET_APP, TARGET_ARTICLE,
+};
+use crate::models::{AppResponse, CreateAppRequest, UpdateAppRequest};
+use crate::services::audit::AuditEvent;
+use crate::services::{AppService, ArticleService};
+use crate::utils::precondition::Precondition;
+use crate::utils::AppError;
+use async_graphql::{Context, Object, Result};
+use validator::Validate;
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    async fn create_app(&self, ctx: &Context<'_>, input: CreateAppRequest) -> Result<GqlApp> {
+        let user_id = require_user(ctx)?;
+        input.validate().map_err(AppError::from)?;
+        let aal = ctx.data_unchecked::<ViewerAal>().0;
+        let app = ctx.data_unchecked::<AppService>().create(&input, user_id, aal).await?;
+        let event = AuditEvent::created(APP_CREATE, TARGET_APP, app.id, &AppResponse::from(app.clone()));
+        ctx.data_unchecked::<Audit>().record(event.with_app(app.id)).await;
+        Ok(GqlApp(app))
+    }
+
+    async fn update_app(&self, ctx: &Context<'_>, id: i64, input: UpdateAppRequest) -> Result<GqlApp> {
+        let user_id = require_user(ctx)?;
+        input.validate().map_err(AppError::from)?;
+        let aal = ctx.data_unchecked::<ViewerAal>().0;
+        let precondition = Precondition::from_version(input.version)?;
+        let (before, app) = ctx
+            .data_unchecked::<AppService>()
+            .update(id, &input, user_id, aal, &precondition)
+            .await?;
+        let event = AuditEvent::updated(
+            APP_UPDATE,
+            TARGET_APP,
+            id,
+            &AppResponse::from(before),
+            &AppResponse::from(app.clone()),
+        );
+        ctx.data_unchecked::<Audit>().record(event.with_app(id)).await;
+        Ok(GqlApp(app))
+    }
+
+    // 移入回收站
+    async fn delete_app(&self, ctx: &Context<'_>, id: i64) -> Result<bool> {
+        let user_id = require_user(ctx)?;
+        let aal = ctx.data_unchecked::<ViewerAal>().0;
+        let app = ctx.data_unchecked::<AppService>().delete(id, user_id, aal).await?;
+        let event = AuditEvent::deleted(APP_DELETE, TARGET_APP, id, &AppResponse::from(app));
+        ctx.data_unchecked::<Audit>().record(event.with_app(id)).await;
+        Ok(true)
+    }
+
+    async fn restore_app(&self, ctx: &Context<'_>, id: i64) -> Result<GqlApp> {
+        require_user(ctx)?;
+        let aal = ctx.data_unchecked::<ViewerAal>().0;
+        let app = ctx.data_unchecked::<AppService>().restore(id, aal).await?;
+        let event = AuditEvent::new(APP_RESTORE, TARGET_APP, Some(id));
+        ctx.data_unchecked::<Audit>().record(event.with_app(id)).await;
+        Ok(GqlApp(app))
+    }
+
+    async fn create_article(&self, ctx: &Context<'_>, input: CreateArticleRequest) -> Result<GqlArticle> {
+        let user_id = require_user(ctx)?;
+        input.validate().map_err(AppError::from)?;
+        let aal = ctx.data_unchecked::<ViewerAal>().0;
+        let article = ctx.data_unchecked::<ArticleService>().create(&input, user_id, aal).await?;
+        let event = AuditEvent::created(ARTICLE_CREATE, TARGET_ARTICLE, article.id, &article);
+        ctx.data_unchecked::<Audit>().record(event).await;
+        Ok(GqlArticle(article))
+    }
+
+    async fn update_article(
+        &self,
+        ctx: &Context<'_>,
+        id: i64,
+        input: UpdateArticleRequest,
+    ) -> Result<GqlArticle> {
+        let user_id = require_user(ctx)?;
+        input.validate().map_err(AppError::from)?;
+        let aal = ctx.data_unchecked::<ViewerAal>().0;
+        let precondition = Precondition::from_version(input.version)?;
+        let (before, article) = ctx
+            .data_unchecked::<ArticleService>()
+            .update(id, user_id, aal, &input, &precondition)
+            .await?;
+        let event = AuditEvent::updated(ARTICLE_UPDATE, TARGET_ARTICLE, id, &before, &article);
+        ctx.data_unchecked::<Audit>().record(event).await;
+        Ok(GqlArticle(article))
+    }
+
+    // 移入回收站
+    async fn delete_article(&self, ctx: &Context<'_>, id: i64) -> Result<bool> {
+        let user_id = require_user(ctx)?;
+        let aal = ctx.data_unchecked::<ViewerAal>().0;
+        let article = ctx.data_unchecked::<ArticleService>().delete(id, user_id, aal).await?;
+        let event = AuditEvent::deleted(ARTICLE_DELETE, TARGET_ARTICLE, id, &article);
+        ctx.data_unchecked::<Audit>().record(event).await;
+        Ok(true)
+    }
+
+    async fn restore_article(&self, ctx: &Context<'_>, id: i64) -> Result<GqlArticle> {
+        let user_id = require_user(ctx)?;
+        let aal = ctx.data_unchecked::<ViewerAal>().0;
+        let article = ctx.data_unchecked::<ArticleService>().restore(id, user_id, aal).await?;
+        let mut event = AuditEvent::new(ARTICLE_RESTORE, TARGET_ARTICLE, Some(id));
+        if let Some(app_id) = article.app_id {
+            event = event.with_app(app_id);
+        }
+        ctx.data_unchecked::<Audit>().record(event).await;
+        Ok(GqlArticle(article))
+    }
+}
@@ -0,0 +1,134 @@
+use super::loaders::UserLoader;
+use super::{require_user, MAX_PAGE_SIZE};
+use super::types::{AppConnection, ArticleConnection, GqlApp, GqlArticle, GqlUser};
+use crate::db;
+use crate::services::{AppService, ArticleService};
+use crate::utils::AppError;
+use async_graphql::dataloader::DataLoader;
+use async_graphql::{Context, InputObject, Object, Result};
+
+// 与 REST 列表接口一致的分页参数
+fn pagination(page: Option<i64>, page_size: Option<i64>) -> (i64, i64) {
+    let page = page.unwrap_or(1).max(1);
+    let page_size = page_size.unwrap_or(10).clamp(1, MAX_PAGE_SIZE);
+    (page, page_size)
+}
+
+#[derive(InputObject, Default)]
+pub struct AppFilter {
+    pub keyword: Option<String>,
+    pub identifier: Option<String>,
+    pub creator_id: Option<i64>,
+}
+
+impl From<AppFilter> for db::AppFilter {
+    fn from(filter: AppFilter) -> Self {
+        db::AppFilter {
+            keyword: filter.keyword,
+            identifier: filter.identifier,
+            creator_id: filter.creator_id,
+            trashed: false,
+        }
+    }
+}
+
+#[derive(InputObject, Default)]
+pub struct ArticleFilter {
+    pub keyword: Option<String>,
+    pub status: Option<i16>,
+    pub author_id: Option<i64>,
+    pub app_id: Option<i64>,
+    pub category: Option<String>,
+}
+
+impl From<ArticleFilter> for db::ArticleFilter {
+    fn from(filter: ArticleFilter) -> Self {
+        db::ArticleFilter {
+            keyword: filter.keyword,
+            status: filter.status,
+            author_id: filter.author_id,
+            app_id: filter.app_id,
+            category: filter.category,
+            indexable: false,
+        }
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn me(&self, ctx: &Context<'_>) -> Result<Option<GqlUser>> {
+        let user_id = require_user(ctx)?;
+        let user = ctx.data_unchecked::<DataLoader<UserLoader>>().load_one(user_id).await?;
+        Ok(user.map(GqlUser))
+    }
+
+    // 需要登录，避免匿名调用者按 id 枚举用户及其应用
+    async fn user(&self, ctx: &Context<'_>, id: i64) -> Result<Option<GqlUser>> {
+        require_user(ctx)?;
+        let user = ctx.data_unchecked::<DataLoader<UserLoader>>().load_one(id).await?;
+        Ok(user.map(GqlUser))
+    }
+
+    async fn app(&self, ctx: &Context<'_>, id: i64) -> Result<Option<GqlApp>> {
+        match ctx.data_unchecked::<AppService>().get(id).await {
+            Ok(app) => Ok(Some(GqlApp(app))),
+            Err(AppError::NotFound(_)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    #[graphql(complexity = "page_size.unwrap_or(10).clamp(1, MAX_PAGE_SIZE) as usize * child_complexity")]
+    async fn apps(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<AppFilter>,
+        page: Option<i64>,
+        page_size: Option<i64>,
+    ) -> Result<AppConnection> {
+        let filter = db::AppFilter::from(filter.unwrap_or_default());
+        let (page, page_size) = pagination(page, page_size);
+        let (apps, total) = ctx.data_unchecked::<AppService>().list(&filter, page, page_size).await?;
+
+        Ok(AppConnection {
+            items: apps.into_iter().map(GqlApp).collect(),
+            total,
+            page,
+            page_size,
+        })
+    }
+
+    async fn article(&self, ctx: &Context<'_>, id: i64) -> Result<Option<GqlArticle>> {
+        let user_id = require_user(ctx)?;
+        match ctx.data_unchecked::<ArticleService>().get(id, user_id).await {
+            Ok(article) => Ok(Some(GqlArticle(article))),
+            Err(AppError::NotFound(_)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    #[graphql(complexity = "page_size.unwrap_or(10).clamp(1, MAX_PAGE_SIZE) as usize * child_complexity")]
+    async fn articles(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<ArticleFilter>,
+        page: Option<i64>,
+        page_size: Option<i64>,
+    ) -> Result<ArticleConnection> {
+        let user_id = require_user(ctx)?;
+        let filter = db::ArticleFilter::from(filter.unwrap_or_default());
+        let (page, page_size) = pagination(page, page_size);
+        let (articles, total) = ctx
+            .data_unchecked::<ArticleService>()
+            .list_page(user_id, &filter, page, page_size)
+            .await?;
+
+        Ok(ArticleConnection {
+            items: articles.into_iter().map(GqlArticle).collect(),
+            total,
+            page,
+            page_size,
+        })
+    }
+}
@@ -0,0 +1,247 @@
+use super::loaders::{AppsByCreatorLoader, ArticlesByAuthorLoader, UserLoader};
+use super::{Viewer, MAX_PAGE_SIZE};
+use crate::models::{App, Article, User};
+use async_graphql::dataloader::DataLoader;
+use async_graphql::{Context, Object, Result, SimpleObject};
+use chrono::{DateTime, Utc};
+
+// 文章对当前用户是否可见：已发布或自己的草稿
+pub fn article_visible(article: &Article, viewer: Option<i64>) -> bool {
+    article.status == 2 || Some(article.author_id) == viewer
+}
+
+// 嵌套列表字段默认返回的条数
+const DEFAULT_FIRST: i64 = 10;
+
+fn first_n(first: Option<i64>) -> usize {
+    first.unwrap_or(DEFAULT_FIRST).clamp(1, MAX_PAGE_SIZE) as usize
+}
+
+pub struct GqlUser(pub User);
+
+#[Object(name = "User")]
+impl GqlUser {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+
+    async fn username(&self) -> &str {
+        &self.0.username
+    }
+
+    // 邮箱仅对本人可见
+    async fn email(&self, ctx: &Context<'_>) -> Option<&str> {
+        let viewer = ctx.data_unchecked::<Viewer>().0;
+        (viewer == Some(self.0.id)).then_some(self.0.email.as_str())
+    }
+
+    async fn email_verified(&self) -> bool {
+        self.0.email_verified != 0
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    #[graphql(complexity = "first_n(first) * child_complexity")]
+    async fn articles(&self, ctx: &Context<'_>, status: Option<i16>, first: Option<i64>) -> Result<Vec<GqlArticle>> {
+        let viewer = ctx.data_unchecked::<Viewer>().0;
+        let articles = ctx
+            .data_unchecked::<DataLoader<ArticlesByAuthorLoader>>()
+            .load_one(self.0.id)
+            .await?
+            .unwrap_or_default();
+
+        Ok(articles
+            .into_iter()
+            .filter(|article| article_visible(article, viewer))
+            .filter(|article| status.is_none_or(|status| article.status == status))
+            .take(first_n(first))
+            .map(GqlArticle)
+            .collect())
+    }
+
+    #[graphql(complexity = "first_n(first) * child_complexity")]
+    async fn apps(&self, ctx: &Context<'_>, first: Option<i64>) -> Result<Vec<GqlApp>> {
+        let apps = ctx
+            .data_unchecked::<DataLoader<AppsByCreatorLoader>>()
+            .load_one(self.0.id)
+            .await?
+            .unwrap_or_default();
+        Ok(apps.into_iter().take(first_n(first)).map(GqlApp).collect())
+    }
+}
+
+pub struct GqlArticle(pub Article);
+
+#[Object(name = "Article")]
+impl GqlArticle {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+
+    async fn title(&self) -> &str {
+        &self.0.title
+    }
+
+    async fn content(&self) -> &str {
+        &self.0.content
+    }
+
+    async fn status(&self) -> i16 {
+        self.0.status
+    }
+
+    async fn author_id(&self) -> i64 {
+        self.0.author_id
+    }
+
+    async fn app_id(&self) -> Option<i64> {
+        self.0.app_id
+    }
+
+    async fn category(&self) -> Option<&str> {
+        self.0.category.as_deref()
+    }
+
+    async fn locale(&self) -> Option<&str> {
+        self.0.locale.as_deref()
+    }
+
+    async fn translation_of(&self) -> Option<i64> {
+        self.0.translation_of
+    }
+
+    async fn meta_title(&self) -> Option<&str> {
+        self.0.meta_title.as_deref()
+    }
+
+    async fn meta_description(&self) -> Option<&str> {
+        self.0.meta_description.as_deref()
+    }
+
+    async fn canonical_url(&self) -> Option<&str> {
+        self.0.canonical_url.as_deref()
+    }
+
+    async fn og_image_url(&self) -> Option<&str> {
+        self.0.og_image_url.as_deref()
+    }
+
+    async fn noindex(&self) -> bool {
+        self.0.noindex != 0
+    }
+
+    async fn author(&self, ctx: &Context<'_>) -> Result<Option<GqlUser>> {
+        let user = ctx
+            .data_unchecked::<DataLoader<UserLoader>>()
+            .load_one(self.0.author_id)
+            .await?;
+        Ok(user.map(GqlUser))
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    async fn updated_at(&self) -> DateTime<Utc> {
+        self.0.updated_at
+    }
+
+    async fn version(&self) -> i64 {
+        self.0.version
+    }
+}
+
+pub struct GqlApp(pub App);
+
+#[Object(name = "App")]
+impl GqlApp {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn description(&self) -> &str {
+        &self.0.description
+    }
+
+    async fn identifier(&self) -> &str {
+        &self.0.identifier
+    }
+
+    async fn logo_url(&self) -> Option<&str> {
+        self.0.logo_url.as_deref()
+    }
+
+    async fn email_from(&self) -> Option<&str> {
+        self.0.email_from.as_deref()
+    }
+
+    async fn feed_title(&self) -> Option<&str> {
+        self.0.feed_title.as_deref()
+    }
+
+    async fn feed_description(&self) -> Option<&str> {
+        self.0.feed_description.as_deref()
+    }
+
+    async fn feed_link(&self) -> Option<&str> {
+        self.0.feed_link.as_deref()
+    }
+
+    async fn base_url(&self) -> Option<&str> {
+        self.0.base_url.as_deref()
+    }
+
+    async fn require_two_factor(&self) -> bool {
+        self.0.require_two_factor != 0
+    }
+
+    async fn creator(&self, ctx: &Context<'_>) -> Result<Option<GqlUser>> {
+        let user = ctx
+            .data_unchecked::<DataLoader<UserLoader>>()
+            .load_one(self.0.creator_id)
+            .await?;
+        Ok(user.map(GqlUser))
+    }
+
+    async fn updater(&self, ctx: &Context<'_>) -> Result<Option<GqlUser>> {
+        let user = ctx
+            .data_unchecked::<DataLoader<UserLoader>>()
+            .load_one(self.0.updater_id)
+            .await?;
+        Ok(user.map(GqlUser))
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    async fn updated_at(&self) -> DateTime<Utc> {
+        self.0.updated_at
+    }
+
+    async fn version(&self) -> i64 {
+        self.0.version
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct AppConnection {
+    pub items: Vec<GqlApp>,
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+}
+
+#[derive(SimpleObject)]
+pub struct ArticleConnection {
+    pub items: Vec<GqlArticle>,
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+}
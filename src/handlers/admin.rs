@@ -0,0 +1,310 @@
+use crate::db::{AuditFilter, JobFilter, OutboxFilter};
+use crate::middleware::auth::AdminUser;
+use crate::models::audit::{AuditListResponse, AuditQuery};
+use crate::models::job::{JobListResponse, JobQuery, JobRecord, JobStatsResponse};
+use crate::models::outbox::{OutboxEmail, OutboxListResponse, OutboxQuery};
+use crate::services::{AppService, AuditService, JobService, OutboxService};
+use crate::utils::email::EmailService;
+use crate::utils::email_template::{EmailTemplate, RenderedEmail, DEFAULT_LOCALE, SUPPORTED_LOCALES};
+use crate::utils::validation::ValidatedQuery;
+use crate::utils::{AppError, ErrorResponse};
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EmailTemplateList {
+    pub templates: Vec<String>,
+    pub locales: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct EmailPreviewQuery {
+    // 默认使用 en
+    pub locale: Option<String>,
+    // 使用该应用的品牌
+    pub app: Option<String>,
+    // json（默认）、html 或 text
+    pub format: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/email-templates",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Available email templates and locales", body = EmailTemplateList),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Not an admin", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_email_templates(_admin: AdminUser) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(EmailTemplateList {
+        templates: EmailTemplate::ALL.iter().map(|template| template.name().to_string()).collect(),
+        locales: SUPPORTED_LOCALES.iter().map(|locale| locale.to_string()).collect(),
+    }))
+}
+
+// 使用示例数据渲染模板
+#[utoipa::path(
+    get,
+    path = "/api/admin/email-templates/{name}/preview",
+    tag = "admin",
+    params(("name" = String, Path, description = "Template name"), EmailPreviewQuery),
+    responses(
+        (status = 200, description = "Rendered email", body = RenderedEmail),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Not an admin", body = ErrorResponse),
+        (status = 404, description = "Template or app not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn preview_email_template(
+    _admin: AdminUser,
+    path: web::Path<String>,
+    query: web::Query<EmailPreviewQuery>,
+    apps: web::Data<AppService>,
+    email_service: web::Data<EmailService>,
+) -> Result<HttpResponse, AppError> {
+    let template = EmailTemplate::parse(&path)
+        .ok_or_else(|| AppError::NotFound("Email template not found".to_string()))?;
+
+    let branding = match &query.app {
+        Some(identifier) => email_service.branding(Some(&apps.get_by_identifier(identifier).await?)),
+        None => email_service.branding(None),
+    };
+    let locale = query.locale.as_deref().unwrap_or(DEFAULT_LOCALE);
+
+    let rendered = email_service
+        .render(template, locale, &branding, &template.sample_context())
+        .map_err(|e| AppError::Internal(format!("Failed to render email template: {}", e)))?;
+
+    match query.format.as_deref() {
+        Some("html") => Ok(HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(rendered.html)),
+        Some("text") => Ok(HttpResponse::Ok()
+            .content_type("text/plain; charset=utf-8")
+            .body(rendered.text)),
+        None | Some("json") => Ok(HttpResponse::Ok().json(rendered)),
+        Some(other) => Err(AppError::BadRequest(format!("Unsupported preview format: {}", other))),
+    }
+}
+
+// 查看发件箱，可以按状态筛选，例如 status=dead 查看死信
+#[utoipa::path(
+    get,
+    path = "/api/admin/outbox",
+    tag = "admin",
+    params(OutboxQuery),
+    responses(
+        (status = 200, description = "Paginated outbox messages", body = OutboxListResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Not an admin", body = ErrorResponse),
+        (status = 422, description = "Invalid query", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_outbox(
+    _admin: AdminUser,
+    outbox: web::Data<OutboxService>,
+    query: ValidatedQuery<OutboxQuery>,
+) -> Result<HttpResponse, AppError> {
+    let page = query.page.unwrap_or(1);
+    let page_size = query.page_size.unwrap_or(20);
+    let filter = OutboxFilter {
+        status: query.status.clone(),
+    };
+
+    let (messages, total) = outbox.list(&filter, page, page_size).await?;
+
+    Ok(HttpResponse::Ok().json(OutboxListResponse {
+        messages,
+        total,
+        page,
+        page_size,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/outbox/{id}",
+    tag = "admin",
+    params(("id" = i64, Path, description = "Outbox message ID")),
+    responses(
+        (status = 200, description = "Outbox message", body = OutboxEmail),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Not an admin", body = ErrorResponse),
+        (status = 404, description = "Message not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_outbox_email(
+    _admin: AdminUser,
+    outbox: web::Data<OutboxService>,
+    path: web::Path<i64>,
+) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(outbox.get(path.into_inner()).await?))
+}
+
+// 把死信重新放回队列
+#[utoipa::path(
+    post,
+    path = "/api/admin/outbox/{id}/retry",
+    tag = "admin",
+    params(("id" = i64, Path, description = "Outbox message ID")),
+    responses(
+        (status = 200, description = "Message queued for delivery", body = OutboxEmail),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Not an admin", body = ErrorResponse),
+        (status = 404, description = "Message not found", body = ErrorResponse),
+        (status = 409, description = "Message is not dead-lettered", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn retry_outbox_email(
+    _admin: AdminUser,
+    outbox: web::Data<OutboxService>,
+    path: web::Path<i64>,
+) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(outbox.retry(path.into_inner()).await?))
+}
+
+// 查看后台任务，可以按状态、队列和类型筛选
+#[utoipa::path(
+    get,
+    path = "/api/admin/jobs",
+    tag = "admin",
+    params(JobQuery),
+    responses(
+        (status = 200, description = "Paginated jobs", body = JobListResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Not an admin", body = ErrorResponse),
+        (status = 422, description = "Invalid query", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_jobs(
+    _admin: AdminUser,
+    jobs: web::Data<JobService>,
+    query: ValidatedQuery<JobQuery>,
+) -> Result<HttpResponse, AppError> {
+    let page = query.page.unwrap_or(1);
+    let page_size = query.page_size.unwrap_or(20);
+    let filter = JobFilter {
+        status: query.status.clone(),
+        queue: query.queue.clone(),
+        kind: query.kind.clone(),
+    };
+
+    let (items, total) = jobs.list(&filter, page, page_size).await?;
+
+    Ok(HttpResponse::Ok().json(JobListResponse {
+        jobs: items,
+        total,
+        page,
+        page_size,
+    }))
+}
+
+// 各队列的任务数和定时任务
+#[utoipa::path(
+    get,
+    path = "/api/admin/jobs/stats",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Queue state", body = JobStatsResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Not an admin", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn job_stats(_admin: AdminUser, jobs: web::Data<JobService>) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(jobs.stats().await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/jobs/{id}",
+    tag = "admin",
+    params(("id" = i64, Path, description = "Job ID")),
+    responses(
+        (status = 200, description = "Job", body = JobRecord),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Not an admin", body = ErrorResponse),
+        (status = 404, description = "Job not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_job(
+    _admin: AdminUser,
+    jobs: web::Data<JobService>,
+    path: web::Path<i64>,
+) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(jobs.get(path.into_inner()).await?))
+}
+
+// 把死信任务重新排队
+#[utoipa::path(
+    post,
+    path = "/api/admin/jobs/{id}/retry",
+    tag = "admin",
+    params(("id" = i64, Path, description = "Job ID")),
+    responses(
+        (status = 200, description = "Job queued", body = JobRecord),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Not an admin", body = ErrorResponse),
+        (status = 404, description = "Job not found", body = ErrorResponse),
+        (status = 409, description = "Job is not dead, or a job with the same unique key has not finished", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn retry_job(
+    _admin: AdminUser,
+    jobs: web::Data<JobService>,
+    path: web::Path<i64>,
+) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(jobs.retry(path.into_inner()).await?))
+}
+
+// 查看审计日志，可以按操作者、操作、对象、应用和时间筛选，最新的在前
+#[utoipa::path(
+    get,
+    path = "/api/admin/audit-logs",
+    tag = "admin",
+    params(AuditQuery),
+    responses(
+        (status = 200, description = "Paginated audit log entries", body = AuditListResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Not an admin", body = ErrorResponse),
+        (status = 422, description = "Invalid query", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_audit_logs(
+    _admin: AdminUser,
+    audit: web::Data<AuditService>,
+    query: ValidatedQuery<AuditQuery>,
+) -> Result<HttpResponse, AppError> {
+    let page = query.page.unwrap_or(1);
+    let page_size = query.page_size.unwrap_or(20);
+    let filter = AuditFilter {
+        actor_id: query.actor_id,
+        action: query.action.clone(),
+        target_type: query.target_type.clone(),
+        target_id: query.target_id,
+        app_id: query.app_id,
+        since: query.since,
+        until: query.until,
+    };
+
+    let (logs, total) = audit.list(&filter, page, page_size).await?;
+
+    Ok(HttpResponse::Ok().json(AuditListResponse {
+        logs,
+        total,
+        page,
+        page_size,
+    }))
+}
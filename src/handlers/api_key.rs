@@ -0,0 +1,98 @@
+use crate::middleware::audit::Audit;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::models::api_key::{ApiKey, ApiKeyCreated, CreateApiKeyRequest};
+use crate::models::audit::{API_KEY_CREATE, API_KEY_REVOKE, TARGET_API_KEY};
+use crate::models::MessageResponse;
+use crate::services::audit::AuditEvent;
+use crate::services::ApiKeyService;
+use crate::utils::validation::ValidatedJson;
+use crate::utils::{AppError, ErrorResponse};
+use actix_web::{web, HttpResponse};
+
+// 创建应用的 API key，用于订阅变更推送
+#[utoipa::path(
+    post,
+    path = "/api/apps/{id}/api-keys",
+    tag = "apps",
+    params(("id" = i64, Path, description = "App id")),
+    request_body = CreateApiKeyRequest,
+    responses(
+        (status = 201, description = "API key created, the key is only shown once", body = ApiKeyCreated),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "App requires a two-factor session", body = ErrorResponse),
+        (status = 404, description = "App not found", body = ErrorResponse),
+        (status = 422, description = "Invalid request", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_api_key(
+    api_keys: web::Data<ApiKeyService>,
+    user: AuthenticatedUser,
+    audit: Audit,
+    path: web::Path<i64>,
+    req: ValidatedJson<CreateApiKeyRequest>,
+) -> Result<HttpResponse, AppError> {
+    let app_id = path.into_inner();
+
+    let created = api_keys.create(app_id, &req.name, user.user_id, user.aal).await?;
+    let event = AuditEvent::created(API_KEY_CREATE, TARGET_API_KEY, created.api_key.id, &created.api_key);
+    audit.record(event.with_app(app_id)).await;
+
+    Ok(HttpResponse::Created().json(created))
+}
+
+// 应用的 API key，不包含 key 本身
+#[utoipa::path(
+    get,
+    path = "/api/apps/{id}/api-keys",
+    tag = "apps",
+    params(("id" = i64, Path, description = "App id")),
+    responses(
+        (status = 200, description = "API keys of the app", body = [ApiKey]),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 404, description = "App not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_api_keys(
+    api_keys: web::Data<ApiKeyService>,
+    _user: AuthenticatedUser,
+    path: web::Path<i64>,
+) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(api_keys.list(path.into_inner()).await?))
+}
+
+// 吊销 API key，使用它的连接在重连时被拒绝
+#[utoipa::path(
+    delete,
+    path = "/api/apps/{id}/api-keys/{key_id}",
+    tag = "apps",
+    params(
+        ("id" = i64, Path, description = "App id"),
+        ("key_id" = i64, Path, description = "API key id"),
+    ),
+    responses(
+        (status = 200, description = "API key revoked", body = MessageResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "App requires a two-factor session", body = ErrorResponse),
+        (status = 404, description = "App or API key not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn revoke_api_key(
+    api_keys: web::Data<ApiKeyService>,
+    user: AuthenticatedUser,
+    audit: Audit,
+    path: web::Path<(i64, i64)>,
+) -> Result<HttpResponse, AppError> {
+    let (app_id, key_id) = path.into_inner();
+
+    let api_key = api_keys.revoke(app_id, key_id, user.aal).await?;
+    audit
+        .record(AuditEvent::deleted(API_KEY_REVOKE, TARGET_API_KEY, key_id, &api_key).with_app(app_id))
+        .await;
+
+    Ok(HttpResponse::Ok().json(MessageResponse {
+        message: "API key revoked".to_string(),
+    }))
+}
@@ -1,232 +1,251 @@
-use actix_web::{web, HttpResponse, Responder};
-use sqlx::{MySqlPool, Row};
-use crate::models::{App, CreateAppRequest, UpdateAppRequest, AppQuery, AppResponse, AppListResponse, MessageResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
+use crate::db::AppFilter;
+use crate::models::{CreateAppRequest, UpdateAppRequest, AppQuery, AppResponse, AppListResponse, MessageResponse};
+use crate::middleware::audit::Audit;
 use crate::middleware::auth::AuthenticatedUser;
+use crate::models::audit::{APP_CREATE, APP_DELETE, APP_RESTORE, APP_UPDATE, TARGET_APP};
+use crate::services::audit::AuditEvent;
+use crate::services::AppService;
+use crate::utils::precondition::{etag, Precondition};
+use crate::utils::validation::{ValidatedJson, ValidatedQuery};
+use crate::utils::{AppError, ErrorResponse};
 
 // 创建应用
+#[utoipa::path(
+    post,
+    path = "/api/apps",
+    tag = "apps",
+    request_body = CreateAppRequest,
+    responses(
+        (status = 201, description = "App created", body = MessageResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Requiring two-factor needs a two-factor session", body = ErrorResponse),
+        (status = 409, description = "Identifier already exists", body = ErrorResponse),
+        (status = 422, description = "Invalid request", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn create_app(
-    pool: web::Data<MySqlPool>,
+    apps: web::Data<AppService>,
     user: AuthenticatedUser,
-    req: web::Json<CreateAppRequest>,
-) -> impl Responder {
-    // 检查应用标识是否已存在
-    let exists = sqlx::query!("SELECT id FROM apps WHERE identifier = ?", req.identifier)
-        .fetch_optional(pool.get_ref())
+    audit: Audit,
+    req: ValidatedJson<CreateAppRequest>,
+) -> Result<HttpResponse, AppError> {
+    let app = apps.create(&req, user.user_id, user.aal).await?;
+    audit
+        .record(AuditEvent::created(APP_CREATE, TARGET_APP, app.id, &AppResponse::from(app.clone())).with_app(app.id))
         .await;
 
-    match exists {
-        Ok(Some(_)) => return HttpResponse::BadRequest().json(MessageResponse {
-            message: "应用标识已存在".to_string(),
-        }),
-        Ok(None) => {},
-        Err(e) => return HttpResponse::InternalServerError().json(MessageResponse {
-            message: format!("检查应用标识失败: {}", e),
-        }),
-    }
-
-    let result = sqlx::query(
-        r#"
-        INSERT INTO apps (name, description, identifier, creator_id, updater_id)
-        VALUES (?, ?, ?, ?, ?)
-        "#,
-    )
-    .bind(&req.name)
-    .bind(&req.description)
-    .bind(&req.identifier)
-    .bind(user.user_id)
-    .bind(user.user_id)
-    .execute(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(_) => HttpResponse::Created().json(MessageResponse {
-            message: "应用创建成功".to_string(),
-        }),
-        Err(e) => HttpResponse::InternalServerError().json(MessageResponse {
-            message: format!("创建应用失败: {}", e),
-        }),
-    }
+    Ok(HttpResponse::Created().json(MessageResponse {
+        message: "App created successfully".to_string(),
+    }))
 }
 
 // 更新应用
+#[utoipa::path(
+    put,
+    path = "/api/apps/{id}",
+    tag = "apps",
+    params(
+        ("id" = i64, Path, description = "App id"),
+        ("If-Match" = Option<String>, Header, description = "ETag of the version being edited, or send a version field"),
+    ),
+    request_body = UpdateAppRequest,
+    responses(
+        (status = 200, description = "App updated", body = MessageResponse,
+            headers(("ETag" = String, description = "New version"))),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "App requires a two-factor session", body = ErrorResponse),
+        (status = 404, description = "App not found", body = ErrorResponse),
+        (status = 412, description = "App was modified, ETag holds the current version", body = ErrorResponse),
+        (status = 422, description = "Invalid request", body = ErrorResponse),
+        (status = 428, description = "Neither If-Match nor version was sent", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn update_app(
-    pool: web::Data<MySqlPool>,
+    apps: web::Data<AppService>,
     user: AuthenticatedUser,
+    audit: Audit,
     path: web::Path<i64>,
-    req: web::Json<UpdateAppRequest>,
-) -> impl Responder {
+    http_req: HttpRequest,
+    req: ValidatedJson<UpdateAppRequest>,
+) -> Result<HttpResponse, AppError> {
     let app_id = path.into_inner();
-    
-    let mut query = String::from("UPDATE apps SET updater_id = ?");
-    let mut params: Vec<String> = vec![];
-    
-    if let Some(name) = &req.name {
-        query.push_str(", name = ?");
-        params.push(name.clone());
-    }
-    
-    if let Some(description) = &req.description {
-        query.push_str(", description = ?");
-        params.push(description.clone());
-    }
-    
-    query.push_str(" WHERE id = ?");
-    
-    let mut db_query = sqlx::query(&query)
-        .bind(user.user_id);
-    
-    for param in params {
-        db_query = db_query.bind(param);
-    }
-    
-    db_query = db_query.bind(app_id);
-    
-    match db_query.execute(pool.get_ref()).await {
-        Ok(_) => HttpResponse::Ok().json(MessageResponse {
-            message: "应用更新成功".to_string(),
-        }),
-        Err(e) => HttpResponse::InternalServerError().json(MessageResponse {
-            message: format!("更新应用失败: {}", e),
-        }),
-    }
+
+    let precondition = Precondition::from_request(&http_req, req.version)?;
+    let (before, after) = apps.update(app_id, &req, user.user_id, user.aal, &precondition).await?;
+    let version = after.version;
+    let event = AuditEvent::updated(
+        APP_UPDATE,
+        TARGET_APP,
+        app_id,
+        &AppResponse::from(before),
+        &AppResponse::from(after),
+    );
+    audit.record(event.with_app(app_id)).await;
+
+    Ok(HttpResponse::Ok().insert_header(etag(version)).json(MessageResponse {
+        message: "App updated successfully".to_string(),
+    }))
 }
 
-// 删除应用
+// 删除应用：移入回收站，应用下的文章一起移入
+#[utoipa::path(
+    delete,
+    path = "/api/apps/{id}",
+    tag = "apps",
+    params(("id" = i64, Path, description = "App id")),
+    responses(
+        (status = 200, description = "App and its articles moved to the trash", body = MessageResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "App requires a two-factor session", body = ErrorResponse),
+        (status = 404, description = "App not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn delete_app(
-    pool: web::Data<MySqlPool>,
+    apps: web::Data<AppService>,
+    user: AuthenticatedUser,
+    audit: Audit,
     path: web::Path<i64>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let app_id = path.into_inner();
-    
-    match sqlx::query("DELETE FROM apps WHERE id = ?")
-        .bind(app_id)
-        .execute(pool.get_ref())
-        .await
-    {
-        Ok(_) => HttpResponse::Ok().json(MessageResponse {
-            message: "应用删除成功".to_string(),
-        }),
-        Err(e) => HttpResponse::InternalServerError().json(MessageResponse {
-            message: format!("删除应用失败: {}", e),
-        }),
-    }
+
+    let app = apps.delete(app_id, user.user_id, user.aal).await?;
+    audit
+        .record(AuditEvent::deleted(APP_DELETE, TARGET_APP, app_id, &AppResponse::from(app)).with_app(app_id))
+        .await;
+
+    Ok(HttpResponse::Ok().json(MessageResponse {
+        message: "App deleted successfully".to_string(),
+    }))
+}
+
+// 回收站中的应用，最近删除的在前
+#[utoipa::path(
+    get,
+    path = "/api/apps/trash",
+    tag = "apps",
+    params(AppQuery),
+    responses(
+        (status = 200, description = "Paginated list of trashed apps", body = AppListResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 422, description = "Invalid query", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_trashed_apps(
+    apps: web::Data<AppService>,
+    _user: AuthenticatedUser,
+    query: ValidatedQuery<AppQuery>,
+) -> Result<HttpResponse, AppError> {
+    let page = query.page.unwrap_or(1);
+    let page_size = query.page_size.unwrap_or(10);
+
+    let filter = AppFilter {
+        keyword: query.keyword.clone(),
+        identifier: query.identifier.clone(),
+        creator_id: query.creator_id,
+        trashed: true,
+    };
+
+    let (items, count) = apps.list(&filter, page, page_size).await?;
+
+    Ok(HttpResponse::Ok().json(AppListResponse {
+        apps: items.into_iter().map(AppResponse::from).collect(),
+        total: count,
+        page,
+        page_size,
+    }))
+}
+
+// 从回收站恢复应用和随它一起删除的文章
+#[utoipa::path(
+    post,
+    path = "/api/apps/{id}/restore",
+    tag = "apps",
+    params(("id" = i64, Path, description = "App id")),
+    responses(
+        (status = 200, description = "App restored", body = AppResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "App requires a two-factor session", body = ErrorResponse),
+        (status = 404, description = "App not found in trash", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn restore_app(
+    apps: web::Data<AppService>,
+    user: AuthenticatedUser,
+    audit: Audit,
+    path: web::Path<i64>,
+) -> Result<HttpResponse, AppError> {
+    let app_id = path.into_inner();
+
+    let app = apps.restore(app_id, user.aal).await?;
+    audit
+        .record(AuditEvent::new(APP_RESTORE, TARGET_APP, Some(app_id)).with_app(app_id))
+        .await;
+
+    Ok(HttpResponse::Ok().json(AppResponse::from(app)))
 }
 
 // 获取应用列表
+#[utoipa::path(
+    get,
+    path = "/api/apps",
+    tag = "apps",
+    params(AppQuery),
+    responses(
+        (status = 200, description = "Paginated list of apps", body = AppListResponse),
+        (status = 422, description = "Invalid query", body = ErrorResponse),
+    )
+)]
 pub async fn list_apps(
-    pool: web::Data<MySqlPool>,
-    query: web::Query<AppQuery>,
-) -> impl Responder {
+    apps: web::Data<AppService>,
+    query: ValidatedQuery<AppQuery>,
+) -> Result<HttpResponse, AppError> {
     let page = query.page.unwrap_or(1);
     let page_size = query.page_size.unwrap_or(10);
-    let offset = (page - 1) * page_size;
-    
-    let mut sql = String::from("SELECT * FROM apps WHERE 1=1");
-    let mut count_sql = String::from("SELECT COUNT(*) as count FROM apps WHERE 1=1");
-    let mut params = Vec::new();
-    let mut count_params = Vec::new();
-    
-    if let Some(keyword) = &query.keyword {
-        sql.push_str(" AND name LIKE ?");
-        count_sql.push_str(" AND name LIKE ?");
-        let pattern = format!("%{}%", keyword);
-        params.push(pattern.clone());
-        count_params.push(pattern);
-    }
-    
-    if let Some(identifier) = &query.identifier {
-        sql.push_str(" AND identifier = ?");
-        count_sql.push_str(" AND identifier = ?");
-        params.push(identifier.to_string());
-        count_params.push(identifier.to_string());
-    }
-    
-    if let Some(creator_id) = query.creator_id {
-        sql.push_str(" AND creator_id = ?");
-        count_sql.push_str(" AND creator_id = ?");
-        params.push(creator_id.to_string());
-        count_params.push(creator_id.to_string());
-    }
-    
-    sql.push_str(" ORDER BY created_at DESC LIMIT ? OFFSET ?");
-    
-    let mut db_query = sqlx::query_as::<_, App>(&sql);
-    let mut count_query = sqlx::query(&count_sql);
-    
-    for param in &params {
-        db_query = db_query.bind(param);
-    }
-    
-    for param in &count_params {
-        count_query = count_query.bind(param);
-    }
-    
-    db_query = db_query.bind(page_size).bind(offset);
-    
-    let apps = match db_query.fetch_all(pool.get_ref()).await {
-        Ok(apps) => apps,
-        Err(e) => return HttpResponse::InternalServerError().json(MessageResponse {
-            message: format!("获取应用列表失败: {}", e),
-        }),
-    };
-    
-    let count: i64 = match count_query
-        .fetch_one(pool.get_ref())
-        .await
-        .and_then(|row| row.try_get("count"))
-    {
-        Ok(count) => count,
-        Err(e) => return HttpResponse::InternalServerError().json(MessageResponse {
-            message: format!("获取应用总数失败: {}", e),
-        }),
+
+    let filter = AppFilter {
+        keyword: query.keyword.clone(),
+        identifier: query.identifier.clone(),
+        creator_id: query.creator_id,
+        trashed: false,
     };
-    
+
+    let (items, count) = apps.list(&filter, page, page_size).await?;
+
     let response = AppListResponse {
-        apps: apps.into_iter().map(|t| AppResponse {
-            id: t.id,
-            name: t.name,
-            description: t.description,
-            identifier: t.identifier,
-            creator_id: t.creator_id,
-            created_at: t.created_at,
-            updater_id: t.updater_id,
-            updated_at: t.updated_at,
-        }).collect(),
+        apps: items.into_iter().map(AppResponse::from).collect(),
         total: count,
         page,
         page_size,
     };
-    
-    HttpResponse::Ok().json(response)
+
+    Ok(HttpResponse::Ok().json(response))
 }
 
 // 获取单个应用
+#[utoipa::path(
+    get,
+    path = "/api/apps/{id}",
+    tag = "apps",
+    params(("id" = i64, Path, description = "App id")),
+    responses(
+        (status = 200, description = "App details", body = AppResponse,
+            headers(("ETag" = String, description = "Current version, send it back in If-Match when updating"))),
+        (status = 404, description = "App not found", body = ErrorResponse),
+    )
+)]
 pub async fn get_app(
-    pool: web::Data<MySqlPool>,
+    apps: web::Data<AppService>,
     path: web::Path<i64>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let app_id = path.into_inner();
-    
-    match sqlx::query_as::<_, App>("SELECT * FROM apps WHERE id = ?")
-        .bind(app_id)
-        .fetch_optional(pool.get_ref())
-        .await
-    {
-        Ok(Some(app)) => HttpResponse::Ok().json(AppResponse {
-            id: app.id,
-            name: app.name,
-            description: app.description,
-            identifier: app.identifier,
-            creator_id: app.creator_id,
-            created_at: app.created_at,
-            updater_id: app.updater_id,
-            updated_at: app.updated_at,
-        }),
-        Ok(None) => HttpResponse::NotFound().json(MessageResponse {
-            message: "应用不存在".to_string(),
-        }),
-        Err(e) => HttpResponse::InternalServerError().json(MessageResponse {
-            message: format!("获取应用失败: {}", e),
-        }),
-    }
+
+    let app = apps.get(app_id).await?;
+
+    Ok(HttpResponse::Ok().insert_header(etag(app.version)).json(AppResponse::from(app)))
 }
@@ -0,0 +1,143 @@
+use crate::middleware::audit::Audit;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::models::archive::{Archive, ArchiveFormat, ExportQuery, ImportAction, ImportQuery, ImportReport};
+use crate::models::audit::{APP_IMPORT, TARGET_APP};
+use crate::services::archive::ImportOptions;
+use crate::services::audit::AuditEvent;
+use crate::services::ArchiveService;
+use crate::utils::archive::{to_ndjson, NDJSON_CONTENT_TYPE};
+use crate::utils::validation::ValidatedQuery;
+use crate::utils::{AppError, ErrorResponse};
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType, CONTENT_TYPE};
+use actix_web::{web, HttpRequest, HttpResponse};
+use futures::StreamExt;
+use serde_json::json;
+
+// 导出应用
+#[utoipa::path(
+    get,
+    path = "/api/apps/{id}/export",
+    tag = "apps",
+    params(("id" = i64, Path, description = "App id"), ExportQuery),
+    responses(
+        (status = 200, description = "Archive of the app's settings, articles, categories and media references",
+            content((Archive = "application/json"), (String = "application/x-ndjson"))),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Only the creator of the app or an admin can export it, or the app requires a \
+            two-factor session", body = ErrorResponse),
+        (status = 404, description = "App not found", body = ErrorResponse),
+        (status = 422, description = "Invalid query", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn export_app(
+    archives: web::Data<ArchiveService>,
+    user: AuthenticatedUser,
+    path: web::Path<i64>,
+    query: ValidatedQuery<ExportQuery>,
+) -> Result<HttpResponse, AppError> {
+    let archive = archives.export(path.into_inner(), user.user_id, user.aal).await?;
+    let format = query.format.unwrap_or_default();
+    let (extension, content_type, body) = match format {
+        ArchiveFormat::Json => (
+            "json",
+            "application/json",
+            serde_json::to_string(&archive).map_err(|e| AppError::Internal(e.to_string()))?,
+        ),
+        ArchiveFormat::Ndjson => ("ndjson", NDJSON_CONTENT_TYPE, to_ndjson(&archive)),
+    };
+    let disposition = ContentDisposition {
+        disposition: DispositionType::Attachment,
+        parameters: vec![DispositionParam::Filename(format!("{}.{}", archive.app.identifier, extension))],
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(disposition)
+        .body(body))
+}
+
+// 导入应用
+#[utoipa::path(
+    post,
+    path = "/api/apps/import",
+    tag = "apps",
+    params(ImportQuery),
+    request_body(
+        content = String,
+        description = "An exported archive (JSON, or NDJSON with Content-Type application/x-ndjson), \
+            a WordPress WXR file, or a MarkdownDirectory",
+    ),
+    responses(
+        (status = 200, description = "What was imported, or would be with dry_run", body = ImportReport),
+        (status = 400, description = "The file could not be read", body = ErrorResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Importing into an app of another user, or the app requires a two-factor session",
+            body = ErrorResponse),
+        (status = 409, description = "The identifier is used by an app in the trash", body = ErrorResponse),
+        (status = 413, description = "The file exceeds IMPORT_MAX_BYTES", body = ErrorResponse),
+        (status = 422, description = "Invalid query or app settings", body = ErrorResponse),
+        (status = 500, description = "The import stopped part way; what was imported before the error is kept and \
+            listed, with the reason in error", body = ImportReport),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn import_app(
+    archives: web::Data<ArchiveService>,
+    user: AuthenticatedUser,
+    audit: Audit,
+    req: HttpRequest,
+    query: ValidatedQuery<ImportQuery>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, AppError> {
+    // 导入文件可能远大于普通 JSON 请求的限制，单独限制大小
+    let limit = archives.max_import_bytes();
+    let mut body = web::BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|e| AppError::BadRequest(e.to_string()))?;
+        if body.len() + chunk.len() > limit {
+            return Err(AppError::PayloadTooLarge(limit));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    let ndjson = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with(NDJSON_CONTENT_TYPE));
+    let source = query.source.unwrap_or_default();
+    let archive = archives.parse(source, &body, ndjson, query.identifier.as_deref())?;
+    let options = ImportOptions {
+        strategy: query.strategy.unwrap_or_default(),
+        dry_run: query.dry_run,
+        identifier: query.identifier.clone(),
+    };
+    let report = archives.import(&archive, &options, user.user_id, user.aal).await?;
+
+    if let (false, Some(app_id)) = (report.dry_run, report.app.id) {
+        let event = AuditEvent::new(APP_IMPORT, TARGET_APP, Some(app_id))
+            .with_after(json!({
+                "source": source,
+                "strategy": options.strategy,
+                "app": report.app.action,
+                "created": count(&report, ImportAction::Create),
+                "overwritten": count(&report, ImportAction::Overwrite),
+                "renamed": count(&report, ImportAction::Rename),
+                "skipped": count(&report, ImportAction::Skip),
+                "error": report.error,
+            }))
+            .with_app(app_id);
+        audit.record(event).await;
+    }
+
+    // 导入不在一个事务中，中途出错时已导入的内容保留，返回报告让调用方知道导入到了哪里
+    if report.error.is_some() {
+        return Ok(HttpResponse::InternalServerError().json(report));
+    }
+    Ok(HttpResponse::Ok().json(report))
+}
+
+fn count(report: &ImportReport, action: ImportAction) -> usize {
+    report.articles.iter().filter(|item| item.action == action).count()
+}
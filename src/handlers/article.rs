@@ -1,252 +1,204 @@
+use crate::middleware::audit::Audit;
 use crate::middleware::auth::AuthenticatedUser;
 use crate::models::article::{CreateArticleRequest, UpdateArticleRequest};
+use crate::models::audit::{ARTICLE_CREATE, ARTICLE_DELETE, ARTICLE_RESTORE, ARTICLE_UPDATE, TARGET_ARTICLE};
+use crate::services::audit::AuditEvent;
 use crate::models::{Article, MessageResponse};
-use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
-use sqlx::MySqlPool;
-
-#[post("/articles")]
+use crate::services::ArticleService;
+use crate::utils::precondition::{etag, Precondition};
+use crate::utils::validation::ValidatedJson;
+use crate::utils::{AppError, ErrorResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
+
+#[utoipa::path(
+    post,
+    path = "/articles",
+    tag = "articles",
+    request_body = CreateArticleRequest,
+    responses(
+        (status = 200, description = "Article created", body = Article),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "App requires a two-factor session", body = ErrorResponse),
+        (status = 404, description = "App not found", body = ErrorResponse),
+        (status = 422, description = "Invalid request", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn create_article(
-    pool: web::Data<MySqlPool>,
-    article: web::Json<CreateArticleRequest>,
+    articles: web::Data<ArticleService>,
+    article: ValidatedJson<CreateArticleRequest>,
     auth_user: AuthenticatedUser,
-) -> impl Responder {
-    let status = article.status.unwrap_or(1); // 默认为草稿状态
-    
-    let result = sqlx::query!(
-        r#"
-        INSERT INTO articles (title, content, author_id, status)
-        VALUES (?, ?, ?, ?)
-        "#,
-        article.title,
-        article.content,
-        auth_user.user_id,
-        status
-    )
-    .execute(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(result) => {
-            // 获取新创建的文章
-            let article = sqlx::query_as!(
-                Article,
-                "SELECT * FROM articles WHERE id = ?",
-                result.last_insert_id()
-            )
-            .fetch_one(pool.get_ref())
-            .await;
-
-            match article {
-                Ok(article) => HttpResponse::Ok().json(article),
-                Err(e) => {
-                    log::error!("Failed to fetch created article: {:?}", e);
-                    HttpResponse::InternalServerError().json(MessageResponse {
-                        message: "Article created but failed to fetch it".to_string(),
-                    })
-                }
-            }
-        }
-        Err(e) => {
-            log::error!("Failed to create article: {:?}", e);
-            HttpResponse::InternalServerError().json(MessageResponse {
-                message: "Failed to create article".to_string(),
-            })
-        }
-    }
+    audit: Audit,
+) -> Result<HttpResponse, AppError> {
+    let article = articles.create(&article, auth_user.user_id, auth_user.aal).await?;
+    audit
+        .record(AuditEvent::created(ARTICLE_CREATE, TARGET_ARTICLE, article.id, &article))
+        .await;
+
+    Ok(HttpResponse::Ok().json(article))
 }
 
-#[get("/articles/{id}")]
+#[utoipa::path(
+    get,
+    path = "/articles/{id}",
+    tag = "articles",
+    params(("id" = i64, Path, description = "Article id")),
+    responses(
+        (status = 200, description = "Article details", body = Article,
+            headers(("ETag" = String, description = "Current version, send it back in If-Match when updating"))),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 404, description = "Article not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn get_article(
-    pool: web::Data<MySqlPool>,
+    articles: web::Data<ArticleService>,
     article_id: web::Path<i64>,
     auth_user: AuthenticatedUser,
-) -> impl Responder {
-    let result = sqlx::query_as!(
-        Article,
-        r#"
-        SELECT * FROM articles 
-        WHERE id = ? AND (status = 2 OR author_id = ?)
-        "#,
-        article_id.into_inner(),
-        auth_user.user_id
-    )
-    .fetch_optional(pool.get_ref())
-    .await;
+) -> Result<HttpResponse, AppError> {
+    let article = articles.get(article_id.into_inner(), auth_user.user_id).await?;
 
-    match result {
-        Ok(Some(article)) => HttpResponse::Ok().json(article),
-        Ok(None) => HttpResponse::NotFound().json(MessageResponse {
-            message: "Article not found".to_string(),
-        }),
-        Err(e) => {
-            log::error!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(MessageResponse {
-                message: "Internal server error".to_string(),
-            })
-        }
-    }
+    Ok(HttpResponse::Ok().insert_header(etag(article.version)).json(article))
 }
 
-#[get("/articles")]
+#[utoipa::path(
+    get,
+    path = "/articles",
+    tag = "articles",
+    responses(
+        (status = 200, description = "Published articles and own drafts", body = [Article]),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn list_articles(
-    pool: web::Data<MySqlPool>,
+    articles: web::Data<ArticleService>,
     auth_user: AuthenticatedUser,
-) -> impl Responder {
-    let result = sqlx::query_as!(
-        Article,
-        r#"
-        SELECT * FROM articles 
-        WHERE status = 2 OR author_id = ?
-        ORDER BY created_at DESC
-        "#,
-        auth_user.user_id
-    )
-    .fetch_all(pool.get_ref())
-    .await;
+) -> Result<HttpResponse, AppError> {
+    let articles = articles.list(auth_user.user_id).await?;
 
-    match result {
-        Ok(articles) => HttpResponse::Ok().json(articles),
-        Err(e) => {
-            log::error!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(MessageResponse {
-                message: "Internal server error".to_string(),
-            })
-        }
-    }
+    Ok(HttpResponse::Ok().json(articles))
 }
 
-#[put("/articles/{id}")]
+#[utoipa::path(
+    put,
+    path = "/articles/{id}",
+    tag = "articles",
+    params(
+        ("id" = i64, Path, description = "Article id"),
+        ("If-Match" = Option<String>, Header, description = "ETag of the version being edited, or send a version field"),
+    ),
+    request_body = UpdateArticleRequest,
+    responses(
+        (status = 200, description = "Article updated", body = Article,
+            headers(("ETag" = String, description = "New version"))),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "App requires a two-factor session", body = ErrorResponse),
+        (status = 404, description = "Article not found", body = ErrorResponse),
+        (status = 412, description = "Article was modified, ETag holds the current version", body = ErrorResponse),
+        (status = 422, description = "Invalid request", body = ErrorResponse),
+        (status = 428, description = "Neither If-Match nor version was sent", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn update_article(
-    pool: web::Data<MySqlPool>,
+    articles: web::Data<ArticleService>,
     article_id: web::Path<i64>,
-    article: web::Json<UpdateArticleRequest>,
+    article: ValidatedJson<UpdateArticleRequest>,
     auth_user: AuthenticatedUser,
-) -> impl Responder {
+    audit: Audit,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
     let article_id = article_id.into_inner();
-    
-    // 首先检查文章是否存在且属于当前用户
-    let existing = sqlx::query!(
-        "SELECT id FROM articles WHERE id = ? AND author_id = ?",
-        article_id,
-        auth_user.user_id
-    )
-    .fetch_optional(pool.get_ref())
-    .await;
-
-    match existing {
-        Ok(Some(_)) => {
-            let mut query_parts = Vec::new();
-            let mut query_values = Vec::new();
-
-            if let Some(title) = &article.title {
-                query_parts.push("title = ?");
-                query_values.push(title.clone());
-            }
-            if let Some(content) = &article.content {
-                query_parts.push("content = ?");
-                query_values.push(content.clone());
-            }
-            if let Some(status) = article.status {
-                query_parts.push("status = ?");
-                query_values.push(status.to_string());
-            }
-
-            if query_parts.is_empty() {
-                return HttpResponse::BadRequest().json(MessageResponse {
-                    message: "No fields to update".to_string(),
-                });
-            }
-
-            let query = format!(
-                "UPDATE articles SET {} WHERE id = ? AND author_id = ?",
-                query_parts.join(", ")
-            );
-
-            let mut db_query = sqlx::query(&query);
-            
-            // 绑定所有参数
-            for value in query_values {
-                db_query = db_query.bind(value);
-            }
-            
-            // 绑定 WHERE 子句的参数
-            db_query = db_query.bind(article_id).bind(auth_user.user_id);
+    let precondition = Precondition::from_request(&req, article.version)?;
+    // 只能更新自己的文章
+    let (before, updated) = articles
+        .update(article_id, auth_user.user_id, auth_user.aal, &article, &precondition)
+        .await?;
+    audit
+        .record(AuditEvent::updated(ARTICLE_UPDATE, TARGET_ARTICLE, article_id, &before, &updated))
+        .await;
+
+    Ok(HttpResponse::Ok().insert_header(etag(updated.version)).json(updated))
+}
 
-            let result = db_query.execute(pool.get_ref()).await;
+#[utoipa::path(
+    delete,
+    path = "/articles/{id}",
+    tag = "articles",
+    params(("id" = i64, Path, description = "Article id")),
+    responses(
+        (status = 200, description = "Article moved to the trash", body = MessageResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "App requires a two-factor session", body = ErrorResponse),
+        (status = 404, description = "Article not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_article(
+    articles: web::Data<ArticleService>,
+    article_id: web::Path<i64>,
+    auth_user: AuthenticatedUser,
+    audit: Audit,
+) -> Result<HttpResponse, AppError> {
+    let article_id = article_id.into_inner();
+    let article = articles.delete(article_id, auth_user.user_id, auth_user.aal).await?;
+    audit
+        .record(AuditEvent::deleted(ARTICLE_DELETE, TARGET_ARTICLE, article_id, &article))
+        .await;
+
+    Ok(HttpResponse::Ok().json(MessageResponse {
+        message: "Article deleted successfully".to_string(),
+    }))
+}
 
-            match result {
-                Ok(_) => {
-                    // 获取更新后的文章
-                    let updated = sqlx::query_as!(
-                        Article,
-                        "SELECT * FROM articles WHERE id = ? AND author_id = ?",
-                        article_id,
-                        auth_user.user_id
-                    )
-                    .fetch_one(pool.get_ref())
-                    .await;
+#[utoipa::path(
+    get,
+    path = "/articles/trash",
+    tag = "articles",
+    responses(
+        (status = 200, description = "Own trashed articles, most recently deleted first", body = [Article]),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_trashed_articles(
+    articles: web::Data<ArticleService>,
+    auth_user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let articles = articles.list_trash(auth_user.user_id).await?;
 
-                    match updated {
-                        Ok(article) => HttpResponse::Ok().json(article),
-                        Err(e) => {
-                            log::error!("Failed to fetch updated article: {:?}", e);
-                            HttpResponse::InternalServerError().json(MessageResponse {
-                                message: "Article updated but failed to fetch it".to_string(),
-                            })
-                        }
-                    }
-                }
-                Err(e) => {
-                    log::error!("Failed to update article: {:?}", e);
-                    HttpResponse::InternalServerError().json(MessageResponse {
-                        message: "Failed to update article".to_string(),
-                    })
-                }
-            }
-        }
-        Ok(None) => HttpResponse::NotFound().json(MessageResponse {
-            message: "Article not found or you don't have permission to update it".to_string(),
-        }),
-        Err(e) => {
-            log::error!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(MessageResponse {
-                message: "Internal server error".to_string(),
-            })
-        }
-    }
+    Ok(HttpResponse::Ok().json(articles))
 }
 
-#[delete("/articles/{id}")]
-pub async fn delete_article(
-    pool: web::Data<MySqlPool>,
+#[utoipa::path(
+    post,
+    path = "/articles/{id}/restore",
+    tag = "articles",
+    params(("id" = i64, Path, description = "Article id")),
+    responses(
+        (status = 200, description = "Article restored", body = Article),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "App requires a two-factor session", body = ErrorResponse),
+        (status = 404, description = "Article not found in trash", body = ErrorResponse),
+        (status = 409, description = "The app of the article is in the trash", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn restore_article(
+    articles: web::Data<ArticleService>,
     article_id: web::Path<i64>,
     auth_user: AuthenticatedUser,
-) -> impl Responder {
-    let result = sqlx::query!(
-        "DELETE FROM articles WHERE id = ? AND author_id = ?",
-        article_id.into_inner(),
-        auth_user.user_id
-    )
-    .execute(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(result) => {
-            if result.rows_affected() > 0 {
-                HttpResponse::Ok().json(MessageResponse {
-                    message: "Article deleted successfully".to_string(),
-                })
-            } else {
-                HttpResponse::NotFound().json(MessageResponse {
-                    message: "Article not found or you don't have permission to delete it".to_string(),
-                })
-            }
-        }
-        Err(e) => {
-            log::error!("Failed to delete article: {:?}", e);
-            HttpResponse::InternalServerError().json(MessageResponse {
-                message: "Failed to delete article".to_string(),
-            })
-        }
+    audit: Audit,
+) -> Result<HttpResponse, AppError> {
+    let article_id = article_id.into_inner();
+    // 只能恢复自己的文章
+    let article = articles.restore(article_id, auth_user.user_id, auth_user.aal).await?;
+    let mut event = AuditEvent::new(ARTICLE_RESTORE, TARGET_ARTICLE, Some(article_id));
+    if let Some(app_id) = article.app_id {
+        event = event.with_app(app_id);
     }
+    audit.record(event).await;
+
+    Ok(HttpResponse::Ok().json(article))
 }
@@ -1,265 +1,622 @@
-use crate::config::auth::{Claims, JwtConfig};
+use crate::config::auth::{
+    Claims, JwtConfig, AAL_SINGLE_FACTOR, AAL_TWO_FACTOR, AMR_EMAIL, AMR_MFA, AMR_OIDC, AMR_PASSWORD,
+};
+use crate::middleware::audit::Audit;
 use crate::middleware::auth::AuthenticatedUser;
+use crate::models::audit::{
+    TARGET_USER, USER_DISABLE_TOTP, USER_ENABLE_TOTP, USER_LOGIN, USER_REGENERATE_RECOVERY_CODES, USER_REGISTER,
+    USER_RESET_PASSWORD, USER_SETUP_TOTP, USER_SET_PASSWORD,
+};
+use crate::models::credential::{
+    ForgotPasswordRequest, PasswordLoginRequest, RecoveryCodesResponse, ResetPasswordRequest, SetPasswordRequest,
+    TotpCodeRequest, TotpSetupResponse, TwoFactorChallengeResponse, TwoFactorLoginRequest, TwoFactorStatusResponse,
+};
+use crate::models::magic_link::{MagicLinkQuery, MagicLinkRequest};
+use crate::models::oidc::{OidcCallbackQuery, OidcProviderInfo};
 use crate::models::{AuthResponse, GetVerificationCodeRequest, LoginRequest, MessageResponse, RegisterRequest, User};
+use crate::services::audit::AuditEvent;
+use crate::services::user::Login;
+use crate::services::{AppService, OidcService, UserService};
 use crate::utils::email::EmailService;
-use actix_web::{get, post, web, HttpResponse, Responder};
+use crate::utils::email_template::{request_locale, Branding, DEFAULT_LOCALE};
+use crate::utils::token;
+use crate::utils::validation::ValidatedJson;
+use crate::utils::{AppError, ErrorResponse};
+use actix_web::cookie::{time, Cookie, SameSite};
+use actix_web::http::header::{CACHE_CONTROL, LOCATION};
+use actix_web::{web, HttpRequest, HttpResponse};
 use chrono::{Duration, Utc};
-use jsonwebtoken::encode;
-use rand::Rng;
-use sqlx::MySqlPool;
-
-// 生成6位验证码
-fn generate_verification_code() -> String {
-    let mut rng = rand::thread_rng();
-    format!("{:06}", rng.gen_range(0..1000000))
+use serde_json::json;
+
+// 标识发起登录链接请求的设备，链接只能在带有该 cookie 的浏览器中使用
+pub const MAGIC_LINK_DEVICE_COOKIE: &str = "rscms_magic_device";
+
+// 保存发起单点登录的 state，回调必须在同一个浏览器中完成，防止登录 CSRF
+pub const OIDC_STATE_COOKIE: &str = "rscms_oidc_state";
+
+// 生成 JWT token，amr 为本次登录使用的认证方式
+fn issue_token(jwt_config: &JwtConfig, user: &User, amr: &[&str]) -> Result<String, AppError> {
+    let now = Utc::now();
+    let claims = Claims {
+        sub: user.id.to_string(),
+        exp: (now + Duration::hours(jwt_config.ttl_hours)).timestamp() as usize,
+        iat: now.timestamp() as usize,
+        iss: jwt_config.issuer.clone(),
+        aud: jwt_config.audience.clone(),
+        amr: amr.iter().map(|method| method.to_string()).collect(),
+        aal: if amr.len() > 1 { AAL_TWO_FACTOR } else { AAL_SINGLE_FACTOR },
+    };
+
+    jwt_config.encode(&claims, now)
+}
+
+// 请求中指定了应用时使用该应用的品牌
+// 登录请求没有 token，操作者就是登录的用户
+fn login_event(user_id: i64, amr: &[&str]) -> AuditEvent {
+    AuditEvent::new(USER_LOGIN, TARGET_USER, Some(user_id))
+        .with_after(json!({ "amr": amr }))
+        .by_user(user_id)
 }
 
-#[post("/auth/register")]
+async fn branding(apps: &AppService, email_service: &EmailService, app: Option<&str>) -> Result<Branding, AppError> {
+    match app {
+        Some(identifier) => Ok(email_service.branding(Some(&apps.get_by_identifier(identifier).await?))),
+        None => Ok(email_service.branding(None)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/register",
+    tag = "auth",
+    request_body = RegisterRequest,
+    responses(
+        (status = 200, description = "Verification code sent", body = MessageResponse),
+        (status = 404, description = "App not found", body = ErrorResponse),
+        (status = 409, description = "Email already registered", body = ErrorResponse),
+        (status = 422, description = "Invalid request", body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse),
+    )
+)]
 pub async fn register(
-    pool: web::Data<MySqlPool>,
-    user: web::Json<RegisterRequest>,
+    req: HttpRequest,
+    users: web::Data<UserService>,
+    apps: web::Data<AppService>,
+    user: ValidatedJson<RegisterRequest>,
     email_service: web::Data<EmailService>,
-) -> impl Responder {
-    // 检查用户是否已存在
-    let existing_user = sqlx::query!(
-        "SELECT id FROM users WHERE email = ?",
-        user.email
-    )
-    .fetch_optional(pool.get_ref())
-    .await;
-
-    match existing_user {
-        Ok(Some(_)) => {
-            HttpResponse::BadRequest().json(MessageResponse {
-                message: "User with this email already exists".to_string(),
-            })
-        }
-        Ok(None) => {
-            // 生成验证码
-            let verification_code: String = rand::thread_rng()
-                .sample_iter(&rand::distributions::Alphanumeric)
-                .take(6)
-                .map(char::from)
-                .collect();
-
-            // 设置验证码过期时间（30分钟后）
-            let expires_at = Utc::now() + Duration::minutes(30);
-
-            // 创建新用户
-            let result = sqlx::query!(
-                r#"
-                INSERT INTO users (username, email, email_verified, verification_code, verification_code_expires_at)
-                VALUES (?, ?, ?, ?, ?)
-                "#,
-                user.username,
-                user.email,
-                0i8, // false
-                verification_code,
-                expires_at
-            )
-            .execute(pool.get_ref())
-            .await;
-
-            match result {
-                Ok(_) => {
-                    // 发送验证码邮件
-                    if let Err(e) = email_service.send_verification_code(&user.email, &verification_code) {
-                        log::error!("Failed to send verification email: {:?}", e);
-                        return HttpResponse::InternalServerError().json(MessageResponse {
-                            message: "Failed to send verification email".to_string(),
-                        });
-                    }
-
-                    HttpResponse::Ok().json(MessageResponse {
-                        message: "Registration successful. Please check your email for verification code.".to_string(),
-                    })
-                }
-                Err(e) => {
-                    log::error!("Failed to create user: {:?}", e);
-                    HttpResponse::InternalServerError().json(MessageResponse {
-                        message: "Failed to create user".to_string(),
-                    })
-                }
-            }
-        }
-        Err(e) => {
-            log::error!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(MessageResponse {
-                message: "Internal server error".to_string(),
-            })
-        }
-    }
+    audit: Audit,
+) -> Result<HttpResponse, AppError> {
+    let branding = branding(&apps, &email_service, user.app.as_deref()).await?;
+    // 优先使用请求中的语言，其次是 Accept-Language
+    let locale = user
+        .locale
+        .clone()
+        .or_else(|| request_locale(&req))
+        .unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+    // 验证码邮件由发件箱异步发送
+    let (user_id, _) = users.register(&user, &locale, &branding).await?;
+    let event = AuditEvent::new(USER_REGISTER, TARGET_USER, Some(user_id))
+        .with_after(json!({ "username": user.username, "email": user.email }));
+    audit.record(event).await;
+
+    Ok(HttpResponse::Ok().json(MessageResponse {
+        message: "Registration successful. Please check your email for verification code.".to_string(),
+    }))
 }
 
-#[post("/auth/login")]
+#[utoipa::path(
+    post,
+    path = "/api/auth/login",
+    tag = "auth",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Logged in", body = AuthResponse),
+        (status = 202, description = "Code accepted, two-factor code required", body = TwoFactorChallengeResponse),
+        (status = 401, description = "Invalid email or verification code", body = ErrorResponse),
+        (status = 422, description = "Invalid request", body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse),
+    )
+)]
 pub async fn login(
-    pool: web::Data<MySqlPool>,
-    login_data: web::Json<LoginRequest>,
+    users: web::Data<UserService>,
+    login_data: ValidatedJson<LoginRequest>,
     jwt_config: web::Data<JwtConfig>,
-) -> impl Responder {
-    // 验证用户和验证码
-    let user = sqlx::query_as!(
-        User,
-        r#"
-        SELECT * FROM users 
-        WHERE email = ? 
-        AND verification_code = ? 
-        AND verification_code_expires_at > CURRENT_TIMESTAMP
-        "#,
-        login_data.email,
-        login_data.verification_code
-    )
-    .fetch_optional(pool.get_ref())
-    .await;
-
-    match user {
-        Ok(Some(user)) => {
-            // 更新用户状态为已验证
-            let update_result = sqlx::query!(
-                r#"
-                UPDATE users 
-                SET email_verified = ?, 
-                    verification_code = NULL, 
-                    verification_code_expires_at = NULL 
-                WHERE id = ?
-                "#,
-                1i8, // true
-                user.id
-            )
-            .execute(pool.get_ref())
-            .await;
-
-            match update_result {
-                Ok(_) => {
-                    // 生成 JWT token
-                    let claims = Claims {
-                        sub: user.id.to_string(),
-                        exp: (Utc::now() + Duration::hours(24)).timestamp() as usize,
-                    };
-
-                    match encode(
-                        &jsonwebtoken::Header::default(),
-                        &claims,
-                        &jsonwebtoken::EncodingKey::from_secret(jwt_config.secret.as_bytes()),
-                    ) {
-                        Ok(token) => HttpResponse::Ok().json(AuthResponse { token, user }),
-                        Err(e) => {
-                            log::error!("Failed to create JWT token: {:?}", e);
-                            HttpResponse::InternalServerError().json(MessageResponse {
-                                message: "Failed to create authentication token".to_string(),
-                            })
-                        }
-                    }
-                }
-                Err(e) => {
-                    log::error!("Failed to update user: {:?}", e);
-                    HttpResponse::InternalServerError().json(MessageResponse {
-                        message: "Failed to update user status".to_string(),
-                    })
-                }
-            }
-        }
-        Ok(None) => HttpResponse::BadRequest().json(MessageResponse {
-            message: "Invalid email or verification code".to_string(),
-        }),
-        Err(e) => {
-            log::error!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(MessageResponse {
-                message: "Internal server error".to_string(),
-            })
-        }
-    }
+    audit: Audit,
+) -> Result<HttpResponse, AppError> {
+    let login = users
+        .login(&login_data.email, &login_data.verification_code)
+        .await?;
+
+    login_response(&users, &jwt_config, &audit, login, &[AMR_EMAIL]).await
 }
 
-#[post("/auth/verification-code")]
+#[utoipa::path(
+    post,
+    path = "/api/auth/verification-code",
+    tag = "auth",
+    request_body = GetVerificationCodeRequest,
+    responses(
+        (status = 200, description = "Verification code sent", body = MessageResponse),
+        (status = 404, description = "No user with this email or app not found", body = ErrorResponse),
+        (status = 422, description = "Invalid request", body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse),
+    )
+)]
 pub async fn get_verification_code(
-    pool: web::Data<MySqlPool>,
-    request: web::Json<GetVerificationCodeRequest>,
+    users: web::Data<UserService>,
+    apps: web::Data<AppService>,
+    request: ValidatedJson<GetVerificationCodeRequest>,
     email_service: web::Data<EmailService>,
-) -> impl Responder {
-    // 检查用户是否存在
-    let user = sqlx::query!(
-        "SELECT id FROM users WHERE email = ?",
-        request.email
+) -> Result<HttpResponse, AppError> {
+    let branding = branding(&apps, &email_service, request.app.as_deref()).await?;
+    users.issue_verification_code(&request.email, &branding).await?;
+
+    Ok(HttpResponse::Ok().json(MessageResponse {
+        message: "Verification code sent successfully".to_string(),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/magic-link",
+    tag = "auth",
+    request_body = MagicLinkRequest,
+    responses(
+        (status = 200, description = "Login link sent, device cookie set", body = MessageResponse),
+        (status = 404, description = "No user with this email or app not found", body = ErrorResponse),
+        (status = 422, description = "Invalid request", body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse),
     )
-    .fetch_optional(pool.get_ref())
-    .await;
-
-    match user {
-        Ok(Some(_)) => {
-            // 生成新的验证码
-            let verification_code = generate_verification_code();
-            let expires_at = Utc::now() + Duration::minutes(30);
-
-            // 更新用户的验证码
-            let result = sqlx::query!(
-                r#"
-                UPDATE users 
-                SET verification_code = ?, 
-                    verification_code_expires_at = ? 
-                WHERE email = ?
-                "#,
-                verification_code,
-                expires_at,
-                request.email
-            )
-            .execute(pool.get_ref())
-            .await;
-
-            match result {
-                Ok(_) => {
-                    // 发送验证码邮件
-                    if let Err(e) = email_service.send_verification_code(&request.email, &verification_code) {
-                        log::error!("Failed to send verification email: {:?}", e);
-                        return HttpResponse::InternalServerError().json(MessageResponse {
-                            message: "Failed to send verification email".to_string(),
-                        });
-                    }
-
-                    HttpResponse::Ok().json(MessageResponse {
-                        message: "Verification code sent successfully".to_string(),
-                    })
-                }
-                Err(e) => {
-                    log::error!("Failed to update verification code: {:?}", e);
-                    HttpResponse::InternalServerError().json(MessageResponse {
-                        message: "Failed to generate verification code".to_string(),
-                    })
-                }
-            }
-        }
-        Ok(None) => HttpResponse::BadRequest().json(MessageResponse {
-            message: "No user found with this email".to_string(),
-        }),
-        Err(e) => {
-            log::error!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(MessageResponse {
-                message: "Internal server error".to_string(),
-            })
+)]
+pub async fn request_magic_link(
+    req: HttpRequest,
+    users: web::Data<UserService>,
+    apps: web::Data<AppService>,
+    request: ValidatedJson<MagicLinkRequest>,
+    email_service: web::Data<EmailService>,
+) -> Result<HttpResponse, AppError> {
+    let branding = branding(&apps, &email_service, request.app.as_deref()).await?;
+    // 同一设备重复请求时沿用已有的设备标识
+    let device = req
+        .cookie(MAGIC_LINK_DEVICE_COOKIE)
+        .map(|cookie| cookie.value().to_string())
+        .filter(|value| value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit()))
+        .unwrap_or_else(token::random_token);
+    users.issue_magic_link(&request.email, &branding, &device).await?;
+
+    let config = users.magic_link_config();
+    let cookie = Cookie::build(MAGIC_LINK_DEVICE_COOKIE, device)
+        .path("/api/auth")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(config.cookie_secure)
+        .max_age(time::Duration::minutes(config.ttl_minutes))
+        .finish();
+
+    Ok(HttpResponse::Ok().cookie(cookie).json(MessageResponse {
+        message: "Login link sent. Open it on this device, or enter the code from the email.".to_string(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/magic",
+    tag = "auth",
+    params(MagicLinkQuery),
+    responses(
+        (status = 200, description = "Logged in", body = AuthResponse),
+        (status = 202, description = "Link accepted, two-factor code required", body = TwoFactorChallengeResponse),
+        (status = 401, description = "Invalid, expired or used link, or opened on another device", body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse),
+    )
+)]
+pub async fn magic_link_login(
+    req: HttpRequest,
+    users: web::Data<UserService>,
+    query: web::Query<MagicLinkQuery>,
+    jwt_config: web::Data<JwtConfig>,
+    audit: Audit,
+) -> Result<HttpResponse, AppError> {
+    let device = req.cookie(MAGIC_LINK_DEVICE_COOKIE);
+    let login = users
+        .login_with_magic_link(&query.token, device.as_ref().map(|cookie| cookie.value()))
+        .await?;
+
+    login_response(&users, &jwt_config, &audit, login, &[AMR_EMAIL]).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/password/login",
+    tag = "auth",
+    request_body = PasswordLoginRequest,
+    responses(
+        (status = 200, description = "Logged in", body = AuthResponse),
+        (status = 202, description = "Password accepted, two-factor code required", body = TwoFactorChallengeResponse),
+        (status = 401, description = "Invalid email or password", body = ErrorResponse),
+        (status = 422, description = "Invalid request", body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse),
+    )
+)]
+pub async fn password_login(
+    users: web::Data<UserService>,
+    request: ValidatedJson<PasswordLoginRequest>,
+    jwt_config: web::Data<JwtConfig>,
+    audit: Audit,
+) -> Result<HttpResponse, AppError> {
+    let login = users.login_with_password(&request.email, &request.password).await?;
+    login_response(&users, &jwt_config, &audit, login, &[AMR_PASSWORD]).await
+}
+
+// 第一个因素通过后签发 token，开启了两步验证时返回 202 和两步验证 token
+async fn login_response(
+    users: &UserService,
+    jwt_config: &JwtConfig,
+    audit: &Audit,
+    login: Login,
+    amr: &[&str],
+) -> Result<HttpResponse, AppError> {
+    match login {
+        Login::Complete(user) => {
+            let token = issue_token(jwt_config, &user, amr)?;
+            audit.record(login_event(user.id, amr)).await;
+            Ok(HttpResponse::Ok().json(AuthResponse { token, user: *user }))
         }
+        Login::TwoFactorRequired(two_factor_token) => Ok(HttpResponse::Accepted().json(TwoFactorChallengeResponse {
+            two_factor_token,
+            expires_in: users.two_factor_config().challenge_ttl_minutes * 60,
+        })),
     }
 }
 
-#[get("/auth/me")]
-pub async fn me(pool: web::Data<MySqlPool>, auth_user: AuthenticatedUser) -> impl Responder {
-    let user_info = sqlx::query_as!(
-        User,
-        "SELECT * FROM users WHERE id = ?",
-        auth_user.user_id
+#[utoipa::path(
+    post,
+    path = "/api/auth/2fa/login",
+    tag = "auth",
+    request_body = TwoFactorLoginRequest,
+    responses(
+        (status = 200, description = "Logged in with two factors", body = AuthResponse),
+        (status = 401, description = "Invalid code or expired two-factor login", body = ErrorResponse),
+        (status = 422, description = "Invalid request", body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse),
     )
-    .fetch_optional(pool.get_ref())
-    .await;
-
-    match user_info {
-        Ok(Some(user)) => HttpResponse::Ok().json(user),
-        Ok(None) => HttpResponse::NotFound().json(MessageResponse {
-            message: "User not found".to_string(),
-        }),
-        Err(e) => {
-            log::error!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(MessageResponse {
-                message: "Internal server error".to_string(),
-            })
-        }
+)]
+pub async fn two_factor_login(
+    users: web::Data<UserService>,
+    request: ValidatedJson<TwoFactorLoginRequest>,
+    jwt_config: web::Data<JwtConfig>,
+    audit: Audit,
+) -> Result<HttpResponse, AppError> {
+    let (user, amr) = users.verify_two_factor(&request.two_factor_token, &request.code).await?;
+    let token = issue_token(&jwt_config, &user, &amr)?;
+    audit.record(login_event(user.id, &amr)).await;
+
+    Ok(HttpResponse::Ok().json(AuthResponse { token, user }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/auth/password",
+    tag = "auth",
+    request_body = SetPasswordRequest,
+    responses(
+        (status = 200, description = "Password set", body = MessageResponse),
+        (status = 400, description = "Current password is incorrect", body = ErrorResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 422, description = "Invalid request or weak password", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn set_password(
+    users: web::Data<UserService>,
+    auth_user: AuthenticatedUser,
+    request: ValidatedJson<SetPasswordRequest>,
+    audit: Audit,
+) -> Result<HttpResponse, AppError> {
+    users
+        .set_password(auth_user.user_id, request.current_password.as_deref(), &request.new_password)
+        .await?;
+    audit
+        .record(AuditEvent::new(USER_SET_PASSWORD, TARGET_USER, Some(auth_user.user_id)))
+        .await;
+
+    Ok(HttpResponse::Ok().json(MessageResponse {
+        message: "Password updated successfully".to_string(),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/password/forgot",
+    tag = "auth",
+    request_body = ForgotPasswordRequest,
+    responses(
+        (status = 200, description = "Reset email sent if the account exists", body = MessageResponse),
+        (status = 404, description = "App not found", body = ErrorResponse),
+        (status = 422, description = "Invalid request", body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse),
+    )
+)]
+pub async fn forgot_password(
+    users: web::Data<UserService>,
+    apps: web::Data<AppService>,
+    request: ValidatedJson<ForgotPasswordRequest>,
+    email_service: web::Data<EmailService>,
+) -> Result<HttpResponse, AppError> {
+    let branding = branding(&apps, &email_service, request.app.as_deref()).await?;
+    users.request_password_reset(&request.email, &branding).await?;
+
+    Ok(HttpResponse::Ok().json(MessageResponse {
+        message: "If an account exists for this email, a password reset link has been sent.".to_string(),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/password/reset",
+    tag = "auth",
+    request_body = ResetPasswordRequest,
+    responses(
+        (status = 200, description = "Password reset", body = MessageResponse),
+        (status = 400, description = "Invalid or expired reset token", body = ErrorResponse),
+        (status = 422, description = "Invalid request or weak password", body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse),
+    )
+)]
+pub async fn reset_password(
+    users: web::Data<UserService>,
+    request: ValidatedJson<ResetPasswordRequest>,
+    audit: Audit,
+) -> Result<HttpResponse, AppError> {
+    let user_id = users.reset_password(&request.token, &request.new_password).await?;
+    audit
+        .record(AuditEvent::new(USER_RESET_PASSWORD, TARGET_USER, Some(user_id)).by_user(user_id))
+        .await;
+
+    Ok(HttpResponse::Ok().json(MessageResponse {
+        message: "Password reset successfully".to_string(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/2fa",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Two-factor status", body = TwoFactorStatusResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn two_factor_status(
+    users: web::Data<UserService>,
+    auth_user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(users.two_factor_status(auth_user.user_id).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/2fa/totp/setup",
+    tag = "auth",
+    responses(
+        (status = 200, description = "New TOTP secret, confirm it with a code to enable", body = TotpSetupResponse),
+        (status = 400, description = "No password set", body = ErrorResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 409, description = "Two-factor already enabled", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn setup_totp(
+    users: web::Data<UserService>,
+    auth_user: AuthenticatedUser,
+    audit: Audit,
+) -> Result<HttpResponse, AppError> {
+    let setup = users.setup_totp(auth_user.user_id).await?;
+    audit
+        .record(AuditEvent::new(USER_SETUP_TOTP, TARGET_USER, Some(auth_user.user_id)))
+        .await;
+
+    Ok(HttpResponse::Ok().json(setup))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/2fa/totp/enable",
+    tag = "auth",
+    request_body = TotpCodeRequest,
+    responses(
+        (status = 200, description = "Two-factor enabled, recovery codes shown once", body = RecoveryCodesResponse),
+        (status = 400, description = "Invalid code or setup not started", body = ErrorResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 409, description = "Two-factor already enabled", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn enable_totp(
+    users: web::Data<UserService>,
+    auth_user: AuthenticatedUser,
+    request: ValidatedJson<TotpCodeRequest>,
+    audit: Audit,
+) -> Result<HttpResponse, AppError> {
+    let recovery_codes = users.enable_totp(auth_user.user_id, &request.code).await?;
+    audit
+        .record(AuditEvent::new(USER_ENABLE_TOTP, TARGET_USER, Some(auth_user.user_id)))
+        .await;
+
+    Ok(HttpResponse::Ok().json(RecoveryCodesResponse { recovery_codes }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/2fa/totp/disable",
+    tag = "auth",
+    request_body = TotpCodeRequest,
+    responses(
+        (status = 200, description = "Two-factor disabled", body = MessageResponse),
+        (status = 400, description = "Invalid code or two-factor not enabled", body = ErrorResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn disable_totp(
+    users: web::Data<UserService>,
+    auth_user: AuthenticatedUser,
+    request: ValidatedJson<TotpCodeRequest>,
+    audit: Audit,
+) -> Result<HttpResponse, AppError> {
+    users.disable_totp(auth_user.user_id, &request.code).await?;
+    audit
+        .record(AuditEvent::new(USER_DISABLE_TOTP, TARGET_USER, Some(auth_user.user_id)))
+        .await;
+
+    Ok(HttpResponse::Ok().json(MessageResponse {
+        message: "Two-factor authentication disabled".to_string(),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/2fa/recovery-codes",
+    tag = "auth",
+    request_body = TotpCodeRequest,
+    responses(
+        (status = 200, description = "New recovery codes, old ones revoked", body = RecoveryCodesResponse),
+        (status = 400, description = "Invalid code or two-factor not enabled", body = ErrorResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn regenerate_recovery_codes(
+    users: web::Data<UserService>,
+    auth_user: AuthenticatedUser,
+    request: ValidatedJson<TotpCodeRequest>,
+    audit: Audit,
+) -> Result<HttpResponse, AppError> {
+    let recovery_codes = users.regenerate_recovery_codes(auth_user.user_id, &request.code).await?;
+    audit
+        .record(AuditEvent::new(USER_REGENERATE_RECOVERY_CODES, TARGET_USER, Some(auth_user.user_id)))
+        .await;
+
+    Ok(HttpResponse::Ok().json(RecoveryCodesResponse { recovery_codes }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/oidc/providers",
+    tag = "auth",
+    responses((status = 200, description = "Configured identity providers", body = Vec<OidcProviderInfo>))
+)]
+pub async fn oidc_providers(oidc: web::Data<OidcService>) -> HttpResponse {
+    HttpResponse::Ok().json(oidc.providers())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/oidc/{provider}/authorize",
+    tag = "auth",
+    params(("provider" = String, Path, description = "Identity provider name")),
+    responses(
+        (status = 302, description = "Redirect to the identity provider, state cookie set"),
+        (status = 404, description = "Identity provider not found", body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse),
+    )
+)]
+pub async fn oidc_authorize(
+    oidc: web::Data<OidcService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let (url, state) = oidc.authorize(&path.into_inner()).await?;
+
+    let config = oidc.config();
+    let cookie = Cookie::build(OIDC_STATE_COOKIE, state)
+        .path("/api/auth/oidc")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(config.cookie_secure)
+        .max_age(time::Duration::minutes(config.state_ttl_minutes))
+        .finish();
+
+    Ok(HttpResponse::Found().insert_header((LOCATION, url)).cookie(cookie).finish())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/oidc/{provider}/callback",
+    tag = "auth",
+    params(("provider" = String, Path, description = "Identity provider name"), OidcCallbackQuery),
+    responses(
+        (status = 200, description = "Logged in", body = AuthResponse),
+        (status = 202, description = "Two-factor authentication required", body = TwoFactorChallengeResponse),
+        (status = 400, description = "Missing code or state", body = ErrorResponse),
+        (status = 401, description = "Login rejected, state invalid or ID token invalid", body = ErrorResponse),
+        (status = 403, description = "No account for this email and provisioning is disabled", body = ErrorResponse),
+        (status = 404, description = "Identity provider not found", body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse),
+    )
+)]
+pub async fn oidc_callback(
+    req: HttpRequest,
+    oidc: web::Data<OidcService>,
+    users: web::Data<UserService>,
+    path: web::Path<String>,
+    query: web::Query<OidcCallbackQuery>,
+    jwt_config: web::Data<JwtConfig>,
+    audit: Audit,
+) -> Result<HttpResponse, AppError> {
+    if let Some(error) = &query.error {
+        return Err(AppError::Unauthorized(format!(
+            "The identity provider rejected the login: {}",
+            query.error_description.as_deref().unwrap_or(error)
+        )));
     }
+    let (Some(code), Some(state)) = (&query.code, &query.state) else {
+        return Err(AppError::BadRequest("Missing code or state".to_string()));
+    };
+    if req.cookie(OIDC_STATE_COOKIE).as_ref().map(|cookie| cookie.value()) != Some(state.as_str()) {
+        return Err(AppError::Unauthorized(
+            "This login must be completed in the browser that started it".to_string(),
+        ));
+    }
+
+    let identity = oidc.authenticate(&path.into_inner(), code, state).await?;
+    let login = users.login_with_identity(&identity).await?;
+    let amr: &[&str] = if identity.multi_factor { &[AMR_OIDC, AMR_MFA] } else { &[AMR_OIDC] };
+    let mut response = login_response(&users, &jwt_config, &audit, login, amr).await?;
+
+    let mut removal = Cookie::build(OIDC_STATE_COOKIE, "").path("/api/auth/oidc").finish();
+    removal.make_removal();
+    response
+        .add_cookie(&removal)
+        .map_err(|e| AppError::Internal(format!("Failed to clear the login state: {}", e)))?;
+    Ok(response)
+}
+
+// 其他服务用这些公钥校验本服务签发的 token
+#[utoipa::path(
+    get,
+    path = "/.well-known/jwks.json",
+    tag = "auth",
+    responses((status = 200, description = "Public keys for verifying issued tokens, as a JSON Web Key Set", body = Object))
+)]
+pub async fn jwks(jwt_config: web::Data<JwtConfig>) -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header((CACHE_CONTROL, "public, max-age=3600"))
+        .json(jwt_config.jwks(Utc::now()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/me",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Current user", body = User),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn me(users: web::Data<UserService>, auth_user: AuthenticatedUser) -> Result<HttpResponse, AppError> {
+    let user = users.get(auth_user.user_id).await?;
+
+    Ok(HttpResponse::Ok().json(user))
 }
@@ -0,0 +1,228 @@
+use crate::config::auth::JwtConfig;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::models::change::ChangeEvent;
+use crate::services::change_feed::ChangeSubscription;
+use crate::services::{ApiKeyService, AppService, ChangeFeedService};
+use crate::utils::{AppError, ErrorResponse};
+use actix_web::http::header::CACHE_CONTROL;
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse};
+use actix_ws::Message;
+use futures::StreamExt;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use utoipa::IntoParams;
+
+const API_KEY_HEADER: &str = "X-API-Key";
+const LAST_EVENT_ID_HEADER: &str = "Last-Event-ID";
+// SSE 定期发送注释行，避免代理关闭空闲连接
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+// WebSocket 定期 ping 客户端，超过超时时间没有响应的连接视为已断开
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(45);
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ChangeFeedQuery {
+    // 上次收到的事件 id，从它之后续传；Last-Event-ID 头优先
+    pub since: Option<i64>,
+    // 浏览器的 EventSource 和 WebSocket 不能设置请求头；访问日志只记录路径，凭据不会写入日志
+    pub access_token: Option<String>,
+    pub api_key: Option<String>,
+}
+
+// 用户 token 可以订阅任何应用，API key 只能订阅所属的应用
+async fn subscribe(
+    req: &HttpRequest,
+    app_id: i64,
+    query: &ChangeFeedQuery,
+    jwt_config: &JwtConfig,
+    apps: &AppService,
+    api_keys: &ApiKeyService,
+    changes: &ChangeFeedService,
+) -> Result<ChangeSubscription, AppError> {
+    let header = |name: &str| req.headers().get(name).and_then(|value| value.to_str().ok());
+    match (header(API_KEY_HEADER).or(query.api_key.as_deref()), &query.access_token) {
+        (Some(key), _) => {
+            api_keys.authenticate(app_id, key).await?;
+        }
+        (None, Some(token)) => {
+            AuthenticatedUser::from_token(jwt_config, token)?;
+            apps.get(app_id).await?;
+        }
+        (None, None) => {
+            AuthenticatedUser::extract(req).await?;
+            apps.get(app_id).await?;
+        }
+    }
+
+    let since = match header(LAST_EVENT_ID_HEADER) {
+        Some(value) => Some(
+            value
+                .trim()
+                .parse::<i64>()
+                .map_err(|_| AppError::BadRequest("Invalid Last-Event-ID header".to_string()))?,
+        ),
+        None => query.since,
+    };
+    changes.subscribe(app_id, since).await
+}
+
+// id 让 EventSource 断线重连时自动带上 Last-Event-ID
+fn sse_frame(event: &ChangeEvent) -> web::Bytes {
+    let data = serde_json::to_string(event).unwrap_or_default();
+    web::Bytes::from(format!("id: {}\nevent: {}\ndata: {}\n\n", event.id, event.event_type, data))
+}
+
+// 应用和文章的变更，Server-Sent Events 格式，事件名为事件类型
+#[utoipa::path(
+    get,
+    path = "/api/apps/{id}/changes",
+    tag = "apps",
+    params(
+        ("id" = i64, Path, description = "App id"),
+        ("Last-Event-ID" = Option<i64>, Header, description = "Resume after this event id"),
+        ("X-API-Key" = Option<String>, Header, description = "API key of the app, instead of a bearer token"),
+        ChangeFeedQuery,
+    ),
+    responses(
+        (status = 200, description = "text/event-stream of ChangeEvent JSON messages", body = ChangeEvent),
+        (status = 400, description = "Invalid Last-Event-ID header", body = ErrorResponse),
+        (status = 401, description = "Not authenticated or invalid API key", body = ErrorResponse),
+        (status = 404, description = "App not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []), ("api_key" = []))
+)]
+pub async fn change_stream(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    query: web::Query<ChangeFeedQuery>,
+    jwt_config: web::Data<JwtConfig>,
+    apps: web::Data<AppService>,
+    api_keys: web::Data<ApiKeyService>,
+    changes: web::Data<ChangeFeedService>,
+) -> Result<HttpResponse, AppError> {
+    let subscription =
+        subscribe(&req, path.into_inner(), &query, &jwt_config, &apps, &api_keys, &changes).await?;
+    let keepalive = tokio::time::interval_at(tokio::time::Instant::now() + KEEPALIVE_INTERVAL, KEEPALIVE_INTERVAL);
+
+    let stream = futures::stream::unfold((subscription, keepalive), |(mut subscription, mut keepalive)| async move {
+        let frame = tokio::select! {
+            biased;
+            event = subscription.next() => match event {
+                Ok(event) => sse_frame(&event),
+                Err(e) => {
+                    log::error!("Change feed stopped: {}", e);
+                    return None;
+                }
+            },
+            _ = keepalive.tick() => web::Bytes::from_static(b": ping\n\n"),
+        };
+        Some((Ok::<_, actix_web::Error>(frame), (subscription, keepalive)))
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((CACHE_CONTROL, "no-cache"))
+        .streaming(stream))
+}
+
+// 与 SSE 相同的事件，每条消息是一个 JSON 文本帧
+#[utoipa::path(
+    get,
+    path = "/api/apps/{id}/changes/ws",
+    tag = "apps",
+    params(
+        ("id" = i64, Path, description = "App id"),
+        ("Last-Event-ID" = Option<i64>, Header, description = "Resume after this event id"),
+        ("X-API-Key" = Option<String>, Header, description = "API key of the app, instead of a bearer token"),
+        ChangeFeedQuery,
+    ),
+    responses(
+        (status = 101, description = "WebSocket streaming ChangeEvent messages as JSON text frames", body = ChangeEvent),
+        (status = 400, description = "Invalid Last-Event-ID header", body = ErrorResponse),
+        (status = 401, description = "Not authenticated or invalid API key", body = ErrorResponse),
+        (status = 404, description = "App not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []), ("api_key" = []))
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn change_socket(
+    req: HttpRequest,
+    body: web::Payload,
+    path: web::Path<i64>,
+    query: web::Query<ChangeFeedQuery>,
+    jwt_config: web::Data<JwtConfig>,
+    apps: web::Data<AppService>,
+    api_keys: web::Data<ApiKeyService>,
+    changes: web::Data<ChangeFeedService>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut subscription =
+        subscribe(&req, path.into_inner(), &query, &jwt_config, &apps, &api_keys, &changes).await?;
+    let backlog = subscription.backlog().await?;
+
+    let (response, session, messages) = actix_ws::handle(&req, body)?;
+    actix_web::rt::spawn(relay_changes(session, messages, subscription, backlog));
+
+    Ok(response)
+}
+
+async fn relay_changes(
+    mut session: actix_ws::Session,
+    mut messages: actix_ws::MessageStream,
+    mut subscription: ChangeSubscription,
+    backlog: Vec<ChangeEvent>,
+) {
+    let mut pending = VecDeque::from(backlog);
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+    let mut last_seen = Instant::now();
+
+    loop {
+        // 先发送续传时积压的事件
+        let event = match pending.pop_front() {
+            Some(event) => event,
+            None => tokio::select! {
+                event = subscription.next() => match event {
+                    Ok(event) => event,
+                    Err(e) => {
+                        log::error!("Change feed stopped: {}", e);
+                        break;
+                    }
+                },
+                message = messages.next() => match message {
+                    Some(Ok(Message::Ping(bytes))) => {
+                        last_seen = Instant::now();
+                        if session.pong(&bytes).await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    // 客户端不需要发送消息，收到的任何消息都只表示连接仍然存活
+                    Some(Ok(_)) => {
+                        last_seen = Instant::now();
+                        continue;
+                    }
+                },
+                _ = ping.tick() => {
+                    if last_seen.elapsed() > CLIENT_TIMEOUT || session.ping(b"").await.is_err() {
+                        break;
+                    }
+                    continue;
+                }
+            },
+        };
+
+        let text = match serde_json::to_string(&event) {
+            Ok(text) => text,
+            Err(e) => {
+                log::error!("Failed to serialize change event: {}", e);
+                continue;
+            }
+        };
+        if session.text(text).await.is_err() {
+            break;
+        }
+    }
+
+    let _ = session.close(None).await;
+}
@@ -0,0 +1,299 @@
+use crate::config::auth::JwtConfig;
+use crate::middleware::audit::Audit;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::models::audit::{ARTICLE_TAKE_LOCK, TARGET_ARTICLE};
+use crate::models::edit_lock::{EditEvent, EditLock, EditLockGrant, EditLockStatus};
+use crate::models::{Article, MessageResponse};
+use crate::services::audit::AuditEvent;
+use crate::services::edit_lock::EditorSession;
+use crate::services::{ArticleService, EditLockService, UserService};
+use crate::utils::{AppError, ErrorResponse};
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse};
+use actix_ws::Message;
+use futures::StreamExt;
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+use utoipa::IntoParams;
+
+const LOCK_TOKEN_HEADER: &str = "Lock-Token";
+// 定期 ping 客户端，超过超时时间没有响应的连接视为已断开
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(45);
+
+// 作者和管理员可以持有编辑锁，返回文章和是否是管理员
+async fn require_editor(
+    articles: &ArticleService,
+    users: &UserService,
+    article_id: i64,
+    user_id: i64,
+) -> Result<(Article, bool), AppError> {
+    let article = articles.get(article_id, user_id).await?;
+    let is_admin = users.get(user_id).await?.is_admin();
+    if article.author_id != user_id && !is_admin {
+        return Err(AppError::Forbidden(
+            "Only the author or an admin can lock this article".to_string(),
+        ));
+    }
+    Ok((article, is_admin))
+}
+
+fn lock_token(req: &HttpRequest) -> Result<&str, AppError> {
+    req.headers()
+        .get(LOCK_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| AppError::BadRequest("Lock-Token header is required".to_string()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/articles/{id}/lock",
+    tag = "articles",
+    params(("id" = i64, Path, description = "Article id")),
+    responses(
+        (status = 200, description = "Lock holder and connected editors", body = EditLockStatus),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 404, description = "Article not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_edit_lock(
+    articles: web::Data<ArticleService>,
+    edit_locks: web::Data<EditLockService>,
+    article_id: web::Path<i64>,
+    auth_user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let article = articles.get(article_id.into_inner(), auth_user.user_id).await?;
+
+    Ok(HttpResponse::Ok().json(edit_locks.status(article.id)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/articles/{id}/lock",
+    tag = "articles",
+    params(("id" = i64, Path, description = "Article id")),
+    responses(
+        (status = 200, description = "Lock acquired, renew it with the returned token", body = EditLockGrant),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Only the author or an admin can lock", body = ErrorResponse),
+        (status = 404, description = "Article not found", body = ErrorResponse),
+        (status = 409, description = "Article is locked by another editor", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn acquire_edit_lock(
+    articles: web::Data<ArticleService>,
+    users: web::Data<UserService>,
+    edit_locks: web::Data<EditLockService>,
+    article_id: web::Path<i64>,
+    auth_user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let article_id = article_id.into_inner();
+    require_editor(&articles, &users, article_id, auth_user.user_id).await?;
+    let grant = edit_locks.acquire(article_id, auth_user.user_id)?;
+
+    Ok(HttpResponse::Ok().json(grant))
+}
+
+#[utoipa::path(
+    post,
+    path = "/articles/{id}/lock/take",
+    tag = "articles",
+    params(("id" = i64, Path, description = "Article id")),
+    responses(
+        (status = 200, description = "Lock taken over from the previous holder", body = EditLockGrant),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Only an admin can take the lock of another editor", body = ErrorResponse),
+        (status = 404, description = "Article not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn take_edit_lock(
+    articles: web::Data<ArticleService>,
+    users: web::Data<UserService>,
+    edit_locks: web::Data<EditLockService>,
+    article_id: web::Path<i64>,
+    auth_user: AuthenticatedUser,
+    audit: Audit,
+) -> Result<HttpResponse, AppError> {
+    let (article, is_admin) = require_editor(&articles, &users, article_id.into_inner(), auth_user.user_id).await?;
+    let (grant, previous) = edit_locks.take(article.id, auth_user.user_id, is_admin)?;
+
+    // 记录被取走的锁，原持有者未保存的修改可能因此丢失
+    let mut event = AuditEvent::updated(ARTICLE_TAKE_LOCK, TARGET_ARTICLE, article.id, &previous, &grant.lock);
+    if let Some(app_id) = article.app_id {
+        event = event.with_app(app_id);
+    }
+    audit.record(event).await;
+
+    Ok(HttpResponse::Ok().json(grant))
+}
+
+#[utoipa::path(
+    post,
+    path = "/articles/{id}/lock/heartbeat",
+    tag = "articles",
+    params(
+        ("id" = i64, Path, description = "Article id"),
+        ("Lock-Token" = String, Header, description = "Token returned when the lock was acquired"),
+    ),
+    responses(
+        (status = 200, description = "Lock renewed", body = EditLock),
+        (status = 400, description = "Missing Lock-Token header", body = ErrorResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 409, description = "Lock expired or was taken by another editor", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn renew_edit_lock(
+    edit_locks: web::Data<EditLockService>,
+    article_id: web::Path<i64>,
+    req: HttpRequest,
+    _auth_user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let lock = edit_locks.heartbeat(article_id.into_inner(), lock_token(&req)?)?;
+
+    Ok(HttpResponse::Ok().json(lock))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/articles/{id}/lock",
+    tag = "articles",
+    params(
+        ("id" = i64, Path, description = "Article id"),
+        ("Lock-Token" = String, Header, description = "Token returned when the lock was acquired"),
+    ),
+    responses(
+        (status = 200, description = "Lock released", body = MessageResponse),
+        (status = 400, description = "Missing Lock-Token header", body = ErrorResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 409, description = "Lock expired or was taken by another editor", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn release_edit_lock(
+    edit_locks: web::Data<EditLockService>,
+    article_id: web::Path<i64>,
+    req: HttpRequest,
+    _auth_user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    edit_locks.release(article_id.into_inner(), lock_token(&req)?)?;
+
+    Ok(HttpResponse::Ok().json(MessageResponse {
+        message: "Edit lock released".to_string(),
+    }))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PresenceQuery {
+    // 浏览器不能为 WebSocket 设置 Authorization 头；访问日志只记录路径，token 不会写入日志
+    pub access_token: Option<String>,
+}
+
+// 编辑者连接后收到当前状态，之后收到在线编辑者和编辑锁的变化
+#[utoipa::path(
+    get,
+    path = "/articles/{id}/presence",
+    tag = "articles",
+    params(("id" = i64, Path, description = "Article id"), PresenceQuery),
+    responses(
+        (status = 101, description = "WebSocket streaming EditEvent messages as JSON text frames", body = EditEvent),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 404, description = "Article not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn article_presence(
+    req: HttpRequest,
+    body: web::Payload,
+    article_id: web::Path<i64>,
+    query: web::Query<PresenceQuery>,
+    jwt_config: web::Data<JwtConfig>,
+    articles: web::Data<ArticleService>,
+    users: web::Data<UserService>,
+    edit_locks: web::Data<EditLockService>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let auth_user = match &query.access_token {
+        Some(token) => AuthenticatedUser::from_token(&jwt_config, token)?,
+        None => AuthenticatedUser::extract(&req).await?,
+    };
+    let article = articles.get(article_id.into_inner(), auth_user.user_id).await?;
+    let user = users.get(auth_user.user_id).await?;
+
+    let (response, session, messages) = actix_ws::handle(&req, body)?;
+    let (editor, status) = edit_locks.join(article.id, user.id, &user.username);
+    actix_web::rt::spawn(relay_events(session, messages, editor, status));
+
+    Ok(response)
+}
+
+async fn relay_events(
+    mut session: actix_ws::Session,
+    mut messages: actix_ws::MessageStream,
+    mut editor: EditorSession,
+    status: EditLockStatus,
+) {
+    let mut pending = Some(EditEvent::State {
+        lock: status.lock,
+        editors: status.editors,
+    });
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+    let mut last_seen = Instant::now();
+
+    loop {
+        let event = match pending.take() {
+            Some(event) => event,
+            None => tokio::select! {
+                message = messages.next() => match message {
+                    Some(Ok(Message::Ping(bytes))) => {
+                        last_seen = Instant::now();
+                        if session.pong(&bytes).await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    // 客户端不需要发送消息，收到的任何消息都只表示连接仍然存活
+                    Some(Ok(_)) => {
+                        last_seen = Instant::now();
+                        continue;
+                    }
+                },
+                event = editor.events.recv() => match event {
+                    Ok(event) => event,
+                    // 落后太多时丢弃积压的事件，重新发送完整状态
+                    Err(RecvError::Lagged(_)) => {
+                        let status = editor.status();
+                        EditEvent::State {
+                            lock: status.lock,
+                            editors: status.editors,
+                        }
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                _ = ping.tick() => {
+                    if last_seen.elapsed() > CLIENT_TIMEOUT || session.ping(b"").await.is_err() {
+                        break;
+                    }
+                    continue;
+                }
+            },
+        };
+
+        let text = match serde_json::to_string(&event) {
+            Ok(text) => text,
+            Err(e) => {
+                log::error!("Failed to serialize edit event: {}", e);
+                continue;
+            }
+        };
+        if session.text(text).await.is_err() {
+            break;
+        }
+    }
+
+    let _ = session.close(None).await;
+}
@@ -0,0 +1,106 @@
+use crate::models::feed::FeedQuery;
+use crate::services::FeedService;
+use crate::utils::feed::FeedFormat;
+use crate::utils::precondition::none_match;
+use crate::utils::validation::ValidatedQuery;
+use crate::utils::{AppError, ErrorResponse};
+use actix_web::http::header::{EntityTag, ETag, CACHE_CONTROL};
+use actix_web::{web, HttpRequest, HttpResponse};
+
+// 订阅源不需要登录，客户端通过 ETag 做条件请求；撤回发布或删除文章不会留下更新时间，
+// 不能用 Last-Modified 判断订阅源是否变化
+async fn feed_response(
+    req: &HttpRequest,
+    feeds: &FeedService,
+    identifier: &str,
+    format: FeedFormat,
+    query: &FeedQuery,
+) -> Result<HttpResponse, AppError> {
+    let feed = feeds.render(identifier, format, query).await?;
+    let tag = EntityTag::new_strong(feed.etag);
+
+    if none_match(req, &tag) {
+        return Ok(HttpResponse::NotModified().insert_header(ETag(tag)).finish());
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(ETag(tag))
+        .insert_header((CACHE_CONTROL, "public, no-cache"))
+        .body(feed.body))
+}
+
+#[utoipa::path(
+    get,
+    path = "/feeds/{identifier}/rss.xml",
+    tag = "feeds",
+    params(
+        ("identifier" = String, Path, description = "App identifier"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy"),
+        FeedQuery,
+    ),
+    responses(
+        (status = 200, description = "RSS 2.0 feed of the app's published articles", content_type = "application/rss+xml", body = String),
+        (status = 304, description = "The cached copy is still current"),
+        (status = 404, description = "App not found", body = ErrorResponse),
+        (status = 422, description = "Invalid query", body = ErrorResponse),
+    )
+)]
+pub async fn rss_feed(
+    req: HttpRequest,
+    feeds: web::Data<FeedService>,
+    identifier: web::Path<String>,
+    query: ValidatedQuery<FeedQuery>,
+) -> Result<HttpResponse, AppError> {
+    feed_response(&req, &feeds, &identifier, FeedFormat::Rss, &query).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/feeds/{identifier}/atom.xml",
+    tag = "feeds",
+    params(
+        ("identifier" = String, Path, description = "App identifier"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy"),
+        FeedQuery,
+    ),
+    responses(
+        (status = 200, description = "Atom feed of the app's published articles", content_type = "application/atom+xml", body = String),
+        (status = 304, description = "The cached copy is still current"),
+        (status = 404, description = "App not found", body = ErrorResponse),
+        (status = 422, description = "Invalid query", body = ErrorResponse),
+    )
+)]
+pub async fn atom_feed(
+    req: HttpRequest,
+    feeds: web::Data<FeedService>,
+    identifier: web::Path<String>,
+    query: ValidatedQuery<FeedQuery>,
+) -> Result<HttpResponse, AppError> {
+    feed_response(&req, &feeds, &identifier, FeedFormat::Atom, &query).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/feeds/{identifier}/feed.json",
+    tag = "feeds",
+    params(
+        ("identifier" = String, Path, description = "App identifier"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy"),
+        FeedQuery,
+    ),
+    responses(
+        (status = 200, description = "JSON Feed 1.1 of the app's published articles", content_type = "application/feed+json", body = String),
+        (status = 304, description = "The cached copy is still current"),
+        (status = 404, description = "App not found", body = ErrorResponse),
+        (status = 422, description = "Invalid query", body = ErrorResponse),
+    )
+)]
+pub async fn json_feed(
+    req: HttpRequest,
+    feeds: web::Data<FeedService>,
+    identifier: web::Path<String>,
+    query: ValidatedQuery<FeedQuery>,
+) -> Result<HttpResponse, AppError> {
+    feed_response(&req, &feeds, &identifier, FeedFormat::Json, &query).await
+}
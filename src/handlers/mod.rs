@@ -1,14 +1,26 @@
+pub mod admin;
+pub mod api_key;
+pub mod archive;
 pub mod auth;
 pub mod article;
+pub mod change_feed;
+pub mod edit_lock;
+pub mod feed;
+pub mod preview;
+pub mod sitemap;
 pub mod app;
 
-use actix_web::{get, HttpResponse, Responder};
+use actix_web::{HttpResponse, Responder};
 
 pub use auth::*;
-pub use article::*;
 pub use app::*;
 
-#[get("/health")]
+#[utoipa::path(
+    get,
+    path = "/api/health",
+    tag = "system",
+    responses((status = 200, description = "Service is healthy", body = String))
+)]
 pub async fn health_check() -> impl Responder {
     HttpResponse::Ok().json("OK")
 }
@@ -0,0 +1,121 @@
+use crate::middleware::audit::Audit;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::models::audit::{PREVIEW_LINK_CREATE, PREVIEW_LINK_REVOKE, TARGET_PREVIEW_LINK};
+use crate::models::preview::{CreatePreviewLinkRequest, PreviewLink, PreviewLinkCreated};
+use crate::models::{Article, MessageResponse};
+use crate::services::audit::AuditEvent;
+use crate::services::PreviewService;
+use crate::utils::precondition::etag;
+use crate::utils::validation::ValidatedJson;
+use crate::utils::{AppError, ErrorResponse};
+use actix_web::http::header::CACHE_CONTROL;
+use actix_web::{web, HttpResponse};
+
+#[utoipa::path(
+    post,
+    path = "/articles/{id}/previews",
+    tag = "articles",
+    params(("id" = i64, Path, description = "Article id")),
+    request_body = CreatePreviewLinkRequest,
+    responses(
+        (status = 201, description = "Preview link created, the token is only shown once", body = PreviewLinkCreated),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 404, description = "Article not found or not the author", body = ErrorResponse),
+        (status = 412, description = "Pinned version is not the current version", body = ErrorResponse),
+        (status = 422, description = "Invalid request", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_preview_link(
+    previews: web::Data<PreviewService>,
+    article_id: web::Path<i64>,
+    req: ValidatedJson<CreatePreviewLinkRequest>,
+    auth_user: AuthenticatedUser,
+    audit: Audit,
+) -> Result<HttpResponse, AppError> {
+    let created = previews.create(article_id.into_inner(), auth_user.user_id, &req).await?;
+    audit
+        .record(AuditEvent::created(PREVIEW_LINK_CREATE, TARGET_PREVIEW_LINK, created.preview.id, &created.preview))
+        .await;
+
+    Ok(HttpResponse::Created().json(created))
+}
+
+#[utoipa::path(
+    get,
+    path = "/articles/{id}/previews",
+    tag = "articles",
+    params(("id" = i64, Path, description = "Article id")),
+    responses(
+        (status = 200, description = "Preview links of the article, newest first", body = [PreviewLink]),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 404, description = "Article not found or not the author", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_preview_links(
+    previews: web::Data<PreviewService>,
+    article_id: web::Path<i64>,
+    auth_user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(previews.list(article_id.into_inner(), auth_user.user_id).await?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/articles/{id}/previews/{preview_id}",
+    tag = "articles",
+    params(
+        ("id" = i64, Path, description = "Article id"),
+        ("preview_id" = i64, Path, description = "Preview link id"),
+    ),
+    responses(
+        (status = 200, description = "Preview link revoked", body = MessageResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 404, description = "Article or active preview link not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn revoke_preview_link(
+    previews: web::Data<PreviewService>,
+    path: web::Path<(i64, i64)>,
+    auth_user: AuthenticatedUser,
+    audit: Audit,
+) -> Result<HttpResponse, AppError> {
+    let (article_id, preview_id) = path.into_inner();
+    let preview = previews.revoke(article_id, preview_id, auth_user.user_id).await?;
+    audit
+        .record(AuditEvent::deleted(PREVIEW_LINK_REVOKE, TARGET_PREVIEW_LINK, preview_id, &preview))
+        .await;
+
+    Ok(HttpResponse::Ok().json(MessageResponse {
+        message: "Preview link revoked".to_string(),
+    }))
+}
+
+// 不需要登录，返回与 GET /articles/{id} 相同的文章格式
+#[utoipa::path(
+    get,
+    path = "/previews/{token}",
+    tag = "articles",
+    params(("token" = String, Path, description = "Token of the preview link")),
+    responses(
+        (status = 200, description = "Article as it is currently saved, or as it was when a pinned link was created",
+            body = Article, headers(("ETag" = String, description = "Version shown"))),
+        (status = 404, description = "Unknown preview link or the article was deleted", body = ErrorResponse),
+        (status = 410, description = "Preview link expired or was revoked", body = ErrorResponse),
+    )
+)]
+pub async fn open_preview(
+    previews: web::Data<PreviewService>,
+    token: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let article = previews.open(&token).await?;
+
+    // 草稿不应被缓存或被搜索引擎收录
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(article.version))
+        .insert_header((CACHE_CONTROL, "private, no-store"))
+        .insert_header(("X-Robots-Tag", "noindex"))
+        .json(article))
+}
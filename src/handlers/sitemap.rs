@@ -0,0 +1,73 @@
+use crate::services::SitemapService;
+use crate::utils::precondition::none_match;
+use crate::utils::sitemap::CONTENT_TYPE;
+use crate::utils::{AppError, ErrorResponse};
+use actix_web::http::header::{EntityTag, ETag, CACHE_CONTROL};
+use actix_web::{web, HttpRequest, HttpResponse};
+
+// 站点地图不需要登录，客户端通过 ETag 做条件请求
+async fn sitemap_response(
+    req: &HttpRequest,
+    sitemaps: &SitemapService,
+    identifier: &str,
+    page: Option<i64>,
+) -> Result<HttpResponse, AppError> {
+    let rendered = sitemaps.render(identifier, page).await?;
+    let tag = EntityTag::new_strong(rendered.etag);
+
+    if none_match(req, &tag) {
+        return Ok(HttpResponse::NotModified().insert_header(ETag(tag)).finish());
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(CONTENT_TYPE)
+        .insert_header(ETag(tag))
+        .insert_header((CACHE_CONTROL, "public, no-cache"))
+        .body(rendered.body))
+}
+
+#[utoipa::path(
+    get,
+    path = "/sitemaps/{identifier}/sitemap.xml",
+    tag = "sitemaps",
+    params(
+        ("identifier" = String, Path, description = "App identifier"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy"),
+    ),
+    responses(
+        (status = 200, description = "Sitemap of the app's indexable articles, or a sitemap index when they do not fit in one sitemap", content_type = "application/xml", body = String),
+        (status = 304, description = "The cached copy is still current"),
+        (status = 404, description = "App not found or it has no base URL", body = ErrorResponse),
+    )
+)]
+pub async fn sitemap(
+    req: HttpRequest,
+    sitemaps: web::Data<SitemapService>,
+    identifier: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    sitemap_response(&req, &sitemaps, &identifier, None).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/sitemaps/{identifier}/sitemap-{page}.xml",
+    tag = "sitemaps",
+    params(
+        ("identifier" = String, Path, description = "App identifier"),
+        ("page" = i64, Path, description = "Page listed in the sitemap index, starting at 1"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy"),
+    ),
+    responses(
+        (status = 200, description = "One page of the app's sitemap", content_type = "application/xml", body = String),
+        (status = 304, description = "The cached copy is still current"),
+        (status = 404, description = "App or page not found", body = ErrorResponse),
+    )
+)]
+pub async fn sitemap_page(
+    req: HttpRequest,
+    sitemaps: web::Data<SitemapService>,
+    path: web::Path<(String, i64)>,
+) -> Result<HttpResponse, AppError> {
+    let (identifier, page) = path.into_inner();
+    sitemap_response(&req, &sitemaps, &identifier, Some(page)).await
+}
@@ -0,0 +1,78 @@
+use async_trait::async_trait;
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+use super::{Job, JobContext};
+
+// 删除保留期之前完成的任务，死信保留到管理员处理
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurgeCompletedJobs {
+    pub retention_days: i64,
+}
+
+#[async_trait]
+impl Job for PurgeCompletedJobs {
+    const KIND: &'static str = "purge_completed_jobs";
+
+    async fn run(self, ctx: &JobContext) -> Result<(), anyhow::Error> {
+        let before = ctx.clock.now() - Duration::days(self.retention_days);
+        let purged = ctx.repositories.jobs.purge_completed(before).await?;
+        log::info!("Purged {} completed jobs", purged);
+        Ok(())
+    }
+}
+
+// 删除超过保留期的审计日志
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurgeAuditLogs {
+    pub retention_days: i64,
+}
+
+#[async_trait]
+impl Job for PurgeAuditLogs {
+    const KIND: &'static str = "purge_audit_logs";
+
+    async fn run(self, ctx: &JobContext) -> Result<(), anyhow::Error> {
+        let before = ctx.clock.now() - Duration::days(self.retention_days);
+        let purged = ctx.repositories.audit.purge_before(before).await?;
+        log::info!("Purged {} audit log entries", purged);
+        Ok(())
+    }
+}
+
+// 永久删除在回收站中超过保留期的应用（连同它的文章）和文章
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurgeTrash {
+    pub retention_days: i64,
+}
+
+#[async_trait]
+impl Job for PurgeTrash {
+    const KIND: &'static str = "purge_trash";
+
+    async fn run(self, ctx: &JobContext) -> Result<(), anyhow::Error> {
+        let before = ctx.clock.now() - Duration::days(self.retention_days);
+        let apps = ctx.repositories.apps.purge_trashed(before).await?;
+        let articles = ctx.repositories.articles.purge_trashed(before).await?;
+        log::info!("Purged {} apps and {} articles from the trash", apps, articles);
+        Ok(())
+    }
+}
+
+// 删除超过保留期的变更事件，更早断开的客户端只能从最新事件开始订阅
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurgeChangeEvents {
+    pub retention_days: i64,
+}
+
+#[async_trait]
+impl Job for PurgeChangeEvents {
+    const KIND: &'static str = "purge_change_events";
+
+    async fn run(self, ctx: &JobContext) -> Result<(), anyhow::Error> {
+        let before = ctx.clock.now() - Duration::days(self.retention_days);
+        let purged = ctx.repositories.changes.purge_before(before).await?;
+        log::info!("Purged {} change events", purged);
+        Ok(())
+    }
+}
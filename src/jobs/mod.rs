@@ -0,0 +1,386 @@
+// 数据库驱动的后台任务：类型化的任务定义、worker 池和定时任务
+pub mod cleanup;
+pub mod scheduler;
+pub mod worker;
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::config::audit::AuditConfig;
+use crate::config::change_feed::ChangeFeedConfig;
+use crate::config::job::JobConfig;
+use crate::config::trash::TrashConfig;
+use crate::db::Repositories;
+use crate::services::JobService;
+use crate::utils::clock::Clock;
+use crate::utils::email::EmailService;
+
+pub use scheduler::Scheduler;
+pub use worker::JobWorker;
+
+pub const DEFAULT_QUEUE: &str = "default";
+
+// 任务参数以 JSON 保存，KIND 用来找到对应的处理函数
+#[async_trait]
+pub trait Job: Serialize + DeserializeOwned + Send + Sync + 'static {
+    const KIND: &'static str;
+    const QUEUE: &'static str = DEFAULT_QUEUE;
+    const MAX_ATTEMPTS: i32 = 5;
+
+    // 相同 key 的任务在结束前只会存在一个
+    fn unique_key(&self) -> Option<String> {
+        None
+    }
+
+    async fn run(self, ctx: &JobContext) -> Result<(), anyhow::Error>;
+}
+
+// 任务执行时可以使用的依赖
+#[derive(Clone)]
+pub struct JobContext {
+    pub repositories: Repositories,
+    pub email_service: EmailService,
+    pub jobs: JobService,
+    pub clock: Arc<dyn Clock>,
+}
+
+pub type JobHandler =
+    Arc<dyn Fn(JobContext, String) -> BoxFuture<'static, Result<(), anyhow::Error>> + Send + Sync>;
+
+// 从保存的参数重新计算 unique_key
+pub type UniqueKeyFn = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+// 按 cron 表达式定期创建的任务
+#[derive(Clone)]
+pub struct RecurringJob {
+    pub name: String,
+    pub schedule: cron::Schedule,
+    pub kind: &'static str,
+    pub queue: &'static str,
+    pub max_attempts: i32,
+    pub payload: String,
+}
+
+#[derive(Clone, Default)]
+pub struct JobRegistry {
+    handlers: HashMap<&'static str, JobHandler>,
+    unique_keys: HashMap<&'static str, UniqueKeyFn>,
+    recurring: Vec<RecurringJob>,
+}
+
+impl JobRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<J: Job>(mut self) -> Self {
+        let handler: JobHandler = Arc::new(|ctx: JobContext, payload: String| {
+            Box::pin(async move {
+                let job: J = serde_json::from_str(&payload)?;
+                job.run(&ctx).await
+            })
+        });
+        self.handlers.insert(J::KIND, handler);
+        let unique_key: UniqueKeyFn =
+            Arc::new(|payload: &str| serde_json::from_str::<J>(payload).ok().and_then(|job| job.unique_key()));
+        self.unique_keys.insert(J::KIND, unique_key);
+        self
+    }
+
+    // schedule 使用带秒的 cron 表达式，例如 "0 0 3 * * *" 表示每天 3 点
+    pub fn recurring<J: Job>(self, name: &str, schedule: &str, job: J) -> Result<Self, anyhow::Error> {
+        let schedule = cron::Schedule::from_str(schedule)
+            .map_err(|e| anyhow::anyhow!("Invalid schedule for {}: {}", name, e))?;
+        let mut registry = self.register::<J>();
+        registry.recurring.push(RecurringJob {
+            name: name.to_string(),
+            schedule,
+            kind: J::KIND,
+            queue: J::QUEUE,
+            max_attempts: J::MAX_ATTEMPTS,
+            payload: serde_json::to_string(&job)?,
+        });
+        Ok(registry)
+    }
+
+    pub fn handler(&self, kind: &str) -> Option<JobHandler> {
+        self.handlers.get(kind).cloned()
+    }
+
+    // 未注册的类型或参数无法解析时为 None
+    pub fn unique_key(&self, kind: &str, payload: &str) -> Option<String> {
+        self.unique_keys.get(kind).and_then(|unique_key| unique_key(payload))
+    }
+
+    pub fn recurring_jobs(&self) -> &[RecurringJob] {
+        &self.recurring
+    }
+}
+
+// 内置任务
+pub fn default_registry(
+    config: &JobConfig,
+    audit: &AuditConfig,
+    trash: &TrashConfig,
+    change_feed: &ChangeFeedConfig,
+) -> JobRegistry {
+    let mut registry = JobRegistry::new()
+        .recurring(
+            "purge_completed_jobs",
+            "0 0 3 * * *",
+            cleanup::PurgeCompletedJobs {
+                retention_days: config.retention_days,
+            },
+        )
+        .expect("Built-in job schedules must be valid");
+    if audit.retention_days > 0 {
+        registry = registry
+            .recurring(
+                "purge_audit_logs",
+                "0 30 3 * * *",
+                cleanup::PurgeAuditLogs {
+                    retention_days: audit.retention_days,
+                },
+            )
+            .expect("Built-in job schedules must be valid");
+    }
+    if trash.retention_days > 0 {
+        registry = registry
+            .recurring(
+                "purge_trash",
+                "0 45 3 * * *",
+                cleanup::PurgeTrash {
+                    retention_days: trash.retention_days,
+                },
+            )
+            .expect("Built-in job schedules must be valid");
+    }
+    if change_feed.retention_days > 0 {
+        registry = registry
+            .recurring(
+                "purge_change_events",
+                "0 15 3 * * *",
+                cleanup::PurgeChangeEvents {
+                    retention_days: change_feed.retention_days,
+                },
+            )
+            .expect("Built-in job schedules must be valid");
+    }
+    registry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory::MemoryRepository;
+    use crate::models::job::{NewJob, JOB_COMPLETED, JOB_DEAD, JOB_PENDING};
+    use crate::test_support::FixedClock;
+    use crate::utils::email::MemoryEmailTransport;
+    use crate::utils::AppError;
+    use chrono::Duration;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize)]
+    struct Echo {
+        key: Option<String>,
+        fail: bool,
+        panic: bool,
+    }
+
+    #[async_trait]
+    impl Job for Echo {
+        const KIND: &'static str = "echo";
+        const MAX_ATTEMPTS: i32 = 2;
+
+        fn unique_key(&self) -> Option<String> {
+            self.key.clone()
+        }
+
+        async fn run(self, _ctx: &JobContext) -> Result<(), anyhow::Error> {
+            if self.panic {
+                panic!("boom");
+            }
+            if self.fail {
+                return Err(anyhow::anyhow!("echo failed"));
+            }
+            Ok(())
+        }
+    }
+
+    fn echo(fail: bool) -> Echo {
+        Echo {
+            key: None,
+            fail,
+            panic: false,
+        }
+    }
+
+    fn context(clock: &Arc<FixedClock>) -> JobContext {
+        let repositories = Repositories::new(MemoryRepository::new());
+        let registry = JobRegistry::new()
+            .register::<Echo>()
+            .recurring("nightly_echo", "0 0 3 * * *", echo(false))
+            .unwrap();
+        JobContext {
+            jobs: JobService::new(repositories.jobs.clone(), registry).with_clock(clock.clone()),
+            repositories,
+            email_service: EmailService::new(Arc::new(MemoryEmailTransport::new()), "noreply@example.com"),
+            clock: clock.clone(),
+        }
+    }
+
+    fn worker(ctx: &JobContext) -> JobWorker {
+        JobWorker::new(ctx.clone(), JobConfig::default())
+    }
+
+    #[actix_web::test]
+    async fn typed_jobs_run_once() {
+        let clock = Arc::new(FixedClock::default());
+        let ctx = context(&clock);
+        let id = ctx.jobs.enqueue(echo(false)).await.unwrap();
+
+        assert_eq!(worker(&ctx).run_due(10).await.unwrap(), 1);
+        let job = ctx.jobs.get(id).await.unwrap();
+        assert_eq!(job.status, JOB_COMPLETED);
+        assert_eq!(job.attempts, 1);
+        assert_eq!(worker(&ctx).run_due(10).await.unwrap(), 0);
+    }
+
+    #[actix_web::test]
+    async fn failed_jobs_back_off_then_die() {
+        let clock = Arc::new(FixedClock::default());
+        let ctx = context(&clock);
+        let id = ctx.jobs.enqueue(echo(true)).await.unwrap();
+
+        worker(&ctx).run_due(10).await.unwrap();
+        let job = ctx.jobs.get(id).await.unwrap();
+        assert_eq!(job.status, JOB_PENDING);
+        assert_eq!(job.last_error.as_deref(), Some("echo failed"));
+        assert_eq!(job.run_at, clock.now() + Duration::seconds(10));
+
+        // 退避时间未到不会执行
+        assert_eq!(worker(&ctx).run_due(10).await.unwrap(), 0);
+        clock.advance(Duration::seconds(10));
+        worker(&ctx).run_due(10).await.unwrap();
+        assert_eq!(ctx.jobs.get(id).await.unwrap().status, JOB_DEAD);
+
+        let retried = ctx.jobs.retry(id).await.unwrap();
+        assert_eq!(retried.status, JOB_PENDING);
+        assert_eq!(retried.attempts, 0);
+        assert!(matches!(ctx.jobs.retry(id).await, Err(AppError::Conflict(_))));
+    }
+
+    #[actix_web::test]
+    async fn panics_and_unknown_kinds_are_failures() {
+        let clock = Arc::new(FixedClock::default());
+        let ctx = context(&clock);
+        let panicking = ctx
+            .jobs
+            .enqueue(Echo {
+                key: None,
+                fail: false,
+                panic: true,
+            })
+            .await
+            .unwrap();
+        let (unknown, _) = ctx
+            .repositories
+            .jobs
+            .enqueue(&NewJob {
+                queue: DEFAULT_QUEUE.to_string(),
+                kind: "missing".to_string(),
+                payload: "{}".to_string(),
+                max_attempts: 5,
+                unique_key: None,
+                run_at: clock.now(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(worker(&ctx).run_due(10).await.unwrap(), 2);
+        let job = ctx.jobs.get(panicking).await.unwrap();
+        assert_eq!(job.status, JOB_PENDING);
+        assert!(job.last_error.unwrap().contains("panicked"));
+        // 未知类型直接进入死信
+        assert_eq!(ctx.jobs.get(unknown).await.unwrap().status, JOB_DEAD);
+    }
+
+    #[actix_web::test]
+    async fn unique_jobs_are_deduplicated_until_finished() {
+        let clock = Arc::new(FixedClock::default());
+        let ctx = context(&clock);
+        let unique = || Echo {
+            key: Some("echo:1".to_string()),
+            fail: false,
+            panic: false,
+        };
+
+        let first = ctx.jobs.enqueue(unique()).await.unwrap();
+        assert_eq!(ctx.jobs.enqueue(unique()).await.unwrap(), first);
+
+        worker(&ctx).run_due(10).await.unwrap();
+        assert_ne!(ctx.jobs.enqueue(unique()).await.unwrap(), first);
+    }
+
+    #[actix_web::test]
+    async fn retried_unique_jobs_take_their_key_back() {
+        let clock = Arc::new(FixedClock::default());
+        let ctx = context(&clock);
+        let failing = || Echo {
+            key: Some("echo:2".to_string()),
+            fail: true,
+            panic: false,
+        };
+        let die = |id: i64| {
+            let ctx = ctx.clone();
+            let clock = clock.clone();
+            async move {
+                while ctx.jobs.get(id).await.unwrap().status != JOB_DEAD {
+                    worker(&ctx).run_due(10).await.unwrap();
+                    clock.advance(Duration::seconds(60));
+                }
+            }
+        };
+
+        let first = ctx.jobs.enqueue(failing()).await.unwrap();
+        die(first).await;
+        assert!(ctx.jobs.get(first).await.unwrap().unique_key.is_none());
+
+        // 重试后仍然去重
+        let retried = ctx.jobs.retry(first).await.unwrap();
+        assert_eq!(retried.unique_key.as_deref(), Some("echo:2"));
+        assert_eq!(ctx.jobs.enqueue(failing()).await.unwrap(), first);
+
+        // 相同的任务未结束时不能重试
+        die(first).await;
+        let second = ctx.jobs.enqueue(failing()).await.unwrap();
+        assert_ne!(second, first);
+        assert!(matches!(ctx.jobs.retry(first).await, Err(AppError::Conflict(_))));
+        assert_eq!(ctx.jobs.get(first).await.unwrap().status, JOB_DEAD);
+    }
+
+    #[actix_web::test]
+    async fn recurring_jobs_fire_once_across_schedulers() {
+        let clock = Arc::new(FixedClock::default());
+        let ctx = context(&clock);
+        let mut first = Scheduler::new(ctx.clone());
+        let mut second = Scheduler::new(ctx.clone());
+
+        // 2024-01-01 00:00 启动，3 点之前不会触发
+        assert_eq!(first.tick().await.unwrap(), 0);
+        clock.advance(Duration::hours(3));
+        assert_eq!(first.tick().await.unwrap(), 1);
+        assert_eq!(second.tick().await.unwrap(), 0);
+        assert_eq!(first.tick().await.unwrap(), 0);
+
+        let stats = ctx.jobs.stats().await.unwrap();
+        assert_eq!(stats.counts[0].count, 1);
+        assert_eq!(stats.recurring[0].name, "nightly_echo");
+        assert_eq!(stats.recurring[0].next_run_at, Some(clock.now() + Duration::days(1)));
+    }
+}
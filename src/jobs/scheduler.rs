@@ -0,0 +1,73 @@
+use chrono::{DateTime, Utc};
+
+use super::JobContext;
+use crate::models::job::NewJob;
+use crate::utils::AppError;
+
+// 按 cron 表达式创建定时任务；多个进程同时运行时由 job_schedules 保证每个时间点只触发一次
+pub struct Scheduler {
+    context: JobContext,
+    // 上次检查的时间，启动前错过的时间点不会补跑
+    checked_at: DateTime<Utc>,
+}
+
+impl Scheduler {
+    pub fn new(context: JobContext) -> Self {
+        let checked_at = context.clock.now();
+        Self { context, checked_at }
+    }
+
+    // 为到期的定时任务创建任务，返回新建的数量
+    pub async fn tick(&mut self) -> Result<usize, AppError> {
+        let now = self.context.clock.now();
+        let registry = self.context.jobs.registry();
+        let repository = &self.context.repositories.jobs;
+        let mut created = 0;
+
+        for recurring in registry.recurring_jobs() {
+            // 两次检查之间错过多个时间点时只触发最近的一次
+            let Some(fire_at) = recurring
+                .schedule
+                .after(&self.checked_at)
+                .take_while(|fire_at| *fire_at <= now)
+                .last()
+            else {
+                continue;
+            };
+
+            if !repository.claim_schedule(&recurring.name, fire_at).await? {
+                continue;
+            }
+
+            // 上一次还没执行完时不重复创建
+            let (id, inserted) = repository
+                .enqueue(&NewJob {
+                    queue: recurring.queue.to_string(),
+                    kind: recurring.kind.to_string(),
+                    payload: recurring.payload.clone(),
+                    max_attempts: recurring.max_attempts,
+                    unique_key: Some(format!("recurring:{}", recurring.name)),
+                    run_at: fire_at,
+                })
+                .await?;
+            if inserted {
+                log::info!("Scheduled recurring job {} as job {}", recurring.name, id);
+                created += 1;
+            }
+        }
+
+        self.checked_at = now;
+        Ok(created)
+    }
+
+    pub fn spawn(mut self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = self.tick().await {
+                    log::error!("Job scheduler failed: {}", e);
+                }
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+        })
+    }
+}
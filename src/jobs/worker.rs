@@ -0,0 +1,99 @@
+use chrono::Duration;
+
+use super::JobContext;
+use crate::config::job::JobConfig;
+use crate::models::job::JobRecord;
+use crate::utils::backoff;
+use crate::utils::AppError;
+
+// 从配置的队列中领取并执行任务
+#[derive(Clone)]
+pub struct JobWorker {
+    context: JobContext,
+    config: JobConfig,
+}
+
+impl JobWorker {
+    pub fn new(context: JobContext, config: JobConfig) -> Self {
+        Self { context, config }
+    }
+
+    // 领取并执行最多 limit 个到期任务，返回领取的数量
+    pub async fn run_due(&self, limit: i64) -> Result<usize, AppError> {
+        let now = self.context.clock.now();
+        let lease = Duration::from_std(self.config.lease).unwrap_or_else(|_| Duration::weeks(52));
+        let jobs = self
+            .context
+            .repositories
+            .jobs
+            .claim(&self.config.queues, now, now + lease, limit)
+            .await?;
+
+        for job in &jobs {
+            self.execute(job).await?;
+        }
+        Ok(jobs.len())
+    }
+
+    async fn execute(&self, job: &JobRecord) -> Result<(), AppError> {
+        let repository = &self.context.repositories.jobs;
+
+        let Some(handler) = self.context.jobs.registry().handler(&job.kind) else {
+            // 没有处理函数时重试也没有意义，直接进入死信
+            log::error!("No handler registered for job {} ({})", job.id, job.kind);
+            let error = format!("Unknown job kind: {}", job.kind);
+            repository.fail(job.id, &error, None, self.context.clock.now()).await?;
+            return Ok(());
+        };
+
+        // 在独立的任务中执行，panic 按失败处理
+        let result = match tokio::spawn(handler(self.context.clone(), job.payload.clone())).await {
+            Ok(result) => result,
+            Err(e) => Err(anyhow::anyhow!("Job panicked: {}", e)),
+        };
+
+        let now = self.context.clock.now();
+        match result {
+            Ok(()) => {
+                log::debug!("Job {} ({}) completed", job.id, job.kind);
+                repository.complete(job.id, now).await?;
+            }
+            Err(e) if job.attempts >= job.max_attempts => {
+                log::error!("Job {} ({}) failed after {} attempts: {}", job.id, job.kind, job.attempts, e);
+                repository.fail(job.id, &e.to_string(), None, now).await?;
+            }
+            Err(e) => {
+                log::warn!("Job {} ({}) failed (attempt {}): {}", job.id, job.kind, job.attempts, e);
+                let retry_at = now + backoff::exponential(self.config.retry_base, self.config.retry_max, job.attempts);
+                repository.fail(job.id, &e.to_string(), Some(retry_at), now).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // 启动 concurrency 个循环，每个循环一次执行一个任务
+    pub fn spawn(self) -> Vec<tokio::task::JoinHandle<()>> {
+        log::info!(
+            "Starting {} job workers for queues: {}",
+            self.config.concurrency,
+            self.config.queues.join(", ")
+        );
+        (0..self.config.concurrency)
+            .map(|_| {
+                let worker = self.clone();
+                tokio::spawn(async move {
+                    loop {
+                        match worker.run_due(1).await {
+                            Ok(0) => tokio::time::sleep(worker.config.poll_interval).await,
+                            Ok(_) => {}
+                            Err(e) => {
+                                log::error!("Job worker failed: {}", e);
+                                tokio::time::sleep(worker.config.poll_interval).await;
+                            }
+                        }
+                    }
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,14 @@
+pub mod config;
+pub mod db;
+pub mod graphql;
+pub mod handlers;
+pub mod jobs;
+pub mod middleware;
+pub mod models;
+pub mod openapi;
+pub mod routes;
+pub mod server;
+pub mod services;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+pub mod utils;
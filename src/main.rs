@@ -1,19 +1,15 @@
-use actix_web::{web, App, HttpServer, middleware::Logger};
-use actix_web::{HttpResponse, http::StatusCode};
-use actix_cors::Cors;
+use actix_web::HttpServer;
 use dotenv::dotenv;
-use sqlx::mysql::MySqlPool;
 use std::env;
+use std::sync::Arc;
 
-mod config;
-mod middleware;
-mod models;
-mod handlers;
-mod db;
-mod utils;
-
-use crate::config::auth::JwtConfig;
-use crate::utils::email::EmailService;
+use rscms::config::rate_limit::RateLimitBackend;
+use rscms::db::Repositories;
+use rscms::middleware::rate_limit::{MemoryStore, RateLimitStore};
+use rscms::server::{build_app, AppState, Dependencies, Settings};
+use rscms::services::user::RandomCodeGenerator;
+use rscms::utils::clock::SystemClock;
+use rscms::utils::email::EmailService;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -21,73 +17,65 @@ async fn main() -> std::io::Result<()> {
     dotenv().ok();
     env_logger::init();
 
+    // 运行模式：serve（默认）或 worker
+    let mode = env::args().nth(1).unwrap_or_else(|| "serve".to_string());
+    if mode != "serve" && mode != "worker" {
+        eprintln!("Usage: rscms [serve|worker]");
+        std::process::exit(2);
+    }
+
     // 获取配置
     let host = env::var("SERVER_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
     let port = env::var("SERVER_PORT").unwrap_or_else(|_| "8080".to_string());
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let settings = Settings::from_env();
 
-    // 创建数据库连接池
-    let pool = MySqlPool::connect(&database_url)
+    // 根据 DATABASE_URL 连接数据库并执行迁移
+    let repositories = Repositories::connect(&database_url)
         .await
-        .expect("Failed to create pool");
+        .expect("Failed to connect to database");
 
-    // 创建 JWT 配置
-    let jwt_config = JwtConfig::from_env();
+    // 创建限流存储
+    let rate_limit_store: Arc<dyn RateLimitStore> = match &settings.rate_limit.backend {
+        RateLimitBackend::Memory => Arc::new(MemoryStore::new()),
+        #[cfg(feature = "redis")]
+        RateLimitBackend::Redis(url) => Arc::new(
+            rscms::middleware::rate_limit::RedisStore::connect(url)
+                .await
+                .expect("Failed to connect to rate limit redis"),
+        ),
+        #[cfg(not(feature = "redis"))]
+        RateLimitBackend::Redis(_) => panic!("RATE_LIMIT_BACKEND=redis requires the `redis` feature"),
+    };
 
-    // 创建邮件服务
-    let email_service = EmailService::new();
+    let state = AppState::new(
+        Dependencies {
+            repositories,
+            email_service: EmailService::from_env().expect("Failed to configure email transport"),
+            rate_limit_store,
+            clock: Arc::new(SystemClock),
+            code_generator: Arc::new(RandomCodeGenerator),
+        },
+        settings,
+    );
 
-    // 共享数据库连接池
-    let db_pool = web::Data::new(pool);
+    // worker 模式只运行后台任务，不启动 HTTP 服务
+    if mode == "worker" {
+        state.spawn_background();
+        log::info!("Running in worker mode");
+        return tokio::signal::ctrl_c().await;
+    }
+    if state.job_config.run_in_server {
+        state.spawn_background();
+    }
+    // 编辑锁和 WebSocket 连接都在 HTTP 服务进程内
+    state.edit_lock_service.clone().spawn_sweeper();
 
     log::info!("Starting server at http://{}:{}", host, port);
 
     // 创建并启动 HTTP 服务器
-    HttpServer::new(move || {
-        // 配置 CORS
-        let cors = Cors::default()
-            .allow_any_origin()
-            .allow_any_method()
-            .allow_any_header()
-            .max_age(3600);
-
-        App::new()
-            .wrap(Logger::default())
-            .wrap(cors)
-            .app_data(db_pool.clone())
-            .app_data(web::Data::new(jwt_config.clone()))
-            .app_data(web::Data::new(email_service.clone()))
-            .service(
-                web::scope("/api")
-                    .service(handlers::health_check)
-                    .service(handlers::register)
-                    .service(handlers::login)
-                    .service(handlers::get_verification_code)
-                    .service(
-                        web::scope("/apps")
-                            .route("", web::post().to(handlers::create_app))
-                            .route("", web::get().to(handlers::list_apps))
-                            .route("/{id}", web::get().to(handlers::get_app))
-                            .route("/{id}", web::put().to(handlers::update_app))
-                            .route("/{id}", web::delete().to(handlers::delete_app))
-                    )
-            )
-            .service(handlers::auth::me)
-            .service(handlers::article::create_article)
-            .service(handlers::article::get_article)
-            .service(handlers::article::list_articles)
-            .service(handlers::article::update_article)
-            .service(handlers::article::delete_article)
-            // 404 处理
-            .default_service(web::route().to(|| async {
-                HttpResponse::NotFound()
-                    .json(serde_json::json!({
-                        "code": StatusCode::NOT_FOUND.as_u16(),
-                        "message": "Resource not found"
-                    }))
-            }))
-    })
-    .bind(format!("{}:{}", host, port))?
-    .run()
-    .await
+    HttpServer::new(move || build_app(state.clone()))
+        .bind(format!("{}:{}", host, port))?
+        .run()
+        .await
 }
@@ -0,0 +1,81 @@
+use crate::config::auth::JwtConfig;
+use crate::middleware::request_id::current_request_id;
+use crate::models::audit::{NewAuditLog, ACTOR_ANONYMOUS, ACTOR_API_KEY, ACTOR_USER};
+use crate::services::audit::{AuditEvent, AuditService};
+use crate::utils::{token, AppError};
+use actix_web::http::header::USER_AGENT;
+use actix_web::{dev, web, FromRequest, HttpRequest};
+use chrono::Utc;
+use futures::future::{err, ok, Ready};
+
+// 请求的操作者和来源，处理器在变更成功后调用 record
+#[derive(Clone)]
+pub struct Audit {
+    service: AuditService,
+    actor_id: Option<i64>,
+    api_key: Option<String>,
+    ip: Option<String>,
+    user_agent: Option<String>,
+    request_id: Option<String>,
+}
+
+impl Audit {
+    // 写入失败不影响已经完成的变更，只记录错误
+    pub async fn record(&self, event: AuditEvent) {
+        let actor_id = event.actor_id.or(self.actor_id);
+        let actor_type = match (actor_id, &self.api_key) {
+            (Some(_), _) => ACTOR_USER,
+            (None, Some(_)) => ACTOR_API_KEY,
+            (None, None) => ACTOR_ANONYMOUS,
+        };
+        let entry = NewAuditLog {
+            actor_type: actor_type.to_string(),
+            actor_id,
+            api_key: self.api_key.clone(),
+            action: event.action.to_string(),
+            target_type: event.target_type.to_string(),
+            target_id: event.target_id,
+            app_id: event.app_id,
+            before_data: event.before.map(|value| value.to_string()),
+            after_data: event.after.map(|value| value.to_string()),
+            ip: self.ip.clone(),
+            user_agent: self.user_agent.clone(),
+            request_id: self.request_id.clone(),
+        };
+        if let Err(e) = self.service.append(&entry).await {
+            log::error!("Failed to write audit log for {}: {:?}", entry.action, e);
+        }
+    }
+}
+
+impl FromRequest for Audit {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut dev::Payload) -> Self::Future {
+        let Some(service) = req.app_data::<web::Data<AuditService>>() else {
+            return err(AppError::Internal("Audit service is not registered".to_string()));
+        };
+        let header = |name| req.headers().get(name).and_then(|value| value.to_str().ok());
+
+        // token 无效时由处理器的 AuthenticatedUser 拒绝请求，这里只记录合法的操作者
+        let actor_id = req.app_data::<web::Data<JwtConfig>>().and_then(|jwt_config| {
+            let token = header("Authorization")?.strip_prefix("Bearer ")?;
+            jwt_config.decode(token, Utc::now()).ok()?.sub.parse().ok()
+        });
+        let ip = if service.trust_proxy() {
+            req.connection_info().realip_remote_addr().map(str::to_string)
+        } else {
+            req.peer_addr().map(|addr| addr.ip().to_string())
+        };
+
+        ok(Audit {
+            service: service.get_ref().clone(),
+            actor_id,
+            api_key: header("X-API-Key").map(|key| token::digest(key)[..16].to_string()),
+            ip,
+            user_agent: header(USER_AGENT.as_str()).map(|agent| agent.chars().take(500).collect()),
+            request_id: current_request_id(),
+        })
+    }
+}
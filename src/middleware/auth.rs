@@ -1,47 +1,86 @@
-use crate::config::auth::{Claims, JwtConfig};
-use actix_web::error::ErrorUnauthorized;
-use actix_web::{dev, Error, FromRequest, HttpRequest};
-use futures::future::{err, ok, Ready};
-use jsonwebtoken::{decode, Validation};
+use crate::config::auth::JwtConfig;
+use crate::models::User;
+use crate::services::UserService;
+use crate::utils::AppError;
+use actix_web::{dev, web, FromRequest, HttpRequest};
+use futures::future::{err, ok, LocalBoxFuture, Ready};
+use chrono::Utc;
 
 pub struct AuthenticatedUser {
     pub user_id: i64,
+    pub amr: Vec<String>,  // 登录时使用的认证方式
+    pub aal: u8,  // 认证强度，两步验证登录为 2
 }
 
 impl FromRequest for AuthenticatedUser {
-    type Error = Error;
+    type Error = AppError;
     type Future = Ready<Result<Self, Self::Error>>;
 
     fn from_request(req: &HttpRequest, _: &mut dev::Payload) -> Self::Future {
         let auth_header = match req.headers().get("Authorization") {
             Some(header) => header,
-            None => return err(ErrorUnauthorized("No authorization header")),
+            None => return err(AppError::Unauthorized("No authorization header".to_string())),
         };
 
         let auth_str = match auth_header.to_str() {
             Ok(str) => str,
-            Err(_) => return err(ErrorUnauthorized("Invalid authorization header")),
+            Err(_) => return err(AppError::Unauthorized("Invalid authorization header".to_string())),
         };
 
         if !auth_str.starts_with("Bearer ") {
-            return err(ErrorUnauthorized("Invalid authorization header format"));
+            return err(AppError::Unauthorized("Invalid authorization header format".to_string()));
         }
 
         let token = &auth_str[7..];
-        let jwt_config = JwtConfig::from_env();
-
-        match decode::<Claims>(
-            token,
-            &jwt_config.decoding_key,
-            &Validation::default(),
-        ) {
-            Ok(token_data) => {
-                match token_data.claims.sub.parse::<i64>() {
-                    Ok(user_id) => ok(AuthenticatedUser { user_id }),
-                    Err(_) => err(ErrorUnauthorized("Invalid user ID in token")),
-                }
-            }
-            Err(_) => err(ErrorUnauthorized("Invalid token")),
+        let jwt_config = match req.app_data::<web::Data<JwtConfig>>() {
+            Some(config) => config,
+            None => return err(AppError::Internal("JWT config is not registered".to_string())),
+        };
+
+        match AuthenticatedUser::from_token(jwt_config, token) {
+            Ok(user) => ok(user),
+            Err(e) => err(e),
         }
     }
 }
+
+impl AuthenticatedUser {
+    // 浏览器建立 WebSocket 时不能设置请求头，token 通过查询参数传入
+    pub fn from_token(jwt_config: &JwtConfig, token: &str) -> Result<Self, AppError> {
+        let claims = jwt_config.decode(token, Utc::now())?;
+        let user_id = claims
+            .sub
+            .parse::<i64>()
+            .map_err(|_| AppError::Unauthorized("Invalid user ID in token".to_string()))?;
+        Ok(AuthenticatedUser {
+            user_id,
+            amr: claims.amr,
+            aal: claims.aal,
+        })
+    }
+}
+
+// 当前用户必须是管理员
+pub struct AdminUser {
+    pub user: User,
+}
+
+impl FromRequest for AdminUser {
+    type Error = AppError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut dev::Payload) -> Self::Future {
+        let authenticated = AuthenticatedUser::from_request(req, payload).into_inner();
+        let users = req.app_data::<web::Data<UserService>>().cloned();
+
+        Box::pin(async move {
+            let authenticated = authenticated?;
+            let users = users.ok_or_else(|| AppError::Internal("User service is not registered".to_string()))?;
+            let user = users.get(authenticated.user_id).await?;
+            if !user.is_admin() {
+                return Err(AppError::Forbidden("Admin access required".to_string()));
+            }
+            Ok(AdminUser { user })
+        })
+    }
+}
@@ -1,4 +1,7 @@
+pub mod audit;
 pub mod auth;
+pub mod rate_limit;
+pub mod request_id;
 
 use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
 use actix_web::Error;
@@ -6,6 +9,7 @@ use futures::future::{ok, Ready};
 use futures::Future;
 use std::pin::Pin;
 
+#[derive(Default)]
 pub struct Auth;
 
 impl Auth {
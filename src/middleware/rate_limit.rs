@@ -0,0 +1,382 @@
+use crate::config::auth::JwtConfig;
+use crate::config::rate_limit::{Quota, RateLimitKey, RateLimitRule};
+use crate::utils::{token, AppError};
+use actix_web::dev::{Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::error::PayloadError;
+use actix_web::{web, Error, HttpMessage};
+use async_trait::async_trait;
+use futures::future::{ok, Ready};
+use futures::{Future, StreamExt};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// 单次取令牌的结果
+#[derive(Debug, Clone, Copy)]
+pub struct Decision {
+    pub allowed: bool,
+    pub retry_after: Duration,
+}
+
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    async fn acquire(&self, key: &str, quota: &Quota) -> Result<Decision, anyhow::Error>;
+}
+
+// 桶记录自己的配额，清理时不受当前请求规则的影响
+struct Bucket {
+    tokens: f64,
+    capacity: f64,
+    rate: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.updated_at = now;
+    }
+
+    fn is_full(&self, now: Instant) -> bool {
+        self.tokens + now.duration_since(self.updated_at).as_secs_f64() * self.rate >= self.capacity
+    }
+}
+
+// 进程内令牌桶，适合单实例部署
+#[derive(Default)]
+pub struct MemoryStore {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+const MEMORY_STORE_PRUNE_THRESHOLD: usize = 10_000;
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryStore {
+    async fn acquire(&self, key: &str, quota: &Quota) -> Result<Decision, anyhow::Error> {
+        let now = Instant::now();
+        let capacity = quota.limit as f64;
+        let rate = quota.refill_rate();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        // 清理已经补满的桶，避免内存无限增长
+        if buckets.len() > MEMORY_STORE_PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| !bucket.is_full(now));
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            capacity,
+            rate,
+            updated_at: now,
+        });
+        bucket.refill(now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(Decision {
+                allowed: true,
+                retry_after: Duration::ZERO,
+            })
+        } else {
+            Ok(Decision {
+                allowed: false,
+                retry_after: Duration::from_secs_f64((1.0 - bucket.tokens) / bucket.rate),
+            })
+        }
+    }
+}
+
+#[cfg(feature = "redis")]
+pub use self::redis_store::RedisStore;
+
+#[cfg(feature = "redis")]
+mod redis_store {
+    use super::{Decision, RateLimitStore};
+    use crate::config::rate_limit::Quota;
+    use async_trait::async_trait;
+    use redis::aio::ConnectionManager;
+    use redis::Script;
+    use std::time::Duration;
+
+    // 与内存实现相同的令牌桶算法，在 Redis 中原子执行，支持多实例共享
+    const TOKEN_BUCKET_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local rate = tonumber(ARGV[2])
+local now = tonumber(ARGV[3])
+local ttl = tonumber(ARGV[4])
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'ts')
+local tokens = tonumber(bucket[1]) or capacity
+local ts = tonumber(bucket[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - ts) * rate)
+local allowed = 0
+local wait = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+    allowed = 1
+else
+    wait = math.ceil((1 - tokens) / rate)
+end
+redis.call('HMSET', KEYS[1], 'tokens', tostring(tokens), 'ts', now)
+redis.call('PEXPIRE', KEYS[1], ttl)
+return {allowed, wait}
+"#;
+
+    pub struct RedisStore {
+        connection: ConnectionManager,
+        script: Script,
+    }
+
+    impl RedisStore {
+        pub async fn connect(url: &str) -> Result<Self, redis::RedisError> {
+            let client = redis::Client::open(url)?;
+            let connection = ConnectionManager::new(client).await?;
+            Ok(Self {
+                connection,
+                script: Script::new(TOKEN_BUCKET_SCRIPT),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl RateLimitStore for RedisStore {
+        async fn acquire(&self, key: &str, quota: &Quota) -> Result<Decision, anyhow::Error> {
+            let now_ms = chrono::Utc::now().timestamp_millis();
+            // 每毫秒补充的令牌数
+            let rate = quota.refill_rate() / 1000.0;
+            let ttl_ms = quota.period.as_millis() as u64;
+
+            let mut connection = self.connection.clone();
+            let (allowed, wait_ms): (i64, i64) = self
+                .script
+                .key(key)
+                .arg(quota.limit)
+                .arg(rate)
+                .arg(now_ms)
+                .arg(ttl_ms)
+                .invoke_async(&mut connection)
+                .await?;
+
+            Ok(Decision {
+                allowed: allowed == 1,
+                retry_after: Duration::from_millis(wait_ms.max(0) as u64),
+            })
+        }
+    }
+}
+
+pub struct RateLimit {
+    store: Arc<dyn RateLimitStore>,
+    rules: Arc<Vec<RateLimitRule>>,
+    trust_proxy: bool,
+    body_limit: usize,
+}
+
+impl RateLimit {
+    // body_limit 与 JSON 请求体限制一致，按邮箱限流时读取请求体不会超过它
+    pub fn new(
+        store: Arc<dyn RateLimitStore>,
+        rules: Arc<Vec<RateLimitRule>>,
+        trust_proxy: bool,
+        body_limit: usize,
+    ) -> Self {
+        RateLimit {
+            store,
+            rules,
+            trust_proxy,
+            body_limit,
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            store: self.store.clone(),
+            rules: self.rules.clone(),
+            trust_proxy: self.trust_proxy,
+            body_limit: self.body_limit,
+        })
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    store: Arc<dyn RateLimitStore>,
+    rules: Arc<Vec<RateLimitRule>>,
+    trust_proxy: bool,
+    body_limit: usize,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(
+        &self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let method = req.method().as_str().to_string();
+        let path = req.path().to_string();
+        let rules: Vec<RateLimitRule> = self
+            .rules
+            .iter()
+            .filter(|rule| rule.matches(&method, &path))
+            .cloned()
+            .collect();
+
+        if rules.is_empty() {
+            let fut = self.service.call(req);
+            return Box::pin(fut);
+        }
+
+        let service = self.service.clone();
+        let store = self.store.clone();
+        let trust_proxy = self.trust_proxy;
+        let body_limit = self.body_limit;
+
+        Box::pin(async move {
+            // 按邮箱限流需要读取请求体，读取后再放回去供处理函数使用
+            let email = if rules.iter().any(|rule| rule.key == RateLimitKey::Email) {
+                let body = read_body(&mut req, body_limit).await?;
+                let email = extract_email(&body);
+                req.set_payload(bytes_to_payload(body));
+                email
+            } else {
+                None
+            };
+
+            for rule in &rules {
+                let value = match rule.key {
+                    RateLimitKey::Ip => client_ip(&req, trust_proxy),
+                    RateLimitKey::Email => email.clone(),
+                    RateLimitKey::User => user_id(&req),
+                    RateLimitKey::ApiKey => api_key(&req),
+                };
+
+                // 取不到限流键时该规则不适用
+                let Some(value) = value else { continue };
+                let key = format!("rl:{}:{}:{}:{}", rule.method, rule.path, rule.key.as_str(), value);
+
+                match store.acquire(&key, &rule.quota).await {
+                    Ok(decision) if !decision.allowed => {
+                        log::warn!("Rate limit exceeded for {} {} by {}", method, path, rule.key.as_str());
+                        let retry_after = decision.retry_after.as_secs_f64().ceil().max(1.0) as u64;
+                        return Err(AppError::TooManyRequests(retry_after).into());
+                    }
+                    Ok(_) => {}
+                    // 限流存储不可用时放行，避免影响正常业务
+                    Err(e) => log::error!("Rate limit store error: {:?}", e),
+                }
+            }
+
+            service.call(req).await
+        })
+    }
+}
+
+async fn read_body(req: &mut ServiceRequest, limit: usize) -> Result<web::Bytes, AppError> {
+    let mut payload = req.take_payload();
+    let mut body = web::BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|e| AppError::BadRequest(e.to_string()))?;
+        if body.len() + chunk.len() > limit {
+            return Err(AppError::PayloadTooLarge(limit));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body.freeze())
+}
+
+fn bytes_to_payload(body: web::Bytes) -> Payload {
+    let stream = futures::stream::once(async move { Ok::<_, PayloadError>(body) });
+    Payload::Stream {
+        payload: Box::pin(stream),
+    }
+}
+
+fn extract_email(body: &[u8]) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_slice(body).ok()?;
+    let email = value.get("email")?.as_str()?.trim().to_lowercase();
+    if email.is_empty() {
+        None
+    } else {
+        Some(email)
+    }
+}
+
+fn client_ip(req: &ServiceRequest, trust_proxy: bool) -> Option<String> {
+    if trust_proxy {
+        req.connection_info().realip_remote_addr().map(|addr| addr.to_string())
+    } else {
+        req.peer_addr().map(|addr| addr.ip().to_string())
+    }
+}
+
+fn user_id(req: &ServiceRequest) -> Option<String> {
+    let jwt_config = req.app_data::<web::Data<JwtConfig>>()?;
+    let auth_str = req.headers().get("Authorization")?.to_str().ok()?;
+    let token = auth_str.strip_prefix("Bearer ")?;
+    jwt_config.decode(token, Utc::now()).ok().map(|claims| claims.sub)
+}
+
+fn api_key(req: &ServiceRequest) -> Option<String> {
+    req.headers()
+        .get("X-API-Key")
+        .and_then(|value| value.to_str().ok())
+        // 与 ApiKeyService 一样只使用摘要，限流键中不出现明文密钥
+        .map(token::digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn pruning_keeps_buckets_that_are_not_full_under_their_own_quota() {
+        let store = MemoryStore::new();
+        let slow = Quota::new(2, 3600);
+        let fast = Quota::new(1, 1);
+        assert!(store.acquire("slow", &slow).await.unwrap().allowed);
+
+        // 其余桶的容量比 slow 剩余的令牌还少，清理时不能按它们的配额判断 slow
+        for i in 0..MEMORY_STORE_PRUNE_THRESHOLD {
+            store.acquire(&format!("fast:{}", i), &fast).await.unwrap();
+        }
+        store.acquire("fast:last", &fast).await.unwrap();
+
+        assert!(store.acquire("slow", &slow).await.unwrap().allowed);
+        assert!(!store.acquire("slow", &slow).await.unwrap().allowed);
+    }
+}
@@ -0,0 +1,42 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::utils::validation::validate_not_blank;
+
+// 应用的 API key，只保存 key 的 SHA-256
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct ApiKey {
+    pub id: i64,
+    pub app_id: i64,
+    pub name: String,
+    pub prefix: String,  // key 的开头几位，用于区分不同的 key
+    #[serde(skip)]
+    pub key_hash: String,
+    pub created_by: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewApiKey {
+    pub app_id: i64,
+    pub name: String,
+    pub prefix: String,
+    pub key_hash: String,
+    pub created_by: i64,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreateApiKeyRequest {
+    #[validate(length(min = 1, max = 100), custom(function = validate_not_blank))]
+    pub name: String,
+}
+
+// key 只在创建时返回一次
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiKeyCreated {
+    pub api_key: ApiKey,
+    pub key: String,
+}
@@ -1,54 +1,143 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use chrono::{DateTime, Utc};
+use async_graphql::InputObject;
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+use crate::utils::validation::{validate_not_blank, IDENTIFIER_REGEX};
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct App {
     pub id: i64,
     pub name: String,
     pub description: String,
     pub identifier: String,  // 应用标识，用于唯一标识一个应用
+    pub logo_url: Option<String>,  // 邮件中显示的 logo
+    pub email_from: Option<String>,  // 该应用发送邮件使用的发件地址
+    pub feed_title: Option<String>,  // 订阅源标题，为空时使用应用名称
+    pub feed_description: Option<String>,
+    pub feed_link: Option<String>,  // 订阅源指向的网站地址
+    pub base_url: Option<String>,  // 文章发布所在的网站，生成站点地图时使用
+    pub require_two_factor: i16,  // 修改该应用是否需要两步验证登录
     pub creator_id: i64,
     pub created_at: DateTime<Utc>,
     pub updater_id: i64,
     pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,  // 移入回收站的时间，为空表示未删除
+    pub deleted_by: Option<i64>,
+    pub version: i64,  // 每次更新加一，用作 ETag
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize, Validate, ToSchema, InputObject)]
+#[graphql(name = "CreateAppInput")]
 pub struct CreateAppRequest {
+    #[validate(length(min = 1, max = 100), custom(function = validate_not_blank))]
     pub name: String,
+    #[validate(length(max = 2000))]
     pub description: String,
+    #[validate(length(min = 1, max = 50), regex(path = *IDENTIFIER_REGEX))]
     pub identifier: String,  // 应用标识
+    #[validate(url, length(max = 500))]
+    pub logo_url: Option<String>,
+    #[validate(email, length(max = 255))]
+    pub email_from: Option<String>,
+    #[validate(length(min = 1, max = 255), custom(function = validate_not_blank))]
+    pub feed_title: Option<String>,
+    #[validate(length(max = 2000))]
+    pub feed_description: Option<String>,
+    #[validate(url, length(max = 500))]
+    pub feed_link: Option<String>,
+    #[validate(url, length(max = 500))]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    #[graphql(default)]
+    pub require_two_factor: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema, InputObject)]
+#[graphql(name = "UpdateAppInput")]
 pub struct UpdateAppRequest {
+    #[validate(length(min = 1, max = 100), custom(function = validate_not_blank))]
     pub name: Option<String>,
+    #[validate(length(max = 2000))]
     pub description: Option<String>,
+    #[validate(url, length(max = 500))]
+    pub logo_url: Option<String>,
+    #[validate(email, length(max = 255))]
+    pub email_from: Option<String>,
+    #[validate(length(min = 1, max = 255), custom(function = validate_not_blank))]
+    pub feed_title: Option<String>,
+    #[validate(length(max = 2000))]
+    pub feed_description: Option<String>,
+    #[validate(url, length(max = 500))]
+    pub feed_link: Option<String>,
+    #[validate(url, length(max = 500))]
+    pub base_url: Option<String>,
+    pub require_two_factor: Option<bool>,
+    pub version: Option<i64>,  // 未发送 If-Match 时用于版本检查
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate, IntoParams)]
 pub struct AppQuery {
+    #[validate(length(max = 100))]
     pub keyword: Option<String>,
+    #[validate(length(max = 50))]
     pub identifier: Option<String>,  // 按应用标识搜索
     pub creator_id: Option<i64>,
+    #[validate(range(min = 1))]
     pub page: Option<i64>,
+    #[validate(range(min = 1, max = 100))]
     pub page_size: Option<i64>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct AppResponse {
     pub id: i64,
     pub name: String,
     pub description: String,
     pub identifier: String,
+    pub logo_url: Option<String>,
+    pub email_from: Option<String>,
+    pub feed_title: Option<String>,
+    pub feed_description: Option<String>,
+    pub feed_link: Option<String>,
+    pub base_url: Option<String>,
+    pub require_two_factor: bool,
     pub creator_id: i64,
     pub created_at: DateTime<Utc>,
     pub updater_id: i64,
     pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub deleted_by: Option<i64>,
+    pub version: i64,
 }
 
-#[derive(Debug, Serialize)]
+impl From<App> for AppResponse {
+    fn from(app: App) -> Self {
+        Self {
+            id: app.id,
+            name: app.name,
+            description: app.description,
+            identifier: app.identifier,
+            logo_url: app.logo_url,
+            email_from: app.email_from,
+            feed_title: app.feed_title,
+            feed_description: app.feed_description,
+            feed_link: app.feed_link,
+            base_url: app.base_url,
+            require_two_factor: app.require_two_factor != 0,
+            creator_id: app.creator_id,
+            created_at: app.created_at,
+            updater_id: app.updater_id,
+            updated_at: app.updated_at,
+            deleted_at: app.deleted_at,
+            deleted_by: app.deleted_by,
+            version: app.version,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct AppListResponse {
     pub apps: Vec<AppResponse>,
     pub total: i64,
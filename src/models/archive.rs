@@ -0,0 +1,189 @@
+use crate::utils::validation::IDENTIFIER_REGEX;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+// 导出文件的格式标识和版本，结构不兼容地变化时增加版本
+pub const ARCHIVE_FORMAT: &str = "rscms-archive";
+pub const ARCHIVE_VERSION: u32 = 1;
+
+// 一个应用的完整导出，文章 id 为导出时的 id，导入时重新分配
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Archive {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub app: ArchivedApp,
+    // 文章使用的分类及文章数
+    #[serde(default)]
+    pub categories: Vec<ArchivedCategory>,
+    // 引用的图片等外部文件，只保存地址
+    #[serde(default)]
+    pub media: Vec<MediaReference>,
+    #[serde(default)]
+    pub articles: Vec<ArchivedArticle>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ArchivedApp {
+    pub identifier: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub logo_url: Option<String>,
+    pub email_from: Option<String>,
+    pub feed_title: Option<String>,
+    pub feed_description: Option<String>,
+    pub feed_link: Option<String>,
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub require_two_factor: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ArchivedCategory {
+    pub name: String,
+    pub articles: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct MediaReference {
+    pub url: String,
+    // 引用它的文章，应用图标为空
+    pub article_id: Option<i64>,
+    // logo_url、og_image_url、content 或 attachment
+    pub field: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ArchivedArticle {
+    pub id: i64,
+    pub title: String,
+    pub content: String,
+    pub status: i16,
+    pub category: Option<String>,
+    pub locale: Option<String>,
+    pub translation_of: Option<i64>,  // 原文在导出文件中的 id
+    pub meta_title: Option<String>,
+    pub meta_description: Option<String>,
+    pub canonical_url: Option<String>,
+    pub og_image_url: Option<String>,
+    #[serde(default)]
+    pub noindex: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// NDJSON 导出的每一行，第一行为 archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ArchiveRecord {
+    Archive {
+        format: String,
+        version: u32,
+        exported_at: DateTime<Utc>,
+    },
+    App(ArchivedApp),
+    Category(ArchivedCategory),
+    Media(MediaReference),
+    Article(ArchivedArticle),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveFormat {
+    #[default]
+    Json,
+    // 每行一个 JSON 对象，便于流式处理大量文章
+    Ndjson,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, IntoParams)]
+pub struct ExportQuery {
+    pub format: Option<ArchiveFormat>,
+}
+
+// 导入内容的来源
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportSource {
+    // 本系统导出的 JSON 或 NDJSON
+    #[default]
+    Archive,
+    // WordPress 导出的 WXR 文件
+    Wxr,
+    // 带 front matter 的 Markdown 文件
+    Markdown,
+}
+
+// 应用标识或文章（同一应用中标题和语言相同）已存在时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictStrategy {
+    // 保留已有的内容
+    #[default]
+    Skip,
+    // 用导入的内容覆盖
+    Overwrite,
+    // 改名后作为新的应用或文章导入
+    Rename,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, IntoParams)]
+pub struct ImportQuery {
+    pub source: Option<ImportSource>,
+    pub strategy: Option<ConflictStrategy>,
+    // 为 true 时只返回将要进行的修改
+    #[serde(default)]
+    pub dry_run: bool,
+    // 导入到该标识的应用，默认使用导出文件中的标识；导入 Markdown 时必填
+    #[validate(length(min = 1, max = 50), regex(path = *IDENTIFIER_REGEX))]
+    pub identifier: Option<String>,
+}
+
+// Markdown 导入的请求体，path 为文件在目录中的相对路径
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MarkdownDirectory {
+    pub files: Vec<MarkdownFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MarkdownFile {
+    pub path: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportAction {
+    Create,
+    Overwrite,
+    Skip,
+    Rename,
+}
+
+// 一个应用或文章的导入结果；试运行时为将要进行的操作，新建的对象没有 id
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ImportItem {
+    pub action: ImportAction,
+    // 导入后的应用标识或文章标题
+    pub name: String,
+    // 文章在导入文件中的 id
+    pub source_id: Option<i64>,
+    pub id: Option<i64>,
+    // 跳过的原因
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub app: ImportItem,
+    pub articles: Vec<ImportItem>,
+    // 需要另外复制的外部文件
+    pub media: Vec<MediaReference>,
+    pub warnings: Vec<String>,
+    // 导入中途出错的原因；已导入的应用和文章不会回滚
+    pub error: Option<String>,
+}
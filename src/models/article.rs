@@ -1,28 +1,81 @@
 use serde::{Serialize, Deserialize};
 use sqlx::FromRow;
 use chrono::{DateTime, Utc};
+use async_graphql::InputObject;
+use utoipa::ToSchema;
+use validator::Validate;
+use crate::utils::validation::{validate_article_status, validate_not_blank, validate_text_column};
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct Article {
     pub id: i64,
     pub title: String,
     pub content: String,
     pub author_id: i64,
-    pub status: i8,
+    pub app_id: Option<i64>,  // 所属应用
+    pub category: Option<String>,  // 分类，订阅源可以按分类过滤
+    pub locale: Option<String>,  // 文章的语言，用于站点地图中的多语言链接
+    pub translation_of: Option<i64>,  // 原文的 id，原文本身为空
+    pub meta_title: Option<String>,  // 为空时使用标题
+    pub meta_description: Option<String>,
+    pub canonical_url: Option<String>,  // 为空时使用应用 base_url 下的地址
+    pub og_image_url: Option<String>,
+    pub noindex: i16,  // 为 1 时不希望被搜索引擎收录，不出现在站点地图中
+    pub status: i16,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,  // 移入回收站的时间，为空表示未删除
+    pub deleted_by: Option<i64>,
+    pub version: i64,  // 每次更新加一，用作 ETag
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize, Validate, ToSchema, InputObject)]
+#[graphql(name = "CreateArticleInput")]
 pub struct CreateArticleRequest {
+    #[validate(length(min = 1, max = 255), custom(function = validate_not_blank))]
     pub title: String,
+    #[validate(length(min = 1), custom(function = validate_text_column))]
     pub content: String,
-    pub status: Option<i8>,
+    #[validate(custom(function = validate_article_status))]
+    pub status: Option<i16>,
+    pub app_id: Option<i64>,  // 所属应用，必须存在且不在回收站中
+    #[validate(length(min = 1, max = 50), custom(function = validate_not_blank))]
+    pub category: Option<String>,
+    #[validate(length(min = 2, max = 16))]
+    pub locale: Option<String>,
+    pub translation_of: Option<i64>,  // 作为该文章的译文，必须属于同一个应用
+    #[validate(length(min = 1, max = 255), custom(function = validate_not_blank))]
+    pub meta_title: Option<String>,
+    #[validate(length(max = 500))]
+    pub meta_description: Option<String>,
+    #[validate(url, length(max = 500))]
+    pub canonical_url: Option<String>,
+    #[validate(url, length(max = 500))]
+    pub og_image_url: Option<String>,
+    pub noindex: Option<bool>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize, Validate, ToSchema, InputObject)]
+#[graphql(name = "UpdateArticleInput")]
 pub struct UpdateArticleRequest {
+    #[validate(length(min = 1, max = 255), custom(function = validate_not_blank))]
     pub title: Option<String>,
+    #[validate(length(min = 1), custom(function = validate_text_column))]
     pub content: Option<String>,
-    pub status: Option<i8>,
+    #[validate(custom(function = validate_article_status))]
+    pub status: Option<i16>,
+    #[validate(length(min = 1, max = 50), custom(function = validate_not_blank))]
+    pub category: Option<String>,
+    #[validate(length(min = 2, max = 16))]
+    pub locale: Option<String>,
+    #[validate(length(min = 1, max = 255), custom(function = validate_not_blank))]
+    pub meta_title: Option<String>,
+    #[validate(length(max = 500))]
+    pub meta_description: Option<String>,
+    #[validate(url, length(max = 500))]
+    pub canonical_url: Option<String>,
+    #[validate(url, length(max = 500))]
+    pub og_image_url: Option<String>,
+    pub noindex: Option<bool>,
+    pub version: Option<i64>,  // 未发送 If-Match 时用于版本检查
 }
@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use chrono::{DateTime, Utc};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+// 操作者类型
+pub const ACTOR_USER: &str = "user";
+pub const ACTOR_API_KEY: &str = "api_key";
+pub const ACTOR_ANONYMOUS: &str = "anonymous";
+
+// 操作对象类型
+pub const TARGET_APP: &str = "app";
+pub const TARGET_ARTICLE: &str = "article";
+pub const TARGET_USER: &str = "user";
+pub const TARGET_API_KEY: &str = "api_key";
+pub const TARGET_PREVIEW_LINK: &str = "preview_link";
+
+// 操作名称为 对象.动作
+pub const APP_CREATE: &str = "app.create";
+pub const APP_UPDATE: &str = "app.update";
+pub const APP_DELETE: &str = "app.delete";
+pub const APP_RESTORE: &str = "app.restore";
+pub const APP_IMPORT: &str = "app.import";
+pub const ARTICLE_CREATE: &str = "article.create";
+pub const ARTICLE_UPDATE: &str = "article.update";
+pub const ARTICLE_DELETE: &str = "article.delete";
+pub const ARTICLE_RESTORE: &str = "article.restore";
+pub const ARTICLE_TAKE_LOCK: &str = "article.take_lock";
+pub const API_KEY_CREATE: &str = "api_key.create";
+pub const API_KEY_REVOKE: &str = "api_key.revoke";
+pub const PREVIEW_LINK_CREATE: &str = "preview_link.create";
+pub const PREVIEW_LINK_REVOKE: &str = "preview_link.revoke";
+pub const USER_REGISTER: &str = "user.register";
+pub const USER_LOGIN: &str = "user.login";
+pub const USER_SET_PASSWORD: &str = "user.set_password";
+pub const USER_RESET_PASSWORD: &str = "user.reset_password";
+pub const USER_SETUP_TOTP: &str = "user.setup_totp";
+pub const USER_ENABLE_TOTP: &str = "user.enable_totp";
+pub const USER_DISABLE_TOTP: &str = "user.disable_totp";
+pub const USER_REGENERATE_RECOVERY_CODES: &str = "user.regenerate_recovery_codes";
+
+// 审计日志只追加，不修改
+#[derive(Debug, Clone, FromRow)]
+pub struct AuditLog {
+    pub id: i64,
+    pub actor_type: String,  // user、api_key 或 anonymous
+    pub actor_id: Option<i64>,
+    pub api_key: Option<String>,  // API key 的 SHA-256 前缀，不保存 key 本身
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<i64>,
+    pub app_id: Option<i64>,
+    pub before_data: Option<String>,  // JSON，只包含变化的字段
+    pub after_data: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewAuditLog {
+    pub actor_type: String,
+    pub actor_id: Option<i64>,
+    pub api_key: Option<String>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<i64>,
+    pub app_id: Option<i64>,
+    pub before_data: Option<String>,
+    pub after_data: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuditLogResponse {
+    pub id: i64,
+    pub actor_type: String,
+    pub actor_id: Option<i64>,
+    pub api_key: Option<String>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<i64>,
+    pub app_id: Option<i64>,
+    #[schema(value_type = Option<Object>)]
+    pub before: Option<Value>,
+    #[schema(value_type = Option<Object>)]
+    pub after: Option<Value>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<AuditLog> for AuditLogResponse {
+    fn from(log: AuditLog) -> Self {
+        let parse = |data: Option<String>| data.and_then(|data| serde_json::from_str(&data).ok());
+        Self {
+            id: log.id,
+            actor_type: log.actor_type,
+            actor_id: log.actor_id,
+            api_key: log.api_key,
+            action: log.action,
+            target_type: log.target_type,
+            target_id: log.target_id,
+            app_id: log.app_id,
+            before: parse(log.before_data),
+            after: parse(log.after_data),
+            ip: log.ip,
+            user_agent: log.user_agent,
+            request_id: log.request_id,
+            created_at: log.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, IntoParams)]
+pub struct AuditQuery {
+    pub actor_id: Option<i64>,
+    #[validate(length(max = 50))]
+    pub action: Option<String>,
+    #[validate(length(max = 20))]
+    pub target_type: Option<String>,
+    pub target_id: Option<i64>,
+    pub app_id: Option<i64>,
+    // 时间范围，RFC 3339
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    #[validate(range(min = 1))]
+    pub page: Option<i64>,
+    #[validate(range(min = 1, max = 100))]
+    pub page_size: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuditListResponse {
+    pub logs: Vec<AuditLogResponse>,
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+}
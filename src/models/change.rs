@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use crate::models::audit::{TARGET_APP, TARGET_ARTICLE};
+
+// 事件类型为 对象.动作，对象类型沿用审计日志的 app 和 article
+pub const APP_CREATED: &str = "app.created";
+pub const APP_UPDATED: &str = "app.updated";
+pub const APP_DELETED: &str = "app.deleted";
+pub const APP_RESTORED: &str = "app.restored";
+pub const ARTICLE_CREATED: &str = "article.created";
+pub const ARTICLE_UPDATED: &str = "article.updated";
+pub const ARTICLE_PUBLISHED: &str = "article.published";
+pub const ARTICLE_DELETED: &str = "article.deleted";
+pub const ARTICLE_RESTORED: &str = "article.restored";
+
+// 应用的变更事件，id 单调递增，客户端用它续传
+#[derive(Debug, Clone, PartialEq, Serialize, FromRow, ToSchema)]
+pub struct ChangeEvent {
+    pub id: i64,
+    pub app_id: i64,
+    pub event_type: String,
+    pub target_type: String,
+    pub target_id: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewChangeEvent {
+    pub app_id: i64,
+    pub event_type: String,
+    pub target_type: String,
+    pub target_id: i64,
+}
+
+impl NewChangeEvent {
+    pub fn app(app_id: i64, event_type: &str) -> Self {
+        Self {
+            app_id,
+            event_type: event_type.to_string(),
+            target_type: TARGET_APP.to_string(),
+            target_id: app_id,
+        }
+    }
+
+    pub fn article(app_id: i64, article_id: i64, event_type: &str) -> Self {
+        Self {
+            app_id,
+            event_type: event_type.to_string(),
+            target_type: TARGET_ARTICLE.to_string(),
+            target_id: article_id,
+        }
+    }
+}
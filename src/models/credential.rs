@@ -0,0 +1,112 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use validator::Validate;
+
+// user_tokens 的用途
+pub const TOKEN_PASSWORD_RESET: &str = "password_reset";
+pub const TOKEN_TWO_FACTOR: &str = "two_factor";
+// 邮件验证码或登录链接通过后等待第二个因素
+pub const TOKEN_TWO_FACTOR_EMAIL: &str = "two_factor_email";
+// 单点登录通过后等待本地账号的第二个因素
+pub const TOKEN_TWO_FACTOR_OIDC: &str = "two_factor_oidc";
+
+// 一次性 token，只保存 SHA-256
+#[derive(Debug, Clone, FromRow)]
+pub struct UserToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub purpose: String,
+    pub token_hash: String,
+    pub attempts: i32,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewUserToken {
+    pub user_id: i64,
+    pub purpose: String,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct PasswordLoginRequest {
+    #[validate(email, length(max = 255))]
+    pub email: String,
+    #[validate(length(min = 1, max = 128))]
+    pub password: String,
+}
+
+// 密码正确但需要第二个因素时返回
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TwoFactorChallengeResponse {
+    pub two_factor_token: String,
+    pub expires_in: i64,  // 秒
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct TwoFactorLoginRequest {
+    #[validate(length(min = 1, max = 128))]
+    pub two_factor_token: String,
+    // TOTP 验证码或恢复码
+    #[validate(length(min = 6, max = 32))]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct SetPasswordRequest {
+    // 已经设置过密码时必填
+    #[validate(length(min = 1, max = 128))]
+    pub current_password: Option<String>,
+    #[validate(length(max = 128))]
+    pub new_password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct ForgotPasswordRequest {
+    #[validate(email, length(max = 255))]
+    pub email: String,
+    // 邮件使用该应用的品牌
+    #[validate(length(max = 50))]
+    pub app: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct ResetPasswordRequest {
+    #[validate(length(min = 1, max = 128))]
+    pub token: String,
+    #[validate(length(max = 128))]
+    pub new_password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct TotpCodeRequest {
+    // TOTP 验证码，关闭两步验证和重新生成恢复码时也可以使用恢复码
+    #[validate(length(min = 6, max = 32))]
+    pub code: String,
+}
+
+// otpauth_uri 可以直接生成二维码供验证器应用扫描
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TotpSetupResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+// 恢复码只在生成时返回一次
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TwoFactorStatusResponse {
+    pub has_password: bool,
+    pub totp_enabled: bool,
+    pub recovery_codes_remaining: i64,
+}
+
@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+// 文章的编辑锁，只是提示，不阻止保存
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct EditLock {
+    pub article_id: i64,
+    pub user_id: i64,
+    pub acquired_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+// 获得锁时返回，token 用于心跳续期和释放，只有持有者知道
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct EditLockGrant {
+    pub lock: EditLock,
+    pub token: String,
+}
+
+// 正在打开这篇文章的用户
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct Editor {
+    pub user_id: i64,
+    pub username: String,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct EditLockStatus {
+    pub lock: Option<EditLock>,
+    pub editors: Vec<Editor>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LockChange {
+    Acquired,
+    Released,
+    Taken,
+    Expired,
+}
+
+// 通过 WebSocket 推送给编辑者的事件
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EditEvent {
+    // 连接后首先收到的完整状态
+    State { lock: Option<EditLock>, editors: Vec<Editor> },
+    Presence { editors: Vec<Editor> },
+    Lock { lock: Option<EditLock>, change: LockChange },
+}
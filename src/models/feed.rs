@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+// 订阅源中文章内容的输出方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedMode {
+    #[default]
+    Full,
+    // 只输出截取的摘要
+    Summary,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, IntoParams)]
+pub struct FeedQuery {
+    // 只包含该分类的文章
+    #[validate(length(min = 1, max = 50))]
+    pub category: Option<String>,
+    pub mode: Option<FeedMode>,
+}
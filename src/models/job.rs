@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use chrono::{DateTime, Utc};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+// 任务状态
+pub const JOB_PENDING: &str = "pending";
+pub const JOB_RUNNING: &str = "running";
+pub const JOB_COMPLETED: &str = "completed";
+pub const JOB_DEAD: &str = "dead";
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct JobRecord {
+    pub id: i64,
+    pub queue: String,
+    pub kind: String,
+    pub payload: String,  // JSON
+    pub status: String,  // pending、running、completed 或 dead
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub unique_key: Option<String>,  // 任务结束后清空
+    pub run_at: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,  // 运行中的租约，过期后可被重新领取
+    pub last_error: Option<String>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewJob {
+    pub queue: String,
+    pub kind: String,
+    pub payload: String,
+    pub max_attempts: i32,
+    pub unique_key: Option<String>,
+    pub run_at: DateTime<Utc>,
+}
+
+// 每个队列各状态的任务数
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct JobCount {
+    pub queue: String,
+    pub status: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RecurringJobInfo {
+    pub name: String,
+    pub kind: String,
+    pub schedule: String,
+    pub next_run_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct JobStatsResponse {
+    pub counts: Vec<JobCount>,
+    pub recurring: Vec<RecurringJobInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, IntoParams)]
+pub struct JobQuery {
+    #[validate(length(max = 20))]
+    pub status: Option<String>,
+    #[validate(length(max = 50))]
+    pub queue: Option<String>,
+    #[validate(length(max = 100))]
+    pub kind: Option<String>,
+    #[validate(range(min = 1))]
+    pub page: Option<i64>,
+    #[validate(range(min = 1, max = 100))]
+    pub page_size: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct JobListResponse {
+    pub jobs: Vec<JobRecord>,
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+}
@@ -0,0 +1,39 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+// 只保存 token 和设备标识的 HMAC，数据库泄露也无法还原出链接
+#[derive(Debug, Clone, FromRow)]
+pub struct MagicLink {
+    pub id: i64,
+    pub user_id: i64,
+    pub token_hash: String,
+    pub device_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewMagicLink {
+    pub user_id: i64,
+    pub token_hash: String,
+    pub device_hash: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct MagicLinkRequest {
+    #[validate(email, length(max = 255))]
+    pub email: String,
+    // 邮件使用该应用的品牌
+    #[validate(length(max = 50))]
+    pub app: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct MagicLinkQuery {
+    pub token: String,
+}
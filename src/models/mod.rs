@@ -1,42 +1,94 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use chrono::{DateTime, Utc};
+use utoipa::ToSchema;
+use validator::Validate;
 
 pub mod article;
 pub mod app;
+pub mod api_key;
+pub mod archive;
+pub mod audit;
+pub mod change;
+pub mod credential;
+pub mod edit_lock;
+pub mod feed;
+pub mod job;
+pub mod magic_link;
+pub mod oidc;
+pub mod outbox;
+pub mod preview;
 pub use article::Article;
 pub use app::*;
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct User {
     pub id: i64,
     pub username: String,
     pub email: String,
-    pub email_verified: i8,
+    pub email_verified: i16,
     pub verification_code: Option<String>,
     pub verification_code_expires_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    pub verification_attempts: i32,  // 当前验证码的错误尝试次数
+    pub locale: String,  // 邮件使用的语言
+    pub role: String,  // user 或 admin
+    #[serde(skip)]
+    pub password_hash: Option<String>,  // argon2，未设置密码时为空
+    #[serde(skip)]
+    pub totp_secret: Option<String>,  // base32，启用前为待确认的密钥
+    pub totp_enabled: i16,
+    #[serde(skip)]
+    pub totp_last_step: Option<i64>,  // 最近一次使用的 TOTP 时间步，防止验证码重放
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+// 用户角色
+pub const ROLE_USER: &str = "user";
+pub const ROLE_ADMIN: &str = "admin";
+
+impl User {
+    pub fn is_admin(&self) -> bool {
+        self.role == ROLE_ADMIN
+    }
+
+    pub fn has_totp(&self) -> bool {
+        self.totp_enabled != 0 && self.totp_secret.is_some()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
 pub struct LoginRequest {
+    #[validate(email, length(max = 255))]
     pub email: String,
+    #[validate(length(equal = 6))]
     pub verification_code: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
 pub struct RegisterRequest {
+    #[validate(length(min = 2, max = 50), custom(function = crate::utils::validation::validate_not_blank))]
     pub username: String,
+    #[validate(email, length(max = 255))]
     pub email: String,
+    // 未提供时使用 Accept-Language
+    #[validate(length(min = 2, max = 16))]
+    pub locale: Option<String>,
+    // 邮件使用该应用的品牌
+    #[validate(length(max = 50))]
+    pub app: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
 pub struct GetVerificationCodeRequest {
+    #[validate(email, length(max = 255))]
     pub email: String,
+    #[validate(length(max = 50))]
+    pub app: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct UserResponse {
     pub id: i64,
     pub username: String,
@@ -46,13 +98,13 @@ pub struct UserResponse {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct AuthResponse {
     pub token: String,
     pub user: User,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct MessageResponse {
     pub message: String,
 }
@@ -0,0 +1,85 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+
+// 发起登录时保存，回调时取出并删除；state 只保存 SHA-256
+#[derive(Debug, Clone, FromRow)]
+pub struct OidcState {
+    pub id: i64,
+    pub provider: String,
+    pub state_hash: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewOidcState {
+    pub provider: String,
+    pub state_hash: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+// 身份提供方的账号，按 (provider, subject) 唯一
+#[derive(Debug, Clone, FromRow)]
+pub struct UserIdentity {
+    pub id: i64,
+    pub user_id: i64,
+    pub provider: String,
+    pub subject: String,
+    pub email: String,
+    pub created_at: DateTime<Utc>,
+    pub last_login_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewUserIdentity {
+    pub provider: String,
+    pub subject: String,
+    pub email: String,
+}
+
+// 首次单点登录时自动创建的用户
+#[derive(Debug, Clone)]
+pub struct NewFederatedUser {
+    pub username: String,
+    pub email: String,
+    pub locale: String,
+    pub role: String,
+}
+
+// 校验 ID token 后得到的身份
+#[derive(Debug, Clone)]
+pub struct FederatedIdentity {
+    pub identity: NewUserIdentity,
+    pub email_verified: bool,
+    pub username: Option<String>,
+    pub locale: Option<String>,
+    // 由组映射得到的角色，未配置映射时为空
+    pub role: Option<String>,
+    // 没有对应用户时是否自动创建
+    pub provision: bool,
+    // 身份提供方是否使用了多因素认证
+    pub multi_factor: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OidcProviderInfo {
+    pub name: String,
+    pub display_name: String,
+    // 浏览器打开该地址开始登录
+    pub authorize_url: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    // 身份提供方拒绝登录时返回
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
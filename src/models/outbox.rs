@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use chrono::{DateTime, Utc};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+// 发件箱状态
+pub const OUTBOX_PENDING: &str = "pending";
+pub const OUTBOX_SENDING: &str = "sending";
+pub const OUTBOX_SENT: &str = "sent";
+pub const OUTBOX_DEAD: &str = "dead";
+
+// 渲染好的邮件
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct EmailMessage {
+    pub template: String,
+    pub recipient: String,
+    pub from_name: String,
+    pub from_email: String,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct OutboxEmail {
+    pub id: i64,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub message: EmailMessage,
+    pub status: String,  // pending、sending、sent 或 dead
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,  // 发送中的租约，过期后可被重新领取
+    pub sent_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, IntoParams)]
+pub struct OutboxQuery {
+    #[validate(length(max = 20))]
+    pub status: Option<String>,
+    #[validate(range(min = 1))]
+    pub page: Option<i64>,
+    #[validate(range(min = 1, max = 100))]
+    pub page_size: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OutboxListResponse {
+    pub messages: Vec<OutboxEmail>,
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+}
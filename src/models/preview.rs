@@ -0,0 +1,92 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use super::Article;
+
+// 草稿的预览链接，只保存 token 的 HMAC
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct PreviewLink {
+    pub id: i64,
+    pub article_id: i64,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub version: Option<i64>,  // 固定的版本，预览显示创建链接时保存的内容
+    #[sqlx(flatten)]
+    #[serde(skip)]
+    pub snapshot: PreviewSnapshot,
+    pub created_by: i64,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// 固定版本的链接保存的文章内容，未固定的链接全部为空
+#[derive(Debug, Clone, Default, FromRow)]
+pub struct PreviewSnapshot {
+    pub title: Option<String>,
+    pub content: Option<String>,
+    pub meta_title: Option<String>,
+    pub meta_description: Option<String>,
+    pub canonical_url: Option<String>,
+    pub og_image_url: Option<String>,
+    pub noindex: Option<i16>,
+}
+
+impl PreviewSnapshot {
+    pub fn of(article: &Article) -> Self {
+        Self {
+            title: Some(article.title.clone()),
+            content: Some(article.content.clone()),
+            meta_title: article.meta_title.clone(),
+            meta_description: article.meta_description.clone(),
+            canonical_url: article.canonical_url.clone(),
+            og_image_url: article.og_image_url.clone(),
+            noindex: Some(article.noindex),
+        }
+    }
+
+    // 用保存的内容替换文章当前的内容
+    pub fn apply(self, version: i64, article: Article) -> Article {
+        Article {
+            title: self.title.unwrap_or(article.title),
+            content: self.content.unwrap_or(article.content),
+            meta_title: self.meta_title,
+            meta_description: self.meta_description,
+            canonical_url: self.canonical_url,
+            og_image_url: self.og_image_url,
+            noindex: self.noindex.unwrap_or(article.noindex),
+            version,
+            ..article
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NewPreviewLink {
+    pub article_id: i64,
+    pub token_hash: String,
+    pub version: Option<i64>,
+    pub snapshot: PreviewSnapshot,
+    pub created_by: i64,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Deserialize, Validate, ToSchema)]
+pub struct CreatePreviewLinkRequest {
+    // 有效期秒数，未指定时使用默认值，超过上限时取上限
+    #[validate(range(min = 60))]
+    pub expires_in: Option<i64>,
+    // 固定为文章的这个版本，必须是当前版本；之后的修改不会出现在预览中
+    pub version: Option<i64>,
+}
+
+// token 只在创建时返回一次
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PreviewLinkCreated {
+    pub preview: PreviewLink,
+    pub token: String,
+    pub url: String,
+}
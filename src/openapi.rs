@@ -0,0 +1,335 @@
+use actix_web::{HttpResponse, Responder};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use crate::handlers;
+use crate::models;
+use crate::utils::email_template::RenderedEmail;
+use crate::utils::{ErrorBody, ErrorResponse, FieldError};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "RSCMS API", description = "Rust Content Management System"),
+    paths(
+        handlers::health_check,
+        handlers::auth::register,
+        handlers::auth::login,
+        handlers::auth::get_verification_code,
+        handlers::auth::request_magic_link,
+        handlers::auth::magic_link_login,
+        handlers::auth::password_login,
+        handlers::auth::two_factor_login,
+        handlers::auth::set_password,
+        handlers::auth::forgot_password,
+        handlers::auth::reset_password,
+        handlers::auth::two_factor_status,
+        handlers::auth::setup_totp,
+        handlers::auth::enable_totp,
+        handlers::auth::disable_totp,
+        handlers::auth::regenerate_recovery_codes,
+        handlers::auth::oidc_providers,
+        handlers::auth::oidc_authorize,
+        handlers::auth::oidc_callback,
+        handlers::auth::jwks,
+        handlers::auth::me,
+        handlers::admin::list_email_templates,
+        handlers::admin::preview_email_template,
+        handlers::admin::list_outbox,
+        handlers::admin::get_outbox_email,
+        handlers::admin::retry_outbox_email,
+        handlers::admin::list_jobs,
+        handlers::admin::job_stats,
+        handlers::admin::get_job,
+        handlers::admin::retry_job,
+        handlers::admin::list_audit_logs,
+        handlers::app::create_app,
+        handlers::app::list_apps,
+        handlers::app::get_app,
+        handlers::app::update_app,
+        handlers::app::delete_app,
+        handlers::app::list_trashed_apps,
+        handlers::app::restore_app,
+        handlers::archive::export_app,
+        handlers::archive::import_app,
+        handlers::api_key::create_api_key,
+        handlers::api_key::list_api_keys,
+        handlers::api_key::revoke_api_key,
+        handlers::change_feed::change_stream,
+        handlers::change_feed::change_socket,
+        handlers::article::create_article,
+        handlers::article::get_article,
+        handlers::article::list_articles,
+        handlers::article::update_article,
+        handlers::article::delete_article,
+        handlers::article::list_trashed_articles,
+        handlers::article::restore_article,
+        handlers::edit_lock::get_edit_lock,
+        handlers::edit_lock::acquire_edit_lock,
+        handlers::edit_lock::release_edit_lock,
+        handlers::edit_lock::renew_edit_lock,
+        handlers::edit_lock::take_edit_lock,
+        handlers::edit_lock::article_presence,
+        handlers::preview::create_preview_link,
+        handlers::preview::list_preview_links,
+        handlers::preview::revoke_preview_link,
+        handlers::preview::open_preview,
+        handlers::feed::rss_feed,
+        handlers::feed::atom_feed,
+        handlers::feed::json_feed,
+        handlers::sitemap::sitemap,
+        handlers::sitemap::sitemap_page,
+    ),
+    components(schemas(
+        models::User,
+        models::LoginRequest,
+        models::RegisterRequest,
+        models::GetVerificationCodeRequest,
+        models::magic_link::MagicLinkRequest,
+        models::credential::PasswordLoginRequest,
+        models::credential::TwoFactorChallengeResponse,
+        models::credential::TwoFactorLoginRequest,
+        models::credential::SetPasswordRequest,
+        models::credential::ForgotPasswordRequest,
+        models::credential::ResetPasswordRequest,
+        models::credential::TotpCodeRequest,
+        models::credential::TotpSetupResponse,
+        models::credential::RecoveryCodesResponse,
+        models::credential::TwoFactorStatusResponse,
+        models::oidc::OidcProviderInfo,
+        models::AuthResponse,
+        models::MessageResponse,
+        models::CreateAppRequest,
+        models::UpdateAppRequest,
+        models::AppResponse,
+        models::AppListResponse,
+        models::Article,
+        models::article::CreateArticleRequest,
+        models::article::UpdateArticleRequest,
+        models::edit_lock::EditLock,
+        models::edit_lock::EditLockGrant,
+        models::edit_lock::EditLockStatus,
+        models::edit_lock::Editor,
+        models::edit_lock::LockChange,
+        models::edit_lock::EditEvent,
+        models::api_key::ApiKey,
+        models::api_key::CreateApiKeyRequest,
+        models::api_key::ApiKeyCreated,
+        models::preview::PreviewLink,
+        models::preview::CreatePreviewLinkRequest,
+        models::preview::PreviewLinkCreated,
+        models::feed::FeedMode,
+        models::archive::Archive,
+        models::archive::ArchivedApp,
+        models::archive::ArchivedCategory,
+        models::archive::ArchivedArticle,
+        models::archive::MediaReference,
+        models::archive::ArchiveFormat,
+        models::archive::ImportSource,
+        models::archive::ConflictStrategy,
+        models::archive::MarkdownDirectory,
+        models::archive::MarkdownFile,
+        models::archive::ImportAction,
+        models::archive::ImportItem,
+        models::archive::ImportReport,
+        models::change::ChangeEvent,
+        handlers::admin::EmailTemplateList,
+        RenderedEmail,
+        models::outbox::EmailMessage,
+        models::outbox::OutboxEmail,
+        models::outbox::OutboxListResponse,
+        models::job::JobRecord,
+        models::job::JobCount,
+        models::job::RecurringJobInfo,
+        models::job::JobStatsResponse,
+        models::job::JobListResponse,
+        models::audit::AuditLogResponse,
+        models::audit::AuditListResponse,
+        ErrorResponse,
+        ErrorBody,
+        FieldError,
+    )),
+    modifiers(&SecurityAddon),
+    tags(
+        (name = "auth", description = "Registration and login"),
+        (name = "apps", description = "App management"),
+        (name = "articles", description = "Article management"),
+        (name = "feeds", description = "RSS, Atom and JSON Feed syndication"),
+        (name = "sitemaps", description = "XML sitemaps for search engines"),
+        (name = "admin", description = "Administration"),
+        (name = "system", description = "Service status"),
+    )
+)]
+pub struct ApiDoc;
+
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+        components.add_security_scheme("api_key", SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-API-Key"))));
+    }
+}
+
+pub async fn openapi_json() -> impl Responder {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+// Redoc 文档页面，脚本固定到具体版本，升级时需要一并确认页面仍然可用
+const DOCS_HTML: &str = r#"<!DOCTYPE html>
+<html>
+  <head>
+    <title>RSCMS API</title>
+    <meta charset="utf-8"/>
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+  </head>
+  <body>
+    <redoc spec-url="/api/openapi.json"></redoc>
+    <script src="https://cdn.jsdelivr.net/npm/redoc@2.1.5/bundles/redoc.standalone.js" crossorigin="anonymous"
+      referrerpolicy="no-referrer"></script>
+  </body>
+</html>
+"#;
+
+pub async fn docs() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(DOCS_HTML)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes;
+    use crate::utils::AppError;
+    use actix_web::http::{Method, StatusCode};
+    use actix_web::{test, web, App};
+
+    // 与 main 相同的 404 处理，用于区分“路由不存在”
+    async fn not_found() -> Result<HttpResponse, AppError> {
+        Err(AppError::NotFound("Resource not found".to_string()))
+    }
+
+    fn operations(item: &utoipa::openapi::PathItem) -> Vec<Method> {
+        [
+            (Method::GET, item.get.is_some()),
+            (Method::POST, item.post.is_some()),
+            (Method::PUT, item.put.is_some()),
+            (Method::DELETE, item.delete.is_some()),
+            (Method::PATCH, item.patch.is_some()),
+        ]
+        .into_iter()
+        .filter(|(_, present)| *present)
+        .map(|(method, _)| method)
+        .collect()
+    }
+
+    // 路径参数都替换为 1，包括 sitemap-{page}.xml 这样位于一段中间的参数
+    fn concrete_path(path: &str) -> String {
+        regex::Regex::new(r"\{[^}/]+\}").unwrap().replace_all(path, "1").into_owned()
+    }
+
+    // 405 或默认 404 说明没有对应的路由
+    async fn is_routed(res: actix_web::dev::ServiceResponse) -> bool {
+        if res.status() == StatusCode::METHOD_NOT_ALLOWED {
+            return false;
+        }
+        if res.status() != StatusCode::NOT_FOUND {
+            return true;
+        }
+        let body: serde_json::Value = test::read_body_json(res).await;
+        body["error"]["message"] != "Resource not found"
+    }
+
+    #[actix_web::test]
+    async fn spec_matches_registered_routes() {
+        let app = test::init_service(
+            App::new()
+                .configure(routes::configure)
+                .default_service(web::route().to(not_found)),
+        )
+        .await;
+
+        let spec = ApiDoc::openapi();
+        assert!(!spec.paths.paths.is_empty());
+
+        let all_methods = [Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::PATCH];
+        for (path, item) in &spec.paths.paths {
+            let documented = operations(item);
+            for method in all_methods.iter() {
+                let req = test::TestRequest::default()
+                    .method(method.clone())
+                    .uri(&concrete_path(path))
+                    .to_request();
+                let routed = is_routed(test::call_service(&app, req).await).await;
+                if documented.contains(method) {
+                    assert!(routed, "{} {} is documented but not routed", method, path);
+                } else {
+                    assert!(!routed, "{} {} is routed but not documented", method, path);
+                }
+            }
+        }
+    }
+
+    // 文档本身和 GraphQL（有自己的 schema）不在 OpenAPI 中
+    const UNDOCUMENTED: &[(&str, &str)] = &[
+        ("get", "/api/openapi.json"),
+        ("get", "/api/docs"),
+        ("get", "/api/graphql"),
+        ("post", "/api/graphql"),
+    ];
+
+    // 去掉路径参数中的正则，如 {page:\d+} 变为 {page}
+    fn documented_path(pattern: &str) -> String {
+        regex::Regex::new(r"\{([^}:/]+):[^}]*\}").unwrap().replace_all(pattern, "{$1}").into_owned()
+    }
+
+    #[actix_web::test]
+    async fn every_registered_route_is_documented() {
+        let spec = ApiDoc::openapi();
+        let mut documented: Vec<(String, String)> = spec
+            .paths
+            .paths
+            .iter()
+            .flat_map(|(path, item)| {
+                operations(item)
+                    .into_iter()
+                    .map(move |method| (method.as_str().to_lowercase(), path.clone()))
+            })
+            .collect();
+        documented.sort();
+
+        let mut registered: Vec<(String, String)> = routes::ROUTES
+            .iter()
+            .filter(|route| !UNDOCUMENTED.contains(route))
+            .map(|(method, path)| (method.to_string(), documented_path(path)))
+            .collect();
+        registered.sort();
+
+        for route in &registered {
+            assert!(documented.contains(route), "{} {} is routed but not documented", route.0, route.1);
+        }
+        for route in &documented {
+            assert!(registered.contains(route), "{} {} is documented but not routed", route.0, route.1);
+        }
+        assert_eq!(registered.len(), documented.len(), "a route is registered twice");
+    }
+
+    #[actix_web::test]
+    async fn spec_is_served_with_bearer_scheme() {
+        let app = test::init_service(App::new().configure(routes::configure)).await;
+        let req = test::TestRequest::get().uri("/api/openapi.json").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(body["components"]["securitySchemes"]["bearer_auth"]["scheme"], "bearer");
+        assert!(body["paths"]["/api/apps/{id}"]["put"].is_object());
+    }
+}
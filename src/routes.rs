@@ -0,0 +1,111 @@
+use actix_web::{web, Resource, Route};
+
+use crate::graphql;
+use crate::handlers;
+use crate::openapi;
+
+// 路由表：方法、完整路径和处理函数，注册和 OpenAPI 一致性测试共用，新路由只需要加在这里
+macro_rules! route_table {
+    ($($method:ident $path:literal => $handler:path,)*) => {
+        pub const ROUTES: &[(&str, &str)] = &[$((stringify!($method), $path),)*];
+
+        fn routes() -> Vec<(&'static str, Route)> {
+            vec![$(($path, web::$method().to($handler)),)*]
+        }
+    };
+}
+
+route_table! {
+    get "/api/health" => handlers::health_check,
+    post "/api/auth/register" => handlers::register,
+    post "/api/auth/login" => handlers::login,
+    post "/api/auth/verification-code" => handlers::get_verification_code,
+    post "/api/auth/magic-link" => handlers::request_magic_link,
+    get "/api/auth/magic" => handlers::magic_link_login,
+    post "/api/auth/password/login" => handlers::password_login,
+    post "/api/auth/2fa/login" => handlers::two_factor_login,
+    put "/api/auth/password" => handlers::set_password,
+    post "/api/auth/password/forgot" => handlers::forgot_password,
+    post "/api/auth/password/reset" => handlers::reset_password,
+    get "/api/auth/2fa" => handlers::two_factor_status,
+    post "/api/auth/2fa/totp/setup" => handlers::setup_totp,
+    post "/api/auth/2fa/totp/enable" => handlers::enable_totp,
+    post "/api/auth/2fa/totp/disable" => handlers::disable_totp,
+    post "/api/auth/2fa/recovery-codes" => handlers::regenerate_recovery_codes,
+    get "/api/auth/oidc/providers" => handlers::oidc_providers,
+    get "/api/auth/oidc/{provider}/authorize" => handlers::oidc_authorize,
+    get "/api/auth/oidc/{provider}/callback" => handlers::oidc_callback,
+    get "/api/admin/email-templates" => handlers::admin::list_email_templates,
+    get "/api/admin/email-templates/{name}/preview" => handlers::admin::preview_email_template,
+    get "/api/admin/outbox" => handlers::admin::list_outbox,
+    get "/api/admin/outbox/{id}" => handlers::admin::get_outbox_email,
+    post "/api/admin/outbox/{id}/retry" => handlers::admin::retry_outbox_email,
+    get "/api/admin/jobs" => handlers::admin::list_jobs,
+    // 先于 /api/admin/jobs/{id} 注册
+    get "/api/admin/jobs/stats" => handlers::admin::job_stats,
+    get "/api/admin/jobs/{id}" => handlers::admin::get_job,
+    post "/api/admin/jobs/{id}/retry" => handlers::admin::retry_job,
+    get "/api/admin/audit-logs" => handlers::admin::list_audit_logs,
+    get "/api/openapi.json" => openapi::openapi_json,
+    get "/api/docs" => openapi::docs,
+    post "/api/graphql" => graphql::graphql,
+    get "/api/graphql" => graphql::graphiql,
+    post "/api/apps" => handlers::create_app,
+    get "/api/apps" => handlers::list_apps,
+    // 固定路径先于 /api/apps/{id} 注册，其他方法返回 405 而不是匹配 /{id}
+    get "/api/apps/trash" => handlers::list_trashed_apps,
+    post "/api/apps/import" => handlers::archive::import_app,
+    get "/api/apps/{id}" => handlers::get_app,
+    put "/api/apps/{id}" => handlers::update_app,
+    delete "/api/apps/{id}" => handlers::delete_app,
+    post "/api/apps/{id}/restore" => handlers::restore_app,
+    get "/api/apps/{id}/export" => handlers::archive::export_app,
+    post "/api/apps/{id}/api-keys" => handlers::api_key::create_api_key,
+    get "/api/apps/{id}/api-keys" => handlers::api_key::list_api_keys,
+    delete "/api/apps/{id}/api-keys/{key_id}" => handlers::api_key::revoke_api_key,
+    get "/api/apps/{id}/changes" => handlers::change_feed::change_stream,
+    get "/api/apps/{id}/changes/ws" => handlers::change_feed::change_socket,
+    get "/.well-known/jwks.json" => handlers::auth::jwks,
+    get "/auth/me" => handlers::auth::me,
+    post "/articles" => handlers::article::create_article,
+    get "/articles" => handlers::article::list_articles,
+    // 先于 /articles/{id} 注册，其他方法返回 405 而不是匹配 /articles/{id}
+    get "/articles/trash" => handlers::article::list_trashed_articles,
+    get "/articles/{id}" => handlers::article::get_article,
+    put "/articles/{id}" => handlers::article::update_article,
+    delete "/articles/{id}" => handlers::article::delete_article,
+    post "/articles/{id}/restore" => handlers::article::restore_article,
+    get "/articles/{id}/lock" => handlers::edit_lock::get_edit_lock,
+    post "/articles/{id}/lock" => handlers::edit_lock::acquire_edit_lock,
+    delete "/articles/{id}/lock" => handlers::edit_lock::release_edit_lock,
+    post "/articles/{id}/lock/heartbeat" => handlers::edit_lock::renew_edit_lock,
+    post "/articles/{id}/lock/take" => handlers::edit_lock::take_edit_lock,
+    get "/articles/{id}/presence" => handlers::edit_lock::article_presence,
+    post "/articles/{id}/previews" => handlers::preview::create_preview_link,
+    get "/articles/{id}/previews" => handlers::preview::list_preview_links,
+    delete "/articles/{id}/previews/{preview_id}" => handlers::preview::revoke_preview_link,
+    get "/previews/{token}" => handlers::preview::open_preview,
+    get "/feeds/{identifier}/rss.xml" => handlers::feed::rss_feed,
+    get "/feeds/{identifier}/atom.xml" => handlers::feed::atom_feed,
+    get "/feeds/{identifier}/feed.json" => handlers::feed::json_feed,
+    get "/sitemaps/{identifier}/sitemap.xml" => handlers::sitemap::sitemap,
+    get "/sitemaps/{identifier}/sitemap-{page:\\d+}.xml" => handlers::sitemap::sitemap_page,
+}
+
+// 注册所有路由，main 和测试共用；同一路径的方法放在同一个 resource 中，
+// 方法不匹配时返回 405，先出现的路径先匹配
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    let mut resources: Vec<(&str, Resource)> = Vec::new();
+    for (path, route) in routes() {
+        match resources.iter_mut().find(|(existing, _)| *existing == path) {
+            Some((_, resource)) => {
+                let current = std::mem::replace(resource, web::resource(path));
+                *resource = current.route(route);
+            }
+            None => resources.push((path, web::resource(path).route(route))),
+        }
+    }
+    for (_, resource) in resources {
+        cfg.service(resource);
+    }
+}
@@ -0,0 +1,308 @@
+use actix_cors::Cors;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::{middleware::Logger, web, App, HttpResponse};
+use std::env;
+use std::sync::Arc;
+
+use crate::config::archive::ArchiveConfig;
+use crate::config::audit::AuditConfig;
+use crate::config::auth::{JwtConfig, MagicLinkConfig, PasswordConfig, TwoFactorConfig};
+use crate::config::change_feed::ChangeFeedConfig;
+use crate::config::edit_lock::EditLockConfig;
+use crate::config::email::OutboxConfig;
+use crate::config::feed::FeedConfig;
+use crate::config::graphql::GraphqlConfig;
+use crate::config::job::JobConfig;
+use crate::config::oidc::OidcConfig;
+use crate::config::preview::PreviewConfig;
+use crate::config::rate_limit::{RateLimitConfig, RateLimitRule};
+use crate::config::sitemap::SitemapConfig;
+use crate::config::trash::TrashConfig;
+use crate::db::Repositories;
+use crate::graphql::{self, AppSchema};
+use crate::jobs::{self, JobContext, JobWorker, Scheduler};
+use crate::middleware::rate_limit::{RateLimit, RateLimitStore};
+use crate::middleware::request_id::RequestId;
+use crate::routes;
+use crate::services::user::CodeGenerator;
+use crate::services::{
+    ApiKeyService, AppService, ArchiveService, ArticleService, AuditService, ChangeFeedService, EditLockService, FeedService, JobService,
+    OidcService, OutboxService, PreviewService, SitemapService, UserService,
+};
+use crate::utils::clock::Clock;
+use crate::utils::email::EmailService;
+use crate::utils::{json_error_handler, path_error_handler, query_error_handler, AppError};
+
+// 访问日志格式：与默认格式相同，但只记录路径（%U）而不是完整请求行（%r），
+// 查询参数中可能带有 access_token、api_key 或登录链接 token，不能写入日志
+const ACCESS_LOG_FORMAT: &str = r#"%a "%{METHOD}xi %U" %s %b "%{Referer}i" "%{User-Agent}i" %T"#;
+
+// 从环境变量读取的配置
+pub struct Settings {
+    pub jwt: JwtConfig,
+    pub magic_link: MagicLinkConfig,
+    pub password: PasswordConfig,
+    pub two_factor: TwoFactorConfig,
+    pub oidc: OidcConfig,
+    pub rate_limit: RateLimitConfig,
+    pub graphql: GraphqlConfig,
+    pub outbox: OutboxConfig,
+    pub jobs: JobConfig,
+    pub audit: AuditConfig,
+    pub trash: TrashConfig,
+    pub edit_lock: EditLockConfig,
+    pub change_feed: ChangeFeedConfig,
+    pub preview: PreviewConfig,
+    pub feed: FeedConfig,
+    pub sitemap: SitemapConfig,
+    pub archive: ArchiveConfig,
+    pub json_body_limit: usize,
+    // 使用这些邮箱注册的用户成为管理员
+    pub admin_emails: Vec<String>,
+}
+
+impl Settings {
+    pub fn from_env() -> Self {
+        let jwt = JwtConfig::from_env();
+        Self {
+            magic_link: MagicLinkConfig::from_env(&jwt),
+            preview: PreviewConfig::from_env(&jwt),
+            jwt,
+            password: PasswordConfig::from_env(),
+            two_factor: TwoFactorConfig::from_env(),
+            oidc: OidcConfig::from_env(),
+            rate_limit: RateLimitConfig::from_env(),
+            graphql: GraphqlConfig::from_env(),
+            outbox: OutboxConfig::from_env(),
+            jobs: JobConfig::from_env(),
+            audit: AuditConfig::from_env(),
+            trash: TrashConfig::from_env(),
+            edit_lock: EditLockConfig::from_env(),
+            change_feed: ChangeFeedConfig::from_env(),
+            feed: FeedConfig::from_env(),
+            sitemap: SitemapConfig::from_env(),
+            archive: ArchiveConfig::from_env(),
+            json_body_limit: env::var("JSON_BODY_LIMIT")
+                .ok()
+                .and_then(|value| value.parse::<usize>().ok())
+                .unwrap_or(1024 * 1024),
+            admin_emails: env::var("ADMIN_EMAILS")
+                .map(|value| {
+                    value
+                        .split(',')
+                        .map(|email| email.trim().to_string())
+                        .filter(|email| !email.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+// 需要外部注入的依赖，测试时可以替换为假实现
+pub struct Dependencies {
+    pub repositories: Repositories,
+    pub email_service: EmailService,
+    pub rate_limit_store: Arc<dyn RateLimitStore>,
+    pub clock: Arc<dyn Clock>,
+    pub code_generator: Arc<dyn CodeGenerator>,
+}
+
+// 所有 worker 共享的应用状态
+#[derive(Clone)]
+pub struct AppState {
+    pub repositories: Repositories,
+    pub user_service: UserService,
+    pub oidc_service: OidcService,
+    pub app_service: AppService,
+    pub article_service: ArticleService,
+    pub audit_service: AuditService,
+    pub edit_lock_service: EditLockService,
+    pub api_key_service: ApiKeyService,
+    pub change_feed_service: ChangeFeedService,
+    pub preview_service: PreviewService,
+    pub feed_service: FeedService,
+    pub sitemap_service: SitemapService,
+    pub archive_service: ArchiveService,
+    pub outbox_service: OutboxService,
+    pub job_service: JobService,
+    pub email_service: EmailService,
+    pub jwt_config: JwtConfig,
+    pub graphql_schema: AppSchema,
+    pub clock: Arc<dyn Clock>,
+    pub job_config: JobConfig,
+    pub rate_limit_store: Arc<dyn RateLimitStore>,
+    pub rate_limit_rules: Arc<Vec<RateLimitRule>>,
+    pub trust_proxy: bool,
+    pub json_body_limit: usize,
+}
+
+impl AppState {
+    pub fn new(deps: Dependencies, settings: Settings) -> Self {
+        let user_service = UserService::new(
+            deps.repositories.users.clone(),
+            deps.email_service.clone(),
+            settings.rate_limit.max_verification_attempts,
+        )
+        .with_clock(deps.clock.clone())
+        .with_code_generator(deps.code_generator)
+        .with_admin_emails(settings.admin_emails)
+        .with_magic_link(settings.magic_link)
+        .with_password(settings.password)
+        .with_two_factor(settings.two_factor);
+        let oidc_service =
+            OidcService::new(deps.repositories.users.clone(), settings.oidc).with_clock(deps.clock.clone());
+        let change_feed_service =
+            ChangeFeedService::new(deps.repositories.changes.clone(), settings.change_feed.clone());
+        let app_service =
+            AppService::new(deps.repositories.apps.clone()).with_change_feed(change_feed_service.clone());
+        let article_service =
+            ArticleService::new(deps.repositories.articles.clone(), deps.repositories.apps.clone())
+                .with_change_feed(change_feed_service.clone());
+        let api_key_service = ApiKeyService::new(deps.repositories.api_keys.clone(), deps.repositories.apps.clone())
+            .with_clock(deps.clock.clone());
+        let preview_service = PreviewService::new(
+            deps.repositories.previews.clone(),
+            deps.repositories.articles.clone(),
+            settings.preview,
+        )
+        .with_clock(deps.clock.clone());
+        let feed_service =
+            FeedService::new(deps.repositories.apps.clone(), deps.repositories.articles.clone(), settings.feed);
+        let sitemap_service =
+            SitemapService::new(deps.repositories.apps.clone(), deps.repositories.articles.clone(), settings.sitemap);
+        let archive_service = ArchiveService::new(
+            deps.repositories.apps.clone(),
+            deps.repositories.articles.clone(),
+            deps.repositories.users.clone(),
+            app_service.clone(),
+            article_service.clone(),
+            settings.archive,
+        );
+        let audit_service = AuditService::new(deps.repositories.audit.clone())
+            .with_clock(deps.clock.clone())
+            .with_trust_proxy(settings.rate_limit.trust_proxy);
+        let edit_lock_service = EditLockService::new(settings.edit_lock).with_clock(deps.clock.clone());
+        let outbox_service = OutboxService::new(
+            deps.repositories.outbox.clone(),
+            deps.email_service.clone(),
+            settings.outbox,
+        )
+        .with_clock(deps.clock.clone());
+        let job_service = JobService::new(deps.repositories.jobs.clone(), jobs::default_registry(
+            &settings.jobs,
+            &settings.audit,
+            &settings.trash,
+            &settings.change_feed,
+        ))
+        .with_clock(deps.clock.clone());
+        let graphql_schema =
+            graphql::build_schema(app_service.clone(), article_service.clone(), &settings.graphql);
+
+        Self {
+            repositories: deps.repositories,
+            user_service,
+            oidc_service,
+            app_service,
+            article_service,
+            audit_service,
+            edit_lock_service,
+            api_key_service,
+            change_feed_service,
+            preview_service,
+            feed_service,
+            sitemap_service,
+            archive_service,
+            outbox_service,
+            job_service,
+            email_service: deps.email_service,
+            clock: deps.clock,
+            job_config: settings.jobs,
+            jwt_config: settings.jwt,
+            graphql_schema,
+            rate_limit_store: deps.rate_limit_store,
+            rate_limit_rules: Arc::new(settings.rate_limit.rules),
+            trust_proxy: settings.rate_limit.trust_proxy,
+            json_body_limit: settings.json_body_limit,
+        }
+    }
+
+    pub fn job_context(&self) -> JobContext {
+        JobContext {
+            repositories: self.repositories.clone(),
+            email_service: self.email_service.clone(),
+            jobs: self.job_service.clone(),
+            clock: self.clock.clone(),
+        }
+    }
+
+    // 启动发件箱、任务 worker 池和定时任务
+    pub fn spawn_background(&self) -> Vec<tokio::task::JoinHandle<()>> {
+        let mut handles = vec![self.outbox_service.clone().spawn_worker()];
+        handles.extend(JobWorker::new(self.job_context(), self.job_config.clone()).spawn());
+        handles.push(Scheduler::new(self.job_context()).spawn());
+        handles
+    }
+}
+
+// 组装完整的应用，main 和测试共用
+pub fn build_app(
+    state: AppState,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    // 配置 CORS
+    let cors = Cors::default()
+        .allow_any_origin()
+        .allow_any_method()
+        .allow_any_header()
+        .max_age(3600);
+
+    App::new()
+        .wrap(RateLimit::new(
+            state.rate_limit_store.clone(),
+            state.rate_limit_rules.clone(),
+            state.trust_proxy,
+            state.json_body_limit,
+        ))
+        .wrap(RequestId::new())
+        .wrap(Logger::new(ACCESS_LOG_FORMAT).custom_request_replace("METHOD", |req| req.method().to_string()))
+        .wrap(cors)
+        .app_data(web::Data::new(state.user_service.clone()))
+        .app_data(web::Data::new(state.oidc_service.clone()))
+        .app_data(web::Data::new(state.app_service.clone()))
+        .app_data(web::Data::new(state.article_service.clone()))
+        .app_data(web::Data::new(state.audit_service.clone()))
+        .app_data(web::Data::new(state.edit_lock_service.clone()))
+        .app_data(web::Data::new(state.api_key_service.clone()))
+        .app_data(web::Data::new(state.change_feed_service.clone()))
+        .app_data(web::Data::new(state.preview_service.clone()))
+        .app_data(web::Data::new(state.feed_service.clone()))
+        .app_data(web::Data::new(state.sitemap_service.clone()))
+        .app_data(web::Data::new(state.archive_service.clone()))
+        .app_data(web::Data::new(state.outbox_service.clone()))
+        .app_data(web::Data::new(state.job_service.clone()))
+        .app_data(web::Data::new(state.repositories.clone()))
+        .app_data(web::Data::new(state.jwt_config.clone()))
+        .app_data(web::Data::new(state.email_service.clone()))
+        .app_data(web::Data::new(state.graphql_schema.clone()))
+        .app_data(
+            web::JsonConfig::default()
+                .limit(state.json_body_limit)
+                .error_handler(json_error_handler),
+        )
+        .app_data(web::PathConfig::default().error_handler(path_error_handler))
+        .app_data(web::QueryConfig::default().error_handler(query_error_handler))
+        .configure(routes::configure)
+        // 404 处理
+        .default_service(web::route().to(|| async {
+            Err::<HttpResponse, _>(AppError::NotFound("Resource not found".to_string()))
+        }))
+}
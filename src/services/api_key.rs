@@ -0,0 +1,142 @@
+use crate::db::{ApiKeyRepository, AppRepository};
+use crate::models::api_key::{ApiKey, ApiKeyCreated, NewApiKey};
+use crate::models::App;
+use crate::services::app::require_two_factor;
+use crate::utils::clock::{Clock, SystemClock};
+use crate::utils::token;
+use crate::utils::AppError;
+use std::sync::Arc;
+
+// 所有 key 使用同一前缀，便于在日志和代码中识别
+const KEY_PREFIX: &str = "rsk_";
+// 列表中展示的 key 开头长度，包括前缀
+const DISPLAY_PREFIX_LEN: usize = 12;
+
+#[derive(Clone)]
+pub struct ApiKeyService {
+    keys: Arc<dyn ApiKeyRepository>,
+    apps: Arc<dyn AppRepository>,
+    clock: Arc<dyn Clock>,
+}
+
+impl ApiKeyService {
+    pub fn new(keys: Arc<dyn ApiKeyRepository>, apps: Arc<dyn AppRepository>) -> Self {
+        Self {
+            keys,
+            apps,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    async fn app(&self, app_id: i64) -> Result<App, AppError> {
+        self.apps
+            .find_by_id(app_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("App not found".to_string()))
+    }
+
+    // 与修改应用相同，应用要求两步验证时需要两步验证登录
+    pub async fn create(&self, app_id: i64, name: &str, creator_id: i64, aal: u8) -> Result<ApiKeyCreated, AppError> {
+        let app = self.app(app_id).await?;
+        require_two_factor(app.require_two_factor != 0, aal)?;
+
+        let key = format!("{}{}", KEY_PREFIX, token::random_token());
+        let new_key = NewApiKey {
+            app_id,
+            name: name.trim().to_string(),
+            prefix: key[..DISPLAY_PREFIX_LEN].to_string(),
+            key_hash: token::digest(&key),
+            created_by: creator_id,
+        };
+        let api_key = self.keys.create(&new_key, self.clock.now()).await?;
+        Ok(ApiKeyCreated { api_key, key })
+    }
+
+    pub async fn list(&self, app_id: i64) -> Result<Vec<ApiKey>, AppError> {
+        self.app(app_id).await?;
+        Ok(self.keys.list_by_app(app_id).await?)
+    }
+
+    // 返回被吊销的 key
+    pub async fn revoke(&self, app_id: i64, key_id: i64, aal: u8) -> Result<ApiKey, AppError> {
+        let app = self.app(app_id).await?;
+        require_two_factor(app.require_two_factor != 0, aal)?;
+        let not_found = || AppError::NotFound("API key not found".to_string());
+        let api_key = self
+            .keys
+            .list_by_app(app_id)
+            .await?
+            .into_iter()
+            .find(|key| key.id == key_id)
+            .ok_or_else(not_found)?;
+        if !self.keys.delete(app_id, key_id).await? {
+            return Err(not_found());
+        }
+        Ok(api_key)
+    }
+
+    // key 必须属于该应用，应用在回收站中时不能使用
+    pub async fn authenticate(&self, app_id: i64, key: &str) -> Result<ApiKey, AppError> {
+        let invalid = || AppError::Unauthorized("Invalid API key".to_string());
+        let api_key = self.keys.find_by_hash(&token::digest(key)).await?.ok_or_else(invalid)?;
+        if api_key.app_id != app_id {
+            return Err(invalid());
+        }
+        self.app(app_id).await?;
+        Ok(api_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory::MemoryRepository;
+    use crate::models::CreateAppRequest;
+    use crate::test_support::app_request;
+
+    async fn setup(require_two_factor: bool) -> (ApiKeyService, i64) {
+        let repository = Arc::new(MemoryRepository::new());
+        let app = AppRepository::create(
+            repository.as_ref(),
+            &CreateAppRequest {
+                require_two_factor,
+                ..app_request("Blog", "blog")
+            },
+            1,
+        )
+        .await
+        .unwrap();
+        (ApiKeyService::new(repository.clone(), repository), app.id)
+    }
+
+    #[actix_web::test]
+    async fn keys_authenticate_only_for_their_app_until_revoked() {
+        let (service, app_id) = setup(false).await;
+        let created = service.create(app_id, " Preview ", 1, 1).await.unwrap();
+        assert!(created.key.starts_with(KEY_PREFIX));
+        assert!(created.key.starts_with(&created.api_key.prefix));
+        assert_eq!(created.api_key.name, "Preview");
+        assert_ne!(created.api_key.key_hash, created.key);
+
+        assert_eq!(service.authenticate(app_id, &created.key).await.unwrap().id, created.api_key.id);
+        assert!(matches!(service.authenticate(app_id + 1, &created.key).await, Err(AppError::Unauthorized(_))));
+        assert!(matches!(service.authenticate(app_id, "rsk_wrong").await, Err(AppError::Unauthorized(_))));
+
+        service.revoke(app_id, created.api_key.id, 1).await.unwrap();
+        assert!(service.list(app_id).await.unwrap().is_empty());
+        assert!(matches!(service.authenticate(app_id, &created.key).await, Err(AppError::Unauthorized(_))));
+        assert!(matches!(service.revoke(app_id, created.api_key.id, 1).await, Err(AppError::NotFound(_))));
+    }
+
+    #[actix_web::test]
+    async fn two_factor_apps_need_two_factor_sessions_to_manage_keys() {
+        let (service, app_id) = setup(true).await;
+        assert!(matches!(service.create(app_id, "Preview", 1, 1).await, Err(AppError::Forbidden(_))));
+        service.create(app_id, "Preview", 1, 2).await.unwrap();
+    }
+}
@@ -0,0 +1,261 @@
+use crate::config::auth::AAL_TWO_FACTOR;
+use crate::db::{AppFilter, AppRepository, Page};
+use crate::models::{App, CreateAppRequest, UpdateAppRequest};
+use crate::services::ChangeFeedService;
+use crate::utils::precondition::Precondition;
+use crate::utils::AppError;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct AppService {
+    apps: Arc<dyn AppRepository>,
+    changes: Option<ChangeFeedService>,
+}
+
+// 应用要求两步验证时，只有两步验证登录的会话可以修改它
+pub(crate) fn require_two_factor(required: bool, aal: u8) -> Result<(), AppError> {
+    if required && aal < AAL_TWO_FACTOR {
+        return Err(AppError::Forbidden(
+            "This app requires signing in with two-factor authentication".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+impl AppService {
+    pub fn new(apps: Arc<dyn AppRepository>) -> Self {
+        Self { apps, changes: None }
+    }
+
+    pub fn with_change_feed(mut self, changes: ChangeFeedService) -> Self {
+        self.changes = Some(changes);
+        self
+    }
+
+    // 事件已由仓储写入，通知订阅者
+    fn notify(&self, app_id: i64) {
+        if let Some(changes) = &self.changes {
+            changes.notify(app_id);
+        }
+    }
+
+    pub async fn get(&self, id: i64) -> Result<App, AppError> {
+        self.apps
+            .find_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("App not found".to_string()))
+    }
+
+    pub async fn get_by_identifier(&self, identifier: &str) -> Result<App, AppError> {
+        self.apps
+            .find_by_identifier(identifier)
+            .await?
+            .ok_or_else(|| AppError::NotFound("App not found".to_string()))
+    }
+
+    // 返回当前页的应用和总数
+    pub async fn list(&self, filter: &AppFilter, page: i64, page_size: i64) -> Result<(Vec<App>, i64), AppError> {
+        let apps = self.apps.list(filter, Page::new(page, page_size)).await?;
+        let total = self.apps.count(filter).await?;
+        Ok((apps, total))
+    }
+
+    // 应用标识必须唯一，回收站中的应用在永久删除前仍占用标识；aal 为当前登录的认证强度
+    pub async fn create(&self, req: &CreateAppRequest, creator_id: i64, aal: u8) -> Result<App, AppError> {
+        require_two_factor(req.require_two_factor, aal)?;
+        if self.apps.find_by_identifier(&req.identifier).await?.is_some() {
+            return Err(AppError::Conflict("App identifier already exists".to_string()));
+        }
+        let trashed = AppFilter {
+            identifier: Some(req.identifier.clone()),
+            trashed: true,
+            ..Default::default()
+        };
+        if self.apps.count(&trashed).await? > 0 {
+            return Err(AppError::Conflict(
+                "App identifier is used by an app in the trash".to_string(),
+            ));
+        }
+
+        let app = self.apps.create(req, creator_id).await?;
+        self.notify(app.id);
+        Ok(app)
+    }
+
+    // 开启两步验证要求本身也需要两步验证登录，避免把自己锁在外面；返回修改前后的应用
+    pub async fn update(
+        &self,
+        id: i64,
+        req: &UpdateAppRequest,
+        updater_id: i64,
+        aal: u8,
+        precondition: &Precondition,
+    ) -> Result<(App, App), AppError> {
+        let app = self.get(id).await?;
+        require_two_factor(app.require_two_factor != 0 || req.require_two_factor == Some(true), aal)?;
+        precondition.check(app.version)?;
+        match self.apps.update(id, req, updater_id, app.version).await? {
+            Some(updated) => {
+                self.notify(id);
+                Ok((app, updated))
+            }
+            // 检查之后被其他请求修改或删除
+            None => Err(AppError::PreconditionFailed(self.get(id).await?.version)),
+        }
+    }
+
+    // 移入回收站，应用下的文章一起移入；返回被删除的应用
+    pub async fn delete(&self, id: i64, deleted_by: i64, aal: u8) -> Result<App, AppError> {
+        let app = self.get(id).await?;
+        require_two_factor(app.require_two_factor != 0, aal)?;
+        if !self.apps.trash(id, deleted_by).await? {
+            return Err(AppError::NotFound("App not found".to_string()));
+        }
+        self.notify(id);
+        Ok(app)
+    }
+
+    // 从回收站恢复应用和随它一起删除的文章
+    pub async fn restore(&self, id: i64, aal: u8) -> Result<App, AppError> {
+        let not_found = || AppError::NotFound("App not found in trash".to_string());
+        let app = self.apps.find_trashed(id).await?.ok_or_else(not_found)?;
+        require_two_factor(app.require_two_factor != 0, aal)?;
+        if !self.apps.restore(id).await? {
+            return Err(not_found());
+        }
+        self.notify(id);
+        self.get(id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory::MemoryRepository;
+    use crate::test_support::app_request;
+
+    fn service() -> AppService {
+        AppService::new(Arc::new(MemoryRepository::new()))
+    }
+
+    fn create_request(identifier: &str) -> CreateAppRequest {
+        app_request(&format!("App {}", identifier), identifier)
+    }
+
+    #[actix_web::test]
+    async fn rejects_duplicate_identifier() {
+        let service = service();
+        service.create(&create_request("blog"), 1, 1).await.unwrap();
+
+        let err = service.create(&create_request("blog"), 2, 1).await.unwrap_err();
+        assert!(matches!(err, AppError::Conflict(_)));
+    }
+
+    #[actix_web::test]
+    async fn update_records_updater_and_missing_app_is_not_found() {
+        let service = service();
+        let app = service.create(&create_request("blog"), 1, 1).await.unwrap();
+
+        let changes = UpdateAppRequest {
+            name: Some("Weblog".to_string()),
+            description: None,
+            logo_url: None,
+            email_from: None,
+            feed_title: None,
+            feed_description: None,
+            feed_link: None,
+            base_url: None,
+            require_two_factor: None,
+            version: None,
+        };
+        let (before, updated) = service.update(app.id, &changes, 2, 1, &Precondition::Any).await.unwrap();
+        assert_eq!(before.name, app.name);
+        assert_eq!(updated.name, "Weblog");
+        assert_eq!(updated.updater_id, 2);
+        assert_eq!(updated.version, app.version + 1);
+
+        // 基于旧版本的修改返回当前版本
+        let stale = Precondition::Versions(vec![app.version]);
+        assert!(matches!(
+            service.update(app.id, &changes, 2, 1, &stale).await,
+            Err(AppError::PreconditionFailed(version)) if version == updated.version
+        ));
+        assert!(matches!(
+            service.update(999, &changes, 2, 1, &Precondition::Any).await,
+            Err(AppError::NotFound(_))
+        ));
+        service.delete(app.id, 2, 1).await.unwrap();
+        assert!(matches!(service.delete(app.id, 2, 1).await, Err(AppError::NotFound(_))));
+    }
+
+    #[actix_web::test]
+    async fn two_factor_policy_requires_two_factor_sessions() {
+        let service = service();
+        let app = service.create(&create_request("blog"), 1, 1).await.unwrap();
+        let changes = UpdateAppRequest {
+            name: None,
+            description: None,
+            logo_url: None,
+            email_from: None,
+            feed_title: None,
+            feed_description: None,
+            feed_link: None,
+            base_url: None,
+            require_two_factor: Some(true),
+            version: None,
+        };
+
+        // 单因素登录不能开启，也不能修改或删除已开启的应用
+        let any = Precondition::Any;
+        assert!(matches!(service.update(app.id, &changes, 1, 1, &any).await, Err(AppError::Forbidden(_))));
+        service.update(app.id, &changes, 1, 2, &any).await.unwrap();
+        assert!(matches!(service.update(app.id, &changes, 1, 1, &any).await, Err(AppError::Forbidden(_))));
+        assert!(matches!(service.delete(app.id, 1, 1).await, Err(AppError::Forbidden(_))));
+        service.delete(app.id, 1, 2).await.unwrap();
+        assert!(matches!(service.restore(app.id, 1).await, Err(AppError::Forbidden(_))));
+        service.restore(app.id, 2).await.unwrap();
+    }
+
+    #[actix_web::test]
+    async fn trashed_apps_keep_their_identifier_until_restored() {
+        let service = service();
+        let app = service.create(&create_request("blog"), 1, 1).await.unwrap();
+        let deleted = service.delete(app.id, 2, 1).await.unwrap();
+        assert!(deleted.deleted_at.is_none());
+
+        assert!(matches!(service.get(app.id).await, Err(AppError::NotFound(_))));
+        assert!(matches!(service.get_by_identifier("blog").await, Err(AppError::NotFound(_))));
+        assert!(matches!(
+            service.create(&create_request("blog"), 1, 1).await,
+            Err(AppError::Conflict(_))
+        ));
+        let trash = AppFilter {
+            trashed: true,
+            ..Default::default()
+        };
+        let (apps, total) = service.list(&trash, 1, 10).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(apps[0].deleted_by, Some(2));
+
+        let restored = service.restore(app.id, 1).await.unwrap();
+        assert!(restored.deleted_at.is_none());
+        assert!(matches!(service.restore(app.id, 1).await, Err(AppError::NotFound(_))));
+    }
+
+    #[actix_web::test]
+    async fn lists_with_filter_and_pagination() {
+        let service = service();
+        for identifier in ["a", "b", "c"] {
+            service.create(&create_request(identifier), 1, 1).await.unwrap();
+        }
+        service.create(&create_request("d"), 2, 1).await.unwrap();
+
+        let filter = AppFilter {
+            creator_id: Some(1),
+            ..Default::default()
+        };
+        let (apps, total) = service.list(&filter, 2, 2).await.unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(apps.len(), 1);
+    }
+}
@@ -0,0 +1,676 @@
+use crate::config::archive::ArchiveConfig;
+use crate::db::{AppFilter, AppRepository, ArticleRepository, UserRepository};
+use crate::models::archive::{
+    Archive, ArchivedApp, ArchivedArticle, ConflictStrategy, ImportAction, ImportItem, ImportReport, ImportSource,
+    MarkdownDirectory,
+};
+use crate::models::article::{CreateArticleRequest, UpdateArticleRequest};
+use crate::models::{App, Article, CreateAppRequest, UpdateAppRequest};
+use crate::services::app::require_two_factor;
+use crate::services::{AppService, ArticleService};
+use crate::utils::precondition::Precondition;
+use crate::utils::validation::field_errors;
+use crate::utils::{archive, markdown, wxr, AppError};
+use std::collections::HashMap;
+use std::sync::Arc;
+use validator::Validate;
+
+// 导入的参数
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    pub strategy: ConflictStrategy,
+    pub dry_run: bool,
+    // 覆盖导入文件中的应用标识
+    pub identifier: Option<String>,
+}
+
+// 应用内容的导出和导入；导入通过应用和文章服务完成，与接口创建的内容一样产生变更事件
+#[derive(Clone)]
+pub struct ArchiveService {
+    apps: Arc<dyn AppRepository>,
+    articles: Arc<dyn ArticleRepository>,
+    users: Arc<dyn UserRepository>,
+    app_service: AppService,
+    article_service: ArticleService,
+    config: ArchiveConfig,
+}
+
+// 同一应用中标题和语言都相同的文章视为同一篇
+type ArticleKey = (String, Option<String>);
+
+fn article_key(title: &str, locale: Option<&str>) -> ArticleKey {
+    (title.to_string(), locale.map(str::to_string))
+}
+
+impl ArchiveService {
+    pub fn new(
+        apps: Arc<dyn AppRepository>,
+        articles: Arc<dyn ArticleRepository>,
+        users: Arc<dyn UserRepository>,
+        app_service: AppService,
+        article_service: ArticleService,
+        config: ArchiveConfig,
+    ) -> Self {
+        Self {
+            apps,
+            articles,
+            users,
+            app_service,
+            article_service,
+            config,
+        }
+    }
+
+    pub fn max_import_bytes(&self) -> usize {
+        self.config.max_import_bytes
+    }
+
+    // 导出和导入到已有应用会读取或覆盖其他作者的草稿，只允许应用的创建者和管理员，
+    // 应用要求两步验证时还需要两步验证的会话
+    async fn require_owner(&self, app: &App, user_id: i64, aal: u8) -> Result<(), AppError> {
+        require_two_factor(app.require_two_factor != 0, aal)?;
+        if app.creator_id == user_id {
+            return Ok(());
+        }
+        match self.users.find_by_id(user_id).await? {
+            Some(user) if user.is_admin() => Ok(()),
+            _ => Err(AppError::Forbidden(
+                "Only the creator of the app or an admin can export or import into it".to_string(),
+            )),
+        }
+    }
+
+    // 应用的设置、全部未删除的文章（包括草稿）、分类和引用的外部文件
+    pub async fn export(&self, app_id: i64, user_id: i64, aal: u8) -> Result<Archive, AppError> {
+        let app = self.app_service.get(app_id).await?;
+        self.require_owner(&app, user_id, aal).await?;
+        let articles = self.articles.list_in_app(app_id).await?;
+        Ok(archive::new_archive(
+            archived_app(app),
+            articles.into_iter().map(archived_article).collect(),
+        ))
+    }
+
+    // 按来源解析请求体；ndjson 为 true 时导出文件为 NDJSON
+    pub fn parse(
+        &self,
+        source: ImportSource,
+        body: &[u8],
+        ndjson: bool,
+        identifier: Option<&str>,
+    ) -> Result<Archive, AppError> {
+        let text = std::str::from_utf8(body)
+            .map_err(|_| AppError::BadRequest("The import file must be UTF-8".to_string()))?;
+        match source {
+            ImportSource::Archive if ndjson => archive::from_ndjson(text),
+            ImportSource::Archive => archive::from_json(text),
+            ImportSource::Wxr => wxr::parse(text),
+            ImportSource::Markdown => {
+                let identifier = identifier.ok_or_else(|| {
+                    AppError::BadRequest("identifier is required when importing Markdown".to_string())
+                })?;
+                let directory: MarkdownDirectory = serde_json::from_str(text)
+                    .map_err(|e| AppError::BadRequest(format!("Invalid Markdown directory: {}", e)))?;
+                let app = ArchivedApp {
+                    identifier: identifier.to_string(),
+                    name: identifier.to_string(),
+                    ..ArchivedApp::default()
+                };
+                markdown::parse(app, &directory.files)
+            }
+        }
+    }
+
+    // 文章 id 重新分配，译文的 translation_of 指向原文的新 id；试运行时不做任何修改。
+    // 导入通过各个服务逐条写入，不在一个事务中：应用写入后出错时不回滚，
+    // 返回的报告中 error 为出错原因，只列出出错前已处理的文章
+    pub async fn import(
+        &self,
+        archive: &Archive,
+        options: &ImportOptions,
+        user_id: i64,
+        aal: u8,
+    ) -> Result<ImportReport, AppError> {
+        let (app_item, app) = self.import_app(archive, options, user_id, aal).await?;
+        let mut report = ImportReport {
+            dry_run: options.dry_run,
+            app: app_item,
+            articles: Vec::new(),
+            media: archive.media.clone(),
+            warnings: Vec::new(),
+            error: None,
+        };
+        if let Err(e) = self.import_articles(archive, app.as_ref(), options, user_id, aal, &mut report).await {
+            if options.dry_run {
+                return Err(e);
+            }
+            log::error!("Import into app {} stopped part way: {}", report.app.name, e);
+            report.error = Some(e.public_message());
+        }
+        Ok(report)
+    }
+
+    // 导入的文章逐条加入 report
+    async fn import_articles(
+        &self,
+        archive: &Archive,
+        app: Option<&App>,
+        options: &ImportOptions,
+        user_id: i64,
+        aal: u8,
+        report: &mut ImportReport,
+    ) -> Result<(), AppError> {
+        let mut existing: HashMap<ArticleKey, Article> = HashMap::new();
+        if let Some(app) = app {
+            for article in self.articles.list_in_app(app.id).await? {
+                existing.insert(article_key(&article.title, article.locale.as_deref()), article);
+            }
+        }
+
+        // 先导入原文，译文导入时原文已有新的 id
+        let mut sources: Vec<&ArchivedArticle> = archive.articles.iter().collect();
+        sources.sort_by_key(|article| (article.translation_of.is_some(), article.id));
+        // 导入文件中的 id 到新 id，试运行时新建的文章为 None
+        let mut ids: HashMap<i64, Option<i64>> = HashMap::new();
+        for source in sources {
+            let translation_of = match source.translation_of {
+                Some(original) if ids.contains_key(&original) => ids[&original],
+                Some(original) => {
+                    report.warnings.push(format!(
+                        "Article {} is a translation of {} which was not imported, it is imported as an original",
+                        source.id, original
+                    ));
+                    None
+                }
+                None => None,
+            };
+            let item = self
+                .import_article(source, translation_of, app, &mut existing, options, user_id, aal)
+                .await?;
+            if item.action != ImportAction::Skip || item.id.is_some() {
+                ids.insert(source.id, item.id);
+            }
+            report.articles.push(item);
+        }
+        Ok(())
+    }
+
+    // 返回应用的导入结果和导入到的应用；试运行时新建的应用为 None
+    async fn import_app(
+        &self,
+        archive: &Archive,
+        options: &ImportOptions,
+        user_id: i64,
+        aal: u8,
+    ) -> Result<(ImportItem, Option<App>), AppError> {
+        let mut request = create_app_request(&archive.app);
+        if let Some(identifier) = &options.identifier {
+            request.identifier = identifier.clone();
+        }
+        request
+            .validate()
+            .map_err(|errors| AppError::ValidationError(field_errors(&errors)))?;
+
+        let existing = self.apps.find_by_identifier(&request.identifier).await?;
+        let item = |action, app: Option<&App>, identifier: &str| ImportItem {
+            action,
+            name: identifier.to_string(),
+            source_id: None,
+            id: app.map(|app| app.id),
+            reason: None,
+        };
+        match (existing, options.strategy) {
+            (Some(app), ConflictStrategy::Skip) => {
+                self.require_owner(&app, user_id, aal).await?;
+                Ok((item(ImportAction::Skip, Some(&app), &app.identifier), Some(app)))
+            }
+            (Some(app), ConflictStrategy::Overwrite) => {
+                self.require_owner(&app, user_id, aal).await?;
+                if options.dry_run {
+                    return Ok((item(ImportAction::Overwrite, Some(&app), &app.identifier), Some(app)));
+                }
+                let changes = update_app_request(&request);
+                let (_, app) = self
+                    .app_service
+                    .update(app.id, &changes, user_id, aal, &Precondition::Any)
+                    .await?;
+                Ok((item(ImportAction::Overwrite, Some(&app), &app.identifier), Some(app)))
+            }
+            (existing, strategy) => {
+                let action = if existing.is_some() || self.identifier_in_trash(&request.identifier).await? {
+                    if strategy != ConflictStrategy::Rename {
+                        return Err(AppError::Conflict(
+                            "App identifier is used by an app in the trash".to_string(),
+                        ));
+                    }
+                    request.identifier = self.free_identifier(&request.identifier).await?;
+                    ImportAction::Rename
+                } else {
+                    ImportAction::Create
+                };
+                if options.dry_run {
+                    return Ok((item(action, None, &request.identifier), None));
+                }
+                let app = self.app_service.create(&request, user_id, aal).await?;
+                Ok((item(action, Some(&app), &app.identifier), Some(app)))
+            }
+        }
+    }
+
+    async fn identifier_in_trash(&self, identifier: &str) -> Result<bool, AppError> {
+        let trashed = AppFilter {
+            identifier: Some(identifier.to_string()),
+            trashed: true,
+            ..Default::default()
+        };
+        Ok(self.apps.count(&trashed).await? > 0)
+    }
+
+    // 在标识后加上 -2、-3 ... 直到没有被使用
+    async fn free_identifier(&self, identifier: &str) -> Result<String, AppError> {
+        for n in 2.. {
+            let suffix = format!("-{}", n);
+            let base: String = identifier.chars().take(50 - suffix.len()).collect();
+            let candidate = format!("{}{}", base, suffix);
+            let taken = self.apps.find_by_identifier(&candidate).await?.is_some()
+                || self.identifier_in_trash(&candidate).await?;
+            if !taken {
+                return Ok(candidate);
+            }
+        }
+        unreachable!()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn import_article(
+        &self,
+        source: &ArchivedArticle,
+        translation_of: Option<i64>,
+        app: Option<&App>,
+        existing: &mut HashMap<ArticleKey, Article>,
+        options: &ImportOptions,
+        user_id: i64,
+        aal: u8,
+    ) -> Result<ImportItem, AppError> {
+        let mut request = create_article_request(source, app.map(|app| app.id), translation_of);
+        let mut item = ImportItem {
+            action: ImportAction::Create,
+            name: source.title.clone(),
+            source_id: Some(source.id),
+            id: None,
+            reason: None,
+        };
+        if let Err(errors) = request.validate() {
+            let errors: Vec<String> = field_errors(&errors)
+                .into_iter()
+                .map(|error| format!("{}: {}", error.field, error.message))
+                .collect();
+            item.action = ImportAction::Skip;
+            item.reason = Some(format!("Invalid article: {}", errors.join(", ")));
+            return Ok(item);
+        }
+
+        let key = article_key(&request.title, request.locale.as_deref());
+        if let Some(current) = existing.get(&key) {
+            match options.strategy {
+                ConflictStrategy::Skip => {
+                    item.action = ImportAction::Skip;
+                    item.id = Some(current.id);
+                    item.reason = Some("An article with the same title and locale already exists".to_string());
+                    return Ok(item);
+                }
+                ConflictStrategy::Overwrite => {
+                    item.action = ImportAction::Overwrite;
+                    item.id = Some(current.id);
+                    if !options.dry_run {
+                        // 以原作者的身份修改，导入者已确认是应用的创建者或管理员
+                        let changes = update_article_request(&request);
+                        self.article_service
+                            .update(current.id, current.author_id, aal, &changes, &Precondition::Any)
+                            .await?;
+                    }
+                    return Ok(item);
+                }
+                ConflictStrategy::Rename => {
+                    item.action = ImportAction::Rename;
+                    request.title = (2..)
+                        .map(|n| format!("{} ({})", source.title, n))
+                        .find(|title| !existing.contains_key(&article_key(title, request.locale.as_deref())))
+                        .unwrap_or_default();
+                    item.name = request.title.clone();
+                }
+            }
+        }
+
+        if !options.dry_run {
+            match self.article_service.create(&request, user_id, aal).await {
+                Ok(article) => {
+                    self.articles
+                        .set_timestamps(article.id, source.created_at, source.updated_at)
+                        .await?;
+                    item.id = Some(article.id);
+                    existing.insert(key_of(&article), article);
+                }
+                Err(AppError::NotFound(message) | AppError::BadRequest(message)) => {
+                    item.action = ImportAction::Skip;
+                    item.reason = Some(message);
+                }
+                Err(e) => return Err(e),
+            }
+        } else {
+            // 试运行时记录将要创建的文章，使导入文件中重复的文章得到与实际导入相同的结果
+            existing.insert(article_key(&request.title, request.locale.as_deref()), placeholder(&request));
+        }
+        Ok(item)
+    }
+}
+
+fn key_of(article: &Article) -> ArticleKey {
+    article_key(&article.title, article.locale.as_deref())
+}
+
+fn placeholder(request: &CreateArticleRequest) -> Article {
+    let now = chrono::Utc::now();
+    Article {
+        id: 0,
+        title: request.title.clone(),
+        content: String::new(),
+        author_id: 0,
+        app_id: request.app_id,
+        category: None,
+        locale: request.locale.clone(),
+        translation_of: None,
+        meta_title: None,
+        meta_description: None,
+        canonical_url: None,
+        og_image_url: None,
+        noindex: 0,
+        status: 1,
+        created_at: now,
+        updated_at: now,
+        deleted_at: None,
+        deleted_by: None,
+        version: 1,
+    }
+}
+
+fn archived_app(app: App) -> ArchivedApp {
+    ArchivedApp {
+        identifier: app.identifier,
+        name: app.name,
+        description: app.description,
+        logo_url: app.logo_url,
+        email_from: app.email_from,
+        feed_title: app.feed_title,
+        feed_description: app.feed_description,
+        feed_link: app.feed_link,
+        base_url: app.base_url,
+        require_two_factor: app.require_two_factor != 0,
+    }
+}
+
+fn archived_article(article: Article) -> ArchivedArticle {
+    ArchivedArticle {
+        id: article.id,
+        title: article.title,
+        content: article.content,
+        status: article.status,
+        category: article.category,
+        locale: article.locale,
+        translation_of: article.translation_of,
+        meta_title: article.meta_title,
+        meta_description: article.meta_description,
+        canonical_url: article.canonical_url,
+        og_image_url: article.og_image_url,
+        noindex: article.noindex != 0,
+        created_at: article.created_at,
+        updated_at: article.updated_at,
+    }
+}
+
+fn create_app_request(app: &ArchivedApp) -> CreateAppRequest {
+    CreateAppRequest {
+        name: app.name.clone(),
+        description: app.description.clone(),
+        identifier: app.identifier.clone(),
+        logo_url: app.logo_url.clone(),
+        email_from: app.email_from.clone(),
+        feed_title: app.feed_title.clone(),
+        feed_description: app.feed_description.clone(),
+        feed_link: app.feed_link.clone(),
+        base_url: app.base_url.clone(),
+        require_two_factor: app.require_two_factor,
+    }
+}
+
+// 导入文件中为空的字段不清除已有的值
+fn update_app_request(app: &CreateAppRequest) -> UpdateAppRequest {
+    UpdateAppRequest {
+        name: Some(app.name.clone()),
+        description: Some(app.description.clone()),
+        logo_url: app.logo_url.clone(),
+        email_from: app.email_from.clone(),
+        feed_title: app.feed_title.clone(),
+        feed_description: app.feed_description.clone(),
+        feed_link: app.feed_link.clone(),
+        base_url: app.base_url.clone(),
+        require_two_factor: Some(app.require_two_factor),
+        version: None,
+    }
+}
+
+fn create_article_request(
+    article: &ArchivedArticle,
+    app_id: Option<i64>,
+    translation_of: Option<i64>,
+) -> CreateArticleRequest {
+    CreateArticleRequest {
+        title: article.title.clone(),
+        content: article.content.clone(),
+        status: Some(article.status),
+        app_id,
+        category: article.category.clone(),
+        locale: article.locale.clone(),
+        translation_of,
+        meta_title: article.meta_title.clone(),
+        meta_description: article.meta_description.clone(),
+        canonical_url: article.canonical_url.clone(),
+        og_image_url: article.og_image_url.clone(),
+        noindex: Some(article.noindex),
+    }
+}
+
+fn update_article_request(article: &CreateArticleRequest) -> UpdateArticleRequest {
+    UpdateArticleRequest {
+        title: Some(article.title.clone()),
+        content: Some(article.content.clone()),
+        status: article.status,
+        category: article.category.clone(),
+        locale: article.locale.clone(),
+        meta_title: article.meta_title.clone(),
+        meta_description: article.meta_description.clone(),
+        canonical_url: article.canonical_url.clone(),
+        og_image_url: article.og_image_url.clone(),
+        noindex: article.noindex,
+        version: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory::MemoryRepository;
+    use crate::test_support::app_request;
+    use chrono::{TimeZone, Utc};
+
+    async fn setup() -> (ArchiveService, Arc<MemoryRepository>, App) {
+        let repository = Arc::new(MemoryRepository::new());
+        let app_service = AppService::new(repository.clone());
+        let article_service = ArticleService::new(repository.clone(), repository.clone());
+        let service = ArchiveService::new(
+            repository.clone(),
+            repository.clone(),
+            repository.clone(),
+            app_service,
+            article_service,
+            ArchiveConfig::default(),
+        );
+        let app = CreateAppRequest {
+            description: "Posts".to_string(),
+            feed_title: Some("Blog feed".to_string()),
+            ..app_request("Blog", "blog")
+        };
+        let app = AppRepository::create(repository.as_ref(), &app, 1).await.unwrap();
+        let article = |title: &str, locale: &str, translation_of: Option<i64>| CreateArticleRequest {
+            title: title.to_string(),
+            content: "![cover](https://example.com/cover.png)".to_string(),
+            status: Some(2),
+            app_id: Some(app.id),
+            locale: Some(locale.to_string()),
+            translation_of,
+            ..CreateArticleRequest::default()
+        };
+        let original = ArticleRepository::create(repository.as_ref(), &article("Hello", "en", None), 1).await.unwrap();
+        ArticleRepository::create(repository.as_ref(), &article("Hallo", "de", Some(original.id)), 1).await.unwrap();
+        (service, repository, app)
+    }
+
+    fn options(strategy: ConflictStrategy, dry_run: bool, identifier: Option<&str>) -> ImportOptions {
+        ImportOptions {
+            strategy,
+            dry_run,
+            identifier: identifier.map(str::to_string),
+        }
+    }
+
+    #[actix_web::test]
+    async fn imports_remap_ids_and_keep_timestamps() {
+        let (service, repository, app) = setup().await;
+        let mut archive = service.export(app.id, 1, 1).await.unwrap();
+        assert_eq!(archive.app.feed_title.as_deref(), Some("Blog feed"));
+        assert_eq!(archive.articles.len(), 2);
+        assert_eq!(archive.media.len(), 2);
+        let created_at = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+        archive.articles[0].created_at = created_at;
+        // 译文排在原文前面时也先导入原文
+        archive.articles.reverse();
+
+        let report = service
+            .import(&archive, &options(ConflictStrategy::Skip, false, Some("copy")), 2, 1)
+            .await
+            .unwrap();
+        assert_eq!(report.app.action, ImportAction::Create);
+        let copy = repository.find_by_identifier("copy").await.unwrap().unwrap();
+        assert_eq!((copy.creator_id, copy.feed_title.as_deref()), (2, Some("Blog feed")));
+        let articles = repository.list_in_app(copy.id).await.unwrap();
+        assert_eq!(articles.len(), 2);
+        assert!(report.articles.iter().all(|item| item.action == ImportAction::Create));
+        assert_eq!(articles[0].title, "Hello");
+        assert_eq!(articles[0].created_at, created_at);
+        assert_eq!(articles[1].translation_of, Some(articles[0].id));
+        assert_eq!(articles[1].author_id, 2);
+        assert!(report.warnings.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn conflicts_follow_the_strategy() {
+        let (service, repository, app) = setup().await;
+        let mut archive = service.export(app.id, 1, 1).await.unwrap();
+        archive.app.feed_title = Some("New feed".to_string());
+        archive.articles[0].content = "Updated".to_string();
+
+        // 试运行不做任何修改，新建的对象没有 id
+        let report = service
+            .import(&archive, &options(ConflictStrategy::Overwrite, true, None), 1, 1)
+            .await
+            .unwrap();
+        assert_eq!(report.app.action, ImportAction::Overwrite);
+        assert!(report.articles.iter().all(|item| item.action == ImportAction::Overwrite));
+        let feed_title = |app: Option<App>| app.unwrap().feed_title;
+        let current = AppRepository::find_by_id(repository.as_ref(), app.id).await.unwrap();
+        assert_eq!(feed_title(current).as_deref(), Some("Blog feed"));
+        let report = service
+            .import(&archive, &options(ConflictStrategy::Rename, true, None), 1, 1)
+            .await
+            .unwrap();
+        assert_eq!(
+            (report.app.action, report.app.name.as_str(), report.app.id),
+            (ImportAction::Rename, "blog-2", None)
+        );
+        assert!(repository.find_by_identifier("blog-2").await.unwrap().is_none());
+
+        let report = service
+            .import(&archive, &options(ConflictStrategy::Skip, false, None), 1, 1)
+            .await
+            .unwrap();
+        assert_eq!(report.app.action, ImportAction::Skip);
+        assert!(report.articles.iter().all(|item| item.action == ImportAction::Skip && item.id.is_some()));
+        assert_eq!(repository.list_in_app(app.id).await.unwrap().len(), 2);
+
+        service
+            .import(&archive, &options(ConflictStrategy::Overwrite, false, None), 1, 1)
+            .await
+            .unwrap();
+        let current = AppRepository::find_by_id(repository.as_ref(), app.id).await.unwrap();
+        assert_eq!(feed_title(current).as_deref(), Some("New feed"));
+        let articles = repository.list_in_app(app.id).await.unwrap();
+        assert_eq!(articles.len(), 2);
+        assert_eq!(articles[0].content, "Updated");
+
+        // 导入文件中重复的文章改名
+        archive.articles[1].title = "Hello".to_string();
+        archive.articles[1].locale = Some("en".to_string());
+        archive.articles[1].translation_of = None;
+        let report = service
+            .import(&archive, &options(ConflictStrategy::Rename, false, None), 1, 1)
+            .await
+            .unwrap();
+        assert_eq!(report.app.name, "blog-2");
+        let names: Vec<(ImportAction, &str)> =
+            report.articles.iter().map(|item| (item.action, item.name.as_str())).collect();
+        assert_eq!(names, vec![(ImportAction::Create, "Hello"), (ImportAction::Rename, "Hello (2)")]);
+    }
+
+    #[actix_web::test]
+    async fn other_users_cannot_export_or_import_into_the_app() {
+        let (service, _, app) = setup().await;
+        let archive = service.export(app.id, 1, 1).await.unwrap();
+        assert!(matches!(service.export(app.id, 2, 1).await, Err(AppError::Forbidden(_))));
+        let result = service.import(&archive, &options(ConflictStrategy::Skip, true, None), 2, 1).await;
+        assert!(matches!(result, Err(AppError::Forbidden(_))));
+
+        // 缺少原文的译文作为原文导入
+        let mut orphan = archive.clone();
+        orphan.articles.remove(0);
+        let report = service
+            .import(&orphan, &options(ConflictStrategy::Skip, true, Some("orphan")), 2, 1)
+            .await
+            .unwrap();
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!(report.articles[0].action, ImportAction::Create);
+
+        let result = service
+            .import(&archive, &options(ConflictStrategy::Skip, true, Some("Not Valid")), 2, 1)
+            .await;
+        assert!(matches!(result, Err(AppError::ValidationError(_))));
+    }
+
+    #[actix_web::test]
+    async fn two_factor_apps_need_a_two_factor_session() {
+        let (service, repository, _) = setup().await;
+        let app = CreateAppRequest {
+            require_two_factor: true,
+            ..app_request("Vault", "vault")
+        };
+        let app = AppRepository::create(repository.as_ref(), &app, 1).await.unwrap();
+
+        assert!(matches!(service.export(app.id, 1, 1).await, Err(AppError::Forbidden(_))));
+        let archive = service.export(app.id, 1, 2).await.unwrap();
+        // 跳过和覆盖都作用于已有的应用，试运行也需要两步验证
+        for strategy in [ConflictStrategy::Skip, ConflictStrategy::Overwrite] {
+            let result = service.import(&archive, &options(strategy, true, None), 1, 1).await;
+            assert!(matches!(result, Err(AppError::Forbidden(_))));
+            assert!(service.import(&archive, &options(strategy, true, None), 1, 2).await.is_ok());
+        }
+    }
+}
@@ -0,0 +1,404 @@
+use crate::db::{AppRepository, ArticleFilter, ArticleRepository, Page};
+use crate::models::article::{CreateArticleRequest, UpdateArticleRequest};
+use crate::models::Article;
+use crate::services::app::require_two_factor;
+use crate::services::ChangeFeedService;
+use crate::utils::precondition::Precondition;
+use crate::utils::AppError;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct ArticleService {
+    articles: Arc<dyn ArticleRepository>,
+    apps: Arc<dyn AppRepository>,
+    changes: Option<ChangeFeedService>,
+}
+
+impl ArticleService {
+    pub fn new(articles: Arc<dyn ArticleRepository>, apps: Arc<dyn AppRepository>) -> Self {
+        Self {
+            articles,
+            apps,
+            changes: None,
+        }
+    }
+
+    pub fn with_change_feed(mut self, changes: ChangeFeedService) -> Self {
+        self.changes = Some(changes);
+        self
+    }
+
+    // 事件已由仓储写入，通知订阅者；不属于任何应用的文章没有变更推送
+    fn notify(&self, article: &Article) {
+        if let (Some(changes), Some(app_id)) = (&self.changes, article.app_id) {
+            changes.notify(app_id);
+        }
+    }
+
+    // 只能看到已发布的文章和自己的草稿
+    pub async fn get(&self, id: i64, viewer_id: i64) -> Result<Article, AppError> {
+        self.articles
+            .find_visible(id, viewer_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Article not found".to_string()))
+    }
+
+    pub async fn list(&self, viewer_id: i64) -> Result<Vec<Article>, AppError> {
+        Ok(self.articles.list_visible(viewer_id, &ArticleFilter::default(), None).await?)
+    }
+
+    // 返回当前页的文章和总数
+    pub async fn list_page(
+        &self,
+        viewer_id: i64,
+        filter: &ArticleFilter,
+        page: i64,
+        page_size: i64,
+    ) -> Result<(Vec<Article>, i64), AppError> {
+        let articles = self
+            .articles
+            .list_visible(viewer_id, filter, Some(Page::new(page, page_size)))
+            .await?;
+        let total = self.articles.count_visible(viewer_id, filter).await?;
+        Ok((articles, total))
+    }
+
+    // 所属应用要求两步验证时，修改其中的文章也需要两步验证登录
+    async fn require_two_factor(&self, app_id: Option<i64>, aal: u8) -> Result<(), AppError> {
+        if let Some(app) = match app_id {
+            Some(app_id) => self.apps.find_by_id(app_id).await?,
+            None => None,
+        } {
+            require_two_factor(app.require_two_factor != 0, aal)?;
+        }
+        Ok(())
+    }
+
+    // 文章只能放在未删除的应用下；aal 为当前登录的认证强度
+    pub async fn create(&self, req: &CreateArticleRequest, author_id: i64, aal: u8) -> Result<Article, AppError> {
+        if let Some(app_id) = req.app_id {
+            let app = self
+                .apps
+                .find_by_id(app_id)
+                .await?
+                .ok_or_else(|| AppError::NotFound("App not found".to_string()))?;
+            require_two_factor(app.require_two_factor != 0, aal)?;
+        }
+        // 译文指向原文，原文本身不能是译文
+        if let Some(original_id) = req.translation_of {
+            let original = self
+                .articles
+                .find_visible(original_id, author_id)
+                .await?
+                .ok_or_else(|| AppError::NotFound("Original article not found".to_string()))?;
+            if original.translation_of.is_some() || original.app_id.is_none() || original.app_id != req.app_id {
+                return Err(AppError::BadRequest(
+                    "A translation must belong to the same app as an original article".to_string(),
+                ));
+            }
+        }
+        let article = self.articles.create(req, author_id).await?;
+        self.notify(&article);
+        Ok(article)
+    }
+
+    // 只有作者可以修改文章；返回修改前后的文章
+    pub async fn update(
+        &self,
+        id: i64,
+        author_id: i64,
+        aal: u8,
+        req: &UpdateArticleRequest,
+        precondition: &Precondition,
+    ) -> Result<(Article, Article), AppError> {
+        let changed = req.title.is_some()
+            || req.content.is_some()
+            || req.status.is_some()
+            || req.category.is_some()
+            || req.locale.is_some()
+            || req.meta_title.is_some()
+            || req.meta_description.is_some()
+            || req.canonical_url.is_some()
+            || req.og_image_url.is_some()
+            || req.noindex.is_some();
+        if !changed {
+            return Err(AppError::BadRequest("No fields to update".to_string()));
+        }
+
+        let not_found =
+            || AppError::NotFound("Article not found or you don't have permission to update it".to_string());
+        let before = self.articles.find_visible(id, author_id).await?.ok_or_else(not_found)?;
+        if before.author_id != author_id {
+            return Err(not_found());
+        }
+        self.require_two_factor(before.app_id, aal).await?;
+        precondition.check(before.version)?;
+        match self.articles.update(id, author_id, req, before.version).await? {
+            Some(after) => {
+                self.notify(&after);
+                Ok((before, after))
+            }
+            // 检查之后被其他请求修改或删除
+            None => {
+                let current = self.articles.find_visible(id, author_id).await?.ok_or_else(not_found)?;
+                Err(AppError::PreconditionFailed(current.version))
+            }
+        }
+    }
+
+    // 移入回收站；返回被删除的文章
+    pub async fn delete(&self, id: i64, author_id: i64, aal: u8) -> Result<Article, AppError> {
+        let not_found =
+            || AppError::NotFound("Article not found or you don't have permission to delete it".to_string());
+        let article = self.articles.find_visible(id, author_id).await?.ok_or_else(not_found)?;
+        if article.author_id != author_id {
+            return Err(not_found());
+        }
+        self.require_two_factor(article.app_id, aal).await?;
+        if !self.articles.trash(id, author_id).await? {
+            return Err(not_found());
+        }
+        self.notify(&article);
+        Ok(article)
+    }
+
+    // 自己回收站中的文章，包括随应用一起删除的文章
+    pub async fn list_trash(&self, author_id: i64) -> Result<Vec<Article>, AppError> {
+        Ok(self.articles.list_trashed(author_id).await?)
+    }
+
+    // 所属应用在回收站中时需要先恢复应用
+    pub async fn restore(&self, id: i64, author_id: i64, aal: u8) -> Result<Article, AppError> {
+        let not_found = || AppError::NotFound("Article not found in trash".to_string());
+        let article = self.articles.find_trashed(id, author_id).await?.ok_or_else(not_found)?;
+        if let Some(app_id) = article.app_id {
+            let app = self.apps.find_by_id(app_id).await?.ok_or_else(|| {
+                AppError::Conflict("The app of this article is in the trash, restore the app first".to_string())
+            })?;
+            require_two_factor(app.require_two_factor != 0, aal)?;
+        }
+        if !self.articles.restore(id, author_id).await? {
+            return Err(not_found());
+        }
+        self.notify(&article);
+        self.get(id, author_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory::MemoryRepository;
+    use crate::models::CreateAppRequest;
+    use crate::test_support::app_request;
+
+    fn repository() -> Arc<MemoryRepository> {
+        Arc::new(MemoryRepository::new())
+    }
+
+    fn service() -> ArticleService {
+        let repository = repository();
+        ArticleService::new(repository.clone(), repository)
+    }
+
+    fn draft(title: &str) -> CreateArticleRequest {
+        CreateArticleRequest {
+            title: title.to_string(),
+            content: "Content".to_string(),
+            status: None,
+            app_id: None,
+            category: None,
+            ..Default::default()
+        }
+    }
+
+    #[actix_web::test]
+    async fn drafts_are_only_visible_to_their_author() {
+        let service = service();
+        let article = service.create(&draft("Draft"), 1, 1).await.unwrap();
+
+        assert_eq!(service.get(article.id, 1).await.unwrap().status, 1);
+        assert!(matches!(service.get(article.id, 2).await, Err(AppError::NotFound(_))));
+        assert!(service.list(2).await.unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn only_the_author_can_update_or_delete() {
+        let service = service();
+        let article = service.create(&draft("Draft"), 1, 1).await.unwrap();
+        let publish = UpdateArticleRequest {
+            title: None,
+            content: None,
+            status: Some(2),
+            category: None,
+            version: None,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            service.update(article.id, 2, 1, &publish, &Precondition::Any).await,
+            Err(AppError::NotFound(_))
+        ));
+        assert_eq!(service.update(article.id, 1, 1, &publish, &Precondition::Any).await.unwrap().1.status, 2);
+        assert_eq!(service.list(2).await.unwrap().len(), 1);
+
+        assert!(matches!(service.delete(article.id, 2, 1).await, Err(AppError::NotFound(_))));
+        service.delete(article.id, 1, 1).await.unwrap();
+        assert!(matches!(service.get(article.id, 1).await, Err(AppError::NotFound(_))));
+        assert!(service.list_trash(2).await.unwrap().is_empty());
+        assert_eq!(service.list_trash(1).await.unwrap()[0].deleted_by, Some(1));
+
+        assert!(matches!(service.restore(article.id, 2, 1).await, Err(AppError::NotFound(_))));
+        assert!(service.restore(article.id, 1, 1).await.unwrap().deleted_at.is_none());
+    }
+
+    #[actix_web::test]
+    async fn articles_follow_their_app_into_the_trash() {
+        let repository = repository();
+        let service = ArticleService::new(repository.clone(), repository.clone());
+        let app = AppRepository::create(
+            repository.as_ref(),
+            &app_request("Blog", "blog"),
+            1,
+        )
+        .await
+        .unwrap();
+        let in_app = CreateArticleRequest {
+            app_id: Some(app.id),
+            category: None,
+            ..draft("In app")
+        };
+        let article = service.create(&in_app, 1, 1).await.unwrap();
+        let removed_earlier = service.create(&in_app, 1, 1).await.unwrap();
+        service.delete(removed_earlier.id, 1, 1).await.unwrap();
+
+        AppRepository::trash(repository.as_ref(), app.id, 2).await.unwrap();
+        assert!(matches!(service.get(article.id, 1).await, Err(AppError::NotFound(_))));
+        assert!(matches!(service.create(&in_app, 1, 1).await, Err(AppError::NotFound(_))));
+        // 应用在回收站中时不能单独恢复文章
+        assert!(matches!(service.restore(article.id, 1, 1).await, Err(AppError::Conflict(_))));
+
+        // 恢复应用只恢复随它一起删除的文章
+        AppRepository::restore(repository.as_ref(), app.id).await.unwrap();
+        assert_eq!(service.get(article.id, 1).await.unwrap().app_id, Some(app.id));
+        assert!(matches!(service.get(removed_earlier.id, 1).await, Err(AppError::NotFound(_))));
+    }
+
+    #[actix_web::test]
+    async fn two_factor_apps_need_a_two_factor_session_for_articles() {
+        let repository = repository();
+        let service = ArticleService::new(repository.clone(), repository.clone());
+        let app = CreateAppRequest {
+            require_two_factor: true,
+            ..app_request("Vault", "vault")
+        };
+        let app = AppRepository::create(repository.as_ref(), &app, 1).await.unwrap();
+        let in_app = CreateArticleRequest {
+            app_id: Some(app.id),
+            ..draft("Secret")
+        };
+        let rename = UpdateArticleRequest {
+            title: Some("Renamed".to_string()),
+            ..Default::default()
+        };
+        let any = Precondition::Any;
+
+        // 单因素登录不能创建、修改、删除或恢复应用中的文章
+        assert!(matches!(service.create(&in_app, 1, 1).await, Err(AppError::Forbidden(_))));
+        let article = service.create(&in_app, 1, 2).await.unwrap();
+        assert!(matches!(service.update(article.id, 1, 1, &rename, &any).await, Err(AppError::Forbidden(_))));
+        service.update(article.id, 1, 2, &rename, &any).await.unwrap();
+        assert!(matches!(service.delete(article.id, 1, 1).await, Err(AppError::Forbidden(_))));
+        service.delete(article.id, 1, 2).await.unwrap();
+        assert!(matches!(service.restore(article.id, 1, 1).await, Err(AppError::Forbidden(_))));
+        service.restore(article.id, 1, 2).await.unwrap();
+
+        // 不属于应用的文章不受影响
+        service.create(&draft("Loose"), 1, 1).await.unwrap();
+    }
+
+    #[actix_web::test]
+    async fn empty_update_is_rejected() {
+        let service = service();
+        let article = service.create(&draft("Draft"), 1, 1).await.unwrap();
+        let empty = UpdateArticleRequest {
+            title: None,
+            content: None,
+            status: None,
+            category: None,
+            version: None,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            service.update(article.id, 1, 1, &empty, &Precondition::Any).await,
+            Err(AppError::BadRequest(_))
+        ));
+    }
+
+    #[actix_web::test]
+    async fn stale_versions_are_rejected() {
+        let service = service();
+        let article = service.create(&draft("Draft"), 1, 1).await.unwrap();
+        let rename = |title: &str| UpdateArticleRequest {
+            title: Some(title.to_string()),
+            content: None,
+            status: None,
+            category: None,
+            version: None,
+            ..Default::default()
+        };
+
+        let first = Precondition::Versions(vec![article.version]);
+        let (_, updated) = service.update(article.id, 1, 1, &rename("First"), &first).await.unwrap();
+        assert_eq!(updated.version, article.version + 1);
+
+        // 第二个编辑者基于旧版本保存，不能覆盖第一个人的修改
+        assert!(matches!(
+            service.update(article.id, 1, 1, &rename("Second"), &first).await,
+            Err(AppError::PreconditionFailed(version)) if version == updated.version
+        ));
+        assert_eq!(service.get(article.id, 1).await.unwrap().title, "First");
+    }
+
+    #[actix_web::test]
+    async fn changes_to_app_articles_are_published() {
+        use crate::config::change_feed::ChangeFeedConfig;
+        use crate::db::ChangeEventRepository;
+        use crate::models::change::{ARTICLE_CREATED, ARTICLE_DELETED, ARTICLE_PUBLISHED, ARTICLE_UPDATED};
+
+        let repository = repository();
+        let changes = ChangeFeedService::new(repository.clone(), ChangeFeedConfig::default());
+        let service = ArticleService::new(repository.clone(), repository.clone()).with_change_feed(changes);
+        let app = AppRepository::create(
+            repository.as_ref(),
+            &app_request("Blog", "blog"),
+            1,
+        )
+        .await
+        .unwrap();
+        let app_created = repository.last_id(app.id).await.unwrap();
+        let article = service
+            .create(&CreateArticleRequest { app_id: Some(app.id), ..draft("Draft") }, 1, 1)
+            .await
+            .unwrap();
+        let status = |status: i16| UpdateArticleRequest {
+            title: None,
+            content: None,
+            status: Some(status),
+            category: None,
+            version: None,
+            ..Default::default()
+        };
+        service.update(article.id, 1, 1, &status(2), &Precondition::Any).await.unwrap();
+        service.update(article.id, 1, 1, &status(2), &Precondition::Any).await.unwrap();
+        service.delete(article.id, 1, 1).await.unwrap();
+        // 不属于应用的文章没有事件
+        service.create(&draft("Loose"), 1, 1).await.unwrap();
+
+        let events = repository.list_since(app.id, app_created, 10).await.unwrap();
+        let types: Vec<&str> = events.iter().map(|event| event.event_type.as_str()).collect();
+        assert_eq!(types, [ARTICLE_CREATED, ARTICLE_PUBLISHED, ARTICLE_UPDATED, ARTICLE_DELETED]);
+        assert!(events.iter().all(|event| event.target_id == article.id));
+    }
+}
@@ -0,0 +1,167 @@
+use crate::db::{AuditFilter, AuditRepository, Page};
+use crate::models::audit::{AuditLogResponse, NewAuditLog};
+use crate::utils::clock::{Clock, SystemClock};
+use crate::utils::AppError;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::sync::Arc;
+
+// 每次更新都会变化，不写入差异
+const IGNORED_FIELDS: &[&str] = &["updated_at", "updater_id", "version"];
+
+// 一次变更，操作者和请求信息由 Audit 提取器补充
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    pub action: &'static str,
+    pub target_type: &'static str,
+    pub target_id: Option<i64>,
+    pub app_id: Option<i64>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    // 登录、注册等请求没有 token，由事件指定操作者
+    pub actor_id: Option<i64>,
+}
+
+impl AuditEvent {
+    pub fn new(action: &'static str, target_type: &'static str, target_id: Option<i64>) -> Self {
+        Self {
+            action,
+            target_type,
+            target_id,
+            app_id: None,
+            before: None,
+            after: None,
+            actor_id: None,
+        }
+    }
+
+    pub fn created(action: &'static str, target_type: &'static str, target_id: i64, after: &impl Serialize) -> Self {
+        Self::new(action, target_type, Some(target_id)).with_after(to_value(after))
+    }
+
+    // 只记录变化的字段
+    pub fn updated(
+        action: &'static str,
+        target_type: &'static str,
+        target_id: i64,
+        before: &impl Serialize,
+        after: &impl Serialize,
+    ) -> Self {
+        let (before, after) = diff(&to_value(before), &to_value(after));
+        let mut event = Self::new(action, target_type, Some(target_id));
+        event.before = Some(before);
+        event.after = Some(after);
+        event
+    }
+
+    pub fn deleted(action: &'static str, target_type: &'static str, target_id: i64, before: &impl Serialize) -> Self {
+        let mut event = Self::new(action, target_type, Some(target_id));
+        event.before = Some(to_value(before));
+        event
+    }
+
+    pub fn with_app(mut self, app_id: i64) -> Self {
+        self.app_id = Some(app_id);
+        self
+    }
+
+    pub fn with_after(mut self, after: Value) -> Self {
+        self.after = Some(after);
+        self
+    }
+
+    pub fn by_user(mut self, user_id: i64) -> Self {
+        self.actor_id = Some(user_id);
+        self
+    }
+}
+
+fn to_value(value: &impl Serialize) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+// 对比两个 JSON 对象，返回变化字段的旧值和新值
+pub fn diff(before: &Value, after: &Value) -> (Value, Value) {
+    let (Value::Object(before), Value::Object(after)) = (before, after) else {
+        return (before.clone(), after.clone());
+    };
+
+    let mut old = Map::new();
+    let mut new = Map::new();
+    for key in before.keys().chain(after.keys().filter(|key| !before.contains_key(*key))) {
+        if IGNORED_FIELDS.contains(&key.as_str()) {
+            continue;
+        }
+        let old_value = before.get(key).unwrap_or(&Value::Null);
+        let new_value = after.get(key).unwrap_or(&Value::Null);
+        if old_value != new_value {
+            old.insert(key.clone(), old_value.clone());
+            new.insert(key.clone(), new_value.clone());
+        }
+    }
+    (Value::Object(old), Value::Object(new))
+}
+
+#[derive(Clone)]
+pub struct AuditService {
+    logs: Arc<dyn AuditRepository>,
+    clock: Arc<dyn Clock>,
+    // 为 true 时从 X-Forwarded-For 读取客户端 IP
+    trust_proxy: bool,
+}
+
+impl AuditService {
+    pub fn new(logs: Arc<dyn AuditRepository>) -> Self {
+        Self {
+            logs,
+            clock: Arc::new(SystemClock),
+            trust_proxy: false,
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_trust_proxy(mut self, trust_proxy: bool) -> Self {
+        self.trust_proxy = trust_proxy;
+        self
+    }
+
+    pub fn trust_proxy(&self) -> bool {
+        self.trust_proxy
+    }
+
+    pub async fn append(&self, entry: &NewAuditLog) -> Result<i64, AppError> {
+        Ok(self.logs.append(entry, self.clock.now()).await?)
+    }
+
+    // 返回当前页的记录和总数，最新的在前
+    pub async fn list(
+        &self,
+        filter: &AuditFilter,
+        page: i64,
+        page_size: i64,
+    ) -> Result<(Vec<AuditLogResponse>, i64), AppError> {
+        let logs = self.logs.list(filter, Page::new(page, page_size)).await?;
+        let total = self.logs.count(filter).await?;
+        Ok((logs.into_iter().map(AuditLogResponse::from).collect(), total))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn diff_keeps_only_changed_fields() {
+        let before = json!({ "name": "Blog", "description": "Old", "updated_at": "2024-01-01T00:00:00Z", "logo_url": null });
+        let after = json!({ "name": "Blog", "description": "New", "updated_at": "2024-01-02T00:00:00Z", "logo_url": "https://example.com/logo.png" });
+
+        let (old, new) = diff(&before, &after);
+        assert_eq!(old, json!({ "description": "Old", "logo_url": null }));
+        assert_eq!(new, json!({ "description": "New", "logo_url": "https://example.com/logo.png" }));
+    }
+}
@@ -0,0 +1,192 @@
+use crate::config::change_feed::ChangeFeedConfig;
+use crate::db::ChangeEventRepository;
+use crate::models::change::ChangeEvent;
+use crate::utils::AppError;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio::time::{Instant, Interval};
+
+// 每次从事件表读取的数量
+const REPLAY_LIMIT: i64 = 100;
+// 通知只携带应用 id，落后的订阅者直接重新查询事件表
+const WAKEUP_BUFFER: usize = 256;
+
+// 应用和文章的变更推送；事件由仓储在修改的同一事务中写入事件表，提交后再通知本进程的订阅者
+#[derive(Clone)]
+pub struct ChangeFeedService {
+    events: Arc<dyn ChangeEventRepository>,
+    wakeups: broadcast::Sender<i64>,
+    config: ChangeFeedConfig,
+}
+
+// 一个客户端的订阅，从 last_id 之后开始读取
+pub struct ChangeSubscription {
+    events: Arc<dyn ChangeEventRepository>,
+    wakeups: broadcast::Receiver<i64>,
+    poll: Interval,
+    app_id: i64,
+    last_id: i64,
+    pending: VecDeque<ChangeEvent>,
+}
+
+impl ChangeFeedService {
+    pub fn new(events: Arc<dyn ChangeEventRepository>, config: ChangeFeedConfig) -> Self {
+        Self {
+            events,
+            wakeups: broadcast::channel(WAKEUP_BUFFER).0,
+            config,
+        }
+    }
+
+    // 应用的事件已经提交，唤醒本进程的订阅者；错过通知的订阅者由定期查询补上
+    pub fn notify(&self, app_id: i64) {
+        // 没有订阅者时发送失败，可以忽略
+        let _ = self.wakeups.send(app_id);
+    }
+
+    // since 为上次收到的事件 id，为 None 时只接收之后的新事件
+    pub async fn subscribe(&self, app_id: i64, since: Option<i64>) -> Result<ChangeSubscription, AppError> {
+        // 先订阅通知再确定起点，避免漏掉两者之间写入的事件
+        let wakeups = self.wakeups.subscribe();
+        let last_id = match since {
+            Some(since) => since.max(0),
+            None => self.events.last_id(app_id).await?,
+        };
+        let period = self.config.poll_interval;
+        Ok(ChangeSubscription {
+            events: self.events.clone(),
+            wakeups,
+            poll: tokio::time::interval_at(Instant::now() + period, period),
+            app_id,
+            last_id,
+            pending: VecDeque::new(),
+        })
+    }
+}
+
+impl ChangeSubscription {
+    // 下一个事件，没有新事件时等待通知或定期查询；可以在 select! 中取消而不丢失事件
+    pub async fn next(&mut self) -> Result<ChangeEvent, AppError> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                self.last_id = event.id;
+                return Ok(event);
+            }
+            let events = self.events.list_since(self.app_id, self.last_id, REPLAY_LIMIT).await?;
+            if !events.is_empty() {
+                self.pending.extend(events);
+                continue;
+            }
+            self.wait().await;
+        }
+    }
+
+    // 取出已经积压的事件，最多一页，用于连接建立后立即发送
+    pub async fn backlog(&mut self) -> Result<Vec<ChangeEvent>, AppError> {
+        let mut events: Vec<ChangeEvent> = self.pending.drain(..).collect();
+        if events.is_empty() {
+            events = self.events.list_since(self.app_id, self.last_id, REPLAY_LIMIT).await?;
+        }
+        if let Some(last) = events.last() {
+            self.last_id = last.id;
+        }
+        Ok(events)
+    }
+
+    async fn wait(&mut self) {
+        loop {
+            tokio::select! {
+                woken = self.wakeups.recv() => match woken {
+                    Ok(app_id) if app_id != self.app_id => continue,
+                    // 落后时可能错过了本应用的通知
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => return,
+                    // 服务一直持有发送端，只剩定期查询
+                    Err(broadcast::error::RecvError::Closed) => {
+                        self.poll.tick().await;
+                        return;
+                    }
+                },
+                _ = self.poll.tick() => return,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory::MemoryRepository;
+    use crate::db::{AppRepository, ArticleRepository};
+    use crate::models::article::CreateArticleRequest;
+    use crate::models::change::{APP_CREATED, ARTICLE_CREATED};
+    use crate::test_support::app_request;
+    use std::time::Duration;
+
+    fn setup() -> (ChangeFeedService, Arc<MemoryRepository>) {
+        let repository = Arc::new(MemoryRepository::new());
+        (ChangeFeedService::new(repository.clone(), ChangeFeedConfig::default()), repository)
+    }
+
+    // 仓储写入 app.created 事件
+    async fn create_app(repository: &MemoryRepository, identifier: &str) -> i64 {
+        AppRepository::create(repository, &app_request(identifier, identifier), 1).await.unwrap().id
+    }
+
+    // 仓储写入 article.created 事件
+    async fn create_article(repository: &MemoryRepository, app_id: i64) -> i64 {
+        let article = CreateArticleRequest {
+            title: "Title".to_string(),
+            content: "Content".to_string(),
+            app_id: Some(app_id),
+            ..Default::default()
+        };
+        ArticleRepository::create(repository, &article, 1).await.unwrap().id
+    }
+
+    async fn next(subscription: &mut ChangeSubscription) -> ChangeEvent {
+        tokio::time::timeout(Duration::from_secs(1), subscription.next())
+            .await
+            .expect("event should arrive")
+            .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn subscribers_receive_new_events_of_their_app() {
+        let (service, repository) = setup();
+        let blog = create_app(&repository, "blog").await;
+        let docs = create_app(&repository, "docs").await;
+
+        let mut subscription = service.subscribe(blog, None).await.unwrap();
+        let waiting = tokio::spawn(async move { next(&mut subscription).await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        create_article(&repository, docs).await;
+        service.notify(docs);
+        let article = create_article(&repository, blog).await;
+        service.notify(blog);
+
+        let event = waiting.await.unwrap();
+        assert_eq!((event.app_id, event.event_type.as_str(), event.target_id), (blog, ARTICLE_CREATED, article));
+    }
+
+    #[actix_web::test]
+    async fn subscriptions_resume_after_the_last_seen_event() {
+        let (service, repository) = setup();
+        let blog = create_app(&repository, "blog").await;
+        let mut articles = Vec::new();
+        for _ in 0..2 {
+            articles.push(create_article(&repository, blog).await);
+        }
+
+        let mut subscription = service.subscribe(blog, Some(0)).await.unwrap();
+        let first = next(&mut subscription).await;
+        assert_eq!((first.event_type.as_str(), first.target_id), (APP_CREATED, blog));
+        let backlog = subscription.backlog().await.unwrap();
+        assert_eq!(backlog.iter().map(|event| event.target_id).collect::<Vec<_>>(), articles);
+        assert!(subscription.backlog().await.unwrap().is_empty());
+
+        let mut resumed = service.subscribe(blog, Some(first.id)).await.unwrap();
+        assert_eq!(next(&mut resumed).await.target_id, articles[0]);
+        assert_eq!(next(&mut resumed).await.target_id, articles[1]);
+    }
+}
@@ -0,0 +1,367 @@
+use crate::config::edit_lock::EditLockConfig;
+use crate::models::edit_lock::{EditEvent, EditLock, EditLockGrant, EditLockStatus, Editor, LockChange};
+use crate::utils::clock::{Clock, SystemClock};
+use crate::utils::AppError;
+use chrono::Duration;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+// 每篇文章缓存的事件数，落后更多的连接会重新收到完整状态
+const EVENT_BUFFER: usize = 64;
+
+struct HeldLock {
+    lock: EditLock,
+    token: String,
+}
+
+struct Room {
+    lock: Option<HeldLock>,
+    // 每个连接一项，同一用户可以打开多个连接
+    connections: Vec<(u64, Editor)>,
+    events: broadcast::Sender<EditEvent>,
+}
+
+impl Room {
+    fn new() -> Self {
+        Self {
+            lock: None,
+            connections: Vec::new(),
+            events: broadcast::channel(EVENT_BUFFER).0,
+        }
+    }
+
+    fn lock(&self) -> Option<EditLock> {
+        self.lock.as_ref().map(|held| held.lock.clone())
+    }
+
+    // 按加入顺序去重后的用户
+    fn editors(&self) -> Vec<Editor> {
+        let mut editors: Vec<Editor> = Vec::new();
+        for (_, editor) in &self.connections {
+            if !editors.iter().any(|existing| existing.user_id == editor.user_id) {
+                editors.push(editor.clone());
+            }
+        }
+        editors
+    }
+
+    fn status(&self) -> EditLockStatus {
+        EditLockStatus {
+            lock: self.lock(),
+            editors: self.editors(),
+        }
+    }
+
+    // 没有订阅者时发送失败，可以忽略
+    fn publish(&self, event: EditEvent) {
+        let _ = self.events.send(event);
+    }
+
+    fn is_idle(&self) -> bool {
+        self.lock.is_none() && self.connections.is_empty()
+    }
+}
+
+// 文章编辑锁和在线编辑者，保存在进程内，适合单实例部署
+#[derive(Clone)]
+pub struct EditLockService {
+    rooms: Arc<Mutex<HashMap<i64, Room>>>,
+    next_connection: Arc<AtomicU64>,
+    clock: Arc<dyn Clock>,
+    config: EditLockConfig,
+}
+
+// 一个 WebSocket 连接的订阅，drop 时离开并通知其他编辑者
+pub struct EditorSession {
+    pub events: broadcast::Receiver<EditEvent>,
+    article_id: i64,
+    connection_id: u64,
+    service: EditLockService,
+}
+
+impl EditorSession {
+    pub fn status(&self) -> EditLockStatus {
+        self.service.status(self.article_id)
+    }
+}
+
+impl Drop for EditorSession {
+    fn drop(&mut self) {
+        self.service.leave(self.article_id, self.connection_id);
+    }
+}
+
+impl EditLockService {
+    pub fn new(config: EditLockConfig) -> Self {
+        Self {
+            rooms: Arc::new(Mutex::new(HashMap::new())),
+            next_connection: Arc::new(AtomicU64::new(1)),
+            clock: Arc::new(SystemClock),
+            config,
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    // 对一篇文章的房间执行操作，先释放已过期的锁，操作后清理空房间
+    fn with_room<T>(&self, article_id: i64, f: impl FnOnce(&mut Room) -> T) -> T {
+        let mut rooms = self.rooms.lock().unwrap();
+        let room = rooms.entry(article_id).or_insert_with(Room::new);
+        self.expire(room);
+        let result = f(room);
+        if room.is_idle() && room.events.receiver_count() == 0 {
+            rooms.remove(&article_id);
+        }
+        result
+    }
+
+    fn expire(&self, room: &mut Room) -> bool {
+        let now = self.clock.now();
+        if room.lock.as_ref().is_some_and(|held| held.lock.expires_at <= now) {
+            room.lock = None;
+            room.publish(EditEvent::Lock {
+                lock: None,
+                change: LockChange::Expired,
+            });
+            return true;
+        }
+        false
+    }
+
+    fn grant(&self, room: &mut Room, article_id: i64, user_id: i64, change: LockChange) -> EditLockGrant {
+        let now = self.clock.now();
+        let held = HeldLock {
+            lock: EditLock {
+                article_id,
+                user_id,
+                acquired_at: now,
+                expires_at: now + Duration::seconds(self.config.ttl_secs),
+            },
+            token: uuid::Uuid::new_v4().to_string(),
+        };
+        let grant = EditLockGrant {
+            lock: held.lock.clone(),
+            token: held.token.clone(),
+        };
+        room.lock = Some(held);
+        room.publish(EditEvent::Lock {
+            lock: Some(grant.lock.clone()),
+            change,
+        });
+        grant
+    }
+
+    pub fn status(&self, article_id: i64) -> EditLockStatus {
+        self.with_room(article_id, |room| room.status())
+    }
+
+    // 锁被其他会话持有时返回冲突，包括同一用户的其他标签页
+    pub fn acquire(&self, article_id: i64, user_id: i64) -> Result<EditLockGrant, AppError> {
+        self.with_room(article_id, |room| {
+            if let Some(held) = &room.lock {
+                return Err(AppError::Conflict(format!(
+                    "Article is being edited by user {} until {}",
+                    held.lock.user_id,
+                    held.lock.expires_at.to_rfc3339()
+                )));
+            }
+            Ok(self.grant(room, article_id, user_id, LockChange::Acquired))
+        })
+    }
+
+    // 强制取得锁，原持有者的 token 随之失效；管理员可以取走任何人的锁，其他用户只能取回自己持有的锁
+    // （例如另一个标签页中的），返回被取走的锁
+    pub fn take(
+        &self,
+        article_id: i64,
+        user_id: i64,
+        is_admin: bool,
+    ) -> Result<(EditLockGrant, Option<EditLock>), AppError> {
+        self.with_room(article_id, |room| {
+            let previous = room.lock.as_ref().map(|held| held.lock.clone());
+            if !is_admin && previous.as_ref().is_some_and(|lock| lock.user_id != user_id) {
+                return Err(AppError::Forbidden(
+                    "Only an admin can take over the lock of another editor".to_string(),
+                ));
+            }
+            Ok((self.grant(room, article_id, user_id, LockChange::Taken), previous))
+        })
+    }
+
+    // 心跳续期；锁已过期或被其他人取走时返回冲突
+    pub fn heartbeat(&self, article_id: i64, token: &str) -> Result<EditLock, AppError> {
+        let expires_at = self.clock.now() + Duration::seconds(self.config.ttl_secs);
+        self.with_room(article_id, |room| match &mut room.lock {
+            Some(held) if held.token == token => {
+                held.lock.expires_at = expires_at;
+                Ok(held.lock.clone())
+            }
+            _ => Err(lost_lock()),
+        })
+    }
+
+    pub fn release(&self, article_id: i64, token: &str) -> Result<(), AppError> {
+        self.with_room(article_id, |room| {
+            if room.lock.as_ref().is_none_or(|held| held.token != token) {
+                return Err(lost_lock());
+            }
+            room.lock = None;
+            room.publish(EditEvent::Lock {
+                lock: None,
+                change: LockChange::Released,
+            });
+            Ok(())
+        })
+    }
+
+    // 释放所有过期的锁并通知编辑者，返回释放的数量
+    pub fn expire_due(&self) -> usize {
+        let mut rooms = self.rooms.lock().unwrap();
+        let mut expired = 0;
+        for room in rooms.values_mut() {
+            if self.expire(room) {
+                expired += 1;
+            }
+        }
+        rooms.retain(|_, room| !room.is_idle() || room.events.receiver_count() > 0);
+        expired
+    }
+
+    // 加入文章的在线编辑者，返回订阅和当前状态
+    pub fn join(&self, article_id: i64, user_id: i64, username: &str) -> (EditorSession, EditLockStatus) {
+        let connection_id = self.next_connection.fetch_add(1, Ordering::Relaxed);
+        let editor = Editor {
+            user_id,
+            username: username.to_string(),
+            joined_at: self.clock.now(),
+        };
+        let (events, status) = self.with_room(article_id, |room| {
+            let events = room.events.subscribe();
+            room.connections.push((connection_id, editor));
+            room.publish(EditEvent::Presence {
+                editors: room.editors(),
+            });
+            (events, room.status())
+        });
+        let session = EditorSession {
+            events,
+            article_id,
+            connection_id,
+            service: self.clone(),
+        };
+        (session, status)
+    }
+
+    fn leave(&self, article_id: i64, connection_id: u64) {
+        let mut rooms = self.rooms.lock().unwrap();
+        let Some(room) = rooms.get_mut(&article_id) else {
+            return;
+        };
+        room.connections.retain(|(id, _)| *id != connection_id);
+        room.publish(EditEvent::Presence {
+            editors: room.editors(),
+        });
+        // 离开的连接的 receiver 还未释放，这里只看锁和连接
+        if room.is_idle() && room.events.receiver_count() <= 1 {
+            rooms.remove(&article_id);
+        }
+    }
+
+    // 后台定期释放过期的锁，让编辑者及时收到通知
+    pub fn spawn_sweeper(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(self.config.sweep_interval).await;
+                let expired = self.expire_due();
+                if expired > 0 {
+                    log::debug!("Released {} expired edit locks", expired);
+                }
+            }
+        })
+    }
+}
+
+fn lost_lock() -> AppError {
+    AppError::Conflict("You no longer hold the edit lock for this article".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::FixedClock;
+
+    fn service() -> (EditLockService, Arc<FixedClock>) {
+        let clock = Arc::new(FixedClock::default());
+        let service = EditLockService::new(EditLockConfig::default()).with_clock(clock.clone());
+        (service, clock)
+    }
+
+    #[test]
+    fn locks_are_exclusive_until_released_or_expired() {
+        let (service, clock) = service();
+        let grant = service.acquire(1, 10).unwrap();
+        assert!(matches!(service.acquire(1, 20), Err(AppError::Conflict(_))));
+        assert_eq!(service.status(1).lock.unwrap().user_id, 10);
+
+        // 心跳续期
+        clock.advance(Duration::seconds(60));
+        let renewed = service.heartbeat(1, &grant.token).unwrap();
+        assert_eq!(renewed.expires_at, clock.now() + Duration::seconds(90));
+        clock.advance(Duration::seconds(60));
+        assert!(service.status(1).lock.is_some());
+
+        // 没有心跳后过期，旧 token 失效
+        clock.advance(Duration::seconds(90));
+        assert!(service.status(1).lock.is_none());
+        assert!(matches!(service.heartbeat(1, &grant.token), Err(AppError::Conflict(_))));
+
+        let grant = service.acquire(1, 20).unwrap();
+        assert!(matches!(service.release(1, "other"), Err(AppError::Conflict(_))));
+        service.release(1, &grant.token).unwrap();
+        assert!(service.status(1).lock.is_none());
+    }
+
+    #[actix_web::test]
+    async fn editors_are_notified_of_presence_and_lock_changes() {
+        let (service, clock) = service();
+        let (mut alice, status) = service.join(1, 10, "alice");
+        assert_eq!(status.editors.len(), 1);
+        assert!(matches!(alice.events.recv().await.unwrap(), EditEvent::Presence { editors } if editors.len() == 1));
+
+        let (bob, _) = service.join(1, 20, "bob");
+        assert!(matches!(alice.events.recv().await.unwrap(), EditEvent::Presence { editors } if editors.len() == 2));
+
+        let grant = service.acquire(1, 20).unwrap();
+        assert!(matches!(
+            alice.events.recv().await.unwrap(),
+            EditEvent::Lock { lock: Some(lock), change: LockChange::Acquired } if lock.user_id == 20
+        ));
+
+        // 其他用户不能取走锁，管理员强制取得锁后原持有者不能续期
+        assert!(matches!(service.take(1, 30, false), Err(AppError::Forbidden(_))));
+        let (_, previous) = service.take(1, 10, true).unwrap();
+        assert_eq!(previous.unwrap().user_id, 20);
+        assert!(matches!(
+            alice.events.recv().await.unwrap(),
+            EditEvent::Lock { lock: Some(lock), change: LockChange::Taken } if lock.user_id == 10
+        ));
+        assert!(service.heartbeat(1, &grant.token).is_err());
+
+        clock.advance(Duration::seconds(91));
+        assert_eq!(service.expire_due(), 1);
+        assert!(matches!(
+            alice.events.recv().await.unwrap(),
+            EditEvent::Lock { lock: None, change: LockChange::Expired }
+        ));
+
+        drop(bob);
+        assert!(matches!(alice.events.recv().await.unwrap(), EditEvent::Presence { editors } if editors.len() == 1));
+        drop(alice);
+        assert!(service.rooms.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,172 @@
+use crate::config::feed::FeedConfig;
+use crate::db::{AppRepository, ArticleFilter, ArticleRepository, Page};
+use crate::models::feed::{FeedMode, FeedQuery};
+use crate::utils::feed::{summarize, Feed, FeedFormat, FeedItem};
+use crate::utils::token;
+use crate::utils::AppError;
+use chrono::DateTime;
+use std::sync::Arc;
+
+// 渲染好的订阅源，ETag 为内容的摘要
+#[derive(Debug, Clone)]
+pub struct RenderedFeed {
+    pub body: String,
+    pub etag: String,
+}
+
+// 应用已发布文章的 RSS、Atom 和 JSON Feed 订阅源
+#[derive(Clone)]
+pub struct FeedService {
+    apps: Arc<dyn AppRepository>,
+    articles: Arc<dyn ArticleRepository>,
+    config: FeedConfig,
+}
+
+impl FeedService {
+    pub fn new(apps: Arc<dyn AppRepository>, articles: Arc<dyn ArticleRepository>, config: FeedConfig) -> Self {
+        Self { apps, articles, config }
+    }
+
+    pub async fn render(&self, identifier: &str, format: FeedFormat, query: &FeedQuery) -> Result<RenderedFeed, AppError> {
+        let app = self
+            .apps
+            .find_by_identifier(identifier)
+            .await?
+            .ok_or_else(|| AppError::NotFound("App not found".to_string()))?;
+        let filter = ArticleFilter {
+            status: Some(2),
+            app_id: Some(app.id),
+            category: query.category.clone(),
+            ..ArticleFilter::default()
+        };
+        let page = Page {
+            limit: self.config.max_items,
+            offset: 0,
+        };
+        // 只有已发布的文章，viewer 0 不是任何文章的作者
+        let articles = self.articles.list_visible(0, &filter, Some(page)).await?;
+
+        let mode = query.mode.unwrap_or_default();
+        let url = self.feed_url(identifier, format, query);
+        let items: Vec<FeedItem> = articles
+            .into_iter()
+            .map(|article| FeedItem {
+                url: self
+                    .config
+                    .article_url
+                    .replace("{app}", identifier)
+                    .replace("{id}", &article.id.to_string()),
+                content: match mode {
+                    FeedMode::Full => article.content,
+                    FeedMode::Summary => summarize(&article.content, self.config.summary_length),
+                },
+                title: article.title,
+                category: article.category,
+                published: article.created_at,
+                updated: article.updated_at,
+            })
+            .collect();
+        let updated = items.iter().map(|item| item.updated).fold(app.updated_at, DateTime::max);
+        let feed = Feed {
+            title: app.feed_title.unwrap_or_else(|| app.name.clone()),
+            description: app.feed_description.unwrap_or(app.description),
+            link: app.feed_link.unwrap_or_else(|| url.clone()),
+            url,
+            author: app.name,
+            updated,
+            mode,
+            items,
+        };
+
+        let body = feed.render(format);
+        Ok(RenderedFeed {
+            etag: token::digest(&body),
+            body,
+        })
+    }
+
+    // 带上过滤参数，订阅源的 self 链接指向自身
+    fn feed_url(&self, identifier: &str, format: FeedFormat, query: &FeedQuery) -> String {
+        let base = format!("{}/{}/{}", self.config.url.trim_end_matches('/'), identifier, format.file_name());
+        let mut params = Vec::new();
+        if let Some(category) = &query.category {
+            params.push(("category", category.as_str()));
+        }
+        if query.mode == Some(FeedMode::Summary) {
+            params.push(("mode", "summary"));
+        }
+        match reqwest::Url::parse_with_params(&base, &params) {
+            Ok(url) if !params.is_empty() => url.to_string(),
+            _ => base,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory::MemoryRepository;
+    use crate::models::article::CreateArticleRequest;
+    use crate::models::CreateAppRequest;
+    use crate::test_support::app_request;
+
+    async fn setup() -> (FeedService, Arc<MemoryRepository>, i64) {
+        let repository = Arc::new(MemoryRepository::new());
+        let service = FeedService::new(repository.clone(), repository.clone(), FeedConfig::default());
+        let app = CreateAppRequest {
+            description: "Posts".to_string(),
+            feed_title: Some("The Blog".to_string()),
+            feed_link: Some("https://blog.example.com".to_string()),
+            ..app_request("Blog", "blog")
+        };
+        let app = AppRepository::create(repository.as_ref(), &app, 1).await.unwrap();
+        (service, repository, app.id)
+    }
+
+    async fn article(repository: &MemoryRepository, app_id: Option<i64>, status: i16, category: Option<&str>) {
+        let article = CreateArticleRequest {
+            title: format!("Status {}", status),
+            content: "word ".repeat(100),
+            status: Some(status),
+            app_id,
+            category: category.map(str::to_string),
+            ..Default::default()
+        };
+        ArticleRepository::create(repository, &article, 1).await.unwrap();
+    }
+
+    #[actix_web::test]
+    async fn feeds_contain_published_articles_of_the_app() {
+        let (service, repository, app_id) = setup().await;
+        article(&repository, Some(app_id), 2, Some("news")).await;
+        article(&repository, Some(app_id), 2, None).await;
+        article(&repository, Some(app_id), 1, Some("news")).await;
+        article(&repository, None, 2, Some("news")).await;
+
+        let query = FeedQuery {
+            category: None,
+            mode: None,
+        };
+        let rss = service.render("blog", FeedFormat::Rss, &query).await.unwrap();
+        assert_eq!(rss.body.matches("<item>").count(), 2);
+        assert!(rss.body.contains("<title>The Blog</title>"));
+        assert!(rss.body.contains("<link>https://blog.example.com</link>"));
+        assert!(rss.body.contains("<description>Posts</description>"));
+
+        let query = FeedQuery {
+            category: Some("news".to_string()),
+            mode: Some(FeedMode::Summary),
+        };
+        let atom = service.render("blog", FeedFormat::Atom, &query).await.unwrap();
+        assert_eq!(atom.body.matches("<entry>").count(), 1);
+        assert!(atom.body.contains("http://127.0.0.1:8080/feeds/blog/atom.xml?category=news&amp;mode=summary"));
+        assert!(atom.body.contains("word word…</summary>"));
+        assert_ne!(atom.etag, rss.etag);
+        assert_eq!(service.render("blog", FeedFormat::Atom, &query).await.unwrap().etag, atom.etag);
+
+        assert!(matches!(
+            service.render("missing", FeedFormat::Json, &query).await,
+            Err(AppError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,103 @@
+use crate::db::{JobFilter, JobRepository, Page};
+use crate::jobs::{Job, JobRegistry};
+use crate::models::job::{JobRecord, JobStatsResponse, NewJob, RecurringJobInfo, JOB_DEAD};
+use crate::utils::clock::{Clock, SystemClock};
+use crate::utils::AppError;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct JobService {
+    jobs: Arc<dyn JobRepository>,
+    registry: Arc<JobRegistry>,
+    clock: Arc<dyn Clock>,
+}
+
+impl JobService {
+    pub fn new(jobs: Arc<dyn JobRepository>, registry: JobRegistry) -> Self {
+        Self {
+            jobs,
+            registry: Arc::new(registry),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn registry(&self) -> &JobRegistry {
+        &self.registry
+    }
+
+    // 立即执行；设置了 unique_key 且已有相同任务时返回已有任务的 ID
+    pub async fn enqueue<J: Job>(&self, job: J) -> Result<i64, AppError> {
+        self.enqueue_at(job, self.clock.now()).await
+    }
+
+    pub async fn enqueue_at<J: Job>(&self, job: J, run_at: DateTime<Utc>) -> Result<i64, AppError> {
+        let payload = serde_json::to_string(&job)
+            .map_err(|e| AppError::Internal(format!("Failed to serialize job {}: {}", J::KIND, e)))?;
+        let (id, _) = self
+            .jobs
+            .enqueue(&NewJob {
+                queue: J::QUEUE.to_string(),
+                kind: J::KIND.to_string(),
+                payload,
+                max_attempts: J::MAX_ATTEMPTS,
+                unique_key: job.unique_key(),
+                run_at,
+            })
+            .await?;
+        Ok(id)
+    }
+
+    pub async fn get(&self, id: i64) -> Result<JobRecord, AppError> {
+        self.jobs
+            .find_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Job not found".to_string()))
+    }
+
+    // 返回当前页的任务和总数，最新的排在前面
+    pub async fn list(&self, filter: &JobFilter, page: i64, page_size: i64) -> Result<(Vec<JobRecord>, i64), AppError> {
+        let jobs = self.jobs.list(filter, Page::new(page, page_size)).await?;
+        let total = self.jobs.count(filter).await?;
+        Ok((jobs, total))
+    }
+
+    // 各队列的任务数和定时任务的下次执行时间
+    pub async fn stats(&self) -> Result<JobStatsResponse, AppError> {
+        let now = self.clock.now();
+        Ok(JobStatsResponse {
+            counts: self.jobs.counts().await?,
+            recurring: self
+                .registry
+                .recurring_jobs()
+                .iter()
+                .map(|recurring| RecurringJobInfo {
+                    name: recurring.name.clone(),
+                    kind: recurring.kind.to_string(),
+                    schedule: recurring.schedule.to_string(),
+                    next_run_at: recurring.schedule.after(&now).next(),
+                })
+                .collect(),
+        })
+    }
+
+    // 只有死信可以重试；重新计算 unique_key，相同的任务未结束时不重试，避免同时存在两个
+    pub async fn retry(&self, id: i64) -> Result<JobRecord, AppError> {
+        let job = self.get(id).await?;
+        if job.status != JOB_DEAD {
+            return Err(AppError::Conflict("Only dead jobs can be retried".to_string()));
+        }
+        let unique_key = self.registry.unique_key(&job.kind, &job.payload);
+        if !self.jobs.retry(id, unique_key.as_deref(), self.clock.now()).await? {
+            return Err(AppError::Conflict(
+                "The job was retried already or a job with the same unique key has not finished".to_string(),
+            ));
+        }
+        self.get(id).await
+    }
+}
@@ -0,0 +1,29 @@
+pub mod api_key;
+pub mod app;
+pub mod archive;
+pub mod article;
+pub mod audit;
+pub mod change_feed;
+pub mod edit_lock;
+pub mod feed;
+pub mod job;
+pub mod oidc;
+pub mod outbox;
+pub mod preview;
+pub mod sitemap;
+pub mod user;
+
+pub use api_key::ApiKeyService;
+pub use app::AppService;
+pub use archive::ArchiveService;
+pub use article::ArticleService;
+pub use audit::AuditService;
+pub use change_feed::ChangeFeedService;
+pub use edit_lock::EditLockService;
+pub use feed::FeedService;
+pub use job::JobService;
+pub use oidc::OidcService;
+pub use outbox::OutboxService;
+pub use preview::PreviewService;
+pub use sitemap::SitemapService;
+pub use user::UserService;
@@ -0,0 +1,333 @@
+use crate::config::oidc::{OidcConfig, OidcProviderConfig};
+use crate::db::UserRepository;
+use crate::models::oidc::{FederatedIdentity, NewOidcState, NewUserIdentity, OidcProviderInfo};
+use crate::utils::clock::{Clock, SystemClock};
+use crate::utils::{token, AppError};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Duration;
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// 发现文档中用到的字段
+#[derive(Debug, Clone, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+struct Discovery {
+    metadata: ProviderMetadata,
+    jwks: JwkSet,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    email: Option<String>,
+    // 部分身份提供方返回字符串 "true"
+    #[serde(default)]
+    email_verified: Value,
+    nonce: Option<String>,
+    preferred_username: Option<String>,
+    name: Option<String>,
+    locale: Option<String>,
+    #[serde(default)]
+    amr: Vec<String>,
+    #[serde(flatten)]
+    other: HashMap<String, Value>,
+}
+
+// RFC 7636 S256：BASE64URL(SHA256(verifier))
+fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+// 组声明可以是字符串数组或单个字符串
+fn groups(claims: &IdTokenClaims, groups_claim: &str) -> Vec<String> {
+    match claims.other.get(groups_claim) {
+        Some(Value::Array(values)) => values.iter().filter_map(|value| value.as_str().map(str::to_string)).collect(),
+        Some(Value::String(value)) => vec![value.clone()],
+        _ => Vec::new(),
+    }
+}
+
+// OpenID Connect 授权码 + PKCE 登录
+#[derive(Clone)]
+pub struct OidcService {
+    users: Arc<dyn UserRepository>,
+    config: Arc<OidcConfig>,
+    http: reqwest::Client,
+    clock: Arc<dyn Clock>,
+    // 按提供方缓存发现文档和签名公钥
+    discovery: Arc<Mutex<HashMap<String, Arc<Discovery>>>>,
+}
+
+impl OidcService {
+    pub fn new(users: Arc<dyn UserRepository>, config: OidcConfig) -> Self {
+        Self {
+            users,
+            config: Arc::new(config),
+            http: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(10))
+                .build()
+                .expect("Failed to build HTTP client"),
+            clock: Arc::new(SystemClock),
+            discovery: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn config(&self) -> &OidcConfig {
+        &self.config
+    }
+
+    pub fn providers(&self) -> Vec<OidcProviderInfo> {
+        self.config
+            .providers
+            .iter()
+            .map(|provider| OidcProviderInfo {
+                name: provider.name.clone(),
+                display_name: provider.display_name.clone(),
+                authorize_url: self.config.authorize_url(&provider.name),
+            })
+            .collect()
+    }
+
+    fn provider(&self, name: &str) -> Result<&OidcProviderConfig, AppError> {
+        self.config
+            .provider(name)
+            .ok_or_else(|| AppError::NotFound("Identity provider not found".to_string()))
+    }
+
+    // 生成 state、nonce 和 PKCE verifier，返回身份提供方的授权地址和 state
+    pub async fn authorize(&self, name: &str) -> Result<(String, String), AppError> {
+        let provider = self.provider(name)?;
+        let discovery = self.discover(provider, false).await?;
+
+        let state = token::random_token();
+        let nonce = token::random_token();
+        let code_verifier = token::random_token();
+        let url = reqwest::Url::parse_with_params(
+            &discovery.metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", provider.client_id.as_str()),
+                ("redirect_uri", self.config.callback_url(&provider.name).as_str()),
+                ("scope", provider.scopes.join(" ").as_str()),
+                ("state", state.as_str()),
+                ("nonce", nonce.as_str()),
+                ("code_challenge", pkce_challenge(&code_verifier).as_str()),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|e| AppError::Internal(format!("Invalid authorization endpoint: {}", e)))?;
+
+        let now = self.clock.now();
+        let pending = NewOidcState {
+            provider: provider.name.clone(),
+            state_hash: token::digest(&state),
+            nonce,
+            code_verifier,
+            expires_at: now + Duration::minutes(self.config.state_ttl_minutes),
+        };
+        self.users.create_oidc_state(&pending, now).await?;
+
+        Ok((url.to_string(), state))
+    }
+
+    // 用授权码换取 ID token 并校验，state 只能使用一次
+    pub async fn authenticate(&self, name: &str, code: &str, state: &str) -> Result<FederatedIdentity, AppError> {
+        let provider = self.provider(name)?;
+        let now = self.clock.now();
+        let pending = self
+            .users
+            .take_oidc_state(&token::digest(state))
+            .await?
+            .filter(|pending| pending.provider == provider.name && pending.expires_at > now)
+            .ok_or_else(|| AppError::Unauthorized("Invalid or expired login state".to_string()))?;
+        let discovery = self.discover(provider, false).await?;
+
+        let redirect_uri = self.config.callback_url(&provider.name);
+        let mut request = self.http.post(&discovery.metadata.token_endpoint).form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri.as_str()),
+            ("client_id", provider.client_id.as_str()),
+            ("code_verifier", pending.code_verifier.as_str()),
+        ]);
+        if let Some(secret) = &provider.client_secret {
+            request = request.basic_auth(&provider.client_id, Some(secret));
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to reach identity provider: {}", e)))?;
+        if !response.status().is_success() {
+            log::warn!(
+                "Identity provider {} rejected the authorization code: {} {}",
+                provider.name,
+                response.status(),
+                response.text().await.unwrap_or_default()
+            );
+            return Err(AppError::Unauthorized(
+                "The identity provider rejected the authorization code".to_string(),
+            ));
+        }
+        let tokens: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| AppError::Internal(format!("Invalid token response from identity provider: {}", e)))?;
+
+        let claims = self.verify_id_token(provider, &tokens.id_token, &pending.nonce).await?;
+        let email = claims
+            .email
+            .clone()
+            .ok_or_else(|| AppError::Unauthorized("The identity provider did not return an email address".to_string()))?;
+        Ok(FederatedIdentity {
+            identity: NewUserIdentity {
+                provider: provider.name.clone(),
+                subject: claims.sub.clone(),
+                email,
+            },
+            email_verified: claims.email_verified == Value::Bool(true) || claims.email_verified == "true",
+            username: claims.preferred_username.clone().or_else(|| claims.name.clone()),
+            locale: claims.locale.clone(),
+            role: provider.map_role(&groups(&claims, &provider.groups_claim)),
+            provision: provider.auto_provision,
+            multi_factor: claims.amr.iter().any(|method| method == "mfa"),
+        })
+    }
+
+    // 校验签名、issuer、audience、过期时间和 nonce
+    async fn verify_id_token(
+        &self,
+        provider: &OidcProviderConfig,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, AppError> {
+        let invalid_token = || AppError::Unauthorized("Invalid ID token".to_string());
+
+        let header = jsonwebtoken::decode_header(id_token).map_err(|_| invalid_token())?;
+        // 只接受非对称签名，防止用公钥当作 HMAC 密钥伪造
+        if matches!(header.alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+            return Err(invalid_token());
+        }
+
+        let mut discovery = self.discover(provider, false).await?;
+        let find_key = |jwks: &JwkSet| -> Option<Jwk> {
+            match &header.kid {
+                Some(kid) => jwks.find(kid).cloned(),
+                None => jwks.keys.first().cloned(),
+            }
+        };
+        // 找不到 kid 时身份提供方可能轮换了密钥，重新获取一次
+        let jwk = match find_key(&discovery.jwks) {
+            Some(jwk) => jwk,
+            None => {
+                discovery = self.discover(provider, true).await?;
+                find_key(&discovery.jwks).ok_or_else(invalid_token)?
+            }
+        };
+        let key = DecodingKey::from_jwk(&jwk).map_err(|_| invalid_token())?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&discovery.metadata.issuer]);
+        validation.set_audience(&[&provider.client_id]);
+        let claims = jsonwebtoken::decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(|e| {
+                log::warn!("Rejected ID token from {}: {}", provider.name, e);
+                invalid_token()
+            })?
+            .claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(invalid_token());
+        }
+        Ok(claims)
+    }
+
+    async fn discover(&self, provider: &OidcProviderConfig, refresh: bool) -> Result<Arc<Discovery>, AppError> {
+        if !refresh {
+            if let Some(discovery) = self.discovery.lock().unwrap().get(&provider.name) {
+                return Ok(discovery.clone());
+            }
+        }
+
+        let metadata: ProviderMetadata = self
+            .get_json(&format!("{}/.well-known/openid-configuration", provider.issuer))
+            .await?;
+        if metadata.issuer.trim_end_matches('/') != provider.issuer {
+            return Err(AppError::Internal(format!(
+                "Identity provider {} reports issuer {}",
+                provider.name, metadata.issuer
+            )));
+        }
+        let jwks: JwkSet = self.get_json(&metadata.jwks_uri).await?;
+
+        let discovery = Arc::new(Discovery { metadata, jwks });
+        self.discovery
+            .lock()
+            .unwrap()
+            .insert(provider.name.clone(), discovery.clone());
+        Ok(discovery)
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T, AppError> {
+        let fetch = async {
+            self.http
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<T>()
+                .await
+        };
+        fetch
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to fetch {}: {}", url, e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pkce_challenge_matches_rfc_7636() {
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[test]
+    fn groups_claim_accepts_a_list_or_a_single_value() {
+        let claims: IdTokenClaims = serde_json::from_value(serde_json::json!({
+            "sub": "1",
+            "groups": ["editors", 42, "cms-admins"],
+            "department": "marketing",
+        }))
+        .unwrap();
+        assert_eq!(groups(&claims, "groups"), ["editors", "cms-admins"]);
+        assert_eq!(groups(&claims, "department"), ["marketing"]);
+        assert!(groups(&claims, "roles").is_empty());
+    }
+}
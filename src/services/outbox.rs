@@ -0,0 +1,241 @@
+use crate::config::email::OutboxConfig;
+use crate::db::{OutboxFilter, OutboxRepository, Page};
+use crate::models::outbox::{OutboxEmail, OUTBOX_DEAD};
+use crate::utils::backoff;
+use crate::utils::clock::{Clock, SystemClock};
+use crate::utils::email::EmailService;
+use crate::utils::AppError;
+use chrono::Duration;
+use std::sync::Arc;
+
+// 从发件箱领取邮件并投递，失败时按指数退避重试
+#[derive(Clone)]
+pub struct OutboxService {
+    outbox: Arc<dyn OutboxRepository>,
+    email_service: EmailService,
+    clock: Arc<dyn Clock>,
+    config: OutboxConfig,
+}
+
+impl OutboxService {
+    pub fn new(outbox: Arc<dyn OutboxRepository>, email_service: EmailService, config: OutboxConfig) -> Self {
+        Self {
+            outbox,
+            email_service,
+            clock: Arc::new(SystemClock),
+            config,
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    // 第 attempts 次失败后的等待时间
+    pub fn retry_delay(&self, attempts: i32) -> Duration {
+        backoff::exponential(self.config.retry_base, self.config.retry_max, attempts)
+    }
+
+    // 投递一批到期的邮件，返回领取的数量
+    pub async fn deliver_due(&self) -> Result<usize, AppError> {
+        let now = self.clock.now();
+        let lease = Duration::from_std(self.config.lease).unwrap_or_else(|_| Duration::weeks(52));
+        let messages = self
+            .outbox
+            .claim_due(now, now + lease, self.config.batch_size)
+            .await?;
+
+        for message in &messages {
+            match self.email_service.deliver(&message.message).await {
+                Ok(()) => self.outbox.mark_sent(message.id, self.clock.now()).await?,
+                Err(e) => {
+                    let now = self.clock.now();
+                    let next_attempt_at = if message.attempts >= self.config.max_attempts {
+                        log::error!(
+                            "Giving up on {} email {} to {} after {} attempts: {}",
+                            message.message.template,
+                            message.id,
+                            message.message.recipient,
+                            message.attempts,
+                            e
+                        );
+                        None
+                    } else {
+                        log::warn!(
+                            "Failed to send {} email {} to {} (attempt {}): {}",
+                            message.message.template,
+                            message.id,
+                            message.message.recipient,
+                            message.attempts,
+                            e
+                        );
+                        Some(now + self.retry_delay(message.attempts))
+                    };
+                    self.outbox
+                        .mark_failed(message.id, &e.to_string(), next_attempt_at, now)
+                        .await?;
+                }
+            }
+        }
+
+        Ok(messages.len())
+    }
+
+    // 后台循环投递；一批领满时立即继续，否则等待轮询间隔
+    pub fn spawn_worker(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let claimed = match self.deliver_due().await {
+                    Ok(claimed) => claimed,
+                    Err(e) => {
+                        log::error!("Email outbox worker failed: {}", e);
+                        0
+                    }
+                };
+                if (claimed as i64) < self.config.batch_size {
+                    tokio::time::sleep(self.config.poll_interval).await;
+                }
+            }
+        })
+    }
+
+    pub async fn get(&self, id: i64) -> Result<OutboxEmail, AppError> {
+        self.outbox
+            .find_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Email not found".to_string()))
+    }
+
+    // 返回当前页的邮件和总数，最新的排在前面
+    pub async fn list(
+        &self,
+        filter: &OutboxFilter,
+        page: i64,
+        page_size: i64,
+    ) -> Result<(Vec<OutboxEmail>, i64), AppError> {
+        let messages = self.outbox.list(filter, Page::new(page, page_size)).await?;
+        let total = self.outbox.count(filter).await?;
+        Ok((messages, total))
+    }
+
+    // 只有死信可以重试
+    pub async fn retry(&self, id: i64) -> Result<OutboxEmail, AppError> {
+        let message = self.get(id).await?;
+        if message.status != OUTBOX_DEAD || !self.outbox.retry(id, self.clock.now()).await? {
+            return Err(AppError::Conflict("Only dead-lettered emails can be retried".to_string()));
+        }
+        self.get(id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory::MemoryRepository;
+    use crate::models::outbox::{EmailMessage, OUTBOX_PENDING, OUTBOX_SENT};
+    use crate::test_support::FixedClock;
+    use crate::utils::email::{EmailTransport, MemoryEmailTransport};
+    use async_trait::async_trait;
+    use lettre::Message;
+
+    struct FailingTransport;
+
+    #[async_trait]
+    impl EmailTransport for FailingTransport {
+        async fn send(&self, _message: Message) -> Result<(), anyhow::Error> {
+            Err(anyhow::anyhow!("connection refused"))
+        }
+    }
+
+    fn config() -> OutboxConfig {
+        OutboxConfig {
+            max_attempts: 3,
+            retry_base: std::time::Duration::from_secs(30),
+            retry_max: std::time::Duration::from_secs(90),
+            ..OutboxConfig::default()
+        }
+    }
+
+    fn message() -> EmailMessage {
+        EmailMessage {
+            template: "welcome".to_string(),
+            recipient: "alice@example.com".to_string(),
+            from_name: "RSCMS".to_string(),
+            from_email: "noreply@example.com".to_string(),
+            subject: "Welcome".to_string(),
+            html_body: "<p>Hi</p>".to_string(),
+            text_body: "Hi".to_string(),
+        }
+    }
+
+    fn service(
+        transport: Arc<dyn EmailTransport>,
+        repository: &MemoryRepository,
+        clock: &Arc<FixedClock>,
+    ) -> OutboxService {
+        OutboxService::new(
+            Arc::new(repository.clone()),
+            EmailService::new(transport, "noreply@example.com"),
+            config(),
+        )
+        .with_clock(clock.clone())
+    }
+
+    #[test]
+    fn retry_delay_grows_exponentially_up_to_the_cap() {
+        let service = service(Arc::new(FailingTransport), &MemoryRepository::new(), &Arc::new(FixedClock::default()));
+        assert_eq!(service.retry_delay(1), Duration::seconds(30));
+        assert_eq!(service.retry_delay(2), Duration::seconds(60));
+        assert_eq!(service.retry_delay(3), Duration::seconds(90));
+        assert_eq!(service.retry_delay(40), Duration::seconds(90));
+    }
+
+    #[actix_web::test]
+    async fn delivers_pending_messages() {
+        let repository = MemoryRepository::new();
+        let transport = MemoryEmailTransport::new();
+        let service = service(Arc::new(transport.clone()), &repository, &Arc::new(FixedClock::default()));
+        let id = OutboxRepository::enqueue(&repository, &message()).await.unwrap();
+
+        assert_eq!(service.deliver_due().await.unwrap(), 1);
+        assert_eq!(service.get(id).await.unwrap().status, OUTBOX_SENT);
+        assert!(transport.last_sent_to("alice@example.com").unwrap().raw.contains("Welcome"));
+
+        // 已发送的邮件不会再次领取
+        assert_eq!(service.deliver_due().await.unwrap(), 0);
+    }
+
+    #[actix_web::test]
+    async fn failures_back_off_then_dead_letter() {
+        let repository = MemoryRepository::new();
+        let clock = Arc::new(FixedClock::default());
+        let service = service(Arc::new(FailingTransport), &repository, &clock);
+        let id = OutboxRepository::enqueue(&repository, &message()).await.unwrap();
+
+        assert_eq!(service.deliver_due().await.unwrap(), 1);
+        let failed = service.get(id).await.unwrap();
+        assert_eq!(failed.status, OUTBOX_PENDING);
+        assert_eq!(failed.attempts, 1);
+        assert_eq!(failed.last_error.as_deref(), Some("connection refused"));
+        assert_eq!(failed.next_attempt_at, clock.now() + Duration::seconds(30));
+
+        // 退避时间未到不会重试
+        assert_eq!(service.deliver_due().await.unwrap(), 0);
+        for _ in 0..2 {
+            clock.advance(Duration::seconds(90));
+            assert_eq!(service.deliver_due().await.unwrap(), 1);
+        }
+        assert_eq!(service.get(id).await.unwrap().status, OUTBOX_DEAD);
+
+        clock.advance(Duration::hours(1));
+        assert_eq!(service.deliver_due().await.unwrap(), 0);
+
+        // 手动重试后重新进入队列
+        let retried = service.retry(id).await.unwrap();
+        assert_eq!(retried.status, OUTBOX_PENDING);
+        assert_eq!(retried.attempts, 0);
+        assert!(matches!(service.retry(id).await, Err(AppError::Conflict(_))));
+        assert!(matches!(service.retry(999).await, Err(AppError::NotFound(_))));
+    }
+}
@@ -0,0 +1,226 @@
+use crate::config::preview::PreviewConfig;
+use crate::db::{ArticleRepository, PreviewLinkRepository};
+use crate::models::preview::{
+    CreatePreviewLinkRequest, NewPreviewLink, PreviewLink, PreviewLinkCreated, PreviewSnapshot,
+};
+use crate::models::Article;
+use crate::utils::clock::{Clock, SystemClock};
+use crate::utils::token;
+use crate::utils::AppError;
+use chrono::Duration;
+use std::sync::Arc;
+
+// 草稿预览链接，持有链接的人不需要登录就能查看文章
+#[derive(Clone)]
+pub struct PreviewService {
+    links: Arc<dyn PreviewLinkRepository>,
+    articles: Arc<dyn ArticleRepository>,
+    clock: Arc<dyn Clock>,
+    config: PreviewConfig,
+}
+
+impl PreviewService {
+    pub fn new(
+        links: Arc<dyn PreviewLinkRepository>,
+        articles: Arc<dyn ArticleRepository>,
+        config: PreviewConfig,
+    ) -> Self {
+        Self {
+            links,
+            articles,
+            clock: Arc::new(SystemClock),
+            config,
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    // 与修改文章相同，只有作者可以分享和管理预览链接
+    async fn own_article(&self, article_id: i64, author_id: i64) -> Result<Article, AppError> {
+        let not_found = || AppError::NotFound("Article not found or you don't have permission to preview it".to_string());
+        let article = self.articles.find_visible(article_id, author_id).await?.ok_or_else(not_found)?;
+        if article.author_id != author_id {
+            return Err(not_found());
+        }
+        Ok(article)
+    }
+
+    pub async fn create(
+        &self,
+        article_id: i64,
+        author_id: i64,
+        req: &CreatePreviewLinkRequest,
+    ) -> Result<PreviewLinkCreated, AppError> {
+        let article = self.own_article(article_id, author_id).await?;
+        // 没有保存历史版本，只能固定为当前版本，并在链接中保存这个版本的内容
+        if req.version.is_some_and(|version| version != article.version) {
+            return Err(AppError::PreconditionFailed(article.version));
+        }
+        let snapshot = if req.version.is_some() {
+            PreviewSnapshot::of(&article)
+        } else {
+            PreviewSnapshot::default()
+        };
+
+        let ttl = req.expires_in.unwrap_or(self.config.default_ttl_secs).min(self.config.max_ttl_secs);
+        let now = self.clock.now();
+        let token = token::random_token();
+        let link = NewPreviewLink {
+            article_id,
+            token_hash: token::sign(&self.config.secret, &token),
+            version: req.version,
+            snapshot,
+            created_by: author_id,
+            expires_at: now + Duration::seconds(ttl),
+        };
+        let preview = self.links.create(&link, now).await?;
+        Ok(PreviewLinkCreated {
+            preview,
+            url: format!("{}/{}", self.config.url.trim_end_matches('/'), token),
+            token,
+        })
+    }
+
+    pub async fn list(&self, article_id: i64, author_id: i64) -> Result<Vec<PreviewLink>, AppError> {
+        self.own_article(article_id, author_id).await?;
+        Ok(self.links.list_by_article(article_id).await?)
+    }
+
+    // 返回被吊销的链接
+    pub async fn revoke(&self, article_id: i64, preview_id: i64, author_id: i64) -> Result<PreviewLink, AppError> {
+        self.own_article(article_id, author_id).await?;
+        let not_found = || AppError::NotFound("Preview link not found".to_string());
+        let preview = self
+            .links
+            .list_by_article(article_id)
+            .await?
+            .into_iter()
+            .find(|link| link.id == preview_id && link.revoked_at.is_none())
+            .ok_or_else(not_found)?;
+        if !self.links.revoke(article_id, preview_id, self.clock.now()).await? {
+            return Err(not_found());
+        }
+        Ok(preview)
+    }
+
+    // 用链接查看文章；过期或吊销时返回 410，固定版本的链接显示创建时的内容
+    pub async fn open(&self, token: &str) -> Result<Article, AppError> {
+        let not_found = || AppError::NotFound("Preview link not found".to_string());
+        let link = self
+            .links
+            .find_by_hash(&token::sign(&self.config.secret, token))
+            .await?
+            .ok_or_else(not_found)?;
+        if link.revoked_at.is_some() {
+            return Err(AppError::Gone("This preview link has been revoked".to_string()));
+        }
+        if link.expires_at <= self.clock.now() {
+            return Err(AppError::Gone("This preview link has expired".to_string()));
+        }
+        // 以作者身份读取，草稿和已发布的文章都可以预览，回收站中的不可以
+        let article = self
+            .articles
+            .find_visible(link.article_id, link.created_by)
+            .await?
+            .ok_or_else(not_found)?;
+        Ok(match link.version {
+            Some(version) => link.snapshot.apply(version, article),
+            None => article,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory::MemoryRepository;
+    use crate::models::article::{CreateArticleRequest, UpdateArticleRequest};
+    use crate::test_support::FixedClock;
+
+    async fn setup() -> (PreviewService, Arc<MemoryRepository>, Arc<FixedClock>, Article) {
+        let repository = Arc::new(MemoryRepository::new());
+        let clock = Arc::new(FixedClock::default());
+        let service = PreviewService::new(repository.clone(), repository.clone(), PreviewConfig::default())
+            .with_clock(clock.clone());
+        let draft = CreateArticleRequest {
+            title: "Draft".to_string(),
+            content: "Content".to_string(),
+            status: None,
+            app_id: None,
+            category: None,
+            ..Default::default()
+        };
+        let article = ArticleRepository::create(repository.as_ref(), &draft, 1).await.unwrap();
+        (service, repository, clock, article)
+    }
+
+    #[actix_web::test]
+    async fn links_show_drafts_until_they_expire_or_are_revoked() {
+        let (service, _, clock, article) = setup().await;
+        assert!(matches!(
+            service.create(article.id, 2, &CreatePreviewLinkRequest::default()).await,
+            Err(AppError::NotFound(_))
+        ));
+
+        let req = CreatePreviewLinkRequest {
+            expires_in: Some(3600),
+            version: None,
+        };
+        let created = service.create(article.id, 1, &req).await.unwrap();
+        assert!(created.url.ends_with(&format!("/previews/{}", created.token)));
+        assert_ne!(created.preview.token_hash, created.token);
+        assert_eq!(service.open(&created.token).await.unwrap().title, "Draft");
+        assert!(matches!(service.open("unknown").await, Err(AppError::NotFound(_))));
+
+        clock.advance(Duration::seconds(3600));
+        assert!(matches!(service.open(&created.token).await, Err(AppError::Gone(_))));
+
+        let created = service.create(article.id, 1, &CreatePreviewLinkRequest::default()).await.unwrap();
+        assert_eq!(created.preview.expires_at, clock.now() + Duration::days(7));
+        service.revoke(article.id, created.preview.id, 1).await.unwrap();
+        assert!(matches!(service.open(&created.token).await, Err(AppError::Gone(_))));
+        assert!(matches!(
+            service.revoke(article.id, created.preview.id, 1).await,
+            Err(AppError::NotFound(_))
+        ));
+        assert_eq!(service.list(article.id, 1).await.unwrap().len(), 2);
+    }
+
+    #[actix_web::test]
+    async fn pinned_links_keep_showing_the_pinned_version() {
+        let (service, repository, _, article) = setup().await;
+        let pinned = |version: i64| CreatePreviewLinkRequest {
+            expires_in: None,
+            version: Some(version),
+        };
+        assert!(matches!(
+            service.create(article.id, 1, &pinned(article.version + 1)).await,
+            Err(AppError::PreconditionFailed(_))
+        ));
+        let created = service.create(article.id, 1, &pinned(article.version)).await.unwrap();
+        let unpinned = service.create(article.id, 1, &CreatePreviewLinkRequest::default()).await.unwrap();
+
+        let changes = UpdateArticleRequest {
+            title: Some("Edited".to_string()),
+            content: None,
+            status: None,
+            category: None,
+            version: None,
+            ..Default::default()
+        };
+        ArticleRepository::update(repository.as_ref(), article.id, 1, &changes, article.version)
+            .await
+            .unwrap();
+        let pinned = service.open(&created.token).await.unwrap();
+        assert_eq!((pinned.title.as_str(), pinned.version), ("Draft", article.version));
+        let latest = service.open(&unpinned.token).await.unwrap();
+        assert_eq!((latest.title.as_str(), latest.version), ("Edited", article.version + 1));
+
+        // 列表中不返回保存的内容
+        let listed = serde_json::to_value(service.list(article.id, 1).await.unwrap()).unwrap();
+        assert!(listed[1].get("title").is_none());
+    }
+}
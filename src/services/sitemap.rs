@@ -0,0 +1,259 @@
+use crate::config::sitemap::SitemapConfig;
+use crate::db::{AppRepository, ArticleFilter, ArticleRepository, Page};
+use crate::models::{App, Article};
+use crate::utils::sitemap::{self, SitemapUrl};
+use crate::utils::token;
+use crate::utils::AppError;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// 渲染好的站点地图或站点地图索引，ETag 为内容的摘要
+#[derive(Debug, Clone)]
+pub struct RenderedSitemap {
+    pub body: String,
+    pub etag: String,
+}
+
+// 应用已发布且允许收录的文章的站点地图
+#[derive(Clone)]
+pub struct SitemapService {
+    apps: Arc<dyn AppRepository>,
+    articles: Arc<dyn ArticleRepository>,
+    config: SitemapConfig,
+}
+
+impl SitemapService {
+    pub fn new(apps: Arc<dyn AppRepository>, articles: Arc<dyn ArticleRepository>, config: SitemapConfig) -> Self {
+        Self { apps, articles, config }
+    }
+
+    // page 为空时返回 sitemap.xml：文章不超过 max_urls 时为站点地图本身，
+    // 否则为指向各分页的索引
+    pub async fn render(&self, identifier: &str, page: Option<i64>) -> Result<RenderedSitemap, AppError> {
+        let app = self
+            .apps
+            .find_by_identifier(identifier)
+            .await?
+            .ok_or_else(|| AppError::NotFound("App not found".to_string()))?;
+        if app.base_url.is_none() {
+            return Err(AppError::NotFound("The app has no base URL".to_string()));
+        }
+        let filter = Self::filter(&app);
+        // viewer 0 不是任何文章的作者
+        let total = self.articles.count_visible(0, &filter).await?;
+        let pages = ((total + self.config.max_urls - 1) / self.config.max_urls).max(1);
+
+        let body = match page {
+            None if pages > 1 => {
+                let locations: Vec<String> = (1..=pages)
+                    .map(|page| {
+                        format!("{}/{}/sitemap-{}.xml", self.config.url.trim_end_matches('/'), identifier, page)
+                    })
+                    .collect();
+                sitemap::index(&locations)
+            }
+            None => self.urlset(&app, &filter, 1).await?,
+            Some(page) if (1..=pages).contains(&page) => self.urlset(&app, &filter, page).await?,
+            Some(_) => return Err(AppError::NotFound("Sitemap not found".to_string())),
+        };
+        Ok(RenderedSitemap {
+            etag: token::digest(&body),
+            body,
+        })
+    }
+
+    fn filter(app: &App) -> ArticleFilter {
+        ArticleFilter {
+            status: Some(2),
+            app_id: Some(app.id),
+            indexable: true,
+            ..ArticleFilter::default()
+        }
+    }
+
+    async fn urlset(&self, app: &App, filter: &ArticleFilter, page: i64) -> Result<String, AppError> {
+        let articles = self
+            .articles
+            .list_visible(0, filter, Some(Page::new(page, self.config.max_urls)))
+            .await?;
+
+        // 同一篇原文的各语言版本，按原文 id 分组
+        let group_ids: Vec<i64> = articles
+            .iter()
+            .filter(|article| article.locale.is_some())
+            .map(|article| article.translation_of.unwrap_or(article.id))
+            .collect();
+        let mut groups: HashMap<i64, Vec<Article>> = HashMap::new();
+        for translation in self.articles.list_translations(&group_ids).await? {
+            if translation.noindex == 0 && translation.locale.is_some() {
+                groups
+                    .entry(translation.translation_of.unwrap_or(translation.id))
+                    .or_default()
+                    .push(translation);
+            }
+        }
+
+        let urls: Vec<SitemapUrl> = articles
+            .iter()
+            .map(|article| {
+                let group = article
+                    .locale
+                    .as_ref()
+                    .and_then(|_| groups.get(&article.translation_of.unwrap_or(article.id)))
+                    .filter(|group| group.len() > 1);
+                let mut alternates: Vec<(String, String)> = group
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|version| Some((version.locale.clone()?, self.article_url(app, version))))
+                    .collect();
+                // 原文作为没有匹配语言时的默认版本
+                let original = group.and_then(|group| group.iter().find(|version| version.translation_of.is_none()));
+                if let Some(original) = original {
+                    alternates.push(("x-default".to_string(), self.article_url(app, original)));
+                }
+                SitemapUrl {
+                    loc: self.article_url(app, article),
+                    lastmod: article.updated_at,
+                    alternates,
+                }
+            })
+            .collect();
+        Ok(sitemap::urlset(&urls))
+    }
+
+    // 文章设置的规范地址优先，否则为应用 base_url 下的地址
+    fn article_url(&self, app: &App, article: &Article) -> String {
+        article.canonical_url.clone().unwrap_or_else(|| {
+            format!(
+                "{}{}",
+                app.base_url.as_deref().unwrap_or_default().trim_end_matches('/'),
+                self.config.article_path.replace("{id}", &article.id.to_string())
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory::MemoryRepository;
+    use crate::models::article::CreateArticleRequest;
+    use crate::models::CreateAppRequest;
+    use crate::test_support::app_request;
+
+    async fn setup(max_urls: i64) -> (SitemapService, Arc<MemoryRepository>, i64) {
+        let repository = Arc::new(MemoryRepository::new());
+        let config = SitemapConfig {
+            max_urls,
+            ..SitemapConfig::default()
+        };
+        let service = SitemapService::new(repository.clone(), repository.clone(), config);
+        let app = CreateAppRequest {
+            base_url: Some("https://blog.example.com/".to_string()),
+            ..app_request("Blog", "blog")
+        };
+        let app = AppRepository::create(repository.as_ref(), &app, 1).await.unwrap();
+        (service, repository, app.id)
+    }
+
+    async fn article(repository: &MemoryRepository, article: CreateArticleRequest) -> Article {
+        let article = CreateArticleRequest {
+            title: "Title".to_string(),
+            content: "Content".to_string(),
+            ..article
+        };
+        ArticleRepository::create(repository, &article, 1).await.unwrap()
+    }
+
+    #[actix_web::test]
+    async fn sitemaps_list_indexable_articles_with_locale_alternates() {
+        let (service, repository, app_id) = setup(100).await;
+        let published = |app_id| CreateArticleRequest {
+            status: Some(2),
+            app_id: Some(app_id),
+            ..CreateArticleRequest::default()
+        };
+        let original = article(
+            &repository,
+            CreateArticleRequest {
+                locale: Some("en".to_string()),
+                ..published(app_id)
+            },
+        )
+        .await;
+        let translation = article(
+            &repository,
+            CreateArticleRequest {
+                locale: Some("zh-CN".to_string()),
+                translation_of: Some(original.id),
+                ..published(app_id)
+            },
+        )
+        .await;
+        article(
+            &repository,
+            CreateArticleRequest {
+                canonical_url: Some("https://www.example.com/landing".to_string()),
+                ..published(app_id)
+            },
+        )
+        .await;
+        article(
+            &repository,
+            CreateArticleRequest {
+                noindex: Some(true),
+                ..published(app_id)
+            },
+        )
+        .await;
+        article(
+            &repository,
+            CreateArticleRequest {
+                status: Some(1),
+                ..published(app_id)
+            },
+        )
+        .await;
+
+        let sitemap = service.render("blog", None).await.unwrap();
+        assert!(sitemap.body.contains("<urlset"));
+        assert_eq!(sitemap.body.matches("<url>").count(), 3);
+        assert!(sitemap.body.contains(&format!("<loc>https://blog.example.com/articles/{}</loc>", original.id)));
+        assert!(sitemap.body.contains("<loc>https://www.example.com/landing</loc>"));
+        assert_eq!(
+            sitemap.body.matches(&format!(
+                "hreflang=\"zh-CN\" href=\"https://blog.example.com/articles/{}\"",
+                translation.id
+            )).count(),
+            2
+        );
+        assert_eq!(sitemap.body.matches("hreflang=\"x-default\"").count(), 2);
+        assert_eq!(service.render("blog", Some(1)).await.unwrap().etag, sitemap.etag);
+        assert!(matches!(service.render("blog", Some(2)).await, Err(AppError::NotFound(_))));
+        assert!(matches!(service.render("missing", None).await, Err(AppError::NotFound(_))));
+    }
+
+    #[actix_web::test]
+    async fn large_sitemaps_are_split_behind_an_index() {
+        let (service, repository, app_id) = setup(2).await;
+        for _ in 0..5 {
+            article(
+                &repository,
+                CreateArticleRequest {
+                    status: Some(2),
+                    app_id: Some(app_id),
+                    ..CreateArticleRequest::default()
+                },
+            )
+            .await;
+        }
+
+        let index = service.render("blog", None).await.unwrap();
+        assert!(index.body.contains("<sitemapindex"));
+        assert!(index.body.contains("<loc>http://127.0.0.1:8080/sitemaps/blog/sitemap-3.xml</loc>"));
+        assert_eq!(index.body.matches("<sitemap>").count(), 3);
+        assert_eq!(service.render("blog", Some(2)).await.unwrap().body.matches("<url>").count(), 2);
+        assert_eq!(service.render("blog", Some(3)).await.unwrap().body.matches("<url>").count(), 1);
+        assert!(matches!(service.render("blog", Some(4)).await, Err(AppError::NotFound(_))));
+    }
+}
@@ -0,0 +1,1016 @@
+use crate::config::auth::{
+    MagicLinkConfig, PasswordConfig, TwoFactorConfig, AMR_EMAIL, AMR_OIDC, AMR_PASSWORD, AMR_RECOVERY_CODE,
+    AMR_TOTP,
+};
+use crate::db::{NewUser, UserRepository};
+use crate::models::credential::{
+    NewUserToken, TotpSetupResponse, TwoFactorStatusResponse, UserToken, TOKEN_PASSWORD_RESET, TOKEN_TWO_FACTOR,
+    TOKEN_TWO_FACTOR_EMAIL, TOKEN_TWO_FACTOR_OIDC,
+};
+use crate::models::magic_link::NewMagicLink;
+use crate::models::oidc::{FederatedIdentity, NewFederatedUser};
+use crate::models::{RegisterRequest, User, ROLE_ADMIN, ROLE_USER};
+use crate::utils::clock::{Clock, SystemClock};
+use crate::utils::email::EmailService;
+use crate::utils::email_template::{Branding, DEFAULT_LOCALE};
+use crate::utils::{password, token, totp, AppError, FieldError};
+use chrono::Duration;
+use rand::Rng;
+use std::sync::Arc;
+
+// 验证码有效期（分钟）
+pub const VERIFICATION_CODE_TTL_MINUTES: i64 = 30;
+
+// 验证码生成方式，测试中可以替换为固定序列
+pub trait CodeGenerator: Send + Sync {
+    fn generate(&self) -> String;
+}
+
+// 生成6位随机数字验证码
+pub struct RandomCodeGenerator;
+
+impl CodeGenerator for RandomCodeGenerator {
+    fn generate(&self) -> String {
+        let mut rng = rand::thread_rng();
+        format!("{:06}", rng.gen_range(0..1000000))
+    }
+}
+
+// 验证码、登录链接和密码登录的结果：开启了两步验证时需要再提交一次验证码
+#[derive(Debug)]
+pub enum Login {
+    Complete(Box<User>),
+    TwoFactorRequired(String),
+}
+
+#[derive(Clone)]
+pub struct UserService {
+    users: Arc<dyn UserRepository>,
+    email_service: EmailService,
+    max_verification_attempts: i32,
+    clock: Arc<dyn Clock>,
+    code_generator: Arc<dyn CodeGenerator>,
+    // 使用这些邮箱注册的用户成为管理员
+    admin_emails: Arc<Vec<String>>,
+    magic_link: Arc<MagicLinkConfig>,
+    password: Arc<PasswordConfig>,
+    two_factor: Arc<TwoFactorConfig>,
+}
+
+impl UserService {
+    pub fn new(users: Arc<dyn UserRepository>, email_service: EmailService, max_verification_attempts: i32) -> Self {
+        Self {
+            users,
+            email_service,
+            max_verification_attempts,
+            clock: Arc::new(SystemClock),
+            code_generator: Arc::new(RandomCodeGenerator),
+            admin_emails: Arc::new(Vec::new()),
+            magic_link: Arc::new(MagicLinkConfig::default()),
+            password: Arc::new(PasswordConfig::default()),
+            two_factor: Arc::new(TwoFactorConfig::default()),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_code_generator(mut self, code_generator: Arc<dyn CodeGenerator>) -> Self {
+        self.code_generator = code_generator;
+        self
+    }
+
+    pub fn with_admin_emails(mut self, admin_emails: Vec<String>) -> Self {
+        self.admin_emails = Arc::new(admin_emails.into_iter().map(|email| email.to_lowercase()).collect());
+        self
+    }
+
+    pub fn with_magic_link(mut self, config: MagicLinkConfig) -> Self {
+        self.magic_link = Arc::new(config);
+        self
+    }
+
+    pub fn with_password(mut self, config: PasswordConfig) -> Self {
+        self.password = Arc::new(config);
+        self
+    }
+
+    pub fn with_two_factor(mut self, config: TwoFactorConfig) -> Self {
+        self.two_factor = Arc::new(config);
+        self
+    }
+
+    pub fn magic_link_config(&self) -> &MagicLinkConfig {
+        &self.magic_link
+    }
+
+    pub fn two_factor_config(&self) -> &TwoFactorConfig {
+        &self.two_factor
+    }
+
+    pub async fn get(&self, id: i64) -> Result<User, AppError> {
+        self.users
+            .find_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))
+    }
+
+    // 创建未验证的用户，验证码邮件随用户一起写入发件箱；返回用户 ID 和验证码
+    pub async fn register(
+        &self,
+        req: &RegisterRequest,
+        locale: &str,
+        branding: &Branding,
+    ) -> Result<(i64, String), AppError> {
+        if self.users.find_by_email(&req.email).await?.is_some() {
+            return Err(AppError::Conflict("User with this email already exists".to_string()));
+        }
+
+        let verification_code = self.code_generator.generate();
+        let message = self
+            .email_service
+            .compose_verification_code(
+                &req.email,
+                &req.username,
+                locale,
+                branding,
+                &verification_code,
+                VERIFICATION_CODE_TTL_MINUTES,
+            )
+            .map_err(|e| AppError::Internal(format!("Failed to render verification email: {}", e)))?;
+        let user = NewUser {
+            username: req.username.clone(),
+            email: req.email.clone(),
+            verification_code: verification_code.clone(),
+            verification_code_expires_at: self.clock.now() + Duration::minutes(VERIFICATION_CODE_TTL_MINUTES),
+            locale: locale.to_string(),
+            role: if self.admin_emails.contains(&req.email.to_lowercase()) {
+                ROLE_ADMIN.to_string()
+            } else {
+                ROLE_USER.to_string()
+            },
+        };
+        let id = self.users.create(user, &message).await?;
+
+        Ok((id, verification_code))
+    }
+
+    // 为已有用户生成新的验证码，同时清零错误次数并写入验证码邮件
+    pub async fn issue_verification_code(&self, email: &str, branding: &Branding) -> Result<String, AppError> {
+        let user = self
+            .users
+            .find_by_email(email)
+            .await?
+            .ok_or_else(|| AppError::NotFound("No user found with this email".to_string()))?;
+
+        let verification_code = self.code_generator.generate();
+        let expires_at = self.clock.now() + Duration::minutes(VERIFICATION_CODE_TTL_MINUTES);
+        let message = self
+            .email_service
+            .compose_verification_code(
+                &user.email,
+                &user.username,
+                &user.locale,
+                branding,
+                &verification_code,
+                VERIFICATION_CODE_TTL_MINUTES,
+            )
+            .map_err(|e| AppError::Internal(format!("Failed to render verification email: {}", e)))?;
+        self.users
+            .set_verification_code(email, &verification_code, expires_at, &message)
+            .await?;
+
+        Ok(verification_code)
+    }
+
+    // 校验验证码，成功后标记邮箱已验证；首次验证时写入欢迎邮件，开启了两步验证时返回两步验证 token
+    pub async fn login(&self, email: &str, code: &str) -> Result<Login, AppError> {
+        let invalid_credentials = || AppError::Unauthorized("Invalid email or verification code".to_string());
+
+        let user = self
+            .users
+            .find_by_email(email)
+            .await?
+            .ok_or_else(invalid_credentials)?;
+
+        // 验证码必须存在、未过期、未超过错误次数且匹配
+        let code_valid = user.verification_code.as_deref() == Some(code)
+            && user.verification_code_expires_at.is_some_and(|expires_at| expires_at > self.clock.now())
+            && user.verification_attempts < self.max_verification_attempts;
+
+        if !code_valid {
+            if user.verification_code.is_some() {
+                // 记录错误次数，达到上限后作废验证码
+                self.users
+                    .record_failed_attempt(user.id, self.max_verification_attempts)
+                    .await?;
+            }
+
+            return Err(invalid_credentials());
+        }
+
+        let user = self.complete_login(user).await?;
+        self.second_factor(user, TOKEN_TWO_FACTOR_EMAIL).await
+    }
+
+    // 发送只能在 device 上打开的登录链接，邮件中附带在其他设备上使用的验证码；返回验证码
+    pub async fn issue_magic_link(&self, email: &str, branding: &Branding, device: &str) -> Result<String, AppError> {
+        let user = self
+            .users
+            .find_by_email(email)
+            .await?
+            .ok_or_else(|| AppError::NotFound("No user found with this email".to_string()))?;
+
+        let login_token = token::random_token();
+        let separator = if self.magic_link.url.contains('?') { '&' } else { '?' };
+        let login_url = format!("{}{}token={}", self.magic_link.url, separator, login_token);
+        let verification_code = self.code_generator.generate();
+        let message = self
+            .email_service
+            .compose_magic_link(&user, branding, &login_url, &verification_code, self.magic_link.ttl_minutes)
+            .map_err(|e| AppError::Internal(format!("Failed to render login link email: {}", e)))?;
+        let link = NewMagicLink {
+            user_id: user.id,
+            token_hash: token::sign(&self.magic_link.secret, &login_token),
+            device_hash: token::sign(&self.magic_link.secret, device),
+            expires_at: self.clock.now() + Duration::minutes(self.magic_link.ttl_minutes),
+        };
+        self.users.create_magic_link(&link, &verification_code, &message).await?;
+
+        Ok(verification_code)
+    }
+
+    // 链接必须未使用、未过期，并且在发起请求的设备上打开；开启了两步验证时返回两步验证 token
+    pub async fn login_with_magic_link(&self, login_token: &str, device: Option<&str>) -> Result<Login, AppError> {
+        let invalid_link = || AppError::Unauthorized("Invalid or expired login link".to_string());
+        let now = self.clock.now();
+
+        let link = self
+            .users
+            .find_magic_link(&token::sign(&self.magic_link.secret, login_token))
+            .await?
+            .ok_or_else(invalid_link)?;
+        if link.used_at.is_some() || link.expires_at <= now {
+            return Err(invalid_link());
+        }
+
+        // 设备不匹配时不作废链接，转发出去的链接无法使用，本人仍可以在原设备上打开
+        if device.map(|device| token::sign(&self.magic_link.secret, device)) != Some(link.device_hash) {
+            return Err(AppError::Unauthorized(
+                "This login link can only be used on the device that requested it".to_string(),
+            ));
+        }
+
+        if !self.users.consume_magic_link(link.id, now).await? {
+            return Err(invalid_link());
+        }
+        let user = self.users.find_by_id(link.user_id).await?.ok_or_else(invalid_link)?;
+        let user = self.complete_login(user).await?;
+        self.second_factor(user, TOKEN_TWO_FACTOR_EMAIL).await
+    }
+
+    // 单点登录：按 (provider, subject) 找到已关联的用户，或按已验证的邮箱关联、自动创建用户；
+    // 本地开启了两步验证的账号仍需第二个因素
+    pub async fn login_with_identity(&self, federated: &FederatedIdentity) -> Result<Login, AppError> {
+        let identity = &federated.identity;
+        let user = match self.users.find_identity(&identity.provider, &identity.subject).await? {
+            Some(linked) => {
+                self.users.record_identity_login(linked.id, self.clock.now()).await?;
+                self.get(linked.user_id).await?
+            }
+            None => self.link_or_provision(federated).await?,
+        };
+
+        // 每次登录按组映射同步角色，ADMIN_EMAILS 中的用户始终是管理员
+        let user = match &federated.role {
+            Some(role) if *role != user.role && !self.admin_emails.contains(&user.email.to_lowercase()) => {
+                self.users.set_role(user.id, role).await?;
+                User { role: role.clone(), ..user }
+            }
+            _ => user,
+        };
+        let user = self.complete_login(user).await?;
+        self.second_factor(user, TOKEN_TWO_FACTOR_OIDC).await
+    }
+
+    async fn link_or_provision(&self, federated: &FederatedIdentity) -> Result<User, AppError> {
+        // 未经身份提供方验证的邮箱可能属于他人，不能用来关联或创建账号
+        if !federated.email_verified {
+            return Err(AppError::Unauthorized(
+                "The identity provider has not verified this email address".to_string(),
+            ));
+        }
+
+        let identity = &federated.identity;
+        if let Some(user) = self.users.find_by_email(&identity.email).await? {
+            self.users.link_identity(user.id, identity).await?;
+            return Ok(user);
+        }
+        if !federated.provision {
+            return Err(AppError::Forbidden("No account exists for this email address".to_string()));
+        }
+
+        let email_name = identity.email.split('@').next().unwrap_or_default();
+        let username = federated
+            .username
+            .as_deref()
+            .map(str::trim)
+            .filter(|username| username.chars().count() >= 2)
+            .unwrap_or(email_name);
+        let user = NewFederatedUser {
+            username: username.chars().take(50).collect(),
+            email: identity.email.clone(),
+            locale: federated.locale.clone().unwrap_or_else(|| DEFAULT_LOCALE.to_string()),
+            role: if self.admin_emails.contains(&identity.email.to_lowercase()) {
+                ROLE_ADMIN.to_string()
+            } else {
+                federated.role.clone().unwrap_or_else(|| ROLE_USER.to_string())
+            },
+        };
+        let id = self.users.create_federated_user(&user, identity).await?;
+        self.get(id).await
+    }
+
+    // 密码正确且未开启两步验证时直接登录，否则返回两步验证 token
+    pub async fn login_with_password(&self, email: &str, password: &str) -> Result<Login, AppError> {
+        let invalid_credentials = || AppError::Unauthorized("Invalid email or password".to_string());
+
+        let user = self
+            .users
+            .find_by_email(email)
+            .await?
+            .ok_or_else(invalid_credentials)?;
+        let password_valid = user
+            .password_hash
+            .as_deref()
+            .is_some_and(|password_hash| password::verify(password, password_hash));
+        if !password_valid {
+            return Err(invalid_credentials());
+        }
+
+        self.second_factor(user, TOKEN_TWO_FACTOR).await
+    }
+
+    // 第一个因素通过后：未开启两步验证时直接登录，否则创建记录了第一个因素的两步验证 token
+    async fn second_factor(&self, user: User, purpose: &str) -> Result<Login, AppError> {
+        if !user.has_totp() {
+            return Ok(Login::Complete(Box::new(user)));
+        }
+
+        let challenge = token::random_token();
+        let new_token = NewUserToken {
+            user_id: user.id,
+            purpose: purpose.to_string(),
+            token_hash: token::digest(&challenge),
+            expires_at: self.clock.now() + Duration::minutes(self.two_factor.challenge_ttl_minutes),
+        };
+        self.users.create_token(&new_token, None).await?;
+        Ok(Login::TwoFactorRequired(challenge))
+    }
+
+    // 用 TOTP 验证码或恢复码完成登录，返回用户和两个因素（第一个因素是密码、邮件或单点登录）
+    pub async fn verify_two_factor(&self, challenge: &str, code: &str) -> Result<(User, [&'static str; 2]), AppError> {
+        let invalid_challenge = || AppError::Unauthorized("Invalid or expired two-factor login".to_string());
+
+        let mut pending = None;
+        for (purpose, first_factor) in [
+            (TOKEN_TWO_FACTOR, AMR_PASSWORD),
+            (TOKEN_TWO_FACTOR_EMAIL, AMR_EMAIL),
+            (TOKEN_TWO_FACTOR_OIDC, AMR_OIDC),
+        ] {
+            if let Some(token) = self.valid_token(purpose, challenge).await? {
+                pending = Some((token, first_factor));
+                break;
+            }
+        }
+        let (challenge, first_factor) = pending
+            .filter(|(challenge, _)| challenge.attempts < self.two_factor.max_attempts)
+            .ok_or_else(invalid_challenge)?;
+        let user = self.users.find_by_id(challenge.user_id).await?.ok_or_else(invalid_challenge)?;
+
+        let Some(method) = self.verify_second_factor(&user, code).await? else {
+            // 错误次数达到上限后本次登录作废，需要重新验证第一个因素
+            self.users
+                .record_token_failure(challenge.id, self.two_factor.max_attempts, self.clock.now())
+                .await?;
+            return Err(AppError::Unauthorized("Invalid two-factor code".to_string()));
+        };
+        if !self.users.consume_token(challenge.id, self.clock.now()).await? {
+            return Err(invalid_challenge());
+        }
+        Ok((user, [first_factor, method]))
+    }
+
+    // 设置或修改密码；已有密码时必须提供当前密码
+    pub async fn set_password(
+        &self,
+        user_id: i64,
+        current_password: Option<&str>,
+        new_password: &str,
+    ) -> Result<(), AppError> {
+        let user = self.get(user_id).await?;
+        if let Some(password_hash) = &user.password_hash {
+            if !current_password.is_some_and(|current| password::verify(current, password_hash)) {
+                return Err(AppError::BadRequest("Current password is incorrect".to_string()));
+            }
+        }
+        self.check_password(&user, new_password)?;
+        self.store_password(&user, new_password).await
+    }
+
+    // 发送重置密码邮件；邮箱不存在时同样返回成功，避免泄露账号是否存在
+    pub async fn request_password_reset(&self, email: &str, branding: &Branding) -> Result<Option<String>, AppError> {
+        let Some(user) = self.users.find_by_email(email).await? else {
+            return Ok(None);
+        };
+
+        let reset_token = token::random_token();
+        let separator = if self.password.reset_url.contains('?') { '&' } else { '?' };
+        let reset_url = format!("{}{}token={}", self.password.reset_url, separator, reset_token);
+        let message = self
+            .email_service
+            .compose_password_reset(&user, branding, &reset_url, self.password.reset_ttl_minutes)
+            .map_err(|e| AppError::Internal(format!("Failed to render password reset email: {}", e)))?;
+        let new_token = NewUserToken {
+            user_id: user.id,
+            purpose: TOKEN_PASSWORD_RESET.to_string(),
+            token_hash: token::digest(&reset_token),
+            expires_at: self.clock.now() + Duration::minutes(self.password.reset_ttl_minutes),
+        };
+        self.users.create_token(&new_token, Some(&message)).await?;
+
+        Ok(Some(reset_token))
+    }
+
+    // 重置链接只能使用一次；不会关闭两步验证；返回用户 ID
+    pub async fn reset_password(&self, reset_token: &str, new_password: &str) -> Result<i64, AppError> {
+        let invalid_token = || AppError::BadRequest("Invalid or expired password reset token".to_string());
+
+        let reset = self
+            .valid_token(TOKEN_PASSWORD_RESET, reset_token)
+            .await?
+            .ok_or_else(invalid_token)?;
+        let user = self.users.find_by_id(reset.user_id).await?.ok_or_else(invalid_token)?;
+        // 密码不符合要求时不作废 token，用户可以换一个密码重试
+        self.check_password(&user, new_password)?;
+        if !self.users.consume_token(reset.id, self.clock.now()).await? {
+            return Err(invalid_token());
+        }
+        self.store_password(&user, new_password).await?;
+        Ok(user.id)
+    }
+
+    pub async fn two_factor_status(&self, user_id: i64) -> Result<TwoFactorStatusResponse, AppError> {
+        let user = self.get(user_id).await?;
+        Ok(TwoFactorStatusResponse {
+            has_password: user.password_hash.is_some(),
+            totp_enabled: user.has_totp(),
+            recovery_codes_remaining: self.users.count_recovery_codes(user.id).await?,
+        })
+    }
+
+    // 生成新的 TOTP 密钥，验证第一个验证码后才会启用
+    pub async fn setup_totp(&self, user_id: i64) -> Result<TotpSetupResponse, AppError> {
+        let user = self.get(user_id).await?;
+        if user.password_hash.is_none() {
+            return Err(AppError::BadRequest(
+                "Set a password before enabling two-factor authentication".to_string(),
+            ));
+        }
+        if user.has_totp() {
+            return Err(AppError::Conflict("Two-factor authentication is already enabled".to_string()));
+        }
+
+        let secret = totp::generate_secret();
+        let otpauth_uri = totp::provisioning_uri(&secret, &self.two_factor.issuer, &user.email)
+            .ok_or_else(|| AppError::Internal("Failed to build TOTP provisioning URI".to_string()))?;
+        self.users.set_totp_secret(user.id, &secret).await?;
+        Ok(TotpSetupResponse { secret, otpauth_uri })
+    }
+
+    // 验证码正确后启用两步验证，返回恢复码
+    pub async fn enable_totp(&self, user_id: i64, code: &str) -> Result<Vec<String>, AppError> {
+        let user = self.get(user_id).await?;
+        if user.has_totp() {
+            return Err(AppError::Conflict("Two-factor authentication is already enabled".to_string()));
+        }
+        let secret = user
+            .totp_secret
+            .as_deref()
+            .ok_or_else(|| AppError::BadRequest("Set up two-factor authentication first".to_string()))?;
+        let step = totp::matching_step(secret, code, self.clock.now()).ok_or_else(invalid_code)?;
+        self.users.record_totp_step(user.id, step).await?;
+
+        let recovery_codes = totp::generate_recovery_codes();
+        self.users.enable_totp(user.id, &hash_recovery_codes(&recovery_codes)).await?;
+        Ok(recovery_codes)
+    }
+
+    pub async fn disable_totp(&self, user_id: i64, code: &str) -> Result<(), AppError> {
+        let user = self.enrolled_user(user_id).await?;
+        self.verify_second_factor(&user, code).await?.ok_or_else(invalid_code)?;
+        self.users.disable_totp(user.id).await?;
+        Ok(())
+    }
+
+    // 旧的恢复码全部作废
+    pub async fn regenerate_recovery_codes(&self, user_id: i64, code: &str) -> Result<Vec<String>, AppError> {
+        let user = self.enrolled_user(user_id).await?;
+        self.verify_second_factor(&user, code).await?.ok_or_else(invalid_code)?;
+
+        let recovery_codes = totp::generate_recovery_codes();
+        self.users
+            .replace_recovery_codes(user.id, &hash_recovery_codes(&recovery_codes))
+            .await?;
+        Ok(recovery_codes)
+    }
+
+    async fn enrolled_user(&self, user_id: i64) -> Result<User, AppError> {
+        let user = self.get(user_id).await?;
+        if !user.has_totp() {
+            return Err(AppError::BadRequest("Two-factor authentication is not enabled".to_string()));
+        }
+        Ok(user)
+    }
+
+    // 6 位数字按 TOTP 校验（每个时间步只能用一次），其他按恢复码校验（每个只能用一次）
+    async fn verify_second_factor(&self, user: &User, code: &str) -> Result<Option<&'static str>, AppError> {
+        let code = code.trim();
+        if code.len() == 6 && code.chars().all(|c| c.is_ascii_digit()) {
+            let Some(secret) = user.totp_secret.as_deref() else {
+                return Ok(None);
+            };
+            return Ok(match totp::matching_step(secret, code, self.clock.now()) {
+                Some(step) if self.users.record_totp_step(user.id, step).await? => Some(AMR_TOTP),
+                _ => None,
+            });
+        }
+
+        let code_hash = token::digest(&totp::normalize_recovery_code(code));
+        let used = self.users.use_recovery_code(user.id, &code_hash, self.clock.now()).await?;
+        Ok(used.then_some(AMR_RECOVERY_CODE))
+    }
+
+    // 未使用且未过期的 token
+    async fn valid_token(&self, purpose: &str, value: &str) -> Result<Option<UserToken>, AppError> {
+        let now = self.clock.now();
+        Ok(self
+            .users
+            .find_token(purpose, &token::digest(value))
+            .await?
+            .filter(|token| token.used_at.is_none() && token.expires_at > now))
+    }
+
+    fn check_password(&self, user: &User, new_password: &str) -> Result<(), AppError> {
+        let personal = [user.username.as_str(), user.email.as_str(), user.email.split('@').next().unwrap_or("")];
+        password::check_strength(new_password, self.password.min_length, &personal)
+            .map_err(|message| AppError::ValidationError(vec![FieldError::new("new_password", "weak_password", &message)]))
+    }
+
+    async fn store_password(&self, user: &User, new_password: &str) -> Result<(), AppError> {
+        let password_hash = password::hash(new_password)
+            .map_err(|e| AppError::Internal(format!("Failed to hash password: {}", e)))?;
+        self.users.set_password(user.id, &password_hash).await?;
+        Ok(())
+    }
+
+    // 登录成功：标记邮箱已验证，首次验证时写入欢迎邮件
+    async fn complete_login(&self, mut user: User) -> Result<User, AppError> {
+        // 欢迎邮件渲染失败不影响登录
+        let welcome = if user.email_verified == 0 {
+            let branding = self.email_service.branding(None);
+            self.email_service
+                .compose_welcome(&user.email, &user.username, &user.locale, &branding)
+                .inspect_err(|e| log::warn!("Failed to render welcome email to {}: {}", user.email, e))
+                .ok()
+        } else {
+            None
+        };
+        self.users.mark_verified(user.id, welcome.as_ref()).await?;
+
+        user.email_verified = 1;
+        user.verification_code = None;
+        user.verification_code_expires_at = None;
+        user.verification_attempts = 0;
+        Ok(user)
+    }
+}
+
+fn invalid_code() -> AppError {
+    AppError::BadRequest("Invalid two-factor code".to_string())
+}
+
+fn hash_recovery_codes(codes: &[String]) -> Vec<String> {
+    codes
+        .iter()
+        .map(|code| token::digest(&totp::normalize_recovery_code(code)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory::MemoryRepository;
+    use crate::db::{OutboxFilter, OutboxRepository, Page};
+    use crate::utils::email::MemoryEmailTransport;
+
+    fn email_service() -> EmailService {
+        EmailService::new(Arc::new(MemoryEmailTransport::new()), "noreply@example.com")
+    }
+
+    fn service_with(repository: MemoryRepository) -> UserService {
+        UserService::new(Arc::new(repository), email_service(), 3)
+    }
+
+    fn service() -> UserService {
+        service_with(MemoryRepository::new())
+    }
+
+    fn branding() -> Branding {
+        email_service().branding(None)
+    }
+
+    fn register_request(email: &str) -> RegisterRequest {
+        RegisterRequest {
+            username: "alice".to_string(),
+            email: email.to_string(),
+            locale: None,
+            app: None,
+        }
+    }
+
+    fn logged_in(login: Login) -> User {
+        match login {
+            Login::Complete(user) => *user,
+            Login::TwoFactorRequired(_) => panic!("expected to be logged in"),
+        }
+    }
+
+    #[actix_web::test]
+    async fn register_then_login_verifies_email() {
+        let service = service();
+        let (_, code) = service.register(&register_request("alice@example.com"), "en", &branding()).await.unwrap();
+        assert_eq!(code.len(), 6);
+
+        let user = logged_in(service.login("alice@example.com", &code).await.unwrap());
+        assert_eq!(user.email_verified, 1);
+
+        // 验证码只能使用一次
+        assert!(matches!(
+            service.login("alice@example.com", &code).await,
+            Err(AppError::Unauthorized(_))
+        ));
+    }
+
+    #[actix_web::test]
+    async fn duplicate_email_is_rejected() {
+        let service = service();
+        service.register(&register_request("alice@example.com"), "en", &branding()).await.unwrap();
+
+        assert!(matches!(
+            service.register(&register_request("alice@example.com"), "en", &branding()).await,
+            Err(AppError::Conflict(_))
+        ));
+    }
+
+    #[actix_web::test]
+    async fn admin_emails_register_as_admin() {
+        let service = service().with_admin_emails(vec!["Admin@Example.com".to_string()]);
+        let (_, admin_code) = service
+            .register(&register_request("admin@example.com"), "zh-CN", &branding())
+            .await
+            .unwrap();
+        let (_, user_code) = service.register(&register_request("alice@example.com"), "en", &branding()).await.unwrap();
+
+        let admin = logged_in(service.login("admin@example.com", &admin_code).await.unwrap());
+        assert!(admin.is_admin());
+        assert_eq!(admin.locale, "zh-CN");
+        assert!(!logged_in(service.login("alice@example.com", &user_code).await.unwrap()).is_admin());
+    }
+
+    #[actix_web::test]
+    async fn code_is_revoked_after_max_attempts() {
+        let service = service();
+        let (_, code) = service.register(&register_request("alice@example.com"), "en", &branding()).await.unwrap();
+        let wrong = if code == "000000" { "111111" } else { "000000" };
+
+        for _ in 0..3 {
+            assert!(service.login("alice@example.com", wrong).await.is_err());
+        }
+        assert!(matches!(
+            service.login("alice@example.com", &code).await,
+            Err(AppError::Unauthorized(_))
+        ));
+
+        // 重新获取验证码后可以登录
+        let code = service
+            .issue_verification_code("alice@example.com", &branding())
+            .await
+            .unwrap();
+        assert!(service.login("alice@example.com", &code).await.is_ok());
+    }
+
+    #[actix_web::test]
+    async fn unknown_email_cannot_request_code() {
+        assert!(matches!(
+            service().issue_verification_code("nobody@example.com", &branding()).await,
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    #[actix_web::test]
+    async fn emails_are_queued_with_the_user_changes() {
+        let repository = MemoryRepository::new();
+        let service = service_with(repository.clone());
+        let (_, code) = service.register(&register_request("alice@example.com"), "en", &branding()).await.unwrap();
+        service.login("alice@example.com", &code).await.unwrap();
+
+        // 再次登录不会重复发送欢迎邮件
+        let code = service
+            .issue_verification_code("alice@example.com", &branding())
+            .await
+            .unwrap();
+        service.login("alice@example.com", &code).await.unwrap();
+
+        let queued = OutboxRepository::list(&repository, &OutboxFilter::default(), Page::new(1, 10))
+            .await
+            .unwrap();
+        let templates: Vec<&str> = queued.iter().rev().map(|message| message.message.template.as_str()).collect();
+        assert_eq!(templates, ["verification", "welcome", "verification"]);
+        assert!(queued.iter().all(|message| message.message.recipient == "alice@example.com"));
+    }
+
+    // 从最近一封登录链接邮件中取出 token
+    async fn last_magic_token(repository: &MemoryRepository) -> String {
+        let queued = OutboxRepository::list(repository, &OutboxFilter::default(), Page::new(1, 1))
+            .await
+            .unwrap();
+        let (_, rest) = queued[0].message.text_body.split_once("token=").unwrap();
+        rest.chars().take_while(|c| c.is_ascii_hexdigit()).collect()
+    }
+
+    #[actix_web::test]
+    async fn magic_link_works_once_and_only_on_the_requesting_device() {
+        let repository = MemoryRepository::new();
+        let service = service_with(repository.clone());
+        service.register(&register_request("alice@example.com"), "en", &branding()).await.unwrap();
+
+        service.issue_magic_link("alice@example.com", &branding(), "device-a").await.unwrap();
+        let token = last_magic_token(&repository).await;
+
+        // 转发到其他设备的链接无法使用，也不会被作废
+        assert!(matches!(
+            service.login_with_magic_link(&token, Some("device-b")).await,
+            Err(AppError::Unauthorized(_))
+        ));
+        assert!(service.login_with_magic_link(&token, None).await.is_err());
+
+        let user = logged_in(service.login_with_magic_link(&token, Some("device-a")).await.unwrap());
+        assert_eq!(user.email_verified, 1);
+        assert!(service.login_with_magic_link(&token, Some("device-a")).await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn magic_link_expires_and_code_remains_a_fallback() {
+        let repository = MemoryRepository::new();
+        let clock = Arc::new(crate::test_support::FixedClock::default());
+        let service = service_with(repository.clone()).with_clock(clock.clone());
+        service.register(&register_request("alice@example.com"), "en", &branding()).await.unwrap();
+
+        service.issue_magic_link("alice@example.com", &branding(), "device").await.unwrap();
+        let expired = last_magic_token(&repository).await;
+        clock.advance(Duration::minutes(16));
+        assert!(service.login_with_magic_link(&expired, Some("device")).await.is_err());
+
+        // 同一封邮件中的验证码可以在其他设备上登录，登录后链接作废
+        let code = service.issue_magic_link("alice@example.com", &branding(), "device").await.unwrap();
+        let token = last_magic_token(&repository).await;
+        service.login("alice@example.com", &code).await.unwrap();
+        assert!(service.login_with_magic_link(&token, Some("device")).await.is_err());
+    }
+
+    const PASSWORD: &str = "Correct-Horse-9";
+
+    // 注册、登录并设置密码
+    async fn user_with_password(service: &UserService) -> User {
+        let (_, code) = service.register(&register_request("alice@example.com"), "en", &branding()).await.unwrap();
+        let user = logged_in(service.login("alice@example.com", &code).await.unwrap());
+        service.set_password(user.id, None, PASSWORD).await.unwrap();
+        user
+    }
+
+    // 开启两步验证，返回 TOTP 密钥和恢复码
+    async fn enroll_totp(service: &UserService, clock: &crate::test_support::FixedClock, user_id: i64) -> (String, Vec<String>) {
+        let setup = service.setup_totp(user_id).await.unwrap();
+        let code = totp::code_at(&setup.secret, clock.now()).unwrap();
+        let recovery_codes = service.enable_totp(user_id, &code).await.unwrap();
+        (setup.secret, recovery_codes)
+    }
+
+    fn challenge(login: Login) -> String {
+        match login {
+            Login::TwoFactorRequired(challenge) => challenge,
+            Login::Complete(_) => panic!("expected a two-factor challenge"),
+        }
+    }
+
+    #[actix_web::test]
+    async fn password_login_checks_password_and_strength() {
+        let service = service();
+        let user = user_with_password(&service).await;
+
+        assert!(matches!(
+            service.login_with_password("alice@example.com", PASSWORD).await,
+            Ok(Login::Complete(_))
+        ));
+        assert!(matches!(
+            service.login_with_password("alice@example.com", "Wrong-Horse-9").await,
+            Err(AppError::Unauthorized(_))
+        ));
+
+        // 修改密码需要当前密码，新密码必须足够强
+        assert!(matches!(
+            service.set_password(user.id, None, "Another-Horse-7").await,
+            Err(AppError::BadRequest(_))
+        ));
+        assert!(matches!(
+            service.set_password(user.id, Some(PASSWORD), "alice-Horse-7").await,
+            Err(AppError::ValidationError(_))
+        ));
+        service.set_password(user.id, Some(PASSWORD), "Another-Horse-7").await.unwrap();
+        assert!(service.login_with_password("alice@example.com", "Another-Horse-7").await.is_ok());
+    }
+
+    #[actix_web::test]
+    async fn totp_is_required_after_enrollment_and_codes_cannot_be_replayed() {
+        let clock = Arc::new(crate::test_support::FixedClock::default());
+        let service = service().with_clock(clock.clone());
+        let user = user_with_password(&service).await;
+        let (secret, _) = enroll_totp(&service, &clock, user.id).await;
+
+        let login = challenge(service.login_with_password("alice@example.com", PASSWORD).await.unwrap());
+        // 启用时使用过的验证码不能再次使用
+        let used = totp::code_at(&secret, clock.now()).unwrap();
+        assert!(service.verify_two_factor(&login, &used).await.is_err());
+
+        clock.advance(Duration::seconds(30));
+        let code = totp::code_at(&secret, clock.now()).unwrap();
+        let (logged_in, amr) = service.verify_two_factor(&login, &code).await.unwrap();
+        assert_eq!(logged_in.id, user.id);
+        assert_eq!(amr, [AMR_PASSWORD, AMR_TOTP]);
+        // 两步验证 token 只能使用一次
+        clock.advance(Duration::seconds(30));
+        let code = totp::code_at(&secret, clock.now()).unwrap();
+        assert!(service.verify_two_factor(&login, &code).await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn recovery_codes_work_once_and_challenges_expire() {
+        let clock = Arc::new(crate::test_support::FixedClock::default());
+        let service = service().with_clock(clock.clone());
+        let user = user_with_password(&service).await;
+        let (_, recovery_codes) = enroll_totp(&service, &clock, user.id).await;
+
+        let login = challenge(service.login_with_password("alice@example.com", PASSWORD).await.unwrap());
+        let (_, amr) = service
+            .verify_two_factor(&login, &recovery_codes[0].to_uppercase())
+            .await
+            .unwrap();
+        assert_eq!(amr, [AMR_PASSWORD, AMR_RECOVERY_CODE]);
+        assert_eq!(service.two_factor_status(user.id).await.unwrap().recovery_codes_remaining, 9);
+
+        let login = challenge(service.login_with_password("alice@example.com", PASSWORD).await.unwrap());
+        assert!(service.verify_two_factor(&login, &recovery_codes[0]).await.is_err());
+        clock.advance(Duration::minutes(6));
+        assert!(matches!(
+            service.verify_two_factor(&login, &recovery_codes[1]).await,
+            Err(AppError::Unauthorized(_))
+        ));
+
+        // 关闭两步验证后恢复码全部作废，密码直接登录
+        service.disable_totp(user.id, &recovery_codes[1]).await.unwrap();
+        assert_eq!(service.two_factor_status(user.id).await.unwrap().recovery_codes_remaining, 0);
+        assert!(matches!(
+            service.login_with_password("alice@example.com", PASSWORD).await,
+            Ok(Login::Complete(_))
+        ));
+    }
+
+    #[actix_web::test]
+    async fn email_logins_also_require_the_second_factor() {
+        let repository = MemoryRepository::new();
+        let clock = Arc::new(crate::test_support::FixedClock::default());
+        let service = service_with(repository.clone()).with_clock(clock.clone());
+        let user = user_with_password(&service).await;
+        let (secret, _) = enroll_totp(&service, &clock, user.id).await;
+        clock.advance(Duration::seconds(30));
+
+        let code = service.issue_verification_code("alice@example.com", &branding()).await.unwrap();
+        let login = challenge(service.login("alice@example.com", &code).await.unwrap());
+        let (_, amr) = service
+            .verify_two_factor(&login, &totp::code_at(&secret, clock.now()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(amr, [AMR_EMAIL, AMR_TOTP]);
+
+        clock.advance(Duration::seconds(30));
+        service.issue_magic_link("alice@example.com", &branding(), "device").await.unwrap();
+        let token = last_magic_token(&repository).await;
+        let login = challenge(service.login_with_magic_link(&token, Some("device")).await.unwrap());
+        let (_, amr) = service
+            .verify_two_factor(&login, &totp::code_at(&secret, clock.now()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(amr, [AMR_EMAIL, AMR_TOTP]);
+    }
+
+    #[actix_web::test]
+    async fn password_reset_token_is_single_use() {
+        let repository = MemoryRepository::new();
+        let service = service_with(repository.clone());
+        service.register(&register_request("alice@example.com"), "en", &branding()).await.unwrap();
+
+        // 不存在的邮箱不报错，也不发送邮件
+        assert!(service.request_password_reset("nobody@example.com", &branding()).await.unwrap().is_none());
+        let reset_token = service
+            .request_password_reset("alice@example.com", &branding())
+            .await
+            .unwrap()
+            .unwrap();
+        let queued = OutboxRepository::list(&repository, &OutboxFilter::default(), Page::new(1, 1))
+            .await
+            .unwrap();
+        assert_eq!(queued[0].message.template, "password_reset");
+        assert!(queued[0].message.text_body.contains(&reset_token));
+
+        // 弱密码不会作废 token
+        assert!(matches!(
+            service.reset_password(&reset_token, "short").await,
+            Err(AppError::ValidationError(_))
+        ));
+        service.reset_password(&reset_token, PASSWORD).await.unwrap();
+        assert!(service.reset_password(&reset_token, "Another-Horse-7").await.is_err());
+        assert!(service.login_with_password("alice@example.com", PASSWORD).await.is_ok());
+    }
+
+    fn federated(subject: &str, email: &str, role: Option<&str>) -> FederatedIdentity {
+        FederatedIdentity {
+            identity: crate::models::oidc::NewUserIdentity {
+                provider: "corp".to_string(),
+                subject: subject.to_string(),
+                email: email.to_string(),
+            },
+            email_verified: true,
+            username: None,
+            locale: None,
+            role: role.map(str::to_string),
+            provision: true,
+            multi_factor: false,
+        }
+    }
+
+    async fn federated_login(service: &UserService, federated: &FederatedIdentity) -> User {
+        logged_in(service.login_with_identity(federated).await.unwrap())
+    }
+
+    #[actix_web::test]
+    async fn federated_roles_follow_the_group_mapping() {
+        let service = service().with_admin_emails(vec!["admin@example.com".to_string()]);
+
+        let user = federated_login(&service, &federated("1", "carol@example.com", Some(ROLE_ADMIN))).await;
+        assert!(user.is_admin());
+        assert_eq!(user.username, "carol");
+        // 移出管理员组后降级，ADMIN_EMAILS 中的用户不受影响
+        let user = federated_login(&service, &federated("1", "carol@example.com", Some(ROLE_USER))).await;
+        assert!(!user.is_admin());
+        let admin = federated_login(&service, &federated("2", "admin@example.com", Some(ROLE_USER))).await;
+        assert!(admin.is_admin());
+        // 未配置映射时保留现有角色
+        assert!(federated_login(&service, &federated("2", "admin@example.com", None)).await.is_admin());
+
+        let mut closed = federated("3", "dave@example.com", None);
+        closed.provision = false;
+        assert!(matches!(service.login_with_identity(&closed).await, Err(AppError::Forbidden(_))));
+    }
+
+    #[actix_web::test]
+    async fn federated_logins_require_the_local_second_factor() {
+        let repository = MemoryRepository::new();
+        let clock = Arc::new(crate::test_support::FixedClock::default());
+        let service = service_with(repository.clone()).with_clock(clock.clone());
+        let user = user_with_password(&service).await;
+        let (secret, _) = enroll_totp(&service, &clock, user.id).await;
+        clock.advance(Duration::seconds(30));
+
+        // 按已验证的邮箱关联到开启了两步验证的账号
+        let login = challenge(service.login_with_identity(&federated("1", "alice@example.com", None)).await.unwrap());
+        assert!(service.verify_two_factor(&login, "000000").await.is_err());
+        let (signed_in, amr) = service
+            .verify_two_factor(&login, &totp::code_at(&secret, clock.now()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!((signed_in.id, amr), (user.id, [AMR_OIDC, AMR_TOTP]));
+    }
+}
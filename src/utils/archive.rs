@@ -0,0 +1,240 @@
+use crate::models::archive::{
+    Archive, ArchiveRecord, ArchivedApp, ArchivedArticle, ArchivedCategory, MediaReference, ARCHIVE_FORMAT,
+    ARCHIVE_VERSION,
+};
+use crate::utils::AppError;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+// Markdown 图片和 HTML img 标签中的地址
+static IMAGE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"!\[[^\]]*\]\(\s*<?([^)\s>]+)|<img\b[^>]*?\bsrc\s*=\s*["']([^"']+)["']"#).unwrap()
+});
+
+pub fn new_archive(app: ArchivedApp, articles: Vec<ArchivedArticle>) -> Archive {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for category in articles.iter().filter_map(|article| article.category.clone()) {
+        *counts.entry(category).or_default() += 1;
+    }
+    Archive {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        exported_at: chrono::Utc::now(),
+        media: media_references(&app, &articles),
+        categories: counts
+            .into_iter()
+            .map(|(name, articles)| ArchivedCategory { name, articles })
+            .collect(),
+        app,
+        articles,
+    }
+}
+
+// 应用图标、文章的分享图片和正文中的图片，同一文章中重复的地址只保留一个
+pub fn media_references(app: &ArchivedApp, articles: &[ArchivedArticle]) -> Vec<MediaReference> {
+    let mut media = Vec::new();
+    if let Some(url) = &app.logo_url {
+        media.push(MediaReference {
+            url: url.clone(),
+            article_id: None,
+            field: "logo_url".to_string(),
+        });
+    }
+    for article in articles {
+        let mut push = |url: &str, field: &str| {
+            let exists = media
+                .iter()
+                .any(|item: &MediaReference| item.article_id == Some(article.id) && item.url == url);
+            if !exists {
+                media.push(MediaReference {
+                    url: url.to_string(),
+                    article_id: Some(article.id),
+                    field: field.to_string(),
+                });
+            }
+        };
+        if let Some(url) = &article.og_image_url {
+            push(url, "og_image_url");
+        }
+        for captures in IMAGE_REGEX.captures_iter(&article.content) {
+            if let Some(url) = captures.get(1).or_else(|| captures.get(2)) {
+                push(url.as_str(), "content");
+            }
+        }
+    }
+    media
+}
+
+pub fn to_ndjson(archive: &Archive) -> String {
+    let mut records = vec![
+        ArchiveRecord::Archive {
+            format: archive.format.clone(),
+            version: archive.version,
+            exported_at: archive.exported_at,
+        },
+        ArchiveRecord::App(archive.app.clone()),
+    ];
+    records.extend(archive.categories.iter().cloned().map(ArchiveRecord::Category));
+    records.extend(archive.media.iter().cloned().map(ArchiveRecord::Media));
+    records.extend(archive.articles.iter().cloned().map(ArchiveRecord::Article));
+
+    let mut body = String::new();
+    for record in records {
+        body.push_str(&serde_json::to_string(&record).unwrap_or_default());
+        body.push('\n');
+    }
+    body
+}
+
+pub fn from_json(body: &str) -> Result<Archive, AppError> {
+    let archive: Archive =
+        serde_json::from_str(body).map_err(|e| AppError::BadRequest(format!("Invalid archive: {}", e)))?;
+    check_version(&archive.format, archive.version)?;
+    Ok(archive)
+}
+
+pub fn from_ndjson(body: &str) -> Result<Archive, AppError> {
+    let mut lines = body.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let parse = |number: usize, line: &str| {
+        serde_json::from_str::<ArchiveRecord>(line)
+            .map_err(|e| AppError::BadRequest(format!("Invalid archive at line {}: {}", number + 1, e)))
+    };
+    let (format, version, exported_at) = match lines.next().map(|(number, line)| parse(number, line)) {
+        Some(Ok(ArchiveRecord::Archive {
+            format,
+            version,
+            exported_at,
+        })) => (format, version, exported_at),
+        Some(Err(e)) => return Err(e),
+        _ => return Err(AppError::BadRequest("Invalid archive: the first line must be the archive header".to_string())),
+    };
+    check_version(&format, version)?;
+
+    let mut app = None;
+    let mut archive = Archive {
+        format,
+        version,
+        exported_at,
+        app: ArchivedApp::default(),
+        categories: Vec::new(),
+        media: Vec::new(),
+        articles: Vec::new(),
+    };
+    for (number, line) in lines {
+        match parse(number, line)? {
+            ArchiveRecord::Archive { .. } => {
+                return Err(AppError::BadRequest(format!(
+                    "Invalid archive at line {}: duplicate archive header",
+                    number + 1
+                )))
+            }
+            ArchiveRecord::App(record) => app = Some(record),
+            ArchiveRecord::Category(category) => archive.categories.push(category),
+            ArchiveRecord::Media(media) => archive.media.push(media),
+            ArchiveRecord::Article(article) => archive.articles.push(article),
+        }
+    }
+    archive.app = app.ok_or_else(|| AppError::BadRequest("Invalid archive: missing app".to_string()))?;
+    Ok(archive)
+}
+
+// 新版本的导出文件可能包含无法识别的内容，不导入
+fn check_version(format: &str, version: u32) -> Result<(), AppError> {
+    if format != ARCHIVE_FORMAT {
+        return Err(AppError::BadRequest(format!("Unknown archive format: {}", format)));
+    }
+    if version == 0 || version > ARCHIVE_VERSION {
+        return Err(AppError::BadRequest(format!(
+            "Unsupported archive version {}, this server reads up to version {}",
+            version, ARCHIVE_VERSION
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn archive() -> Archive {
+        let app = ArchivedApp {
+            identifier: "blog".to_string(),
+            name: "Blog".to_string(),
+            logo_url: Some("https://example.com/logo.png".to_string()),
+            ..ArchivedApp::default()
+        };
+        let article = |id: i64, category: Option<&str>, content: &str| ArchivedArticle {
+            id,
+            title: format!("Article {}", id),
+            content: content.to_string(),
+            status: 2,
+            category: category.map(str::to_string),
+            locale: None,
+            translation_of: None,
+            meta_title: None,
+            meta_description: None,
+            canonical_url: None,
+            og_image_url: None,
+            noindex: false,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        new_archive(
+            app,
+            vec![
+                article(1, Some("news"), "![a](https://example.com/a.png) ![b]( <https://example.com/b.png> )"),
+                article(2, Some("news"), "<p><img class=\"x\" src='https://example.com/c.png'></p>"),
+                article(3, Some("tips"), "![a](https://example.com/a.png) ![again](https://example.com/a.png)"),
+            ],
+        )
+    }
+
+    #[test]
+    fn archives_collect_categories_and_media() {
+        let archive = archive();
+        let categories: Vec<(&str, usize)> =
+            archive.categories.iter().map(|category| (category.name.as_str(), category.articles)).collect();
+        assert_eq!(categories, vec![("news", 2), ("tips", 1)]);
+        let media: Vec<(Option<i64>, &str)> =
+            archive.media.iter().map(|item| (item.article_id, item.url.as_str())).collect();
+        assert_eq!(
+            media,
+            vec![
+                (None, "https://example.com/logo.png"),
+                (Some(1), "https://example.com/a.png"),
+                (Some(1), "https://example.com/b.png"),
+                (Some(2), "https://example.com/c.png"),
+                (Some(3), "https://example.com/a.png"),
+            ]
+        );
+    }
+
+    #[test]
+    fn ndjson_round_trips_and_checks_the_version() {
+        let archive = archive();
+        let body = to_ndjson(&archive);
+        assert_eq!(body.lines().count(), 2 + 2 + 5 + 3);
+        assert!(body.starts_with("{\"type\":\"archive\",\"format\":\"rscms-archive\",\"version\":1"));
+        let parsed = from_ndjson(&body).unwrap();
+        assert_eq!(parsed.app.identifier, "blog");
+        assert_eq!(parsed.articles.len(), 3);
+        assert_eq!(parsed.media, archive.media);
+
+        let json = serde_json::to_string(&archive).unwrap();
+        assert_eq!(from_json(&json).unwrap().articles.len(), 3);
+        let newer = json.replace("\"version\":1", "\"version\":2");
+        assert!(matches!(from_json(&newer), Err(AppError::BadRequest(_))));
+
+        let headless = body.lines().skip(1).collect::<Vec<_>>().join("\n");
+        assert!(matches!(from_ndjson(&headless), Err(AppError::BadRequest(_))));
+        let broken = format!("{}{{\"type\":\"article\"}}\n", body);
+        match from_ndjson(&broken) {
+            Err(AppError::BadRequest(message)) => assert!(message.contains("line 13")),
+            other => panic!("unexpected result: {:?}", other.map(|archive| archive.articles.len())),
+        }
+    }
+}
@@ -0,0 +1,8 @@
+use chrono::Duration;
+
+// 第 attempts 次失败后的等待时间：base * 2^(attempts-1)，最多 max
+pub fn exponential(base: std::time::Duration, max: std::time::Duration, attempts: i32) -> Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 30) as u32;
+    let delay = base.saturating_mul(2u32.saturating_pow(exponent)).min(max);
+    Duration::from_std(delay).unwrap_or_else(|_| Duration::weeks(52))
+}
@@ -0,0 +1,14 @@
+use chrono::{DateTime, Utc};
+
+// 时间来源，测试中可以替换为固定时间
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
@@ -1,65 +1,329 @@
+use async_trait::async_trait;
 use lettre::transport::smtp::authentication::Credentials;
-use lettre::{Message, SmtpTransport, Transport};
-use lettre::transport::smtp::client::TlsParameters;
-use std::sync::Arc;
-use std::env;
-use log;
+use lettre::transport::smtp::client::{Tls, TlsParameters};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::{AsyncFileTransport, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
 
-#[derive(Clone)]
-pub struct EmailService {
-    smtp_transport: Arc<SmtpTransport>,
-    from_email: String,
+use super::email_template::{Branding, EmailTemplate, EmailTemplates, RenderedEmail};
+use crate::config::email::{EmailConfig, EmailTransportConfig, SmtpConfig, SmtpTls};
+use crate::models::outbox::EmailMessage;
+use crate::models::{App, User};
+
+// 邮件的实际发送方式
+#[async_trait]
+pub trait EmailTransport: Send + Sync {
+    async fn send(&self, message: Message) -> Result<(), anyhow::Error>;
 }
 
-impl EmailService {
+pub struct SmtpEmailTransport {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpEmailTransport {
+    pub fn new(config: &SmtpConfig) -> Result<Self, anyhow::Error> {
+        let mut builder = match config.tls {
+            SmtpTls::Implicit => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)
+                .tls(Tls::Wrapper(TlsParameters::new(config.host.clone())?)),
+            SmtpTls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)
+                .tls(Tls::Required(TlsParameters::new(config.host.clone())?)),
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+        }
+        .port(config.port);
+
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+        })
+    }
+}
+
+#[async_trait]
+impl EmailTransport for SmtpEmailTransport {
+    async fn send(&self, message: Message) -> Result<(), anyhow::Error> {
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+// 把邮件写入目录，每封邮件一个 .eml 文件
+pub struct FileEmailTransport {
+    transport: AsyncFileTransport<Tokio1Executor>,
+}
+
+impl FileEmailTransport {
+    pub fn new(dir: &Path) -> Result<Self, anyhow::Error> {
+        std::fs::create_dir_all(dir)?;
+        Ok(Self {
+            transport: AsyncFileTransport::new(dir),
+        })
+    }
+}
+
+#[async_trait]
+impl EmailTransport for FileEmailTransport {
+    async fn send(&self, message: Message) -> Result<(), anyhow::Error> {
+        let id = self.transport.send(message).await?;
+        log::debug!("Wrote email {}.eml", id);
+        Ok(())
+    }
+}
+
+// 只把邮件内容输出到日志，适合本地开发
+pub struct LogEmailTransport;
+
+#[async_trait]
+impl EmailTransport for LogEmailTransport {
+    async fn send(&self, message: Message) -> Result<(), anyhow::Error> {
+        log::info!("Email not sent (log transport):\n{}", String::from_utf8_lossy(&message.formatted()));
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SentEmail {
+    pub to: Vec<String>,
+    pub raw: String,
+}
+
+// 保存在内存中，供测试读取
+#[derive(Clone, Default)]
+pub struct MemoryEmailTransport {
+    sent: Arc<Mutex<Vec<SentEmail>>>,
+}
+
+impl MemoryEmailTransport {
     pub fn new() -> Self {
-        let smtp_host = env::var("SMTP_HOST").expect("SMTP_HOST must be set");
-        let smtp_port = env::var("SMTP_PORT")
-            .expect("SMTP_PORT must be set")
-            .parse::<u16>()
-            .expect("SMTP_PORT must be a valid port number");
-        let smtp_username = env::var("SMTP_USERNAME").expect("SMTP_USERNAME must be set");
-        let smtp_password = env::var("SMTP_PASSWORD").expect("SMTP_PASSWORD must be set");
-        let from_email = env::var("SMTP_FROM_EMAIL").expect("SMTP_FROM_EMAIL must be set");
-
-        let creds = Credentials::new(smtp_username, smtp_password);
-
-        let tls_parameters = TlsParameters::new(smtp_host.clone())
-            .unwrap();
+        Self::default()
+    }
 
-        let smtp_transport = SmtpTransport::relay(&smtp_host)
+    pub fn sent(&self) -> Vec<SentEmail> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    // 最近一封发给该邮箱的邮件
+    pub fn last_sent_to(&self, email: &str) -> Option<SentEmail> {
+        self.sent
+            .lock()
             .unwrap()
-            .port(smtp_port)
-            .tls(lettre::transport::smtp::client::Tls::Wrapper(tls_parameters))
-            .credentials(creds)
-            .build();
+            .iter()
+            .rev()
+            .find(|sent| sent.to.iter().any(|to| to == email))
+            .cloned()
+    }
+}
 
+#[async_trait]
+impl EmailTransport for MemoryEmailTransport {
+    async fn send(&self, message: Message) -> Result<(), anyhow::Error> {
+        let to = message.envelope().to().iter().map(|address| address.to_string()).collect();
+        let raw = String::from_utf8_lossy(&message.formatted()).into_owned();
+        self.sent.lock().unwrap().push(SentEmail { to, raw });
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct EmailService {
+    transport: Arc<dyn EmailTransport>,
+    templates: Arc<EmailTemplates>,
+    // 默认品牌，应用可以覆盖
+    branding: Branding,
+}
+
+impl EmailService {
+    pub fn new(transport: Arc<dyn EmailTransport>, from_email: &str) -> Self {
         Self {
-            smtp_transport: Arc::new(smtp_transport),
-            from_email,
+            transport,
+            templates: Arc::new(EmailTemplates::new().expect("Built-in email templates must compile")),
+            branding: Branding {
+                name: "RSCMS".to_string(),
+                logo_url: None,
+                from_email: from_email.to_string(),
+            },
         }
     }
 
-    pub fn send_verification_code(&self, to_email: &str, code: &str) -> Result<(), Box<dyn std::error::Error>> {
-        log::debug!("Attempting to send verification code to {}", to_email);
-        
-        let email = Message::builder()
-            .from(self.from_email.parse()?)
-            .to(to_email.parse()?)
-            .subject("Your Verification Code")
-            .body(format!("Your verification code is: {}", code))?;
-
-        log::debug!("Email message built successfully");
-        
-        match self.smtp_transport.send(&email) {
-            Ok(_) => {
-                log::info!("Successfully sent verification code to {}", to_email);
-                Ok(())
-            }
-            Err(e) => {
-                log::error!("Failed to send email: {:?}", e);
-                Err(Box::new(e))
-            }
+    pub fn with_branding(mut self, name: &str, logo_url: Option<String>) -> Self {
+        self.branding.name = name.to_string();
+        self.branding.logo_url = logo_url;
+        self
+    }
+
+    // 根据配置选择发送方式
+    pub fn from_config(config: &EmailConfig) -> Result<Self, anyhow::Error> {
+        let transport: Arc<dyn EmailTransport> = match &config.transport {
+            EmailTransportConfig::Smtp(smtp) => Arc::new(SmtpEmailTransport::new(smtp)?),
+            EmailTransportConfig::File(dir) => Arc::new(FileEmailTransport::new(dir)?),
+            EmailTransportConfig::Log => Arc::new(LogEmailTransport),
+            EmailTransportConfig::Memory => Arc::new(MemoryEmailTransport::new()),
+        };
+        Ok(Self::new(transport, &config.from_email)
+            .with_branding(&config.brand_name, config.brand_logo_url.clone()))
+    }
+
+    pub fn from_env() -> Result<Self, anyhow::Error> {
+        Self::from_config(&EmailConfig::from_env()?)
+    }
+
+    // 应用设置了名称、logo 或发件地址时覆盖默认品牌
+    pub fn branding(&self, app: Option<&App>) -> Branding {
+        match app {
+            Some(app) => Branding {
+                name: app.name.clone(),
+                logo_url: app.logo_url.clone().or_else(|| self.branding.logo_url.clone()),
+                from_email: app.email_from.clone().unwrap_or_else(|| self.branding.from_email.clone()),
+            },
+            None => self.branding.clone(),
         }
     }
+
+    pub fn render(
+        &self,
+        template: EmailTemplate,
+        locale: &str,
+        branding: &Branding,
+        context: &Value,
+    ) -> Result<RenderedEmail, anyhow::Error> {
+        Ok(self.templates.render(template, locale, branding, context)?)
+    }
+
+    // 渲染模板，得到可以写入发件箱的邮件
+    pub fn compose(
+        &self,
+        to_email: &str,
+        locale: &str,
+        branding: &Branding,
+        template: EmailTemplate,
+        context: Value,
+    ) -> Result<EmailMessage, anyhow::Error> {
+        let rendered = self.render(template, locale, branding, &context)?;
+        Ok(EmailMessage {
+            template: template.name().to_string(),
+            recipient: to_email.to_string(),
+            from_name: branding.name.clone(),
+            from_email: branding.from_email.clone(),
+            subject: rendered.subject,
+            html_body: rendered.html,
+            text_body: rendered.text,
+        })
+    }
+
+    pub fn compose_verification_code(
+        &self,
+        to_email: &str,
+        username: &str,
+        locale: &str,
+        branding: &Branding,
+        code: &str,
+        expires_minutes: i64,
+    ) -> Result<EmailMessage, anyhow::Error> {
+        let context = json!({ "username": username, "code": code, "expires_minutes": expires_minutes });
+        self.compose(to_email, locale, branding, EmailTemplate::Verification, context)
+    }
+
+    pub fn compose_welcome(
+        &self,
+        to_email: &str,
+        username: &str,
+        locale: &str,
+        branding: &Branding,
+    ) -> Result<EmailMessage, anyhow::Error> {
+        self.compose(to_email, locale, branding, EmailTemplate::Welcome, json!({ "username": username }))
+    }
+
+    // 登录链接邮件，附带在其他设备上使用的验证码
+    pub fn compose_magic_link(
+        &self,
+        user: &User,
+        branding: &Branding,
+        login_url: &str,
+        code: &str,
+        expires_minutes: i64,
+    ) -> Result<EmailMessage, anyhow::Error> {
+        let context = json!({
+            "username": user.username,
+            "login_url": login_url,
+            "code": code,
+            "expires_minutes": expires_minutes,
+        });
+        self.compose(&user.email, &user.locale, branding, EmailTemplate::MagicLink, context)
+    }
+
+    pub fn compose_password_reset(
+        &self,
+        user: &User,
+        branding: &Branding,
+        reset_url: &str,
+        expires_minutes: i64,
+    ) -> Result<EmailMessage, anyhow::Error> {
+        let context = json!({
+            "username": user.username,
+            "reset_url": reset_url,
+            "expires_minutes": expires_minutes,
+        });
+        self.compose(&user.email, &user.locale, branding, EmailTemplate::PasswordReset, context)
+    }
+
+    // 以 HTML + 纯文本的形式发送，由发件箱 worker 调用
+    pub async fn deliver(&self, message: &EmailMessage) -> Result<(), anyhow::Error> {
+        log::debug!("Attempting to send {} email to {}", message.template, message.recipient);
+
+        let email = Message::builder()
+            .from(Mailbox::new(Some(message.from_name.clone()), message.from_email.parse()?))
+            .to(message.recipient.parse()?)
+            .subject(message.subject.clone())
+            .multipart(MultiPart::alternative_plain_html(
+                message.text_body.clone(),
+                message.html_body.clone(),
+            ))?;
+
+        self.transport.send(email).await?;
+        log::info!("Successfully sent {} email to {}", message.template, message.recipient);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn send_code(service: &EmailService) {
+        let branding = service.branding(None);
+        let message = service
+            .compose_verification_code("alice@example.com", "alice", "en", &branding, "123456", 30)
+            .unwrap();
+        service.deliver(&message).await.unwrap();
+    }
+
+    #[actix_web::test]
+    async fn memory_transport_records_messages() {
+        let transport = MemoryEmailTransport::new();
+        let service = EmailService::new(Arc::new(transport.clone()), "noreply@example.com");
+
+        send_code(&service).await;
+
+        let sent = transport.last_sent_to("alice@example.com").unwrap();
+        assert!(sent.raw.contains("Your verification code is: 123456"));
+        assert!(transport.last_sent_to("bob@example.com").is_none());
+    }
+
+    #[actix_web::test]
+    async fn file_transport_writes_eml_files() {
+        let dir = std::env::temp_dir().join(format!("rscms-emails-{}", uuid::Uuid::new_v4()));
+        let service = EmailService::new(Arc::new(FileEmailTransport::new(&dir).unwrap()), "noreply@example.com");
+
+        send_code(&service).await;
+
+        let files: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().path()).collect();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].extension().unwrap(), "eml");
+        assert!(std::fs::read_to_string(&files[0]).unwrap().contains("123456"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,246 @@
+use actix_web::HttpRequest;
+use minijinja::Environment;
+use serde::Serialize;
+use serde_json::{json, Value};
+use utoipa::ToSchema;
+
+// 支持的语言，第一个为默认语言
+pub const SUPPORTED_LOCALES: &[&str] = &["en", "zh"];
+pub const DEFAULT_LOCALE: &str = "en";
+
+// 事务邮件的种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailTemplate {
+    Verification,
+    Welcome,
+    Invitation,
+    CommentNotification,
+    MagicLink,
+    PasswordReset,
+}
+
+impl EmailTemplate {
+    pub const ALL: [EmailTemplate; 6] = [
+        EmailTemplate::Verification,
+        EmailTemplate::Welcome,
+        EmailTemplate::Invitation,
+        EmailTemplate::CommentNotification,
+        EmailTemplate::MagicLink,
+        EmailTemplate::PasswordReset,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EmailTemplate::Verification => "verification",
+            EmailTemplate::Welcome => "welcome",
+            EmailTemplate::Invitation => "invitation",
+            EmailTemplate::CommentNotification => "comment_notification",
+            EmailTemplate::MagicLink => "magic_link",
+            EmailTemplate::PasswordReset => "password_reset",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|template| template.name() == name)
+    }
+
+    // 预览时使用的示例数据
+    pub fn sample_context(&self) -> Value {
+        match self {
+            EmailTemplate::Verification => json!({ "username": "alice", "code": "123456", "expires_minutes": 30 }),
+            EmailTemplate::Welcome => json!({ "username": "alice" }),
+            EmailTemplate::Invitation => json!({
+                "inviter": "bob",
+                "accept_url": "https://example.com/invitations/accept?token=sample",
+            }),
+            EmailTemplate::CommentNotification => json!({
+                "username": "alice",
+                "commenter": "bob",
+                "article_title": "Hello World",
+                "comment": "Great post!",
+                "article_url": "https://example.com/articles/1",
+            }),
+            EmailTemplate::MagicLink => json!({
+                "username": "alice",
+                "login_url": "https://example.com/api/auth/magic?token=sample",
+                "code": "123456",
+                "expires_minutes": 15,
+            }),
+            EmailTemplate::PasswordReset => json!({
+                "username": "alice",
+                "reset_url": "https://example.com/reset-password?token=sample",
+                "expires_minutes": 60,
+            }),
+        }
+    }
+}
+
+// 邮件中展示的品牌信息
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Branding {
+    pub name: String,
+    pub logo_url: Option<String>,
+    pub from_email: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+}
+
+// 每个模板由 subject、html 和纯文本三部分组成
+macro_rules! template_sources {
+    ($locale:literal) => {
+        template_sources!($locale, ["verification", "welcome", "invitation", "comment_notification", "magic_link", "password_reset"])
+    };
+    ($locale:literal, [$($name:literal),*]) => {
+        &[
+            $(
+                (concat!($locale, "/", $name, ".subject.txt"), include_str!(concat!("../../templates/email/", $locale, "/", $name, ".subject.txt"))),
+                (concat!($locale, "/", $name, ".html"), include_str!(concat!("../../templates/email/", $locale, "/", $name, ".html"))),
+                (concat!($locale, "/", $name, ".txt"), include_str!(concat!("../../templates/email/", $locale, "/", $name, ".txt"))),
+            )*
+        ]
+    };
+}
+
+const LAYOUT_SOURCE: &str = include_str!("../../templates/email/layout.html");
+const TEMPLATE_SOURCES: &[&[(&str, &str)]] = &[template_sources!("en"), template_sources!("zh")];
+
+// 模板在编译时嵌入二进制
+pub struct EmailTemplates {
+    env: Environment<'static>,
+}
+
+impl EmailTemplates {
+    pub fn new() -> Result<Self, minijinja::Error> {
+        let mut env = Environment::new();
+        env.add_template("layout.html", LAYOUT_SOURCE)?;
+        for (name, source) in TEMPLATE_SOURCES.iter().flat_map(|sources| sources.iter()) {
+            env.add_template(name, source)?;
+        }
+        Ok(Self { env })
+    }
+
+    pub fn render(
+        &self,
+        template: EmailTemplate,
+        locale: &str,
+        branding: &Branding,
+        context: &Value,
+    ) -> Result<RenderedEmail, minijinja::Error> {
+        let locale = resolve_locale(locale);
+        let mut context = context.clone();
+        if let Value::Object(map) = &mut context {
+            map.insert("brand".to_string(), json!(branding));
+            map.insert("locale".to_string(), json!(locale));
+        }
+
+        let render = |suffix: &str, context: &Value| {
+            self.env
+                .get_template(&format!("{}/{}.{}", locale, template.name(), suffix))?
+                .render(context)
+        };
+
+        let subject = render("subject.txt", &context)?.trim().to_string();
+        if let Value::Object(map) = &mut context {
+            map.insert("subject".to_string(), json!(subject));
+        }
+        let html = render("html", &context)?;
+        let text = render("txt", &context)?;
+
+        Ok(RenderedEmail { subject, html, text })
+    }
+}
+
+// 选择最接近的支持语言，例如 zh-CN 使用 zh
+pub fn resolve_locale(preferred: &str) -> &'static str {
+    let preferred = preferred.trim().to_lowercase().replace('_', "-");
+    let language = preferred.split('-').next().unwrap_or_default();
+    SUPPORTED_LOCALES
+        .iter()
+        .find(|locale| **locale == preferred || **locale == language)
+        .copied()
+        .unwrap_or(DEFAULT_LOCALE)
+}
+
+// Accept-Language 中优先级最高的语言
+pub fn request_locale(req: &HttpRequest) -> Option<String> {
+    let header = req.headers().get("Accept-Language")?.to_str().ok()?;
+    header
+        .split(',')
+        .map(|part| {
+            let mut pieces = part.split(';');
+            let tag = pieces.next().unwrap_or_default().trim();
+            let quality = pieces
+                .find_map(|piece| piece.trim().strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (tag, quality)
+        })
+        .filter(|(tag, quality)| !tag.is_empty() && *tag != "*" && *quality > 0.0)
+        // 权重相同时取靠前的
+        .fold(None, |best: Option<(&str, f32)>, item| match best {
+            Some(best) if best.1 >= item.1 => Some(best),
+            _ => Some(item),
+        })
+        .map(|(tag, _)| tag.chars().take(16).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn branding() -> Branding {
+        Branding {
+            name: "Blog <Inc>".to_string(),
+            logo_url: Some("https://example.com/logo.png".to_string()),
+            from_email: "blog@example.com".to_string(),
+        }
+    }
+
+    #[test]
+    fn every_template_renders_in_every_locale() {
+        let templates = EmailTemplates::new().unwrap();
+        for template in EmailTemplate::ALL {
+            for locale in SUPPORTED_LOCALES {
+                let rendered = templates
+                    .render(template, locale, &branding(), &template.sample_context())
+                    .unwrap();
+                assert!(!rendered.subject.is_empty());
+                assert!(!rendered.subject.contains('\n'));
+                assert!(rendered.html.contains("logo.png"));
+                assert!(!rendered.text.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn html_is_escaped_but_text_is_not() {
+        let templates = EmailTemplates::new().unwrap();
+        let rendered = templates
+            .render(EmailTemplate::Welcome, "en", &branding(), &json!({ "username": "<b>alice</b>" }))
+            .unwrap();
+        assert!(rendered.html.contains("&lt;b&gt;alice&lt;&#x2f;b&gt;"));
+        assert!(rendered.text.contains("<b>alice</b>"));
+        assert_eq!(rendered.subject, "Welcome to Blog <Inc>");
+    }
+
+    #[test]
+    fn locale_falls_back_to_language_then_default() {
+        assert_eq!(resolve_locale("zh-CN"), "zh");
+        assert_eq!(resolve_locale("zh_TW"), "zh");
+        assert_eq!(resolve_locale("EN"), "en");
+        assert_eq!(resolve_locale("fr"), "en");
+    }
+
+    #[test]
+    fn accept_language_uses_highest_quality() {
+        let req = actix_web::test::TestRequest::default()
+            .insert_header(("Accept-Language", "en;q=0.5, zh-CN, *;q=0.1"))
+            .to_http_request();
+        assert_eq!(request_locale(&req).as_deref(), Some("zh-CN"));
+    }
+}
@@ -0,0 +1,245 @@
+use crate::models::feed::FeedMode;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Value};
+
+// 订阅源格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedFormat {
+    Rss,
+    Atom,
+    Json,
+}
+
+impl FeedFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            FeedFormat::Rss => "application/rss+xml; charset=utf-8",
+            FeedFormat::Atom => "application/atom+xml; charset=utf-8",
+            FeedFormat::Json => "application/feed+json; charset=utf-8",
+        }
+    }
+
+    // 订阅源地址的最后一段
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            FeedFormat::Rss => "rss.xml",
+            FeedFormat::Atom => "atom.xml",
+            FeedFormat::Json => "feed.json",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Feed {
+    pub title: String,
+    pub description: String,
+    // 网站地址
+    pub link: String,
+    // 订阅源自身的地址
+    pub url: String,
+    pub author: String,
+    pub updated: DateTime<Utc>,
+    pub mode: FeedMode,
+    pub items: Vec<FeedItem>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FeedItem {
+    pub title: String,
+    pub url: String,
+    pub category: Option<String>,
+    // 摘要模式下已截取
+    pub content: String,
+    pub published: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+}
+
+impl Feed {
+    pub fn render(&self, format: FeedFormat) -> String {
+        match format {
+            FeedFormat::Rss => self.rss(),
+            FeedFormat::Atom => self.atom(),
+            FeedFormat::Json => self.json().to_string(),
+        }
+    }
+
+    // RSS 2.0
+    fn rss(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\n<channel>\n");
+        xml.push_str(&format!("<title>{}</title>\n", escape(&self.title)));
+        xml.push_str(&format!("<link>{}</link>\n", escape(&self.link)));
+        xml.push_str(&format!("<description>{}</description>\n", escape(&self.description)));
+        xml.push_str(&format!(
+            "<atom:link href=\"{}\" rel=\"self\" type=\"application/rss+xml\"/>\n",
+            escape(&self.url)
+        ));
+        xml.push_str(&format!("<lastBuildDate>{}</lastBuildDate>\n", self.updated.to_rfc2822()));
+        for item in &self.items {
+            xml.push_str("<item>\n");
+            xml.push_str(&format!("<title>{}</title>\n", escape(&item.title)));
+            xml.push_str(&format!("<link>{}</link>\n", escape(&item.url)));
+            xml.push_str(&format!("<guid isPermaLink=\"true\">{}</guid>\n", escape(&item.url)));
+            xml.push_str(&format!("<pubDate>{}</pubDate>\n", item.published.to_rfc2822()));
+            if let Some(category) = &item.category {
+                xml.push_str(&format!("<category>{}</category>\n", escape(category)));
+            }
+            xml.push_str(&format!("<description>{}</description>\n", escape(&item.content)));
+            xml.push_str("</item>\n");
+        }
+        xml.push_str("</channel>\n</rss>\n");
+        xml
+    }
+
+    // Atom 1.0，文章内容按纯文本输出
+    fn atom(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+        xml.push_str(&format!("<id>{}</id>\n", escape(&self.url)));
+        xml.push_str(&format!("<title>{}</title>\n", escape(&self.title)));
+        xml.push_str(&format!("<subtitle>{}</subtitle>\n", escape(&self.description)));
+        xml.push_str(&format!("<link rel=\"self\" href=\"{}\"/>\n", escape(&self.url)));
+        xml.push_str(&format!("<link rel=\"alternate\" href=\"{}\"/>\n", escape(&self.link)));
+        xml.push_str(&format!("<updated>{}</updated>\n", rfc3339(self.updated)));
+        xml.push_str(&format!("<author><name>{}</name></author>\n", escape(&self.author)));
+        let element = match self.mode {
+            FeedMode::Full => "content",
+            FeedMode::Summary => "summary",
+        };
+        for item in &self.items {
+            xml.push_str("<entry>\n");
+            xml.push_str(&format!("<id>{}</id>\n", escape(&item.url)));
+            xml.push_str(&format!("<title>{}</title>\n", escape(&item.title)));
+            xml.push_str(&format!("<link rel=\"alternate\" href=\"{}\"/>\n", escape(&item.url)));
+            xml.push_str(&format!("<published>{}</published>\n", rfc3339(item.published)));
+            xml.push_str(&format!("<updated>{}</updated>\n", rfc3339(item.updated)));
+            if let Some(category) = &item.category {
+                xml.push_str(&format!("<category term=\"{}\"/>\n", escape(category)));
+            }
+            xml.push_str(&format!("<{0} type=\"text\">{1}</{0}>\n", element, escape(&item.content)));
+            xml.push_str("</entry>\n");
+        }
+        xml.push_str("</feed>\n");
+        xml
+    }
+
+    // JSON Feed 1.1
+    fn json(&self) -> Value {
+        let items: Vec<Value> = self
+            .items
+            .iter()
+            .map(|item| {
+                let mut entry = json!({
+                    "id": item.url,
+                    "url": item.url,
+                    "title": item.title,
+                    "content_text": item.content,
+                    "date_published": rfc3339(item.published),
+                    "date_modified": rfc3339(item.updated),
+                });
+                if self.mode == FeedMode::Summary {
+                    entry["summary"] = json!(item.content);
+                }
+                if let Some(category) = &item.category {
+                    entry["tags"] = json!([category]);
+                }
+                entry
+            })
+            .collect();
+        json!({
+            "version": "https://jsonfeed.org/version/1.1",
+            "title": self.title,
+            "home_page_url": self.link,
+            "feed_url": self.url,
+            "description": self.description,
+            "authors": [{ "name": self.author }],
+            "items": items,
+        })
+    }
+}
+
+fn rfc3339(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+// 转义 XML 特殊字符，并去掉 XML 1.0 不允许出现的控制字符
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' || c == '\u{FFFE}' || c == '\u{FFFF}' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// 合并空白后截取前 max_chars 个字符
+pub fn summarize(content: &str, max_chars: usize) -> String {
+    let text = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+    let truncated: String = text.chars().take(max_chars).collect();
+    format!("{}…", truncated.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn feed(mode: FeedMode) -> Feed {
+        let time = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+        Feed {
+            title: "Tom & Jerry's <Blog>".to_string(),
+            description: "News".to_string(),
+            link: "https://example.com/?a=1&b=2".to_string(),
+            url: "https://cms.example.com/feeds/blog/rss.xml".to_string(),
+            author: "Blog".to_string(),
+            updated: time,
+            mode,
+            items: vec![FeedItem {
+                title: "1 < 2 \u{1}".to_string(),
+                url: "https://example.com/articles/1".to_string(),
+                category: Some("news".to_string()),
+                content: "<script>alert(\"x\")</script>".to_string(),
+                published: time,
+                updated: time,
+            }],
+        }
+    }
+
+    #[test]
+    fn xml_feeds_escape_text_and_drop_invalid_characters() {
+        let rss = feed(FeedMode::Full).render(FeedFormat::Rss);
+        assert!(rss.contains("<title>Tom &amp; Jerry&apos;s &lt;Blog&gt;</title>"));
+        assert!(rss.contains("<link>https://example.com/?a=1&amp;b=2</link>"));
+        assert!(rss.contains("<title>1 &lt; 2 </title>"));
+        assert!(rss.contains("<description>&lt;script&gt;alert(&quot;x&quot;)&lt;/script&gt;</description>"));
+        assert!(rss.contains("<pubDate>Tue, 2 Jan 2024 03:04:05 +0000</pubDate>"));
+        assert!(rss.contains("<category>news</category>"));
+
+        let atom = feed(FeedMode::Summary).render(FeedFormat::Atom);
+        assert!(atom.contains("<updated>2024-01-02T03:04:05Z</updated>"));
+        assert!(atom.contains("<summary type=\"text\">&lt;script&gt;"));
+        assert!(!atom.contains("<content"));
+    }
+
+    #[test]
+    fn json_feed_includes_summaries_and_tags() {
+        let value: Value = serde_json::from_str(&feed(FeedMode::Summary).render(FeedFormat::Json)).unwrap();
+        assert_eq!(value["version"], "https://jsonfeed.org/version/1.1");
+        assert_eq!(value["items"][0]["summary"], "<script>alert(\"x\")</script>");
+        assert_eq!(value["items"][0]["tags"], json!(["news"]));
+
+        assert_eq!(summarize("  one\n two   three ", 20), "one two three");
+        assert_eq!(summarize("one two three", 8), "one two…");
+    }
+}
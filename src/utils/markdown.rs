@@ -0,0 +1,204 @@
+use crate::models::archive::{Archive, ArchivedApp, ArchivedArticle, MarkdownFile};
+use crate::utils::archive::new_archive;
+use crate::utils::AppError;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::collections::HashMap;
+
+// front matter 中可识别的字段，其余字段忽略
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct FrontMatter {
+    title: Option<String>,
+    draft: bool,
+    // draft 或 published
+    status: Option<String>,
+    category: Option<String>,
+    // 只使用第一个分类
+    categories: Option<Value>,
+    #[serde(alias = "lang", alias = "language")]
+    locale: Option<String>,
+    // 原文文件的相对路径
+    translation_of: Option<String>,
+    meta_title: Option<String>,
+    #[serde(alias = "meta_description", alias = "summary")]
+    description: Option<String>,
+    #[serde(alias = "canonical")]
+    canonical_url: Option<String>,
+    #[serde(alias = "image", alias = "og_image")]
+    og_image_url: Option<String>,
+    noindex: bool,
+    date: Option<Value>,
+    #[serde(alias = "lastmod", alias = "updated_at")]
+    updated: Option<Value>,
+}
+
+// 一个目录中带 YAML front matter（--- 包围）的 Markdown 文件转换为导出文件；
+// 文章 id 按路径顺序分配，没有设置分类时使用第一级子目录的名称
+pub fn parse(app: ArchivedApp, files: &[MarkdownFile]) -> Result<Archive, AppError> {
+    let mut files: Vec<(String, &MarkdownFile)> = files
+        .iter()
+        .map(|file| (normalize_path(&file.path), file))
+        .filter(|(path, _)| path.ends_with(".md") || path.ends_with(".markdown"))
+        .collect();
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    let ids: HashMap<&str, i64> = files
+        .iter()
+        .enumerate()
+        .map(|(index, (path, _))| (path.as_str(), index as i64 + 1))
+        .collect();
+
+    let now = Utc::now();
+    let mut articles = Vec::new();
+    for (path, file) in &files {
+        let (front_matter, body) = split_front_matter(&file.content);
+        let meta: FrontMatter = match front_matter {
+            Some(yaml) if !yaml.trim().is_empty() => serde_yaml::from_str(yaml)
+                .map_err(|e| AppError::BadRequest(format!("Invalid front matter in {}: {}", path, e)))?,
+            _ => FrontMatter::default(),
+        };
+        let translation_of = match &meta.translation_of {
+            Some(original) => Some(*ids.get(normalize_path(original).as_str()).ok_or_else(|| {
+                AppError::BadRequest(format!("{}: translation_of refers to a missing file {}", path, original))
+            })?),
+            None => None,
+        };
+        let published = !meta.draft && meta.status.as_deref() != Some("draft");
+        let category = meta
+            .category
+            .clone()
+            .or_else(|| first_category(meta.categories.as_ref()))
+            .or_else(|| path.split_once('/').map(|(directory, _)| directory.to_string()));
+        let created_at = meta.date.as_ref().and_then(parse_date);
+        let updated_at = meta.updated.as_ref().and_then(parse_date);
+        articles.push(ArchivedArticle {
+            id: ids[path.as_str()],
+            title: meta.title.clone().or_else(|| heading(body)).unwrap_or_else(|| file_stem(path)),
+            content: body.trim().to_string(),
+            status: if published { 2 } else { 1 },
+            category,
+            locale: meta.locale,
+            translation_of,
+            meta_title: meta.meta_title,
+            meta_description: meta.description,
+            canonical_url: meta.canonical_url,
+            og_image_url: meta.og_image_url,
+            noindex: meta.noindex,
+            created_at: created_at.unwrap_or(now),
+            updated_at: updated_at.or(created_at).unwrap_or(now),
+        });
+    }
+    Ok(new_archive(app, articles))
+}
+
+fn normalize_path(path: &str) -> String {
+    path.replace('\\', "/").trim_start_matches("./").trim_start_matches('/').to_string()
+}
+
+// 返回 front matter 和正文，没有 front matter 时全部为正文
+fn split_front_matter(content: &str) -> (Option<&str>, &str) {
+    let content = content.trim_start_matches('\u{feff}');
+    let Some(rest) = content.strip_prefix("---").and_then(|rest| rest.strip_prefix('\n').or(rest.strip_prefix("\r\n")))
+    else {
+        return (None, content);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (None, content)
+}
+
+fn first_category(categories: Option<&Value>) -> Option<String> {
+    match categories? {
+        Value::String(category) => Some(category.clone()),
+        Value::Sequence(categories) => categories.first()?.as_str().map(str::to_string),
+        _ => None,
+    }
+}
+
+// 没有 title 时使用第一个一级标题
+fn heading(body: &str) -> Option<String> {
+    body.lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+}
+
+fn file_stem(path: &str) -> String {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.rsplit_once('.').map_or(name, |(stem, _)| stem).to_string()
+}
+
+// 支持 RFC 3339、"2024-01-02 03:04:05" 和 "2024-01-02"
+fn parse_date(value: &Value) -> Option<DateTime<Utc>> {
+    let value = value.as_str()?.trim();
+    DateTime::parse_from_rfc3339(value)
+        .map(|date| date.with_timezone(&Utc))
+        .ok()
+        .or_else(|| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").ok().map(|date| date.and_utc()))
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0).map(|date| date.and_utc()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, content: &str) -> MarkdownFile {
+        MarkdownFile {
+            path: path.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn markdown_files_become_articles() {
+        let files = vec![
+            file(
+                "./guides/setup.md",
+                "---\ntitle: Setup\ndate: 2024-01-02\nlang: en\ncategories: [Docs, Howto]\ndescription: How to set up\n---\n\nInstall it.\n",
+            ),
+            file(
+                "guides/setup.de.md",
+                "---\ntitle: Einrichtung\nlocale: de\ntranslation_of: guides/setup.md\ndraft: true\n---\nInstallieren.",
+            ),
+            file("news/launch.markdown", "# We launched\r\n\r\nHello"),
+            file("notes.md", "---\nimage: https://example.com/a.png\nnoindex: true\nstatus: published\n---\n"),
+            file("logo.png", "binary"),
+        ];
+        let archive = parse(ArchivedApp::default(), &files).unwrap();
+        let titles: Vec<(i64, &str)> =
+            archive.articles.iter().map(|article| (article.id, article.title.as_str())).collect();
+        assert_eq!(titles, vec![(1, "Einrichtung"), (2, "Setup"), (3, "We launched"), (4, "notes")]);
+
+        let setup = &archive.articles[1];
+        assert_eq!(setup.content, "Install it.");
+        assert_eq!(setup.status, 2);
+        assert_eq!(setup.category.as_deref(), Some("Docs"));
+        assert_eq!(setup.locale.as_deref(), Some("en"));
+        assert_eq!(setup.meta_description.as_deref(), Some("How to set up"));
+        assert_eq!(setup.created_at.to_rfc3339(), "2024-01-02T00:00:00+00:00");
+
+        let translation = &archive.articles[0];
+        assert_eq!(translation.translation_of, Some(2));
+        assert_eq!(translation.status, 1);
+        assert_eq!(translation.category.as_deref(), Some("guides"));
+
+        assert_eq!(archive.articles[2].category.as_deref(), Some("news"));
+        let notes = &archive.articles[3];
+        assert!(notes.noindex);
+        assert_eq!(notes.status, 2);
+        assert!(notes.category.is_none());
+        assert_eq!(notes.og_image_url.as_deref(), Some("https://example.com/a.png"));
+
+        let broken = vec![file("a.md", "---\ntranslation_of: missing.md\n---\n")];
+        assert!(matches!(parse(ArchivedApp::default(), &broken), Err(AppError::BadRequest(_))));
+        let broken = vec![file("a.md", "---\ntitle: [unclosed\n---\n")];
+        assert!(matches!(parse(ArchivedApp::default(), &broken), Err(AppError::BadRequest(_))));
+        assert_eq!(split_front_matter("---\nno end"), (None, "---\nno end"));
+    }
+}
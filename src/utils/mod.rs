@@ -1,45 +1,218 @@
+pub mod archive;
+pub mod backoff;
+pub mod clock;
 pub mod email;
+pub mod email_template;
+pub mod feed;
+pub mod markdown;
+pub mod password;
+pub mod precondition;
+pub mod sitemap;
+pub mod token;
+pub mod totp;
+pub mod validation;
+pub mod wxr;
 
+use crate::middleware::request_id::current_request_id;
+use actix_web::http::StatusCode;
+use actix_web::error::{JsonPayloadError, PathError, QueryPayloadError};
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use serde::Serialize;
 use thiserror::Error;
+use utoipa::ToSchema;
+
+// 字段级错误信息
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, code: &str, message: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            code: code.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
 
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Database error: {0}")]
     DatabaseError(#[from] sqlx::Error),
 
-    #[error("Validation error: {0}")]
-    ValidationError(String),
+    #[error("Internal error: {0}")]
+    Internal(String),
+
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+
+    #[error("Validation failed")]
+    ValidationError(Vec<FieldError>),
 
     #[error("Not found: {0}")]
     NotFound(String),
 
     #[error("Unauthorized: {0}")]
     Unauthorized(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Gone: {0}")]
+    Gone(String),
+
+    #[error("Precondition failed, current version is {0}")]
+    PreconditionFailed(i64),
+
+    #[error("Precondition required: {0}")]
+    PreconditionRequired(String),
+
+    #[error("Payload too large, limit is {0} bytes")]
+    PayloadTooLarge(usize),
+
+    #[error("Too many requests, retry after {0} seconds")]
+    TooManyRequests(u64),
+}
+
+// 统一的错误响应格式
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorResponse {
+    pub error: ErrorBody,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorBody {
+    pub code: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<FieldError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
-impl actix_web::ResponseError for AppError {
-    fn error_response(&self) -> actix_web::HttpResponse {
+impl AppError {
+    // 稳定的机器可读错误码，客户端应依赖此字段而不是 message
+    pub fn code(&self) -> &'static str {
         match self {
-            AppError::DatabaseError(_) => {
-                actix_web::HttpResponse::InternalServerError().json(serde_json::json!({
-                    "error": "Internal server error"
-                }))
-            }
-            AppError::ValidationError(msg) => {
-                actix_web::HttpResponse::BadRequest().json(serde_json::json!({
-                    "error": msg
-                }))
+            AppError::DatabaseError(_) | AppError::Internal(_) => "internal_error",
+            AppError::BadRequest(_) => "bad_request",
+            AppError::ValidationError(_) => "validation_failed",
+            AppError::NotFound(_) => "not_found",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::Conflict(_) => "conflict",
+            AppError::Gone(_) => "gone",
+            AppError::PreconditionFailed(_) => "precondition_failed",
+            AppError::PreconditionRequired(_) => "precondition_required",
+            AppError::PayloadTooLarge(_) => "payload_too_large",
+            AppError::TooManyRequests(_) => "rate_limited",
+        }
+    }
+
+    // 返回给客户端的信息，内部错误不暴露细节
+    pub fn public_message(&self) -> String {
+        match self {
+            AppError::DatabaseError(_) | AppError::Internal(_) => "Internal server error".to_string(),
+            AppError::ValidationError(_) => "Request validation failed".to_string(),
+            AppError::TooManyRequests(_) => "Too many requests".to_string(),
+            AppError::PayloadTooLarge(limit) => format!("Request body exceeds {} bytes", limit),
+            AppError::PreconditionFailed(version) => {
+                format!("The resource has been modified, the current version is {}", version)
             }
-            AppError::NotFound(msg) => {
-                actix_web::HttpResponse::NotFound().json(serde_json::json!({
-                    "error": msg
-                }))
+            AppError::BadRequest(msg)
+            | AppError::NotFound(msg)
+            | AppError::Unauthorized(msg)
+            | AppError::Forbidden(msg)
+            | AppError::Conflict(msg)
+            | AppError::Gone(msg)
+            | AppError::PreconditionRequired(msg) => msg.clone(),
+        }
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::DatabaseError(_) | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::ValidationError(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Gone(_) => StatusCode::GONE,
+            AppError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            AppError::PreconditionRequired(_) => StatusCode::PRECONDITION_REQUIRED,
+            AppError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let request_id = current_request_id();
+
+        if self.status_code().is_server_error() {
+            log::error!("[{}] {:?}", request_id.as_deref().unwrap_or("-"), self);
+        }
+
+        let details = match self {
+            AppError::ValidationError(details) => details.clone(),
+            AppError::PreconditionFailed(version) => vec![FieldError::new(
+                "version",
+                "stale",
+                &format!("The current version is {}", version),
+            )],
+            _ => Vec::new(),
+        };
+
+        let mut response = HttpResponse::build(self.status_code());
+        match self {
+            AppError::TooManyRequests(retry_after) => {
+                response.insert_header((actix_web::http::header::RETRY_AFTER, retry_after.to_string()));
             }
-            AppError::Unauthorized(msg) => {
-                actix_web::HttpResponse::Unauthorized().json(serde_json::json!({
-                    "error": msg
-                }))
+            // 客户端可以用当前版本重新读取后再提交
+            AppError::PreconditionFailed(version) => {
+                response.insert_header(precondition::etag(*version));
             }
+            _ => {}
         }
+
+        response.json(ErrorResponse {
+            error: ErrorBody {
+                code: self.code().to_string(),
+                message: self.public_message(),
+                details,
+                request_id,
+            },
+        })
     }
 }
+
+// 请求体、路径、查询参数解析失败时统一返回 AppError
+pub fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    match err {
+        JsonPayloadError::OverflowKnownLength { limit, .. } | JsonPayloadError::Overflow { limit } => {
+            AppError::PayloadTooLarge(limit).into()
+        }
+        // 字段缺失或类型错误视为校验失败
+        JsonPayloadError::Deserialize(e) if e.is_data() => {
+            AppError::ValidationError(vec![FieldError::new("body", "invalid_type", &e.to_string())]).into()
+        }
+        err => AppError::BadRequest(err.to_string()).into(),
+    }
+}
+
+pub fn path_error_handler(err: PathError, _req: &HttpRequest) -> actix_web::Error {
+    AppError::BadRequest(err.to_string()).into()
+}
+
+pub fn query_error_handler(err: QueryPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    AppError::BadRequest(err.to_string()).into()
+}
@@ -0,0 +1,86 @@
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+
+// 常见的弱密码，长度满足要求也不允许使用
+const COMMON_PASSWORDS: &[&str] = &[
+    "password123",
+    "password1234",
+    "passw0rd123",
+    "qwerty12345",
+    "qwertyuiop1",
+    "1234567890a",
+    "iloveyou123",
+    "welcome12345",
+    "administrator",
+    "letmein12345",
+];
+
+// argon2id，盐值随机生成并保存在结果中
+pub fn hash(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default().hash_password(password.as_bytes(), &salt)?.to_string())
+}
+
+pub fn verify(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .is_ok_and(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+}
+
+// 检查密码强度，personal 为用户名、邮箱等不能出现在密码中的内容；不满足时返回原因
+pub fn check_strength(password: &str, min_length: usize, personal: &[&str]) -> Result<(), String> {
+    let length = password.chars().count();
+    if length < min_length {
+        return Err(format!("Password must be at least {} characters", min_length));
+    }
+
+    // 16 位以上的口令短语不要求字符种类
+    let classes = [
+        password.chars().any(|c| c.is_lowercase()),
+        password.chars().any(|c| c.is_uppercase()),
+        password.chars().any(|c| c.is_ascii_digit()),
+        password.chars().any(|c| !c.is_alphanumeric()),
+    ];
+    if length < 16 && classes.iter().filter(|present| **present).count() < 3 {
+        return Err("Password must contain at least three of: lowercase letters, uppercase letters, digits and symbols".to_string());
+    }
+
+    let lowercase = password.to_lowercase();
+    if COMMON_PASSWORDS.contains(&lowercase.as_str()) || password.chars().all(|c| password.starts_with(c)) {
+        return Err("Password is too common".to_string());
+    }
+    if personal
+        .iter()
+        .map(|value| value.to_lowercase())
+        .any(|value| value.chars().count() >= 3 && lowercase.contains(&value))
+    {
+        return Err("Password must not contain your username or email".to_string());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_verify_only_the_original_password() {
+        let password_hash = hash("Correct-Horse-9").unwrap();
+        assert!(password_hash.starts_with("$argon2id$"));
+        assert!(verify("Correct-Horse-9", &password_hash));
+        assert!(!verify("correct-horse-9", &password_hash));
+        assert!(!verify("Correct-Horse-9", "not a hash"));
+    }
+
+    #[test]
+    fn rejects_weak_passwords() {
+        assert!(check_strength("Short-1", 10, &[]).is_err());
+        assert!(check_strength("alllowercase", 10, &[]).is_err());
+        assert!(check_strength("Password123", 10, &[]).is_err());
+        assert!(check_strength("Alice-2024!", 10, &["alice"]).is_err());
+        assert!(check_strength("Tr0ub4dor&3x", 10, &["alice"]).is_ok());
+        // 足够长的口令短语
+        assert!(check_strength("correct horse battery staple", 10, &[]).is_ok());
+    }
+}
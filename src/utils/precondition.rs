@@ -0,0 +1,101 @@
+use super::AppError;
+use actix_web::http::header::{EntityTag, Header, IfMatch, IfNoneMatch, ETag, IF_MATCH};
+use actix_web::HttpRequest;
+
+// 版本号作为强 ETag
+pub fn etag(version: i64) -> ETag {
+    ETag(EntityTag::new_strong(version.to_string()))
+}
+
+// 只按 If-None-Match 做条件 GET，使用弱比较；为 true 时返回 304
+pub fn none_match(req: &HttpRequest, tag: &EntityTag) -> bool {
+    match IfNoneMatch::parse(req) {
+        Ok(IfNoneMatch::Any) => true,
+        Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|item| item.weak_eq(tag)),
+        Err(_) => false,
+    }
+}
+
+// 更新请求期望的版本，与当前版本不一致时返回 412
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Precondition {
+    // If-Match: *，不检查版本
+    Any,
+    Versions(Vec<i64>),
+}
+
+impl Precondition {
+    // If-Match 头优先，其次是请求体中的 version 字段，都没有时返回 428
+    pub fn from_request(req: &HttpRequest, version: Option<i64>) -> Result<Self, AppError> {
+        if !req.headers().contains_key(IF_MATCH) {
+            return Self::from_version(version);
+        }
+        match IfMatch::parse(req) {
+            Ok(IfMatch::Any) => Ok(Precondition::Any),
+            // If-Match 使用强比较，弱 ETag 永远不匹配
+            Ok(IfMatch::Items(tags)) => Ok(Precondition::Versions(
+                tags.iter()
+                    .filter(|tag| !tag.weak)
+                    .filter_map(|tag| tag.tag().parse().ok())
+                    .collect(),
+            )),
+            Err(_) => Err(AppError::BadRequest("Invalid If-Match header".to_string())),
+        }
+    }
+
+    pub fn from_version(version: Option<i64>) -> Result<Self, AppError> {
+        version.map(|version| Precondition::Versions(vec![version])).ok_or_else(|| {
+            AppError::PreconditionRequired(
+                "Send the current version in an If-Match header or a version field".to_string(),
+            )
+        })
+    }
+
+    pub fn check(&self, current: i64) -> Result<(), AppError> {
+        match self {
+            Precondition::Versions(versions) if !versions.contains(&current) => {
+                Err(AppError::PreconditionFailed(current))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::IF_NONE_MATCH;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn if_match_takes_precedence_over_the_version_field() {
+        let req = TestRequest::default().insert_header((IF_MATCH, "\"3\", W/\"4\"")).to_http_request();
+        let precondition = Precondition::from_request(&req, Some(4)).unwrap();
+        assert_eq!(precondition, Precondition::Versions(vec![3]));
+        assert!(precondition.check(3).is_ok());
+        assert!(matches!(precondition.check(4), Err(AppError::PreconditionFailed(4))));
+
+        let req = TestRequest::default().insert_header((IF_MATCH, "*")).to_http_request();
+        assert_eq!(Precondition::from_request(&req, None).unwrap(), Precondition::Any);
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(Precondition::from_request(&req, Some(2)).unwrap(), Precondition::Versions(vec![2]));
+        assert!(matches!(
+            Precondition::from_request(&req, None),
+            Err(AppError::PreconditionRequired(_))
+        ));
+    }
+
+    #[test]
+    fn if_none_match_uses_weak_comparison() {
+        let tag = EntityTag::new_strong("abc".to_string());
+
+        let req = TestRequest::default().insert_header((IF_NONE_MATCH, "\"x\", W/\"abc\"")).to_http_request();
+        assert!(none_match(&req, &tag));
+        let req = TestRequest::default().insert_header((IF_NONE_MATCH, "*")).to_http_request();
+        assert!(none_match(&req, &tag));
+        let req = TestRequest::default().insert_header((IF_NONE_MATCH, "\"x\"")).to_http_request();
+        assert!(!none_match(&req, &tag));
+        assert!(!none_match(&TestRequest::default().to_http_request(), &tag));
+    }
+}
@@ -0,0 +1,96 @@
+use crate::utils::feed::escape;
+use chrono::{DateTime, SecondsFormat, Utc};
+
+pub const CONTENT_TYPE: &str = "application/xml; charset=utf-8";
+
+// 站点地图中的一个地址
+#[derive(Debug, Clone)]
+pub struct SitemapUrl {
+    pub loc: String,
+    pub lastmod: DateTime<Utc>,
+    // 其他语言版本，(hreflang, 地址)，包括自身
+    pub alternates: Vec<(String, String)>,
+}
+
+// 站点地图协议 0.9，多语言版本使用 xhtml:link
+pub fn urlset(urls: &[SitemapUrl]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\"");
+    if urls.iter().any(|url| !url.alternates.is_empty()) {
+        xml.push_str(" xmlns:xhtml=\"http://www.w3.org/1999/xhtml\"");
+    }
+    xml.push_str(">\n");
+    for url in urls {
+        xml.push_str("<url>\n");
+        xml.push_str(&format!("<loc>{}</loc>\n", escape(&url.loc)));
+        xml.push_str(&format!("<lastmod>{}</lastmod>\n", w3c_datetime(url.lastmod)));
+        for (hreflang, href) in &url.alternates {
+            xml.push_str(&format!(
+                "<xhtml:link rel=\"alternate\" hreflang=\"{}\" href=\"{}\"/>\n",
+                escape(hreflang),
+                escape(href)
+            ));
+        }
+        xml.push_str("</url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+// 站点地图索引，locations 为各个分页站点地图的地址
+pub fn index(locations: &[String]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for location in locations {
+        xml.push_str(&format!("<sitemap><loc>{}</loc></sitemap>\n", escape(location)));
+    }
+    xml.push_str("</sitemapindex>\n");
+    xml
+}
+
+fn w3c_datetime(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn urlset_lists_alternates_and_escapes_locations() {
+        let time = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+        let urls = vec![
+            SitemapUrl {
+                loc: "https://example.com/articles/1?a=1&b=2".to_string(),
+                lastmod: time,
+                alternates: Vec::new(),
+            },
+            SitemapUrl {
+                loc: "https://example.com/articles/2".to_string(),
+                lastmod: time,
+                alternates: vec![
+                    ("en".to_string(), "https://example.com/articles/2".to_string()),
+                    ("zh-CN".to_string(), "https://example.com/articles/3".to_string()),
+                ],
+            },
+        ];
+        let xml = urlset(&urls);
+        assert!(xml.contains("xmlns:xhtml=\"http://www.w3.org/1999/xhtml\""));
+        assert!(xml.contains("<loc>https://example.com/articles/1?a=1&amp;b=2</loc>"));
+        assert!(xml.contains("<lastmod>2024-01-02T03:04:05Z</lastmod>"));
+        assert!(xml.contains(
+            "<xhtml:link rel=\"alternate\" hreflang=\"zh-CN\" href=\"https://example.com/articles/3\"/>"
+        ));
+        assert_eq!(xml.matches("<url>").count(), 2);
+        assert!(!urlset(&urls[..1]).contains("xmlns:xhtml"));
+
+        let xml = index(&[
+            "https://cms.example.com/sitemaps/blog/sitemap-1.xml".to_string(),
+            "https://cms.example.com/sitemaps/blog/sitemap-2.xml?a&b".to_string(),
+        ]);
+        assert!(xml.contains("<sitemapindex"));
+        assert_eq!(xml.matches("<sitemap>").count(), 2);
+        assert!(xml.contains("<loc>https://cms.example.com/sitemaps/blog/sitemap-2.xml?a&amp;b</loc>"));
+    }
+}
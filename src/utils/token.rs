@@ -0,0 +1,38 @@
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+// 32 字节随机数的十六进制表示，用作一次性 token
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+// HMAC-SHA256 签名，数据库中只保存签名，不保存 token 本身
+pub fn sign(secret: &str, value: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(value.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+// SHA-256，用于保存高熵的随机 token，不需要密钥
+pub fn digest(value: &str) -> String {
+    hex::encode(Sha256::digest(value.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_random_and_signatures_depend_on_the_secret() {
+        let token = random_token();
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, random_token());
+
+        assert_eq!(sign("secret", &token), sign("secret", &token));
+        assert_ne!(sign("secret", &token), sign("other", &token));
+        assert_eq!(sign("secret", &token).len(), 64);
+    }
+}
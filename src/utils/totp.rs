@@ -0,0 +1,105 @@
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+// 验证器应用的通用参数：SHA1、6 位、30 秒
+const DIGITS: usize = 6;
+const STEP: u64 = 30;
+
+// 恢复码使用的字符，去掉了容易混淆的 0、o、1、l、i
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+fn totp(secret: &str, issuer: &str, account: &str) -> Option<TOTP> {
+    let bytes = Secret::Encoded(secret.to_string()).to_bytes().ok()?;
+    Some(TOTP::new_unchecked(
+        Algorithm::SHA1,
+        DIGITS,
+        1,
+        STEP,
+        bytes,
+        Some(issuer.to_string()),
+        account.to_string(),
+    ))
+}
+
+// 160 位随机密钥的 base32 表示
+pub fn generate_secret() -> String {
+    Secret::generate_secret().to_encoded().to_string()
+}
+
+// otpauth:// 地址，验证器应用扫描其二维码完成绑定
+pub fn provisioning_uri(secret: &str, issuer: &str, account: &str) -> Option<String> {
+    Some(totp(secret, issuer, account)?.get_url())
+}
+
+// 指定时间的验证码，与验证器应用显示的一致
+pub fn code_at(secret: &str, time: DateTime<Utc>) -> Option<String> {
+    Some(totp(secret, "", "")?.generate(time.timestamp().max(0) as u64))
+}
+
+// 允许前后各一个时间步的误差，返回匹配的时间步
+pub fn matching_step(secret: &str, code: &str, now: DateTime<Utc>) -> Option<i64> {
+    let totp = totp(secret, "", "")?;
+    let current = now.timestamp().max(0) as u64 / STEP;
+    [current.saturating_sub(1), current, current + 1]
+        .into_iter()
+        .find(|step| totp.generate(step * STEP) == code)
+        .map(|step| step as i64)
+}
+
+// 形如 abcde-fghjk
+pub fn generate_recovery_codes() -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let chars: String = (0..10)
+                .map(|_| RECOVERY_CODE_ALPHABET[rng.gen_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+                .collect();
+            format!("{}-{}", &chars[..5], &chars[5..])
+        })
+        .collect()
+}
+
+// 忽略大小写、空格和连字符
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // RFC 6238 附录 B 中的 SHA1 测试密钥
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn matches_rfc_vectors_within_one_step() {
+        let now = Utc.timestamp_opt(59, 0).unwrap();
+        assert_eq!(code_at(RFC_SECRET, now).as_deref(), Some("287082"));
+        assert_eq!(matching_step(RFC_SECRET, "287082", now), Some(1));
+        // 前一个时间步仍然有效，更早的无效
+        assert_eq!(matching_step(RFC_SECRET, "287082", now + chrono::Duration::seconds(30)), Some(1));
+        assert_eq!(matching_step(RFC_SECRET, "287082", now + chrono::Duration::seconds(90)), None);
+    }
+
+    #[test]
+    fn provisioning_uri_contains_issuer_and_account() {
+        let uri = provisioning_uri(&generate_secret(), "RSCMS", "alice@example.com").unwrap();
+        assert!(uri.starts_with("otpauth://totp/RSCMS:alice%40example.com?secret="));
+        assert!(uri.contains("issuer=RSCMS"));
+    }
+
+    #[test]
+    fn recovery_codes_are_unique_and_normalized() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert_eq!(codes[0].len(), 11);
+        assert_ne!(codes[0], codes[1]);
+        assert_eq!(normalize_recovery_code(" ABCDE-fghjk "), "abcdefghjk");
+    }
+}
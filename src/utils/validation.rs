@@ -0,0 +1,175 @@
+use crate::utils::{AppError, FieldError};
+use actix_web::{dev, web, FromRequest, HttpRequest};
+use futures::future::LocalBoxFuture;
+use regex::Regex;
+use serde::de::DeserializeOwned;
+use std::ops::Deref;
+use std::sync::LazyLock;
+use validator::{Validate, ValidationError, ValidationErrors, ValidationErrorsKind};
+
+// 应用标识只允许字母、数字、下划线和连字符
+pub static IDENTIFIER_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z0-9][A-Za-z0-9_-]*$").unwrap());
+
+// MySQL TEXT 列的最大字节数
+const TEXT_COLUMN_MAX_BYTES: usize = 65_535;
+
+pub fn validate_article_status(status: i16) -> Result<(), ValidationError> {
+    // 1: 草稿, 2: 已发布
+    if status == 1 || status == 2 {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid_status").with_message("status must be 1 (draft) or 2 (published)".into()))
+    }
+}
+
+pub fn validate_text_column(value: &str) -> Result<(), ValidationError> {
+    if value.len() > TEXT_COLUMN_MAX_BYTES {
+        Err(ValidationError::new("too_long").with_message("value exceeds 65535 bytes".into()))
+    } else {
+        Ok(())
+    }
+}
+
+pub fn validate_not_blank(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        Err(ValidationError::new("blank").with_message("value must not be blank".into()))
+    } else {
+        Ok(())
+    }
+}
+
+// 将 validator 的错误展开为按字段排序的列表
+pub fn field_errors(errors: &ValidationErrors) -> Vec<FieldError> {
+    let mut result = Vec::new();
+    collect_field_errors("", errors, &mut result);
+    result.sort_by(|a, b| a.field.cmp(&b.field));
+    result
+}
+
+fn collect_field_errors(prefix: &str, errors: &ValidationErrors, result: &mut Vec<FieldError>) {
+    for (field, kind) in errors.errors() {
+        let path = if prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", prefix, field)
+        };
+
+        match kind {
+            ValidationErrorsKind::Field(errors) => {
+                for error in errors {
+                    let message = error
+                        .message
+                        .as_ref()
+                        .map(|message| message.to_string())
+                        .unwrap_or_else(|| default_message(error));
+                    result.push(FieldError::new(&path, &error.code, &message));
+                }
+            }
+            ValidationErrorsKind::Struct(errors) => collect_field_errors(&path, errors, result),
+            ValidationErrorsKind::List(items) => {
+                for (index, errors) in items {
+                    collect_field_errors(&format!("{}[{}]", path, index), errors, result);
+                }
+            }
+        }
+    }
+}
+
+fn default_message(error: &ValidationError) -> String {
+    let param = |name: &str| error.params.get(name).map(|value| value.to_string());
+    match error.code.as_ref() {
+        "length" => match (param("min"), param("max"), param("equal")) {
+            (_, _, Some(equal)) => format!("length must be exactly {}", equal),
+            (Some(min), Some(max), _) => format!("length must be between {} and {}", min, max),
+            (Some(min), None, _) => format!("length must be at least {}", min),
+            (None, Some(max), _) => format!("length must be at most {}", max),
+            _ => "invalid length".to_string(),
+        },
+        "range" => match (param("min"), param("max")) {
+            (Some(min), Some(max)) => format!("value must be between {} and {}", min, max),
+            (Some(min), None) => format!("value must be at least {}", min),
+            (None, Some(max)) => format!("value must be at most {}", max),
+            _ => "value out of range".to_string(),
+        },
+        "email" => "invalid email address".to_string(),
+        "regex" => "invalid format".to_string(),
+        code => format!("invalid value ({})", code),
+    }
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(errors: ValidationErrors) -> Self {
+        AppError::ValidationError(field_errors(&errors))
+    }
+}
+
+// 反序列化后自动执行校验的 JSON 提取器
+#[derive(Debug)]
+pub struct ValidatedJson<T>(pub T);
+
+impl<T> ValidatedJson<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for ValidatedJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> FromRequest for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut dev::Payload) -> Self::Future {
+        let json = web::Json::<T>::from_request(req, payload);
+        Box::pin(async move {
+            let value = json.await?.into_inner();
+            value.validate().map_err(AppError::from)?;
+            Ok(ValidatedJson(value))
+        })
+    }
+}
+
+// 查询参数版本
+#[derive(Debug)]
+pub struct ValidatedQuery<T>(pub T);
+
+impl<T> ValidatedQuery<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for ValidatedQuery<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> FromRequest for ValidatedQuery<T>
+where
+    T: DeserializeOwned + Validate + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut dev::Payload) -> Self::Future {
+        let query = web::Query::<T>::from_request(req, payload);
+        Box::pin(async move {
+            let value = query.await?.into_inner();
+            value.validate().map_err(AppError::from)?;
+            Ok(ValidatedQuery(value))
+        })
+    }
+}
@@ -0,0 +1,218 @@
+use crate::models::archive::{Archive, ArchivedApp, ArchivedArticle, MediaReference};
+use crate::utils::archive::new_archive;
+use crate::utils::AppError;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use roxmltree::{Document, Node};
+
+// WordPress 导出的 WXR 文件（RSS 2.0 加 wp 命名空间）转换为导出文件；
+// 只导入文章和页面，附件作为外部文件引用，标签、评论和自定义字段不导入
+pub fn parse(xml: &str) -> Result<Archive, AppError> {
+    let document = Document::parse(xml).map_err(|e| AppError::BadRequest(format!("Invalid WXR file: {}", e)))?;
+    let channel = document
+        .root_element()
+        .children()
+        .find(|node| node.has_tag_name("channel"))
+        .ok_or_else(|| AppError::BadRequest("Invalid WXR file: missing channel".to_string()))?;
+
+    let title = child_text(channel, "title").unwrap_or_default();
+    let language = child_text(channel, "language");
+    let app = ArchivedApp {
+        identifier: slugify(&title),
+        name: title,
+        description: child_text(channel, "description").unwrap_or_default(),
+        base_url: child_text(channel, "link"),
+        ..ArchivedApp::default()
+    };
+
+    let mut articles = Vec::new();
+    let mut attachments = Vec::new();
+    for item in channel.children().filter(|node| node.has_tag_name("item")) {
+        let id = wp_text(item, "post_id").and_then(|id| id.parse::<i64>().ok());
+        let post_type = wp_text(item, "post_type").unwrap_or_else(|| "post".to_string());
+        if post_type == "attachment" {
+            if let Some(url) = wp_text(item, "attachment_url") {
+                attachments.push(url);
+            }
+            continue;
+        }
+        let (Some(id), "post" | "page") = (id, post_type.as_str()) else {
+            continue;
+        };
+        let status = match wp_text(item, "status").as_deref() {
+            Some("publish") => 2,
+            Some("draft") | Some("pending") | Some("private") | Some("future") | None => 1,
+            // trash、auto-draft 和 inherit（修订版本）不导入
+            Some(_) => continue,
+        };
+        let created_at = wp_text(item, "post_date_gmt").and_then(|date| parse_date(&date));
+        let updated_at = wp_text(item, "post_modified_gmt").and_then(|date| parse_date(&date));
+        let category = item
+            .children()
+            .find(|node| node.has_tag_name("category") && node.attribute("domain") == Some("category"))
+            .and_then(text);
+        let excerpt = item
+            .children()
+            .find(|node| node.tag_name().name() == "encoded" && namespace_contains(*node, "/excerpt/"))
+            .and_then(text);
+        let now = Utc::now();
+        articles.push(ArchivedArticle {
+            id,
+            title: child_text(item, "title").unwrap_or_default(),
+            content: item
+                .children()
+                .find(|node| node.tag_name().name() == "encoded" && namespace_contains(*node, "/content/"))
+                .and_then(text)
+                .unwrap_or_default(),
+            status,
+            category,
+            locale: language.clone(),
+            translation_of: None,
+            meta_title: None,
+            meta_description: excerpt,
+            canonical_url: None,
+            og_image_url: None,
+            noindex: false,
+            created_at: created_at.unwrap_or(now),
+            updated_at: updated_at.or(created_at).unwrap_or(now),
+        });
+    }
+
+    let mut archive = new_archive(app, articles);
+    archive.media.extend(attachments.into_iter().map(|url| MediaReference {
+        url,
+        article_id: None,
+        field: "attachment".to_string(),
+    }));
+    Ok(archive)
+}
+
+fn text(node: Node) -> Option<String> {
+    let value: String = node.descendants().filter(|child| child.is_text()).filter_map(|child| child.text()).collect();
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+// 没有命名空间的子元素，如 RSS 的 title 和 link
+fn child_text(node: Node, name: &str) -> Option<String> {
+    node.children()
+        .find(|child| child.has_tag_name(name) && child.tag_name().namespace().is_none())
+        .and_then(text)
+}
+
+// wp 命名空间的版本号随 WordPress 版本变化，只按名称前缀匹配
+fn wp_text(node: Node, name: &str) -> Option<String> {
+    node.children()
+        .find(|child| child.tag_name().name() == name && namespace_contains(*child, "wordpress.org/export/"))
+        .and_then(text)
+}
+
+fn namespace_contains(node: Node, fragment: &str) -> bool {
+    node.tag_name().namespace().is_some_and(|namespace| namespace.contains(fragment))
+}
+
+// WordPress 使用 "2024-01-02 03:04:05"，未发布的文章为 0000-00-00 00:00:00
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").ok().map(|date| date.and_utc())
+}
+
+// 站点名称转换为应用标识，导入时可以用 identifier 参数覆盖
+pub fn slugify(value: &str) -> String {
+    let mut slug = String::new();
+    for c in value.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug: String = slug.trim_end_matches('-').chars().take(50).collect();
+    slug.trim_end_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WXR: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0"
+    xmlns:excerpt="http://wordpress.org/export/1.2/excerpt/"
+    xmlns:content="http://purl.org/rss/1.0/modules/content/"
+    xmlns:dc="http://purl.org/dc/elements/1.1/"
+    xmlns:wp="http://wordpress.org/export/1.2/">
+<channel>
+    <title>Tom &amp; Jerry's Blog</title>
+    <link>https://blog.example.com</link>
+    <description>Just another WordPress site</description>
+    <language>en-US</language>
+    <item>
+        <title>Hello world!</title>
+        <dc:creator><![CDATA[admin]]></dc:creator>
+        <content:encoded><![CDATA[<p>Welcome to <b>WordPress</b>.</p>]]></content:encoded>
+        <excerpt:encoded><![CDATA[Welcome]]></excerpt:encoded>
+        <wp:post_id>1</wp:post_id>
+        <wp:post_date_gmt>2024-01-02 03:04:05</wp:post_date_gmt>
+        <wp:post_modified_gmt>2024-02-03 04:05:06</wp:post_modified_gmt>
+        <wp:status>publish</wp:status>
+        <wp:post_type>post</wp:post_type>
+        <category domain="post_tag" nicename="intro"><![CDATA[Intro]]></category>
+        <category domain="category" nicename="news"><![CDATA[News]]></category>
+    </item>
+    <item>
+        <title>About</title>
+        <content:encoded><![CDATA[About us]]></content:encoded>
+        <excerpt:encoded><![CDATA[]]></excerpt:encoded>
+        <wp:post_id>2</wp:post_id>
+        <wp:post_date_gmt>0000-00-00 00:00:00</wp:post_date_gmt>
+        <wp:status>draft</wp:status>
+        <wp:post_type>page</wp:post_type>
+    </item>
+    <item>
+        <title>logo</title>
+        <wp:post_id>3</wp:post_id>
+        <wp:status>inherit</wp:status>
+        <wp:post_type>attachment</wp:post_type>
+        <wp:attachment_url>https://blog.example.com/wp-content/uploads/logo.png</wp:attachment_url>
+    </item>
+    <item>
+        <title>Deleted</title>
+        <wp:post_id>4</wp:post_id>
+        <wp:status>trash</wp:status>
+        <wp:post_type>post</wp:post_type>
+    </item>
+    <item>
+        <title>Menu</title>
+        <wp:post_id>5</wp:post_id>
+        <wp:status>publish</wp:status>
+        <wp:post_type>nav_menu_item</wp:post_type>
+    </item>
+</channel>
+</rss>"#;
+
+    #[test]
+    fn wxr_posts_and_pages_become_articles() {
+        let archive = parse(WXR).unwrap();
+        assert_eq!(archive.app.identifier, "tom-jerry-s-blog");
+        assert_eq!(archive.app.name, "Tom & Jerry's Blog");
+        assert_eq!(archive.app.base_url.as_deref(), Some("https://blog.example.com"));
+        assert_eq!(archive.articles.len(), 2);
+
+        let post = &archive.articles[0];
+        assert_eq!((post.id, post.status), (1, 2));
+        assert_eq!(post.content, "<p>Welcome to <b>WordPress</b>.</p>");
+        assert_eq!(post.category.as_deref(), Some("News"));
+        assert_eq!(post.meta_description.as_deref(), Some("Welcome"));
+        assert_eq!(post.locale.as_deref(), Some("en-US"));
+        assert_eq!(post.created_at.to_rfc3339(), "2024-01-02T03:04:05+00:00");
+        assert_eq!(post.updated_at.to_rfc3339(), "2024-02-03T04:05:06+00:00");
+
+        let page = &archive.articles[1];
+        assert_eq!((page.id, page.status), (2, 1));
+        assert!(page.meta_description.is_none());
+        assert_eq!(archive.categories.len(), 1);
+        assert_eq!(archive.media.last().unwrap().url, "https://blog.example.com/wp-content/uploads/logo.png");
+
+        assert!(matches!(parse("<rss>"), Err(AppError::BadRequest(_))));
+        assert!(matches!(parse("<rss/>"), Err(AppError::BadRequest(_))));
+        assert_eq!(slugify("  --Hello, World!  "), "hello-world");
+    }
+}
@@ -0,0 +1,1659 @@
+use actix_http::{ws, Request};
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::test;
+use actix_web::web::BytesMut;
+use chrono::Duration;
+use std::pin::Pin;
+use serde_json::{json, Value};
+
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+
+use rscms::server::build_app;
+use rscms::config::auth::Claims;
+use rscms::config::oidc::parse_role_mapping;
+use rscms::test_support::{test_settings, MockOidcProvider, TestApp, TEST_JWT_AUDIENCE, TEST_JWT_ISSUER};
+use rscms::utils::clock::Clock;
+use rscms::utils::totp;
+
+#[actix_web::test]
+async fn register_login_and_create_content() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+
+    // 注册，验证码通过假邮件服务捕获
+    let req = test::TestRequest::post()
+        .uri("/api/auth/register")
+        .set_json(json!({ "username": "alice", "email": "alice@example.com" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let code = test_app.last_code_for("alice@example.com").await.unwrap();
+    assert_eq!(code, "100000");
+
+    // 登录
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(json!({ "email": "alice@example.com", "verification_code": code }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let token = body["token"].as_str().unwrap().to_string();
+    assert_eq!(body["user"]["email"], "alice@example.com");
+    let auth = ("Authorization", format!("Bearer {}", token));
+
+    // 创建应用
+    let req = test::TestRequest::post()
+        .uri("/api/apps")
+        .insert_header(auth.clone())
+        .set_json(json!({ "name": "Blog", "description": "My blog", "identifier": "blog" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    let req = test::TestRequest::get()
+        .uri("/api/apps?identifier=blog")
+        .insert_header(auth.clone())
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["apps"][0]["name"], "Blog");
+
+    // 创建文章
+    let req = test::TestRequest::post()
+        .uri("/articles")
+        .insert_header(auth.clone())
+        .set_json(json!({ "title": "Hello", "content": "First post", "status": 2 }))
+        .to_request();
+    let article: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(article["title"], "Hello");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/articles/{}", article["id"]))
+        .insert_header(auth)
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["content"], "First post");
+}
+
+#[actix_web::test]
+async fn expired_verification_code_is_rejected() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/auth/register")
+        .set_json(json!({ "username": "bob", "email": "bob@example.com" }))
+        .to_request();
+    test::call_service(&app, req).await;
+    let code = test_app.last_code_for("bob@example.com").await.unwrap();
+
+    test_app.clock.advance(Duration::minutes(31));
+
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(json!({ "email": "bob@example.com", "verification_code": code }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn rate_limited_bodies_respect_the_json_limit() {
+    let mut settings = test_settings();
+    settings.json_body_limit = 256;
+    let test_app = TestApp::with_settings(settings).await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+
+    // 按邮箱限流读取请求体时同样受 JSON 大小限制，错误使用统一格式
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(json!({ "email": "bob@example.com", "verification_code": "x".repeat(512) }))
+        .to_request();
+    let Err(err) = test::try_call_service(&app, req).await else {
+        panic!("oversized body was accepted");
+    };
+    let resp = err.error_response();
+    assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    let body: Value = serde_json::from_slice(&actix_web::body::to_bytes(resp.into_body()).await.unwrap()).unwrap();
+    assert_eq!(body["error"]["code"], "payload_too_large");
+
+    let req = test::TestRequest::post()
+        .uri("/api/auth/register")
+        .set_json(json!({ "username": "bob", "email": "bob@example.com" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn protected_routes_require_token() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/apps")
+        .set_json(json!({ "name": "Blog", "description": "", "identifier": "blog" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let request_id = resp.headers().get("x-request-id").unwrap().to_str().unwrap().to_string();
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"]["code"], "unauthorized");
+    assert_eq!(body["error"]["request_id"], request_id);
+}
+
+#[actix_web::test]
+async fn tokens_verify_against_the_published_jwks() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+    let token = sign_in(&app, &test_app, "alice", "alice@example.com").await;
+
+    let req = test::TestRequest::get().uri("/.well-known/jwks.json").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let jwks: JwkSet = test::read_body_json(resp).await;
+    assert_eq!(jwks.keys.len(), 1);
+
+    // 其他服务只依赖 JWKS 校验 token
+    let header = jsonwebtoken::decode_header(&token).unwrap();
+    assert_eq!(header.alg, Algorithm::EdDSA);
+    let jwk = jwks.find(header.kid.as_deref().unwrap()).unwrap();
+    let mut validation = Validation::new(Algorithm::EdDSA);
+    validation.set_issuer(&[TEST_JWT_ISSUER]);
+    validation.set_audience(&[TEST_JWT_AUDIENCE]);
+    let claims = jsonwebtoken::decode::<Claims>(&token, &DecodingKey::from_jwk(jwk).unwrap(), &validation)
+        .unwrap()
+        .claims;
+    assert_eq!(claims.amr, ["email"]);
+
+    // 同一把密钥签发给其他服务的 token 不能用于本服务
+    let jwt = test_settings().jwt;
+    let forged = jwt
+        .encode(&Claims { aud: "billing".to_string(), ..claims }, chrono::Utc::now())
+        .unwrap();
+    let req = test::TestRequest::get()
+        .uri("/auth/me")
+        .insert_header(("Authorization", format!("Bearer {}", forged)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+}
+
+// 注册并登录，返回 token
+async fn sign_in<S, B>(app: &S, test_app: &TestApp, username: &str, email: &str) -> String
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let req = test::TestRequest::post()
+        .uri("/api/auth/register")
+        .set_json(json!({ "username": username, "email": email }))
+        .to_request();
+    assert_eq!(test::call_service(app, req).await.status(), StatusCode::OK);
+
+    let code = test_app.last_code_for(email).await.unwrap();
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(json!({ "email": email, "verification_code": code }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(app, req).await;
+    body["token"].as_str().unwrap().to_string()
+}
+
+#[actix_web::test]
+async fn emails_use_app_branding_and_locale() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+
+    let token = sign_in(&app, &test_app, "alice", "alice@example.com").await;
+    // 首次登录后收到欢迎邮件
+    test_app.flush_outbox().await;
+    let welcome = test_app.emails.last_sent_to("alice@example.com").unwrap();
+    assert!(welcome.raw.contains("Subject: Welcome to RSCMS"));
+
+    let req = test::TestRequest::post()
+        .uri("/api/apps")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(json!({
+            "name": "Blog",
+            "description": "",
+            "identifier": "blog",
+            "email_from": "blog@example.com",
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+
+    let req = test::TestRequest::post()
+        .uri("/api/auth/register")
+        .insert_header(("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8"))
+        .set_json(json!({ "username": "bob", "email": "bob@example.com", "app": "blog" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    // 邮件在发件箱投递之后才会发出
+    assert!(test_app.emails.last_sent_to("bob@example.com").is_none());
+    test_app.flush_outbox().await;
+    let sent = test_app.emails.last_sent_to("bob@example.com").unwrap();
+    assert!(sent.raw.contains("From: Blog <blog@example.com>"));
+    assert!(sent.raw.contains("multipart/alternative"));
+    assert!(!sent.raw.contains("Your verification code is"));
+}
+
+#[actix_web::test]
+async fn admins_can_preview_email_templates() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+
+    let user_token = sign_in(&app, &test_app, "alice", "alice@example.com").await;
+    let req = test::TestRequest::get()
+        .uri("/api/admin/email-templates/verification/preview")
+        .insert_header(("Authorization", format!("Bearer {}", user_token)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    // test_settings 中 admin@example.com 为管理员
+    let admin_token = sign_in(&app, &test_app, "admin", "admin@example.com").await;
+    let req = test::TestRequest::get()
+        .uri("/api/admin/email-templates/verification/preview?locale=zh-CN")
+        .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["subject"], "您的 RSCMS 验证码");
+    assert!(body["html"].as_str().unwrap().contains("123456"));
+
+    let req = test::TestRequest::get()
+        .uri("/api/admin/email-templates/missing/preview")
+        .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn admins_can_inspect_the_outbox() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+
+    let admin_token = sign_in(&app, &test_app, "admin", "admin@example.com").await;
+    test_app.flush_outbox().await;
+
+    // 验证码和欢迎邮件都已发送
+    let req = test::TestRequest::get()
+        .uri("/api/admin/outbox?status=sent")
+        .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["total"], 2);
+    assert_eq!(body["messages"][0]["template"], "welcome");
+    assert_eq!(body["messages"][0]["recipient"], "admin@example.com");
+
+    let id = body["messages"][0]["id"].as_i64().unwrap();
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/admin/outbox/{}", id))
+        .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["status"], "sent");
+    assert_eq!(body["attempts"], 1);
+
+    // 只有死信可以重试
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/admin/outbox/{}/retry", id))
+        .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CONFLICT);
+
+    let req = test::TestRequest::post()
+        .uri("/api/admin/outbox/999/retry")
+        .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn admins_can_view_job_queue_state() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+
+    let user_token = sign_in(&app, &test_app, "alice", "alice@example.com").await;
+    let req = test::TestRequest::get()
+        .uri("/api/admin/jobs/stats")
+        .insert_header(("Authorization", format!("Bearer {}", user_token)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    let admin_token = sign_in(&app, &test_app, "admin", "admin@example.com").await;
+    let req = test::TestRequest::get()
+        .uri("/api/admin/jobs/stats")
+        .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["recurring"][0]["name"], "purge_completed_jobs");
+    assert_eq!(body["recurring"][0]["next_run_at"], "2024-01-01T03:00:00Z");
+
+    // 内置的清理任务可以直接执行
+    let id = test_app
+        .state
+        .job_service
+        .enqueue(rscms::jobs::cleanup::PurgeCompletedJobs { retention_days: 7 })
+        .await
+        .unwrap();
+    assert_eq!(test_app.run_jobs().await, 1);
+
+    let req = test::TestRequest::get()
+        .uri("/api/admin/jobs?status=completed&kind=purge_completed_jobs")
+        .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["jobs"][0]["id"], id);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/admin/jobs/{}/retry", id))
+        .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CONFLICT);
+}
+
+#[actix_web::test]
+async fn admins_can_query_the_audit_log() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+
+    let token = sign_in(&app, &test_app, "alice", "alice@example.com").await;
+    let auth = ("Authorization", format!("Bearer {}", token));
+    let req = test::TestRequest::post()
+        .uri("/api/apps")
+        .insert_header(auth.clone())
+        .set_json(json!({ "name": "Blog", "description": "My blog", "identifier": "blog" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+
+    let req = test::TestRequest::get().uri("/api/apps?identifier=blog").insert_header(auth.clone()).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let app_id = body["apps"][0]["id"].as_i64().unwrap();
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/apps/{}", app_id))
+        .insert_header(auth.clone())
+        .set_json(json!({ "description": "Notes", "version": 1 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/apps/{}", app_id))
+        .insert_header(auth.clone())
+        .insert_header(("User-Agent", "cleanup-script/1.0"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    // 普通用户不能查看审计日志
+    let req = test::TestRequest::get().uri("/api/admin/audit-logs").insert_header(auth.clone()).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    let admin_token = sign_in(&app, &test_app, "admin", "admin@example.com").await;
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/admin/audit-logs?target_type=app&app_id={}", app_id))
+        .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["total"], 3);
+    let deleted = &body["logs"][0];
+    assert_eq!(deleted["action"], "app.delete");
+    assert_eq!(deleted["actor_type"], "user");
+    assert_eq!(deleted["user_agent"], "cleanup-script/1.0");
+    assert_eq!(deleted["before"]["name"], "Blog");
+    let actor_id = deleted["actor_id"].as_i64().unwrap();
+
+    // 更新只记录变化的字段
+    let updated = &body["logs"][1];
+    assert_eq!(updated["action"], "app.update");
+    assert_eq!(updated["before"], json!({ "description": "My blog" }));
+    assert_eq!(updated["after"], json!({ "description": "Notes" }));
+
+    // 登录和注册也会记录
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/admin/audit-logs?actor_id={}&action=user.login", actor_id))
+        .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["logs"][0]["after"]["amr"], json!(["email"]));
+
+    let req = test::TestRequest::get()
+        .uri("/api/admin/audit-logs?page_size=500")
+        .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[actix_web::test]
+async fn deleted_apps_and_articles_go_to_the_trash() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+
+    let token = sign_in(&app, &test_app, "alice", "alice@example.com").await;
+    let auth = ("Authorization", format!("Bearer {}", token));
+    let req = test::TestRequest::post()
+        .uri("/api/apps")
+        .insert_header(auth.clone())
+        .set_json(json!({ "name": "Blog", "description": "My blog", "identifier": "blog" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+    let req = test::TestRequest::get().uri("/api/apps?identifier=blog").insert_header(auth.clone()).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let app_id = body["apps"][0]["id"].as_i64().unwrap();
+
+    let req = test::TestRequest::post()
+        .uri("/articles")
+        .insert_header(auth.clone())
+        .set_json(json!({ "title": "Hello", "content": "World", "status": 2, "app_id": app_id }))
+        .to_request();
+    let article: Value = test::call_and_read_body_json(&app, req).await;
+    let article_id = article["id"].as_i64().unwrap();
+    assert_eq!(article["app_id"], app_id);
+
+    // 删除应用时文章一起移入回收站
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/apps/{}", app_id))
+        .insert_header(auth.clone())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::get()
+        .uri(&format!("/articles/{}", article_id))
+        .insert_header(auth.clone())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+
+    let req = test::TestRequest::get().uri("/api/apps/trash").insert_header(auth.clone()).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["apps"][0]["identifier"], "blog");
+    assert!(body["apps"][0]["deleted_at"].is_string());
+    let req = test::TestRequest::get().uri("/articles/trash").insert_header(auth.clone()).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body[0]["id"], article_id);
+
+    // 应用恢复前不能单独恢复文章，标识也不能被新应用使用
+    let req = test::TestRequest::post()
+        .uri(&format!("/articles/{}/restore", article_id))
+        .insert_header(auth.clone())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CONFLICT);
+    let req = test::TestRequest::post()
+        .uri("/api/apps")
+        .insert_header(auth.clone())
+        .set_json(json!({ "name": "Blog", "description": "", "identifier": "blog" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CONFLICT);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/apps/{}/restore", app_id))
+        .insert_header(auth.clone())
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(body["deleted_at"].is_null());
+    let req = test::TestRequest::get()
+        .uri(&format!("/articles/{}", article_id))
+        .insert_header(auth.clone())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    // 单独删除和恢复文章
+    let req = test::TestRequest::delete()
+        .uri(&format!("/articles/{}", article_id))
+        .insert_header(auth.clone())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::post()
+        .uri(&format!("/articles/{}/restore", article_id))
+        .insert_header(auth.clone())
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["id"], article_id);
+    assert!(body["deleted_at"].is_null());
+}
+
+#[actix_web::test]
+async fn stale_article_saves_are_rejected() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+
+    let token = sign_in(&app, &test_app, "alice", "alice@example.com").await;
+    let auth = ("Authorization", format!("Bearer {}", token));
+    let req = test::TestRequest::post()
+        .uri("/articles")
+        .insert_header(auth.clone())
+        .set_json(json!({ "title": "Hello", "content": "World" }))
+        .to_request();
+    let article: Value = test::call_and_read_body_json(&app, req).await;
+    let uri = format!("/articles/{}", article["id"]);
+
+    let req = test::TestRequest::get().uri(&uri).insert_header(auth.clone()).to_request();
+    let resp = test::call_service(&app, req).await;
+    let etag = resp.headers().get("ETag").unwrap().to_str().unwrap().to_string();
+    assert_eq!(etag, "\"1\"");
+
+    // 不带版本的修改会被拒绝
+    let req = test::TestRequest::put()
+        .uri(&uri)
+        .insert_header(auth.clone())
+        .set_json(json!({ "title": "Edited" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::PRECONDITION_REQUIRED);
+
+    let req = test::TestRequest::put()
+        .uri(&uri)
+        .insert_header(auth.clone())
+        .insert_header(("If-Match", etag.clone()))
+        .set_json(json!({ "title": "First" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("ETag").unwrap(), "\"2\"");
+
+    // 第二个编辑者仍持有旧版本，修改不会覆盖第一个人的内容
+    for (if_match, body) in [
+        (Some(etag.as_str()), json!({ "title": "Second" })),
+        (None, json!({ "title": "Second", "version": 1 })),
+    ] {
+        let mut req = test::TestRequest::put().uri(&uri).insert_header(auth.clone()).set_json(body);
+        if let Some(if_match) = if_match {
+            req = req.insert_header(("If-Match", if_match));
+        }
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+        assert_eq!(resp.headers().get("ETag").unwrap(), "\"2\"");
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"]["code"], "precondition_failed");
+        assert_eq!(body["error"]["details"][0]["field"], "version");
+    }
+
+    let req = test::TestRequest::get().uri(&uri).insert_header(auth.clone()).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["title"], "First");
+    assert_eq!(body["version"], 2);
+}
+
+#[actix_web::test]
+async fn edit_locks_can_be_held_renewed_and_taken_over() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+
+    let alice_token = sign_in(&app, &test_app, "alice", "alice@example.com").await;
+    let alice = ("Authorization", format!("Bearer {}", alice_token));
+    let bob_token = sign_in(&app, &test_app, "bob", "bob@example.com").await;
+    let bob = ("Authorization", format!("Bearer {}", bob_token));
+    let admin_token = sign_in(&app, &test_app, "admin", "admin@example.com").await;
+    let admin = ("Authorization", format!("Bearer {}", admin_token));
+    let req = test::TestRequest::get().uri("/auth/me").insert_header(admin.clone()).to_request();
+    let me: Value = test::call_and_read_body_json(&app, req).await;
+    let admin_id = me["id"].clone();
+
+    let req = test::TestRequest::post()
+        .uri("/articles")
+        .insert_header(alice.clone())
+        .set_json(json!({ "title": "Hello", "content": "World", "status": 2 }))
+        .to_request();
+    let article: Value = test::call_and_read_body_json(&app, req).await;
+    let lock_uri = format!("/articles/{}/lock", article["id"]);
+
+    let req = test::TestRequest::post().uri(&lock_uri).insert_header(alice.clone()).to_request();
+    let grant: Value = test::call_and_read_body_json(&app, req).await;
+    let alice_lock = grant["token"].as_str().unwrap().to_string();
+
+    // 其他用户不能加锁，管理员可以看到锁被占用后强制取得
+    let req = test::TestRequest::post().uri(&lock_uri).insert_header(bob.clone()).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+    let req = test::TestRequest::post().uri(&lock_uri).insert_header(admin.clone()).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CONFLICT);
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/take", lock_uri))
+        .insert_header(admin.clone())
+        .to_request();
+    let grant: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(grant["lock"]["user_id"], admin_id);
+    let admin_lock = grant["token"].as_str().unwrap().to_string();
+
+    // 原持有者的心跳失败
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/heartbeat", lock_uri))
+        .insert_header(alice.clone())
+        .insert_header(("Lock-Token", alice_lock))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CONFLICT);
+
+    // 作者不能取走管理员的锁，强制取得记录到审计日志
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/take", lock_uri))
+        .insert_header(alice.clone())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+    let req = test::TestRequest::get()
+        .uri("/api/admin/audit-logs?action=article.take_lock")
+        .insert_header(admin.clone())
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["logs"][0]["target_id"], article["id"]);
+    assert_eq!(body["logs"][0]["before"]["user_id"], article["author_id"]);
+    assert_eq!(body["logs"][0]["after"]["user_id"], admin_id);
+
+    // 连接后首先收到当前的锁和在线编辑者
+    let req = test::TestRequest::get()
+        .uri(&format!("/articles/{}/presence?access_token={}", article["id"], bob_token))
+        .insert_header(("Connection", "Upgrade"))
+        .insert_header(("Upgrade", "websocket"))
+        .insert_header(("Sec-WebSocket-Version", "13"))
+        .insert_header(("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ=="))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+    let mut frames = BytesMut::from(&test::read_body(resp).await[..]);
+    let (_, opcode, payload) = ws::Parser::parse(&mut frames, false, 64 * 1024).unwrap().unwrap();
+    assert_eq!(opcode, ws::OpCode::Text);
+    let state: Value = serde_json::from_slice(&payload.unwrap()).unwrap();
+    assert_eq!(state["type"], "state");
+    assert_eq!(state["lock"]["user_id"], admin_id);
+    assert_eq!(state["editors"][0]["username"], "bob");
+
+    // 没有心跳的锁过期后自动释放
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/heartbeat", lock_uri))
+        .insert_header(admin.clone())
+        .insert_header(("Lock-Token", admin_lock.clone()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    test_app.clock.advance(Duration::seconds(91));
+    let req = test::TestRequest::get().uri(&lock_uri).insert_header(bob.clone()).to_request();
+    let status: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(status["lock"].is_null());
+    assert_eq!(status["editors"], json!([]));
+    let req = test::TestRequest::delete()
+        .uri(&lock_uri)
+        .insert_header(admin.clone())
+        .insert_header(("Lock-Token", admin_lock))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CONFLICT);
+}
+
+#[actix_web::test]
+async fn drafts_can_be_shared_with_preview_links() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+
+    let alice = ("Authorization", format!("Bearer {}", sign_in(&app, &test_app, "alice", "alice@example.com").await));
+    let bob = ("Authorization", format!("Bearer {}", sign_in(&app, &test_app, "bob", "bob@example.com").await));
+    let req = test::TestRequest::post()
+        .uri("/articles")
+        .insert_header(alice.clone())
+        .set_json(json!({ "title": "Draft", "content": "Not yet" }))
+        .to_request();
+    let article: Value = test::call_and_read_body_json(&app, req).await;
+    let previews_uri = format!("/articles/{}/previews", article["id"]);
+
+    // 只有作者可以创建预览链接
+    let req = test::TestRequest::post()
+        .uri(&previews_uri)
+        .insert_header(bob.clone())
+        .set_json(json!({}))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+    let req = test::TestRequest::post()
+        .uri(&previews_uri)
+        .insert_header(alice.clone())
+        .set_json(json!({ "expires_in": 3600 }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let created: Value = test::read_body_json(resp).await;
+    let token = created["token"].as_str().unwrap();
+    assert_eq!(created["url"], format!("http://rscms.test/previews/{}", token));
+    assert!(created["preview"].get("token_hash").is_none());
+
+    // 不需要登录就能查看草稿
+    let req = test::TestRequest::get().uri(&format!("/previews/{}", token)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("ETag").unwrap(), "\"1\"");
+    assert_eq!(resp.headers().get("Cache-Control").unwrap(), "private, no-store");
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!((body["title"].as_str(), body["status"].as_i64()), (Some("Draft"), Some(1)));
+
+    // 固定版本的链接在文章修改后仍显示固定时的内容
+    let req = test::TestRequest::post()
+        .uri(&previews_uri)
+        .insert_header(alice.clone())
+        .set_json(json!({ "version": 1 }))
+        .to_request();
+    let pinned: Value = test::call_and_read_body_json(&app, req).await;
+    let req = test::TestRequest::put()
+        .uri(&format!("/articles/{}", article["id"]))
+        .insert_header(alice.clone())
+        .insert_header(("If-Match", "\"1\""))
+        .set_json(json!({ "title": "Edited draft" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::get()
+        .uri(&format!("/previews/{}", pinned["token"].as_str().unwrap()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("ETag").unwrap(), "\"1\"");
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["title"], "Draft");
+    let req = test::TestRequest::get().uri(&format!("/previews/{}", token)).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["title"], "Edited draft");
+
+    // 吊销后和过期后都返回 410
+    let req = test::TestRequest::get().uri(&previews_uri).insert_header(alice.clone()).to_request();
+    let links: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(links.as_array().unwrap().len(), 2);
+    let req = test::TestRequest::delete()
+        .uri(&format!("{}/{}", previews_uri, pinned["preview"]["id"]))
+        .insert_header(alice.clone())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::get()
+        .uri(&format!("/previews/{}", pinned["token"].as_str().unwrap()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::GONE);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"]["code"], "gone");
+    test_app.clock.advance(Duration::seconds(3600));
+    let req = test::TestRequest::get().uri(&format!("/previews/{}", token)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::GONE);
+    let req = test::TestRequest::get().uri("/previews/unknown").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn published_articles_are_syndicated_as_feeds() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+
+    let auth = ("Authorization", format!("Bearer {}", sign_in(&app, &test_app, "alice", "alice@example.com").await));
+    let req = test::TestRequest::post()
+        .uri("/api/apps")
+        .insert_header(auth.clone())
+        .set_json(json!({
+            "name": "Blog",
+            "description": "My blog",
+            "identifier": "blog",
+            "feed_title": "Tom & Jerry",
+            "feed_link": "https://blog.example.com",
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+    let req = test::TestRequest::get().uri("/api/apps?identifier=blog").to_request();
+    let apps: Value = test::call_and_read_body_json(&app, req).await;
+    let blog = apps["apps"][0].clone();
+    assert_eq!(blog["feed_title"], "Tom & Jerry");
+    for (title, status, category) in [("<News>", 2, "news"), ("Tips", 2, "tips"), ("Draft", 1, "news")] {
+        let req = test::TestRequest::post()
+            .uri("/articles")
+            .insert_header(auth.clone())
+            .set_json(json!({
+                "title": title,
+                "content": "First paragraph.\n\nSecond paragraph.",
+                "status": status,
+                "app_id": blog["id"],
+                "category": category,
+            }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
+
+    // 不需要登录，只包含已发布的文章
+    let req = test::TestRequest::get().uri("/feeds/blog/rss.xml").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("content-type").unwrap(), "application/rss+xml; charset=utf-8");
+    let etag = resp.headers().get("ETag").unwrap().to_str().unwrap().to_string();
+    let rss = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(rss.contains("<title>Tom &amp; Jerry</title>"));
+    assert!(rss.contains("<title>&lt;News&gt;</title>"));
+    assert_eq!(rss.matches("<item>").count(), 2);
+    assert!(!rss.contains("Draft"));
+
+    // 条件请求
+    let req = test::TestRequest::get()
+        .uri("/feeds/blog/rss.xml")
+        .insert_header(("If-None-Match", etag.clone()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+    assert!(test::read_body(resp).await.is_empty());
+
+    let req = test::TestRequest::get().uri("/feeds/blog/atom.xml?mode=summary").to_request();
+    let atom = String::from_utf8(test::call_and_read_body(&app, req).await.to_vec()).unwrap();
+    assert!(atom.contains("<summary type=\"text\">First paragraph. Second paragraph.</summary>"));
+    assert!(atom.contains("<link rel=\"alternate\" href=\"https://blog.example.com\"/>"));
+
+    let req = test::TestRequest::get().uri("/feeds/blog/feed.json?category=tips").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("content-type").unwrap(), "application/feed+json; charset=utf-8");
+    let feed: Value = test::read_body_json(resp).await;
+    assert_eq!(feed["feed_url"], "http://rscms.test/feeds/blog/feed.json?category=tips");
+    assert_eq!(feed["items"].as_array().unwrap().len(), 1);
+    assert_eq!(feed["items"][0]["title"], "Tips");
+    assert_eq!(feed["items"][0]["tags"], json!(["tips"]));
+    assert!(feed["items"][0]["url"].as_str().unwrap().starts_with("http://rscms.test/blog/articles/"));
+
+    // 发布新文章后旧的 ETag 不再匹配
+    let req = test::TestRequest::post()
+        .uri("/articles")
+        .insert_header(auth.clone())
+        .set_json(json!({ "title": "Later", "content": "More", "status": 2, "app_id": blog["id"] }))
+        .to_request();
+    let later: Value = test::call_and_read_body_json(&app, req).await;
+    let req = test::TestRequest::get().uri("/feeds/blog/rss.xml").insert_header(("If-None-Match", etag)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let etag = resp.headers().get("ETag").unwrap().to_str().unwrap().to_string();
+
+    // 删除文章没有更新时间，同样使旧的 ETag 失效
+    let req = test::TestRequest::delete()
+        .uri(&format!("/articles/{}", later["id"]))
+        .insert_header(auth.clone())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::get().uri("/feeds/blog/rss.xml").insert_header(("If-None-Match", etag)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get().uri("/feeds/missing/rss.xml").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn indexable_articles_are_listed_in_sitemaps() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+
+    let auth = ("Authorization", format!("Bearer {}", sign_in(&app, &test_app, "alice", "alice@example.com").await));
+    let req = test::TestRequest::post()
+        .uri("/api/apps")
+        .insert_header(auth.clone())
+        .set_json(json!({ "name": "Blog", "description": "My blog", "identifier": "blog" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+    let req = test::TestRequest::get().uri("/api/apps?identifier=blog").to_request();
+    let apps: Value = test::call_and_read_body_json(&app, req).await;
+    let blog = apps["apps"][0].clone();
+    assert!(blog["base_url"].is_null());
+
+    // 没有 base_url 的应用没有站点地图
+    let req = test::TestRequest::get().uri("/sitemaps/blog/sitemap.xml").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/apps/{}", blog["id"]))
+        .insert_header(auth.clone())
+        .set_json(json!({ "base_url": "https://blog.example.com", "version": blog["version"] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::post()
+        .uri("/articles")
+        .insert_header(auth.clone())
+        .set_json(json!({
+            "title": "Hello",
+            "content": "Hello world",
+            "status": 2,
+            "app_id": blog["id"],
+            "locale": "en",
+            "meta_title": "Hello | Blog",
+            "meta_description": "Our first post",
+            "og_image_url": "https://blog.example.com/hello.png",
+        }))
+        .to_request();
+    let original: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(original["meta_title"], "Hello | Blog");
+    assert_eq!(original["noindex"], 0);
+
+    // 译文必须和原文属于同一个应用
+    let translation = json!({
+        "title": "你好",
+        "content": "你好，世界",
+        "status": 2,
+        "locale": "zh-CN",
+        "translation_of": original["id"],
+    });
+    let req = test::TestRequest::post()
+        .uri("/articles")
+        .insert_header(auth.clone())
+        .set_json(&translation)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+    let mut translation = translation;
+    translation["app_id"] = blog["id"].clone();
+    let req = test::TestRequest::post()
+        .uri("/articles")
+        .insert_header(auth.clone())
+        .set_json(&translation)
+        .to_request();
+    let translation: Value = test::call_and_read_body_json(&app, req).await;
+
+    let req = test::TestRequest::post()
+        .uri("/articles")
+        .insert_header(auth.clone())
+        .set_json(json!({ "title": "Hidden", "content": "Thanks", "status": 2, "app_id": blog["id"] }))
+        .to_request();
+    let hidden: Value = test::call_and_read_body_json(&app, req).await;
+
+    let req = test::TestRequest::get().uri("/sitemaps/blog/sitemap.xml").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("content-type").unwrap(), "application/xml; charset=utf-8");
+    let etag = resp.headers().get("ETag").unwrap().to_str().unwrap().to_string();
+    let xml = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert_eq!(xml.matches("<url>").count(), 3);
+    let translation_url = format!("https://blog.example.com/articles/{}", translation["id"]);
+    assert!(xml.contains(&format!("hreflang=\"zh-CN\" href=\"{}\"", translation_url)));
+
+    let req = test::TestRequest::get()
+        .uri("/sitemaps/blog/sitemap.xml")
+        .insert_header(("If-None-Match", etag.clone()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_MODIFIED);
+
+    // noindex 的文章不出现在站点地图中
+    let req = test::TestRequest::put()
+        .uri(&format!("/articles/{}", hidden["id"]))
+        .insert_header(auth.clone())
+        .set_json(json!({ "noindex": true, "version": hidden["version"] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::get()
+        .uri("/sitemaps/blog/sitemap.xml")
+        .insert_header(("If-None-Match", etag))
+        .to_request();
+    let xml = String::from_utf8(test::call_and_read_body(&app, req).await.to_vec()).unwrap();
+    assert_eq!(xml.matches("<url>").count(), 2);
+    assert!(!xml.contains(&format!("/articles/{}<", hidden["id"])));
+
+    let req = test::TestRequest::get().uri("/sitemaps/blog/sitemap-1.xml").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::get().uri("/sitemaps/blog/sitemap-2.xml").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn apps_are_exported_and_imported() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+
+    let auth = ("Authorization", format!("Bearer {}", sign_in(&app, &test_app, "alice", "alice@example.com").await));
+    let req = test::TestRequest::post()
+        .uri("/api/apps")
+        .insert_header(auth.clone())
+        .set_json(json!({ "name": "Blog", "description": "My blog", "identifier": "blog" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+    let req = test::TestRequest::get().uri("/api/apps?identifier=blog").to_request();
+    let apps: Value = test::call_and_read_body_json(&app, req).await;
+    let blog_id = apps["apps"][0]["id"].clone();
+    let req = test::TestRequest::post()
+        .uri("/articles")
+        .insert_header(auth.clone())
+        .set_json(json!({ "title": "Draft", "content": "![x](https://example.com/x.png)", "app_id": blog_id }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/apps/{}/export?format=ndjson", blog_id))
+        .insert_header(auth.clone())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("content-type").unwrap(), "application/x-ndjson");
+    assert_eq!(resp.headers().get("content-disposition").unwrap(), "attachment; filename=\"blog.ndjson\"");
+    let ndjson = test::read_body(resp).await;
+    assert_eq!(ndjson.iter().filter(|byte| **byte == b'\n').count(), 4);
+
+    // 只有应用的创建者和管理员可以导出
+    let bob = ("Authorization", format!("Bearer {}", sign_in(&app, &test_app, "bob", "bob@example.com").await));
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/apps/{}/export", blog_id))
+        .insert_header(bob.clone())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    // 试运行只报告将要进行的修改
+    let import = |query: &str, content_type: &str, body: Vec<u8>| {
+        test::TestRequest::post()
+            .uri(&format!("/api/apps/import?{}", query))
+            .insert_header(bob.clone())
+            .insert_header(("Content-Type", content_type.to_string()))
+            .set_payload(body)
+            .to_request()
+    };
+    let req = import("identifier=copy&dry_run=true", "application/x-ndjson", ndjson.to_vec());
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(report["dry_run"], true);
+    assert_eq!(report["app"]["action"], "create");
+    assert!(report["app"]["id"].is_null());
+    assert_eq!(report["media"][0]["url"], "https://example.com/x.png");
+    let req = test::TestRequest::get().uri("/api/apps?identifier=copy").to_request();
+    let apps: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(apps["total"], 0);
+
+    let req = import("identifier=copy", "application/x-ndjson", ndjson.to_vec());
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(report["articles"][0]["action"], "create");
+    let req = test::TestRequest::get()
+        .uri(&format!("/articles/{}", report["articles"][0]["id"]))
+        .insert_header(bob.clone())
+        .to_request();
+    let draft: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!((draft["title"].as_str(), draft["status"].as_i64()), (Some("Draft"), Some(1)));
+
+    // 导入到其他用户的应用需要是它的创建者
+    let req = import("", "application/x-ndjson", ndjson.to_vec());
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+    let req = import("strategy=rename", "application/x-ndjson", ndjson.to_vec());
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!((report["app"]["action"].as_str(), report["app"]["name"].as_str()), (Some("rename"), Some("blog-2")));
+
+    let files = json!({ "files": [{ "path": "posts/hello.md", "content": "---\ntitle: Hello\n---\nHi" }] });
+    let req = import("source=markdown", "application/json", files.to_string().into_bytes());
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+    let req = import("source=markdown&identifier=notes", "application/json", files.to_string().into_bytes());
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(report["articles"][0]["name"], "Hello");
+    let req = import("source=wxr", "application/xml", b"<rss>".to_vec());
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+}
+
+// 读取 SSE 响应中的下一条事件，返回 id、事件名和数据
+async fn next_change<B: MessageBody>(body: &mut Pin<Box<B>>) -> (i64, String, Value) {
+    let Some(Ok(chunk)) = std::future::poll_fn(|cx| body.as_mut().poll_next(cx)).await else {
+        panic!("change stream ended");
+    };
+    let text = std::str::from_utf8(&chunk).unwrap();
+    let field = |name: &str| {
+        text.lines()
+            .find_map(|line| line.strip_prefix(name))
+            .unwrap_or_else(|| panic!("missing {} in {:?}", name, text))
+            .to_string()
+    };
+    let data: Value = serde_json::from_str(&field("data: ")).unwrap();
+    (field("id: ").parse().unwrap(), field("event: "), data)
+}
+
+#[actix_web::test]
+async fn app_changes_stream_and_resume_with_api_keys() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+
+    let token = sign_in(&app, &test_app, "alice", "alice@example.com").await;
+    let auth = ("Authorization", format!("Bearer {}", token));
+    let mut app_ids = Vec::new();
+    for identifier in ["blog", "docs"] {
+        let req = test::TestRequest::post()
+            .uri("/api/apps")
+            .insert_header(auth.clone())
+            .set_json(json!({ "name": identifier, "description": "", "identifier": identifier }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/apps?identifier={}", identifier))
+            .insert_header(auth.clone())
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        app_ids.push(body["apps"][0]["id"].as_i64().unwrap());
+    }
+    let (blog_id, docs_id) = (app_ids[0], app_ids[1]);
+
+    let req = test::TestRequest::post()
+        .uri("/articles")
+        .insert_header(auth.clone())
+        .set_json(json!({ "title": "Hello", "content": "World", "app_id": blog_id }))
+        .to_request();
+    let article: Value = test::call_and_read_body_json(&app, req).await;
+    let req = test::TestRequest::put()
+        .uri(&format!("/articles/{}", article["id"]))
+        .insert_header(auth.clone())
+        .set_json(json!({ "status": 2, "version": 1 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    // 从头读取 blog 的事件，docs 的事件不会出现
+    let changes_uri = format!("/api/apps/{}/changes", blog_id);
+    let req = test::TestRequest::get()
+        .uri(&format!("{}?since=0", changes_uri))
+        .insert_header(auth.clone())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("content-type").unwrap(), "text/event-stream");
+    let mut body = Box::pin(resp.into_body());
+    let (_, event, data) = next_change(&mut body).await;
+    assert_eq!((event.as_str(), data["target_id"].as_i64()), ("app.created", Some(blog_id)));
+    let (created_id, event, data) = next_change(&mut body).await;
+    assert_eq!((event.as_str(), &data["target_id"]), ("article.created", &article["id"]));
+    let (published_id, event, _) = next_change(&mut body).await;
+    assert_eq!(event, "article.published");
+
+    // API key 只能订阅所属应用，断线后用 Last-Event-ID 续传
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/apps/{}/api-keys", blog_id))
+        .insert_header(auth.clone())
+        .set_json(json!({ "name": "Preview site" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let created: Value = test::read_body_json(resp).await;
+    let key = created["key"].as_str().unwrap().to_string();
+    assert!(created["api_key"]["key_hash"].is_null());
+
+    let req = test::TestRequest::get()
+        .uri(&changes_uri)
+        .insert_header(("X-API-Key", key.clone()))
+        .insert_header(("Last-Event-ID", created_id.to_string()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let mut body = Box::pin(resp.into_body());
+    assert_eq!(next_change(&mut body).await.0, published_id);
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/apps/{}/changes", docs_id))
+        .insert_header(("X-API-Key", key.clone()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+    let req = test::TestRequest::get().uri(&changes_uri).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+
+    // WebSocket 发送同样的事件
+    let req = test::TestRequest::get()
+        .uri(&format!("{}/ws?api_key={}&since={}", changes_uri, key, created_id))
+        .insert_header(("Connection", "Upgrade"))
+        .insert_header(("Upgrade", "websocket"))
+        .insert_header(("Sec-WebSocket-Version", "13"))
+        .insert_header(("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ=="))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+    let mut frames = BytesMut::from(&test::read_body(resp).await[..]);
+    let (_, opcode, payload) = ws::Parser::parse(&mut frames, false, 64 * 1024).unwrap().unwrap();
+    assert_eq!(opcode, ws::OpCode::Text);
+    let event: Value = serde_json::from_slice(&payload.unwrap()).unwrap();
+    assert_eq!((event["id"].as_i64(), &event["event_type"]), (Some(published_id), &json!("article.published")));
+
+    // 吊销后不能再订阅
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/apps/{}/api-keys", blog_id))
+        .insert_header(auth.clone())
+        .to_request();
+    let keys: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(keys[0]["name"], "Preview site");
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/apps/{}/api-keys/{}", blog_id, keys[0]["id"]))
+        .insert_header(auth.clone())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::get()
+        .uri(&changes_uri)
+        .insert_header(("X-API-Key", key))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn magic_link_logs_in_on_the_requesting_device_only() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+    sign_in(&app, &test_app, "alice", "alice@example.com").await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/auth/magic-link")
+        .set_json(json!({ "email": "alice@example.com" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let device = resp
+        .response()
+        .cookies()
+        .find(|cookie| cookie.name() == "rscms_magic_device")
+        .unwrap()
+        .into_owned();
+    assert!(device.http_only().unwrap());
+
+    let link = test_app.last_magic_link_for("alice@example.com").await.unwrap();
+
+    // 没有设备 cookie 的浏览器（例如被转发的链接）无法登录
+    let req = test::TestRequest::get().uri(&link).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+
+    let req = test::TestRequest::get().uri(&link).cookie(device.clone()).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["user"]["email"], "alice@example.com");
+    let req = test::TestRequest::get()
+        .uri("/auth/me")
+        .insert_header(("Authorization", format!("Bearer {}", body["token"].as_str().unwrap())))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    // 链接只能使用一次
+    let req = test::TestRequest::get().uri(&link).cookie(device).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+
+    // 同一封邮件中的验证码仍然可以登录
+    test::call_service(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/auth/magic-link")
+            .set_json(json!({ "email": "alice@example.com" }))
+            .to_request(),
+    )
+    .await;
+    let code = test_app.last_code_for("alice@example.com").await.unwrap();
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(json!({ "email": "alice@example.com", "verification_code": code }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn two_factor_sessions_are_required_by_app_policy() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+    let email_token = sign_in(&app, &test_app, "alice", "alice@example.com").await;
+    let auth = ("Authorization", format!("Bearer {}", email_token));
+
+    let req = test::TestRequest::put()
+        .uri("/api/auth/password")
+        .insert_header(auth.clone())
+        .set_json(json!({ "new_password": "Correct-Horse-9" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    // 邮件验证码登录只有一个因素，不能开启应用的两步验证要求
+    let create_app = json!({ "name": "Blog", "description": "", "identifier": "blog", "require_two_factor": true });
+    let req = test::TestRequest::post()
+        .uri("/api/apps")
+        .insert_header(auth.clone())
+        .set_json(create_app.clone())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    // 绑定验证器并启用两步验证
+    let req = test::TestRequest::post()
+        .uri("/api/auth/2fa/totp/setup")
+        .insert_header(auth.clone())
+        .to_request();
+    let setup: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(setup["otpauth_uri"].as_str().unwrap().starts_with("otpauth://totp/"));
+    let secret = setup["secret"].as_str().unwrap();
+    let code = totp::code_at(secret, test_app.clock.now()).unwrap();
+    let req = test::TestRequest::post()
+        .uri("/api/auth/2fa/totp/enable")
+        .insert_header(auth.clone())
+        .set_json(json!({ "code": code }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["recovery_codes"].as_array().unwrap().len(), 10);
+
+    // 密码正确后需要第二个因素
+    let req = test::TestRequest::post()
+        .uri("/api/auth/password/login")
+        .set_json(json!({ "email": "alice@example.com", "password": "Correct-Horse-9" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::ACCEPTED);
+    let challenge: Value = test::read_body_json(resp).await;
+
+    test_app.clock.advance(Duration::seconds(30));
+    let code = totp::code_at(secret, test_app.clock.now()).unwrap();
+    let req = test::TestRequest::post()
+        .uri("/api/auth/2fa/login")
+        .set_json(json!({ "two_factor_token": challenge["two_factor_token"], "code": code }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let strong_auth = ("Authorization", format!("Bearer {}", body["token"].as_str().unwrap()));
+
+    let req = test::TestRequest::post()
+        .uri("/api/apps")
+        .insert_header(strong_auth.clone())
+        .set_json(create_app)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+    let req = test::TestRequest::get()
+        .uri("/api/apps?identifier=blog")
+        .insert_header(auth.clone())
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["apps"][0]["require_two_factor"], true);
+
+    // 应用中的文章同样需要两步验证会话
+    let app_id = body["apps"][0]["id"].as_i64().unwrap();
+    let article = json!({ "title": "Secret", "content": "Content", "app_id": app_id });
+    let req = test::TestRequest::post()
+        .uri("/articles")
+        .insert_header(auth.clone())
+        .set_json(article.clone())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+    let req = test::TestRequest::post()
+        .uri("/articles")
+        .insert_header(strong_auth.clone())
+        .set_json(article)
+        .to_request();
+    let created: Value = test::call_and_read_body_json(&app, req).await;
+    let article_uri = format!("/articles/{}", created["id"]);
+    let req = test::TestRequest::put()
+        .uri(&article_uri)
+        .insert_header(auth.clone())
+        .set_json(json!({ "title": "Renamed", "version": created["version"] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    // GraphQL 的文章修改使用同样的规则
+    let graphql = |auth: (&'static str, String), query: String| {
+        test::TestRequest::post()
+            .uri("/api/graphql")
+            .insert_header(auth)
+            .set_json(json!({ "query": query }))
+            .to_request()
+    };
+    let create = format!(
+        r#"mutation {{ createArticle(input: {{ title: "Other", content: "Content", appId: {} }}) {{ id }} }}"#,
+        app_id
+    );
+    let body: Value = test::call_and_read_body_json(&app, graphql(auth.clone(), create)).await;
+    assert_eq!(body["errors"][0]["extensions"]["code"], "forbidden");
+    let delete = format!("mutation {{ deleteArticle(id: {}) }}", created["id"]);
+    let body: Value = test::call_and_read_body_json(&app, graphql(auth.clone(), delete.clone())).await;
+    assert_eq!(body["errors"][0]["extensions"]["code"], "forbidden");
+    let body: Value = test::call_and_read_body_json(&app, graphql(strong_auth.clone(), delete)).await;
+    assert_eq!(body["data"]["deleteArticle"], true);
+
+    // 单因素会话不能修改或删除该应用
+    let uri = format!("/api/apps/{}", app_id);
+    let req = test::TestRequest::delete().uri(&uri).insert_header(auth).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+    let req = test::TestRequest::delete().uri(&uri).insert_header(strong_auth).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn graphql_user_lookups_require_a_token_and_limit_nested_lists() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+    let token = sign_in(&app, &test_app, "alice", "alice@example.com").await;
+    let auth = ("Authorization", format!("Bearer {}", token));
+
+    for i in 0..3 {
+        let req = test::TestRequest::post()
+            .uri("/api/apps")
+            .insert_header(auth.clone())
+            .set_json(json!({ "name": format!("Blog {}", i), "description": "", "identifier": format!("blog-{}", i) }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+    }
+
+    let me: Value = {
+        let req = test::TestRequest::get().uri("/auth/me").insert_header(auth.clone()).to_request();
+        test::call_and_read_body_json(&app, req).await
+    };
+    let query = format!("{{ user(id: {}) {{ username apps(first: 2) {{ identifier }} }} }}", me["id"]);
+
+    // 匿名调用者不能按 id 查询用户
+    let req = test::TestRequest::post()
+        .uri("/api/graphql")
+        .set_json(json!({ "query": query }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["errors"][0]["extensions"]["code"], "unauthorized");
+    assert!(body["data"]["user"].is_null());
+
+    let req = test::TestRequest::post()
+        .uri("/api/graphql")
+        .insert_header(auth.clone())
+        .set_json(json!({ "query": query }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["data"]["user"]["username"], "alice");
+    assert_eq!(body["data"]["user"]["apps"].as_array().unwrap().len(), 2);
+
+    // 嵌套列表按条数计入复杂度
+    let query = "{ me { apps(first: 100) { creator { apps(first: 100) { id } } } } }";
+    let req = test::TestRequest::post()
+        .uri("/api/graphql")
+        .insert_header(auth)
+        .set_json(json!({ "query": query }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(body["errors"][0]["message"].as_str().unwrap().contains("complex"));
+}
+
+#[actix_web::test]
+async fn email_logins_require_the_second_factor_once_enrolled() {
+    let test_app = TestApp::new().await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+    let email_token = sign_in(&app, &test_app, "alice", "alice@example.com").await;
+    let auth = ("Authorization", format!("Bearer {}", email_token));
+
+    // 开启两步验证前需要设置密码
+    let req = test::TestRequest::put()
+        .uri("/api/auth/password")
+        .insert_header(auth.clone())
+        .set_json(json!({ "new_password": "Correct-Horse-9" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::post()
+        .uri("/api/auth/2fa/totp/setup")
+        .insert_header(auth.clone())
+        .to_request();
+    let setup: Value = test::call_and_read_body_json(&app, req).await;
+    let secret = setup["secret"].as_str().unwrap();
+    let code = totp::code_at(secret, test_app.clock.now()).unwrap();
+    let req = test::TestRequest::post()
+        .uri("/api/auth/2fa/totp/enable")
+        .insert_header(auth)
+        .set_json(json!({ "code": code }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    // 邮件验证码正确后只返回两步验证 token，不签发登录 token
+    let req = test::TestRequest::post()
+        .uri("/api/auth/verification-code")
+        .set_json(json!({ "email": "alice@example.com" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let code = test_app.last_code_for("alice@example.com").await.unwrap();
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(json!({ "email": "alice@example.com", "verification_code": code }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::ACCEPTED);
+    let body: Value = test::read_body_json(resp).await;
+    assert!(body.get("token").is_none());
+
+    // 登录链接同样需要第二个因素，新的两步验证 token 取代之前的
+    let req = test::TestRequest::post()
+        .uri("/api/auth/magic-link")
+        .set_json(json!({ "email": "alice@example.com" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let device = resp
+        .response()
+        .cookies()
+        .find(|cookie| cookie.name() == "rscms_magic_device")
+        .unwrap()
+        .into_owned();
+    let link = test_app.last_magic_link_for("alice@example.com").await.unwrap();
+    let req = test::TestRequest::get().uri(&link).cookie(device).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::ACCEPTED);
+    let challenge: Value = test::read_body_json(resp).await;
+    assert!(challenge.get("token").is_none());
+
+    test_app.clock.advance(Duration::seconds(30));
+    let code = totp::code_at(secret, test_app.clock.now()).unwrap();
+    let req = test::TestRequest::post()
+        .uri("/api/auth/2fa/login")
+        .set_json(json!({ "two_factor_token": challenge["two_factor_token"], "code": code }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let mut validation = Validation::new(Algorithm::EdDSA);
+    validation.insecure_disable_signature_validation();
+    validation.set_issuer(&[TEST_JWT_ISSUER]);
+    validation.set_audience(&[TEST_JWT_AUDIENCE]);
+    let claims = jsonwebtoken::decode::<Claims>(
+        body["token"].as_str().unwrap(),
+        &DecodingKey::from_secret(&[]),
+        &validation,
+    )
+    .unwrap()
+    .claims;
+    assert_eq!(claims.amr, ["email", "otp"]);
+}
+
+// 从授权地址跳转开始一次单点登录，返回身份提供方地址和 state cookie
+async fn start_oidc_login<S, B>(app: &S) -> (String, actix_web::cookie::Cookie<'static>)
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let req = test::TestRequest::get().uri("/api/auth/oidc/corp/authorize").to_request();
+    let resp = test::call_service(app, req).await;
+    assert_eq!(resp.status(), StatusCode::FOUND);
+    let location = resp.headers().get("location").unwrap().to_str().unwrap().to_string();
+    let cookie = resp
+        .response()
+        .cookies()
+        .find(|cookie| cookie.name() == "rscms_oidc_state")
+        .unwrap()
+        .into_owned();
+    (location, cookie)
+}
+
+#[actix_web::test]
+async fn oidc_login_links_provisions_and_maps_roles() {
+    let idp = MockOidcProvider::start().await;
+    let mut settings = test_settings();
+    let mut provider = idp.provider_config("corp");
+    provider.role_mapping = parse_role_mapping("cms-admins=admin");
+    settings.oidc.providers = vec![provider];
+    let test_app = TestApp::with_settings(settings).await;
+    let app = test::init_service(build_app(test_app.state.clone())).await;
+
+    let req = test::TestRequest::get().uri("/api/auth/oidc/providers").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body[0]["authorize_url"], "http://rscms.test/api/auth/oidc/corp/authorize");
+
+    // 首次登录自动创建用户，组映射为管理员；身份提供方使用了多因素认证
+    let (location, cookie) = start_oidc_login(&app).await;
+    assert!(location.starts_with(&format!("{}/authorize?", idp.issuer)));
+    assert!(location.contains("code_challenge_method=S256"));
+    let callback = format!(
+        "/api/auth/oidc/corp/callback?{}",
+        idp.approve(
+            &location,
+            json!({
+                "sub": "idp-bob",
+                "email": "bob@example.com",
+                "email_verified": true,
+                "preferred_username": "bob",
+                "groups": ["cms-admins"],
+                "amr": ["pwd", "mfa"],
+            })
+        )
+    );
+
+    // 回调必须在发起登录的浏览器中完成
+    let req = test::TestRequest::get().uri(&callback).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+    let req = test::TestRequest::get().uri(&callback).cookie(cookie.clone()).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["user"]["username"], "bob");
+    assert_eq!(body["user"]["role"], "admin");
+    assert_eq!(body["user"]["email_verified"], 1);
+    let bob_auth = ("Authorization", format!("Bearer {}", body["token"].as_str().unwrap()));
+
+    // 两步验证会话可以开启应用的两步验证要求
+    let req = test::TestRequest::post()
+        .uri("/api/apps")
+        .insert_header(bob_auth)
+        .set_json(json!({ "name": "Blog", "description": "", "identifier": "blog", "require_two_factor": true }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+
+    // state 只能使用一次
+    let req = test::TestRequest::get().uri(&callback).cookie(cookie).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+
+    // 未验证的邮箱不能关联已有账号
+    sign_in(&app, &test_app, "alice", "alice@example.com").await;
+    let alice_claims = |verified: bool| {
+        json!({ "sub": "idp-alice", "email": "alice@example.com", "email_verified": verified, "groups": ["staff"] })
+    };
+    let (location, cookie) = start_oidc_login(&app).await;
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/auth/oidc/corp/callback?{}", idp.approve(&location, alice_claims(false))))
+        .cookie(cookie)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+
+    // 已验证的邮箱关联到已有账号，之后按 subject 登录
+    let mut alice_token = String::new();
+    for _ in 0..2 {
+        let (location, cookie) = start_oidc_login(&app).await;
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/auth/oidc/corp/callback?{}", idp.approve(&location, alice_claims(true))))
+            .cookie(cookie)
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["user"]["username"], "alice");
+        assert_eq!(body["user"]["role"], "user");
+        alice_token = body["token"].as_str().unwrap().to_string();
+    }
+
+    // 本地开启了两步验证后，单点登录也需要第二个因素
+    let auth = ("Authorization", format!("Bearer {}", alice_token));
+    let req = test::TestRequest::put()
+        .uri("/api/auth/password")
+        .insert_header(auth.clone())
+        .set_json(json!({ "new_password": "Correct-Horse-9" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::post()
+        .uri("/api/auth/2fa/totp/setup")
+        .insert_header(auth.clone())
+        .to_request();
+    let setup: Value = test::call_and_read_body_json(&app, req).await;
+    let secret = setup["secret"].as_str().unwrap();
+    let code = totp::code_at(secret, test_app.clock.now()).unwrap();
+    let req = test::TestRequest::post()
+        .uri("/api/auth/2fa/totp/enable")
+        .insert_header(auth)
+        .set_json(json!({ "code": code }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let (location, cookie) = start_oidc_login(&app).await;
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/auth/oidc/corp/callback?{}", idp.approve(&location, alice_claims(true))))
+        .cookie(cookie)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::ACCEPTED);
+    let state = resp.response().cookies().find(|cookie| cookie.name() == "rscms_oidc_state").unwrap();
+    assert_eq!(state.value(), "");
+    let challenge: Value = test::read_body_json(resp).await;
+    assert!(challenge.get("token").is_none());
+
+    test_app.clock.advance(Duration::seconds(30));
+    let code = totp::code_at(secret, test_app.clock.now()).unwrap();
+    let req = test::TestRequest::post()
+        .uri("/api/auth/2fa/login")
+        .set_json(json!({ "two_factor_token": challenge["two_factor_token"], "code": code }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["user"]["username"], "alice");
+}